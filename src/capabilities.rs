@@ -0,0 +1,219 @@
+use std::env;
+use std::io::{Result, stdout};
+
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::{
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+};
+use ratatui::style::Color;
+
+use crate::config::CapabilityOverrides;
+
+// Features of the terminal tv is running in. Features that are not supported are not used,
+// instead of sending escape sequences the terminal would render as garbage.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub mouse: bool,
+    pub truecolor: bool,
+    pub kitty_graphics: bool,
+    pub iterm_images: bool, // Inline images of iTerm2
+    pub osc52: bool,        // Setting the clipboard through the terminal
+    pub bracketed_paste: bool,
+}
+
+impl Capabilities {
+    // Guess the features of the terminal from the environment it announces, the terminal itself
+    // is not queried. Terminals that announce themselves wrongly, e.g. over ssh or in a
+    // multiplexer, need the features set in the config, see `with_overrides`.
+    pub fn probe() -> Self {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        Self::from_env(
+            &var("TERM"),
+            &var("COLORTERM"),
+            &var("TERM_PROGRAM"),
+            env::var("KITTY_WINDOW_ID").is_ok(),
+            env::var("TMUX").is_ok(),
+        )
+    }
+
+    fn from_env(term: &str, colorterm: &str, term_program: &str, kitty: bool, tmux: bool) -> Self {
+        // The linux console and dumb terminals understand hardly any escape sequences
+        if term.is_empty() || term == "dumb" || term == "linux" {
+            return Capabilities::default();
+        }
+
+        let kitty = kitty || term == "xterm-kitty";
+        let modern_program = matches!(
+            term_program,
+            "iTerm.app" | "WezTerm" | "ghostty" | "vscode" | "Apple_Terminal"
+        );
+        let truecolor = matches!(colorterm, "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || kitty
+            || (modern_program && term_program != "Apple_Terminal");
+        // Graphics are not passed through tmux
+        let kitty_graphics = !tmux && (kitty || matches!(term_program, "WezTerm" | "ghostty"));
+        let iterm_images = !tmux && term_program == "iTerm.app";
+
+        Capabilities {
+            mouse: true,
+            truecolor,
+            kitty_graphics,
+            iterm_images,
+            // Screen swallows OSC 52, tmux forwards it if `set-clipboard` is enabled
+            osc52: !term.starts_with("screen") || tmux,
+            bracketed_paste: true,
+        }
+    }
+
+    // Use the features set in the config instead of the guessed ones
+    pub fn with_overrides(self, overrides: &CapabilityOverrides) -> Self {
+        Capabilities {
+            mouse: overrides.mouse.unwrap_or(self.mouse),
+            truecolor: overrides.truecolor.unwrap_or(self.truecolor),
+            kitty_graphics: overrides.kitty_graphics.unwrap_or(self.kitty_graphics),
+            iterm_images: overrides.iterm_images.unwrap_or(self.iterm_images),
+            osc52: overrides.osc52.unwrap_or(self.osc52),
+            bracketed_paste: overrides.bracketed_paste.unwrap_or(self.bracketed_paste),
+        }
+    }
+
+    // Turn on the supported input features, must be paired with `disable_features`
+    pub fn enable_features(&self) -> Result<()> {
+        if self.mouse {
+            execute!(stdout(), EnableMouseCapture)?;
+        }
+        if self.bracketed_paste {
+            execute!(stdout(), EnableBracketedPaste)?;
+        }
+        Ok(())
+    }
+
+    pub fn disable_features(&self) -> Result<()> {
+        if self.mouse {
+            execute!(stdout(), DisableMouseCapture)?;
+        }
+        if self.bracketed_paste {
+            execute!(stdout(), DisableBracketedPaste)?;
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        let features = [
+            ("mouse", self.mouse),
+            ("truecolor", self.truecolor),
+            ("kitty graphics", self.kitty_graphics),
+            ("iterm images", self.iterm_images),
+            ("osc52", self.osc52),
+            ("bracketed paste", self.bracketed_paste),
+        ];
+        features
+            .iter()
+            .map(|(name, supported)| format!("{name}: {}", if *supported { "yes" } else { "no" }))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+// Replace rgb colors of the rendered buffer by their closest color of the 256 color palette
+pub fn degrade_colors(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        cell.fg = to_indexed(cell.fg);
+        cell.bg = to_indexed(cell.bg);
+    }
+}
+
+// Reset all colors of the rendered buffer, e.g. if NO_COLOR is set. Modifiers like bold are kept.
+pub fn strip_colors(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
+// Replace the borders, bars and markers of the rendered buffer by ascii characters
+pub fn ascii_symbols(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        let mut chars = cell.symbol().chars();
+        let (Some(symbol), None) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        if let Some(ascii) = to_ascii(symbol) {
+            cell.set_char(ascii);
+        }
+    }
+}
+
+fn to_ascii(symbol: char) -> Option<char> {
+    let ascii = match symbol {
+        '─' | '━' | '═' | '–' | '—' => '-',
+        '│' | '┃' | '║' => '|',
+        // Other box drawing characters are corners and junctions
+        '\u{2500}'..='\u{257f}' => '+',
+        // Scrollbar thumb, gauges and bars
+        '\u{2580}'..='\u{259f}' => '#',
+        // Braille dots of charts
+        '\u{2800}' => ' ',
+        '\u{2801}'..='\u{28ff}' | '•' => '*',
+        '▲' => '^',
+        '▼' => 'v',
+        '…' => '~',
+        '↵' => '/',
+        '∅' => '-',
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+fn to_indexed(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+    // Index into the 6x6x6 color cube, or the grayscale ramp if the color is (almost) gray
+    let level = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max - min < 16 {
+        let gray = (r as u16 + g as u16 + b as u16) / 3;
+        if gray < 8 {
+            return Color::Indexed(16);
+        }
+        if gray > 238 {
+            return Color::Indexed(231);
+        }
+        return Color::Indexed(232 + ((gray - 8) * 24 / 231) as u8);
+    }
+    Color::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    use super::*;
+
+    #[test]
+    fn plain_buffer() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 6, 1));
+        buffer.set_string(0, 0, "┌─∅é▲", Style::new().fg(Color::Red).bold());
+        strip_colors(&mut buffer);
+        ascii_symbols(&mut buffer);
+        assert_eq!(buffer, {
+            let mut expected = Buffer::empty(Rect::new(0, 0, 6, 1));
+            expected.set_string(0, 0, "+--é^", Style::new().bold());
+            expected
+        });
+    }
+
+    #[test]
+    fn override_guessed_features() {
+        let guessed = Capabilities::from_env("screen-256color", "", "", false, false);
+        assert!(guessed.mouse && !guessed.truecolor && !guessed.osc52);
+        let overrides: CapabilityOverrides =
+            serde_json::from_str(r#"{"mouse": false, "truecolor": true}"#).unwrap();
+        let capabilities = guessed.with_overrides(&overrides);
+        assert!(!capabilities.mouse && capabilities.truecolor && !capabilities.osc52);
+    }
+}
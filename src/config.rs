@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::domain::TVError;
+
+const CONFIG_PATH: &str = "~/.config/tv/config.json";
+
+// Bins numeric columns with more distinct values are grouped into by the histogram
+pub const DEFAULT_HISTOGRAM_BINS: usize = 20;
+// Milliseconds the controller waits for input before updating the model
+pub const DEFAULT_EVENT_POLL_TIME: usize = 100;
+// Milliseconds without typing after which the matches of a filter are counted
+pub const DEFAULT_FILTER_PREVIEW_DELAY: usize = 200;
+// Files with more rows only get part of their rows loaded, unless started with --force-full
+pub const DEFAULT_MAX_ROWS: usize = 10_000_000;
+// Milliseconds between checks of a file watched for changes
+pub const DEFAULT_WATCH_INTERVAL: usize = 1000;
+// Rows read with --stream are shown after these milliseconds, or once there are this many of them
+pub const DEFAULT_STREAM_INTERVAL: usize = 200;
+pub const DEFAULT_STREAM_BATCH_ROWS: usize = 50_000;
+// Searches and filters over more rows are run on a worker thread, showing their progress
+pub const DEFAULT_BACKGROUND_SEARCH_ROWS: usize = 1_000_000;
+
+// Optional settings read from the config file, missing entries keep their defaults
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub scroll_acceleration: ScrollAcceleration,
+    pub histogram_bins: usize,       // 0 counts every distinct value
+    pub decimal_comma: bool, // Text cast to numbers uses a comma as decimal separator, e.g. `1.234,56`
+    pub event_poll_time: usize, // Milliseconds
+    pub filter_preview_delay: usize, // Milliseconds
+    pub watch_interval: usize, // Milliseconds
+    pub stream_interval: usize, // Milliseconds
+    pub stream_batch_rows: usize,
+    pub background_search_rows: usize,
+    pub stats_cache: bool, // Keep column stats of loaded files in the cache directory
+    pub max_rows: usize,   // 0 always loads all rows
+    pub capabilities: CapabilityOverrides,
+    #[serde(deserialize_with = "in_order")]
+    pub files: Vec<(String, FileConfig)>, // Settings of files matching a glob pattern
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        ConfigFile {
+            scroll_acceleration: ScrollAcceleration::default(),
+            histogram_bins: DEFAULT_HISTOGRAM_BINS,
+            decimal_comma: false,
+            event_poll_time: DEFAULT_EVENT_POLL_TIME,
+            filter_preview_delay: DEFAULT_FILTER_PREVIEW_DELAY,
+            watch_interval: DEFAULT_WATCH_INTERVAL,
+            stream_interval: DEFAULT_STREAM_INTERVAL,
+            stream_batch_rows: DEFAULT_STREAM_BATCH_ROWS,
+            background_search_rows: DEFAULT_BACKGROUND_SEARCH_ROWS,
+            stats_cache: true,
+            max_rows: DEFAULT_MAX_ROWS,
+            capabilities: CapabilityOverrides::default(),
+            files: Vec::new(),
+        }
+    }
+}
+
+// Terminal features to use or not, e.g. `"capabilities": {"mouse": false, "truecolor": true}`.
+// Features that are not given are guessed from the environment of the terminal.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CapabilityOverrides {
+    pub mouse: Option<bool>,
+    pub truecolor: Option<bool>,
+    pub kitty_graphics: Option<bool>,
+    pub iterm_images: Option<bool>,
+    pub osc52: Option<bool>,
+    pub bracketed_paste: Option<bool>,
+}
+
+// Settings of files matching a pattern, e.g. `"files": {"exports/*.csv": {"delimiter": ";"}}`.
+// Relative patterns match the end of the path, the first matching pattern is used.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub delimiter: Option<char>,
+    pub header: Option<bool>, // If the first row holds the column names, guessed without
+    pub dates: BTreeMap<String, String>, // Columns parsed with a strftime format, as datetime if it has a time
+    pub pinned: Vec<String>,             // Columns moved to the front, in this order
+    pub sort: Option<String>,            // Column the rows are sorted by, e.g. `date desc`
+}
+
+impl FileConfig {
+    // Column to sort by and if ascending
+    pub fn sort_order(&self) -> Option<(&str, bool)> {
+        let sort = self.sort.as_deref()?.trim();
+        Some(match sort.rsplit_once(' ') {
+            Some((column, "desc")) => (column.trim(), false),
+            Some((column, "asc")) => (column.trim(), true),
+            _ => (sort, true),
+        })
+    }
+}
+
+// Pattern and settings of the first pattern matching the file
+pub fn file_config<'a>(
+    files: &'a [(String, FileConfig)],
+    path: &Path,
+) -> Option<&'a (String, FileConfig)> {
+    let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let path = path.to_string_lossy();
+    files
+        .iter()
+        .find(|(pattern, _)| glob_matches(pattern, &path))
+}
+
+// `*` and `?` match within a directory, `**` across directories
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern = shellexpand::tilde(pattern);
+    let mut regex = match pattern.starts_with('/') {
+        true => String::from("^"),
+        false => String::from("(^|/)"),
+    };
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.next_if_eq(&'*').is_some() => match chars.next_if_eq(&'/') {
+                Some(_) => regex.push_str("(.*/)?"),
+                None => regex.push_str(".*"),
+            },
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(path))
+}
+
+// Entries of a json object in the order of the file, serde_json keeps it
+fn in_order<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, FileConfig)>, D::Error> {
+    let entries = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+    entries
+        .into_iter()
+        .map(|(pattern, value)| {
+            serde_json::from_value(value)
+                .map(|config| (pattern.clone(), config))
+                .map_err(|e| serde::de::Error::custom(format!("{pattern}: {e}")))
+        })
+        .collect()
+}
+
+// Rows moved per key event while j/k or the arrow keys are held down.
+// Every `repeats_per_step` repeated key events the next step is used.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScrollAcceleration {
+    pub steps: Vec<usize>,
+    pub repeats_per_step: usize,
+    pub repeat_interval_ms: u64, // Key events further apart than this count as separate presses
+}
+
+impl Default for ScrollAcceleration {
+    fn default() -> Self {
+        ScrollAcceleration {
+            steps: vec![1, 5, 20],
+            repeats_per_step: 10,
+            repeat_interval_ms: 100,
+        }
+    }
+}
+
+impl ScrollAcceleration {
+    // Rows to move after the key has been repeated `repeats` times
+    pub fn step(&self, repeats: usize) -> usize {
+        let idx = repeats / self.repeats_per_step.max(1);
+        self.steps
+            .get(idx)
+            .or(self.steps.last())
+            .copied()
+            .unwrap_or(1)
+            .max(1)
+    }
+}
+
+fn config_path() -> PathBuf {
+    PathBuf::from(shellexpand::tilde(CONFIG_PATH).to_string())
+}
+
+pub fn load_config() -> Result<ConfigFile, TVError> {
+    let path = config_path();
+    if !path.exists() {
+        return Ok(ConfigFile::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| TVError::LoadingFailed(format!("Invalid config file {path:?}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_files_by_pattern() {
+        assert!(glob_matches("exports/*.csv", "/home/me/exports/2024.csv"));
+        assert!(!glob_matches(
+            "exports/*.csv",
+            "/home/me/exports/old/2024.csv"
+        ));
+        assert!(glob_matches(
+            "exports/**/*.csv",
+            "/home/me/exports/old/2024.csv"
+        ));
+        assert!(glob_matches(
+            "exports/**/*.csv",
+            "/home/me/exports/2024.csv"
+        ));
+        assert!(!glob_matches("/exports/*.csv", "/home/me/exports/2024.csv"));
+        assert!(glob_matches("log_??.tsv", "/var/log_01.tsv"));
+
+        let config: ConfigFile = serde_json::from_str(
+            r#"{"files": {"b/*.csv": {"sort": "date desc"}, "*.csv": {"delimiter": ";"}}}"#,
+        )
+        .unwrap();
+        let (pattern, file) = file_config(&config.files, Path::new("/data/b/x.csv")).unwrap();
+        assert_eq!(pattern, "b/*.csv");
+        assert_eq!(file.sort_order(), Some(("date", false)));
+        assert!(serde_json::from_str::<ConfigFile>(r#"{"files": {"*": {"pin": []}}}"#).is_err());
+    }
+
+    #[test]
+    fn timing_settings() {
+        let config: ConfigFile =
+            serde_json::from_str(r#"{"watch_interval": 500, "stream_batch_rows": 10}"#).unwrap();
+        assert_eq!(config.watch_interval, 500);
+        assert_eq!(config.stream_batch_rows, 10);
+        assert_eq!(config.stream_interval, DEFAULT_STREAM_INTERVAL);
+        assert_eq!(config.event_poll_time, DEFAULT_EVENT_POLL_TIME);
+    }
+}
@@ -1,79 +1,334 @@
-use std::time::Duration;
-use tracing::trace;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, trace};
 
-use crate::domain::{Message, TVConfig, TVError};
+use crate::config::ScrollAcceleration;
+use crate::domain::{Event, InputMode, Message, TVConfig, TVError};
 use crate::model::Model;
-use ratatui::crossterm::event::{self, KeyCode, KeyModifiers};
+use ratatui::crossterm::event::{self, KeyCode, KeyEvent, KeyModifiers};
+
+// Keys of multi key sequences, what they do and their message. After the first keys of a sequence
+// the keys that can follow are shown in a popup.
+fn key_sequences() -> [(&'static str, &'static str, Message); 7] {
+    [
+        ("gg", "first row", Message::MoveBeginning),
+        ("gt", "next tab", Message::NextTab),
+        ("gT", "previous tab", Message::PreviousTab),
+        ("gm", "next bookmark", Message::NextBookmark),
+        ("gM", "previous bookmark", Message::PreviousBookmark),
+        ("gn", "next null", Message::NextNull),
+        ("gN", "previous null", Message::PreviousNull),
+    ]
+}
+
+// How long the input thread waits for terminal events before checking if it should pause
+const INPUT_POLL_TIME: Duration = Duration::from_millis(250);
 
 pub struct Controller {
     event_poll_time: usize,
+    events: Receiver<Event>,
+    sender: Sender<Event>, // Handed to background workers, so they can wake up the main loop
+    paused: Arc<AtomicBool>,
+    reading: Arc<Mutex<()>>, // Held by the input thread while it reads from the terminal
+    scroll_acceleration: ScrollAcceleration,
+    key_repeat: Cell<Option<(KeyCode, Instant, usize)>>, // Last movement key, when it was seen and how often it repeated
+    prefix: Cell<Option<char>>,                          // Key waiting for a register, `Q` or `@`
+    count: Cell<usize>,                                  // Count typed before a command, 0 if none
+    sequence: RefCell<String>,                           // Keys of a started key sequence
 }
 
 impl Controller {
     pub fn new(cfg: &TVConfig) -> Self {
+        let (sender, events) = mpsc::channel();
+        let paused = Arc::new(AtomicBool::new(false));
+        let reading = Arc::new(Mutex::new(()));
+        Self::spawn_input_thread(sender.clone(), paused.clone(), reading.clone());
         Self {
             event_poll_time: cfg.event_poll_time,
+            events,
+            sender,
+            paused,
+            reading,
+            scroll_acceleration: cfg.scroll_acceleration.clone(),
+            key_repeat: Cell::new(None),
+            prefix: Cell::new(None),
+            count: Cell::new(0),
+            sequence: RefCell::new(String::new()),
         }
     }
 
-    pub fn handle_event(&self, model: &Model) -> Result<Option<Message>, TVError> {
-        if event::poll(Duration::from_millis(self.event_poll_time as u64))? {
-            match event::read()? {
-                // Detect frame resize event
-                event::Event::Resize(width, height) => {
-                    trace!("Resized to {}x{}", width, height);
-                    return Ok(Some(Message::Resize(width as usize, height as usize)));
+    // Terminal events are read on their own thread, so the main loop can block until there is something to do
+    fn spawn_input_thread(sender: Sender<Event>, paused: Arc<AtomicBool>, reading: Arc<Mutex<()>>) {
+        thread::spawn(move || {
+            loop {
+                let guard = reading.lock().unwrap();
+                if paused.load(Ordering::SeqCst) {
+                    drop(guard);
+                    thread::sleep(INPUT_POLL_TIME);
+                    continue;
                 }
-                event::Event::Key(key) if key.kind == event::KeyEventKind::Press => {
-                    if model.raw_keyevents() {
-                        return Ok(Some(Message::RawKey(key)));
+                let event = match event::poll(INPUT_POLL_TIME) {
+                    Ok(true) => event::read(),
+                    Ok(false) => continue,
+                    Err(e) => Err(e),
+                };
+                drop(guard);
+                match event {
+                    Ok(event) => {
+                        if sender.send(Event::Terminal(event)).is_err() {
+                            break; // The controller is gone
+                        }
                     }
-                    return Ok(self.handle_key(key));
+                    Err(e) => error!("Reading terminal events failed: {e}"),
                 }
+            }
+        });
+    }
+
+    pub fn event_sender(&self) -> Sender<Event> {
+        self.sender.clone()
+    }
+
+    // Stop reading terminal input, e.g. while an external program owns the terminal
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        // Wait for a read that is in progress to finish
+        drop(self.reading.lock().unwrap());
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    // Blocks until there is an input event, a worker posted a wakeup, or the model has timed work to do.
+    // Returns None for wakeups and such ticks.
+    pub fn handle_event(&self, model: &Model) -> Result<Option<Message>, TVError> {
+        let timeout = model.tick_interval(Duration::from_millis(self.event_poll_time as u64));
+        let event = match self.events.recv_timeout(timeout) {
+            Ok(Event::Terminal(event)) => event,
+            Ok(Event::Wakeup) => return Ok(None),
+            Ok(Event::Remote(command, reply)) => {
+                return Ok(Some(Message::Remote(command, reply)));
+            }
+            Err(RecvTimeoutError::Timeout) => return Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(TVError::IoError(std::io::Error::other(
+                    "Input thread stopped",
+                )));
+            }
+        };
+        match event {
+            // Detect frame resize event
+            event::Event::Resize(width, height) => {
+                trace!("Resized to {}x{}", width, height);
+                return Ok(Some(Message::Resize(width as usize, height as usize)));
+            }
+            event::Event::Paste(text) if model.input_mode() != InputMode::Normal => {
+                return Ok(Some(Message::Paste(text)));
+            }
+            event::Event::Mouse(mouse) => match mouse.kind {
+                event::MouseEventKind::ScrollDown => return Ok(Some(Message::MoveDown(1))),
+                event::MouseEventKind::ScrollUp => return Ok(Some(Message::MoveUp(1))),
                 _ => {}
+            },
+            event::Event::Key(key) if key.kind == event::KeyEventKind::Press => {
+                return Ok(match model.input_mode() {
+                    InputMode::Normal
+                        if key.code == KeyCode::Char('Q') && model.recording_macro() =>
+                    {
+                        Some(Message::StopMacro)
+                    }
+                    InputMode::Normal => self.handle_key(key),
+                    mode => self.handle_input_key(mode, key),
+                });
             }
+            _ => {}
         }
         Ok(None)
     }
 
+    // Keys typed into the command line. Ctrl+c cancels like Esc, everything else is edited by the input line.
+    fn handle_input_key(&self, mode: InputMode, key: KeyEvent) -> Option<Message> {
+        let key = match (key.code, key.modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => KeyEvent::from(KeyCode::Esc),
+            _ => key,
+        };
+        trace!("{} mode: {key:?}", mode.label());
+        Some(Message::RawKey(key))
+    }
+
+    // Rows to move for a movement key, growing while the key is held down and repeats quickly
+    fn scroll_step(&self, code: KeyCode) -> usize {
+        let now = Instant::now();
+        let interval = Duration::from_millis(self.scroll_acceleration.repeat_interval_ms);
+        let repeats = match self.key_repeat.get() {
+            Some((last_code, last, repeats)) if last_code == code && now - last <= interval => {
+                repeats + 1
+            }
+            _ => 0,
+        };
+        self.key_repeat.set(Some((code, now, repeats)));
+        self.scroll_acceleration.step(repeats)
+    }
+
+    // Counts, and the registers following `Q` and `@`. Returns None if the key is no part of such a sequence.
+    fn handle_prefix(&self, key: event::KeyEvent) -> Option<Option<Message>> {
+        if let Some(prefix) = self.prefix.take() {
+            let count = self.count.replace(0).max(1);
+            return Some(match (prefix, key.code) {
+                ('Q', KeyCode::Char(register @ 'a'..='z')) => Some(Message::RecordMacro(register)),
+                ('@', KeyCode::Char(register @ 'a'..='z')) => {
+                    Some(Message::ReplayMacro(register, count))
+                }
+                _ => None,
+            });
+        }
+        if let Some(message) = self.handle_sequence(key) {
+            return Some(message);
+        }
+        match (key.code, key.modifiers) {
+            // A leading 0 selects the first column
+            (KeyCode::Char(digit @ '0'..='9'), KeyModifiers::NONE)
+                if digit != '0' || self.count.get() > 0 =>
+            {
+                let count = self.count.get() * 10 + digit.to_digit(10).unwrap() as usize;
+                self.count.set(count);
+                Some(None)
+            }
+            (KeyCode::Char(prefix @ ('Q' | '@')), _) => {
+                self.prefix.set(Some(prefix));
+                Some(None)
+            }
+            _ => {
+                self.count.set(0);
+                None
+            }
+        }
+    }
+
+    // Keys of multi key sequences like `gg`. Returns None if the key neither starts nor continues one.
+    fn handle_sequence(&self, key: event::KeyEvent) -> Option<Option<Message>> {
+        let started = !self.sequence.borrow().is_empty();
+        let c = match (key.code, key.modifiers) {
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => c,
+            // Any other key, e.g. Esc, cancels a started sequence
+            _ if started => {
+                self.sequence.borrow_mut().clear();
+                return Some(Some(Message::KeyHints(Vec::new())));
+            }
+            _ => return None,
+        };
+        let mut keys = self.sequence.take();
+        keys.push(c);
+        let matching: Vec<_> = key_sequences()
+            .into_iter()
+            .filter(|(sequence, ..)| sequence.starts_with(&keys))
+            .collect();
+        if matching.is_empty() {
+            return started.then_some(Some(Message::KeyHints(Vec::new())));
+        }
+        self.count.set(0);
+        if let Some((_, _, message)) = matching.iter().find(|(sequence, ..)| *sequence == keys) {
+            return Some(Some(message.clone()));
+        }
+        let hints = matching
+            .iter()
+            .map(|(sequence, description, _)| {
+                (sequence[keys.len()..].to_string(), description.to_string())
+            })
+            .collect();
+        self.sequence.replace(keys);
+        Some(Some(Message::KeyHints(hints)))
+    }
+
     fn handle_key(&self, key: event::KeyEvent) -> Option<Message> {
+        if let Some(message) = self.handle_prefix(key) {
+            return message;
+        }
         let message = match (key.code, key.modifiers) {
             (KeyCode::Char('q'), KeyModifiers::NONE) => Some(Message::Quit),
             (KeyCode::Char('h'), KeyModifiers::NONE) => Some(Message::MoveLeft),
             (KeyCode::Left, KeyModifiers::NONE) => Some(Message::MoveLeft),
-            (KeyCode::Char('j'), KeyModifiers::NONE) => Some(Message::MoveDown),
-            (KeyCode::Down, KeyModifiers::NONE) => Some(Message::MoveDown),
+            (KeyCode::Char('j'), KeyModifiers::NONE) => {
+                Some(Message::MoveDown(self.scroll_step(key.code)))
+            }
+            (KeyCode::Down, KeyModifiers::NONE) => {
+                Some(Message::MoveDown(self.scroll_step(key.code)))
+            }
             (KeyCode::Char('J'), KeyModifiers::SHIFT) => Some(Message::MovePageDown),
             (KeyCode::Down, KeyModifiers::SHIFT) => Some(Message::MovePageDown),
             (KeyCode::PageDown, KeyModifiers::NONE) => Some(Message::MovePageDown),
-            (KeyCode::Char('k'), KeyModifiers::NONE) => Some(Message::MoveUp),
-            (KeyCode::Up, KeyModifiers::NONE) => Some(Message::MoveUp),
+            (KeyCode::Char('k'), KeyModifiers::NONE) => {
+                Some(Message::MoveUp(self.scroll_step(key.code)))
+            }
+            (KeyCode::Up, KeyModifiers::NONE) => Some(Message::MoveUp(self.scroll_step(key.code))),
             (KeyCode::Char('K'), KeyModifiers::SHIFT) => Some(Message::MovePageUp),
             (KeyCode::Up, KeyModifiers::SHIFT) => Some(Message::MovePageUp),
             (KeyCode::PageUp, KeyModifiers::NONE) => Some(Message::MovePageUp),
             (KeyCode::Char('l'), KeyModifiers::NONE) => Some(Message::MoveRight),
+            (KeyCode::Char('H'), KeyModifiers::SHIFT) => Some(Message::MoveColumnLeft),
+            (KeyCode::Char('L'), KeyModifiers::SHIFT) => Some(Message::MoveColumnRight),
             (KeyCode::Right, KeyModifiers::NONE) => Some(Message::MoveRight),
             (KeyCode::Char('G'), KeyModifiers::SHIFT) => Some(Message::MoveEnd),
             (KeyCode::End, KeyModifiers::CONTROL) => Some(Message::MoveEnd),
             (KeyCode::Down, KeyModifiers::CONTROL) => Some(Message::MoveEnd),
-            (KeyCode::Char('g'), KeyModifiers::NONE) => Some(Message::MoveBeginning),
             (KeyCode::Home, KeyModifiers::CONTROL) => Some(Message::MoveBeginning),
             (KeyCode::Up, KeyModifiers::CONTROL) => Some(Message::MoveBeginning),
             (KeyCode::Char('v'), KeyModifiers::NONE) => Some(Message::ToggleIndex),
             (KeyCode::Tab, KeyModifiers::NONE) => Some(Message::ToggleColumnState),
             (KeyCode::BackTab, KeyModifiers::SHIFT) => Some(Message::ToggleExpandColumnState),
+            (KeyCode::Char('z'), KeyModifiers::NONE) => Some(Message::ToggleColumnGroup),
+            (KeyCode::Char('Z'), KeyModifiers::SHIFT) => Some(Message::ToggleAllColumnGroups),
+            (KeyCode::Char('-'), KeyModifiers::NONE) => Some(Message::ToggleRowGroup),
+            (KeyCode::Char('+'), KeyModifiers::NONE) => Some(Message::ToggleAllRowGroups),
             (KeyCode::Char('y'), KeyModifiers::NONE) => Some(Message::CopyCell),
             (KeyCode::Char('Y'), KeyModifiers::SHIFT) => Some(Message::CopyRow),
+            (KeyCode::Char('o'), KeyModifiers::NONE) => Some(Message::OpenInPager),
+            (KeyCode::Char('e'), KeyModifiers::NONE) => Some(Message::OpenInEditor),
+            (KeyCode::Char('w'), KeyModifiers::NONE) => Some(Message::OpenUrl),
+            (KeyCode::Char('m'), KeyModifiers::NONE) => Some(Message::ToggleBookmark),
+            (KeyCode::Char(' '), KeyModifiers::NONE) => Some(Message::ToggleMark),
+            (KeyCode::Char('V'), KeyModifiers::SHIFT) => Some(Message::VisualSelect),
+            (KeyCode::Char('i'), KeyModifiers::NONE) => Some(Message::EditCell),
+            (KeyCode::Char('\''), KeyModifiers::NONE) => Some(Message::NextBookmark),
+            (KeyCode::Char('`'), KeyModifiers::NONE) => Some(Message::PreviousBookmark),
+            (KeyCode::Char(')'), KeyModifiers::NONE) => Some(Message::NextNull),
+            (KeyCode::Char('('), KeyModifiers::NONE) => Some(Message::PreviousNull),
             (KeyCode::Char('?'), KeyModifiers::NONE) => Some(Message::Help),
             (KeyCode::Char(':'), KeyModifiers::NONE) => Some(Message::EnterCommand),
             (KeyCode::Char('/'), KeyModifiers::NONE) => Some(Message::SearchInColumn),
             (KeyCode::Char('7'), KeyModifiers::CONTROL) => Some(Message::Search),
             (KeyCode::Char('f'), KeyModifiers::NONE) => Some(Message::Filter),
             (KeyCode::Char('#'), KeyModifiers::NONE) => Some(Message::Histogram),
+            (KeyCode::Char('%'), KeyModifiers::NONE) => Some(Message::Percentiles),
             (KeyCode::Char('n'), KeyModifiers::NONE) => Some(Message::SearchNext),
             (KeyCode::Char('p'), KeyModifiers::NONE) => Some(Message::SearchPrev),
             (KeyCode::Char('['), KeyModifiers::NONE) => Some(Message::SortAscending),
             (KeyCode::Char(']'), KeyModifiers::NONE) => Some(Message::SortDescending),
+            (KeyCode::Char('b'), KeyModifiers::NONE) => Some(Message::CycleNumberBase),
+            (KeyCode::Char('B'), KeyModifiers::SHIFT) => Some(Message::ShowBitfield),
+            (KeyCode::Char('c'), KeyModifiers::NONE) => Some(Message::Chart),
+            (KeyCode::Char('t'), KeyModifiers::NONE) => Some(Message::Plot),
+            (KeyCode::Char('u'), KeyModifiers::NONE) => Some(Message::ToggleUuidCompaction),
+            (KeyCode::Char('F'), KeyModifiers::SHIFT) => Some(Message::ToggleFillDown),
+            (KeyCode::Char('T'), KeyModifiers::SHIFT) => Some(Message::ToggleTopValues),
+            (KeyCode::Char('S'), KeyModifiers::SHIFT) => Some(Message::ToggleTotals),
+            (KeyCode::Char('C'), KeyModifiers::SHIFT) => Some(Message::FilterColumns),
+            (KeyCode::Char('r'), KeyModifiers::NONE) => Some(Message::RenameColumn),
+            (KeyCode::Char('x'), KeyModifiers::NONE) => Some(Message::ToggleCrosshair),
+            (KeyCode::Char('P'), KeyModifiers::SHIFT) => Some(Message::ToggleProfile),
+            (KeyCode::Char('R'), KeyModifiers::SHIFT) => Some(Message::Reload),
+            (KeyCode::Char('>'), KeyModifiers::NONE) => Some(Message::NextTab),
+            (KeyCode::PageDown, KeyModifiers::CONTROL) => Some(Message::NextTab),
+            (KeyCode::Char('<'), KeyModifiers::NONE) => Some(Message::PreviousTab),
+            (KeyCode::PageUp, KeyModifiers::CONTROL) => Some(Message::PreviousTab),
+            (KeyCode::Char('}'), KeyModifiers::NONE) => Some(Message::NextTable),
+            (KeyCode::Char('{'), KeyModifiers::NONE) => Some(Message::PreviousTable),
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => Some(Message::SwitchPane),
             (KeyCode::Char('0'), KeyModifiers::NONE) => Some(Message::MoveToFirstColumn),
             (KeyCode::Left, KeyModifiers::SHIFT) => Some(Message::MoveToFirstColumn),
             (KeyCode::Home, KeyModifiers::NONE) => Some(Message::MoveToFirstColumn),
@@ -88,3 +343,53 @@ impl Controller {
         message
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Without the input thread, which would read from the terminal
+    fn controller() -> Controller {
+        let (sender, events) = mpsc::channel();
+        Controller {
+            event_poll_time: 0,
+            events,
+            sender,
+            paused: Arc::new(AtomicBool::new(false)),
+            reading: Arc::new(Mutex::new(())),
+            scroll_acceleration: ScrollAcceleration::default(),
+            key_repeat: Cell::new(None),
+            prefix: Cell::new(None),
+            count: Cell::new(0),
+            sequence: RefCell::new(String::new()),
+        }
+    }
+
+    #[test]
+    fn multi_key_sequences() {
+        let controller = controller();
+        let key = |c: char| controller.handle_key(KeyEvent::from(KeyCode::Char(c)));
+
+        let Some(Message::KeyHints(hints)) = key('g') else {
+            panic!("no key hints after g");
+        };
+        assert_eq!(hints[0], ("g".to_string(), "first row".to_string()));
+        assert_eq!(hints.len(), key_sequences().len());
+        assert_eq!(key('g'), Some(Message::MoveBeginning));
+        assert_eq!(key('j'), Some(Message::MoveDown(1)));
+
+        key('g');
+        assert_eq!(
+            controller.handle_key(KeyEvent::new(KeyCode::Char('T'), KeyModifiers::SHIFT)),
+            Some(Message::PreviousTab)
+        );
+        key('g');
+        assert_eq!(key('x'), Some(Message::KeyHints(Vec::new())));
+        key('g');
+        assert_eq!(
+            controller.handle_key(KeyEvent::from(KeyCode::Esc)),
+            Some(Message::KeyHints(Vec::new()))
+        );
+        assert_eq!(key('q'), Some(Message::Quit));
+    }
+}
@@ -1,6 +1,16 @@
 use polars::error::PolarsError;
 use ratatui::crossterm::event;
+use serde::Deserialize;
 use std::io::Error;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use crate::capabilities::Capabilities;
+use crate::config::{
+    DEFAULT_BACKGROUND_SEARCH_ROWS, DEFAULT_EVENT_POLL_TIME, DEFAULT_FILTER_PREVIEW_DELAY,
+    DEFAULT_HISTOGRAM_BINS, DEFAULT_STREAM_BATCH_ROWS, DEFAULT_STREAM_INTERVAL,
+    DEFAULT_WATCH_INTERVAL, FileConfig, ScrollAcceleration,
+};
 
 // This is a custom error type that we will be using in `parse_pos_nonzero()`.
 #[derive(Debug)]
@@ -19,6 +29,7 @@ pub enum CMDMode {
     SearchTable,
     SearchInColumn,
     FilterByColumn,
+    EditCell,
     Raw,
 }
 
@@ -28,11 +39,43 @@ impl CMDMode {
             CMDMode::SearchTable => "Search table:",
             CMDMode::SearchInColumn => "Search column:",
             CMDMode::FilterByColumn => "Filter column:",
+            CMDMode::EditCell => "Edit cell:",
             CMDMode::Raw => "CMD:",
         }
     }
 }
 
+// How keys are handled, shown in the status bar. Esc always returns to Normal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputMode {
+    Normal,
+    Command,
+    Search,
+    Edit,
+}
+
+impl InputMode {
+    pub fn from_cmd_mode(mode: Option<CMDMode>) -> Self {
+        match mode {
+            None => InputMode::Normal,
+            Some(CMDMode::Raw) => InputMode::Command,
+            Some(CMDMode::SearchTable | CMDMode::SearchInColumn | CMDMode::FilterByColumn) => {
+                InputMode::Search
+            }
+            Some(CMDMode::EditCell) => InputMode::Edit,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputMode::Normal => "NORMAL",
+            InputMode::Command => "COMMAND",
+            InputMode::Search => "SEARCH",
+            InputMode::Edit => "EDIT",
+        }
+    }
+}
+
 impl From<Error> for TVError {
     fn from(err: Error) -> Self {
         TVError::IoError(err)
@@ -47,37 +90,141 @@ impl From<PolarsError> for TVError {
 
 #[derive(Debug, Clone)]
 pub struct TVConfig {
-    pub event_poll_time: usize,
+    pub event_poll_time: usize, // Milliseconds the controller waits for input
+    pub filter_preview_delay: usize, // Milliseconds of typing pause before filter matches are counted
+    pub watch_interval: usize,       // Milliseconds between checks of a watched file
+    pub stream_interval: usize,      // Milliseconds after which rows read with --stream are shown
+    pub stream_batch_rows: usize, // Rows read with --stream are shown at the latest once there are this many
+    pub background_search_rows: usize, // Searches over more rows run on a worker thread
     pub max_column_width: usize,
     pub column_margin: usize,
     pub light_colors: bool,
+    pub render_patterns: Vec<String>, // `<regex>=<color>` specifications of additional cell renderers
+    pub capabilities: Capabilities,
+    pub url_template: Option<String>, // Url cells without a url are opened with, `{}` is replaced by the value
+    pub stripe: usize, // Rows per group of alternating background color, 0 disables striping
+    pub profile: bool, // Show the profiling overlay from the start
+    pub scroll_acceleration: ScrollAcceleration,
+    pub lenient_csv: bool, // Pad or skip malformed csv rows instead of failing to load
+    pub histogram_bins: usize,
+    pub decimal_comma: bool, // Text cast to numbers uses a comma as decimal separator
+    pub no_color: bool,      // Only use bold, reversed etc., following the NO_COLOR convention
+    pub ascii: bool,         // Only draw ascii characters, NULL instead of ∅
+    pub stats_cache: bool, // Reuse column stats of files computed before, see stats_cache in the config
+    pub max_rows: usize,   // Larger files only get their first rows loaded, 0 loads all rows
+    pub sample_large_files: bool, // Files over max_rows get a random sample of their rows loaded instead
+    pub files: Vec<(String, FileConfig)>, // Settings of files matching a glob pattern, see files in the config
 }
 
 impl TVConfig {
     pub fn default() -> TVConfig {
         TVConfig {
-            event_poll_time: 100,
+            event_poll_time: DEFAULT_EVENT_POLL_TIME,
+            filter_preview_delay: DEFAULT_FILTER_PREVIEW_DELAY,
+            watch_interval: DEFAULT_WATCH_INTERVAL,
+            stream_interval: DEFAULT_STREAM_INTERVAL,
+            stream_batch_rows: DEFAULT_STREAM_BATCH_ROWS,
+            background_search_rows: DEFAULT_BACKGROUND_SEARCH_ROWS,
             max_column_width: 25,
             column_margin: 1,
             light_colors: true,
+            render_patterns: Vec::new(),
+            capabilities: Capabilities::default(),
+            url_template: None,
+            stripe: 1,
+            profile: false,
+            scroll_acceleration: ScrollAcceleration::default(),
+            lenient_csv: false,
+            histogram_bins: DEFAULT_HISTOGRAM_BINS,
+            decimal_comma: false,
+            no_color: false,
+            ascii: false,
+            stats_cache: false,
+            max_rows: 0,
+            sample_large_files: false,
+            files: Vec::new(),
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
+// What the main loop waits for: terminal input, or a background worker having finished its work
+#[derive(Debug)]
+pub enum Event {
+    Terminal(event::Event),
+    Wakeup, // The model has results to pick up, e.g. a histogram counted in the background
+    Remote(RemoteCommand, RemoteReply), // Received on the control socket, see --listen
+}
+
+// Json commands of the control socket, e.g. `{"cmd": "goto", "row": 42}`
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase", deny_unknown_fields)]
+pub enum RemoteCommand {
+    Goto {
+        row: usize, // Starting at 1, as shown in the index
+        column: Option<String>,
+    },
+    Filter {
+        column: Option<String>, // The selected one without
+        term: String,
+    },
+    Load {
+        file: PathBuf,
+    },
+    Command {
+        input: String, // Like typed after `:`
+    },
+}
+
+// Where the outcome of a remote command goes, back to the client that sent it.
+// Replaying a recorded command has nobody to answer.
+#[derive(Clone, Default)]
+pub struct RemoteReply(Option<Sender<Result<(), String>>>);
+
+impl RemoteReply {
+    pub fn new(sender: Sender<Result<(), String>>) -> Self {
+        RemoteReply(Some(sender))
+    }
+
+    pub fn send(&self, result: Result<(), String>) {
+        if let Some(sender) = self.0.as_ref() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+// Messages compare by what they do, not by who waits for the answer
+impl PartialEq for RemoteReply {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl std::fmt::Debug for RemoteReply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RemoteReply")
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum Message {
-    MoveUp,
+    MoveUp(usize),
     MovePageUp,
-    MoveDown,
+    MoveDown(usize),
     MovePageDown,
     MoveLeft,
     MoveRight,
     MoveEnd,
     MoveToFirstColumn,
     MoveToLastColumn,
+    MoveColumnLeft,
+    MoveColumnRight,
     MoveBeginning,
     ToggleColumnState,
     ToggleExpandColumnState,
+    ToggleColumnGroup,
+    ToggleAllColumnGroups,
+    ToggleRowGroup,
+    ToggleAllRowGroups,
     ToggleIndex,
     Resize(usize, usize),
     CopyCell,
@@ -96,6 +243,42 @@ pub enum Message {
     RawKey(event::KeyEvent),
     SortAscending,
     SortDescending,
+    CycleNumberBase,
+    ShowBitfield,
+    Percentiles,
+    NextTab,
+    PreviousTab,
+    NextTable,
+    PreviousTable,
+    SwitchPane,
+    ToggleUuidCompaction,
+    ToggleFillDown,
+    ToggleTopValues,
+    ToggleTotals,
+    FilterColumns,
+    RenameColumn,
+    ToggleCrosshair,
+    ToggleProfile,
+    OpenInPager,
+    OpenInEditor,
+    OpenUrl,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    NextNull,
+    PreviousNull,
+    ToggleMark,
+    VisualSelect,
+    Reload,
+    EditCell,
+    Paste(String),
+    Chart,
+    Plot,
+    RecordMacro(char),
+    StopMacro,
+    ReplayMacro(char, usize),        // Register and how often to replay it
+    KeyHints(Vec<(String, String)>), // Continuations of a started key sequence, none once it ended
+    Remote(RemoteCommand, RemoteReply),
 }
 
 pub const HELP_TEXT: &str = "
@@ -107,32 +290,86 @@ pub const HELP_TEXT: &str = "
     h           : Move selection to the left.
     j/Left      : Move selection to the down.
     k/Up        : Move selection to the up.
+                  Holding the key speeds up scrolling, see scroll_acceleration
+                  in ~/.config/tv/config.json
     l/Right     : Move selection to the right.
     J           : Jump page down
     K           : Jump page up
-    g/Ctrl+Home : Jump to the first row
+    gg/Ctrl+Home: Jump to the first row
     G/Ctrl+End  : Jump to the last row
     0/Home      : Jump to the first column
     $/End       : Jump to the last column
+    H/L         : Move the selected column to the left/right
     y           : Copy cell value
     Y           : Copy row
+    o           : Open cell in $PAGER
+    e           : Open cell in $EDITOR
+    w           : Open url in the cell (or --url-template) in the browser
+    m           : Bookmark/Unbookmark the selected row
+    '/`         : Jump to the next/previous bookmarked row
+    )/(         : Jump to the next/previous null in the current column
+    Space       : Select/Unselect row for bulk actions (:sel)
+    V           : Start a visual selection of rows, y copies them as tsv with
+                  headers, V again adds them to the selection, Esc cancels.
+    i           : Edit the selected cell (write back with :export)
     Tab         : Expand/Collapse column
+    z/Z         : Fold the columns sharing the prefix of the current one (e.g.
+                  user.id, user.name) into a summary column / all such groups
+    -/+         : Fold the section of the curser row / all sections (see :group)
     /           : Search in current column
     Ctrl+/      : Search in complete table
     n           : Jump to next search result
     p           : Jump to previous search result
-    /           : Filter table on matches in the current column
-    #           : Show histogram of current column
+    f           : Filter table on matches in the current column
+                  `in 10.0.0.0/8` or `<column> in <network>` filters
+                  ip addresses within a network.
+                  `10..20` filters numbers from 10 up to 20, `10..=20`
+                  includes 20.
+    C           : Only show the columns whose name matches a pattern (:cols)
+    r           : Rename the current column (:rename)
+    S           : Show a totals row below the table, the sum of numeric columns
+                  and the count of values of other columns over the rows shown
+    #           : Show histogram of current column, numeric columns are
+                  grouped into bins (:bins <n>, histogram_bins in the config)
     [           : Sort in ascending order
     ]           : Sort in descending order
+    b           : Cycle integer display base (dec/hex/bin/oct)
+    B           : Show the bits set in the selected integer cell
+    %           : Show the percentiles (p1 to p99) of the selected numeric column
+    c           : Select the category column of a chart, press c
+                  again on a numeric column to chart its aggregates
+                  per category, or on the same column to chart counts.
+    t           : Plot the numeric column as line over the first datetime
+                  column, or over the row order without one
+    u           : Shorten/expand uuid columns (full value in record view)
+    F           : Show the value above in empty cells of the current column, dimmed
+    T           : Show/hide the most frequent values of the current column below
+                  the table, following the curser and filters
+    x           : Toggle crosshair mode, dimming all but the curser row and column
+    P           : Toggle the profiling overlay (load, collect, draw times)
+    R           : Reload the file, keeping filters, sort and the curser row
+    g           : Start a key sequence, a popup lists the keys continuing it:
+                  gg first row, gt/gT next/previous tab, gm/gM next/previous
+                  bookmark, gn/gN next/previous null. Esc cancels.
+    Q<a-z>      : Record the following keys into a register, Q stops recording
+    [N]@<a-z>   : Replay the keys recorded in a register (N times)
+    >/Ctrl+PgDn : Switch to the next tab (e.g. workbook sheet)
+    </Ctrl+PgUp : Switch to the previous tab
+    }/{         : Switch to the next/previous table opened with :open
+    Ctrl+w      : Switch the focus between the panes of a split (:split), and
+                  the file sidebar when browsing a directory (tv <dir>). In the
+                  sidebar j/k select a file, Enter loads it, Esc leaves it.
 
 
                 == Record View ==
     ESC         : Return to Table view
+    o/e         : Open value in $PAGER/$EDITOR
+    w           : Open url in the value in the browser
     h/Left      : Show previous row record.
     j/Down      : Move selection to the down.
     k/Up        : Move selection to the up.
     l/Right     : Show next row record.
+    Enter       : Show selected json value formatted.
 
                 == Histogram View ==
     ESC         : Return to Table view
@@ -141,6 +378,113 @@ pub const HELP_TEXT: &str = "
     k/Up        : Move selection to the up.
     ENTER       : Filter table for selected value
 
+                == Json View ==
+    ESC         : Return to Record view
+    j/k         : Move selection down/up
+    Enter       : Fold/Unfold selected object or array
+    h/l         : Fold/Unfold selected object or array
+    y           : Copy formatted json
+
+                == Chart View ==
+    ESC         : Return to Table view
+    h/l         : Cycle aggregation (count/sum/mean)
+
+                == Plot View ==
+    ESC         : Return to Table view
+    k/j         : Zoom in/out of the time axis
+    h/l         : Move along the time axis
+
+                == Command Line ==
+    Esc/Ctrl+c  : Cancel the input and return to NORMAL mode
+    Enter       : Run the command, search, filter or cell edit
+
+                == Commands ==
+    :melt [c1,c2,..]    : Unpivot columns into variable/value rows.
+                          Without columns, all but the selected one.
+    :groupby [c1,c2,..] [sum(c3), mean(c4), count()]
+                        : Aggregate rows per group of the key columns, or of the
+                          selected column. Functions: count, sum, mean, median,
+                          min, max, std, nunique, first, last.
+    :dups [c1,c2,..]    : Highlight duplicate rows, keyed by the given
+                          columns or by all columns.
+    :fdups [c1,c2,..]   : Filter table to duplicate rows.
+    :nohl               : Clear row highlighting.
+    :relnum             : Toggle showing the distance of rows to the curser row in the
+                          index, like vim's relativenumber, e.g. to read off 17j.
+    :min / :max         : Move the curser to the row with the smallest/largest value
+                          of the numeric column, keeping the order of the rows.
+    :join <file> on <c1,..> [left|inner]
+                        : Join another file into the current view.
+                          Its columns are prefixed with its file name.
+    :view save <name>   : Save filters, sort and collapsed/expanded columns.
+    :view load <name>   : Re-apply a saved view, also on other files.
+    :view delete <name> : Delete a saved view.
+    :view list          : Show all saved views.
+    :marks              : List bookmarked rows.
+    :export-marks <file>: Write bookmarked rows to a csv/parquet/json file.
+    :sel copy [csv]     : Copy the selected rows as tsv, or csv, with headers.
+    :sel export <file>  : Write the selected rows to a csv/parquet file.
+    :sel delete         : Remove the selected rows from the view.
+    :sel stats          : Show sum/mean/min/max of the selected rows.
+    :sel clear          : Clear the row selection.
+    :each <cmd>         : Run a shell command per selected row, or for the current
+                          row, after confirming. {column} is replaced by the value of
+                          the row, {} by the selected column, e.g. :each jobs retry {id}
+    :eachlog            : Show the output of the commands run by :each in the pager.
+    :s/foo/bar/[gi]     : Replace in the current column of the view, after
+                          confirming the number of changed cells.
+    :%s/foo/bar/[gi]    : Replace in all columns of the view.
+    :export <file>      : Write the current view to a csv/parquet/json/ndjson/xlsx
+                          file, or to a styled html table for sharing (e.g. report.html).
+                          --original-names writes repeated column names of the
+                          loaded csv file without the suffix they got, :w keeps them too.
+    :w/:write <file>    : Same as :export.
+    :append             : Insert an empty row after the curser.
+    :delete             : Delete the selected rows, or the current row.
+    :mvcol <position>   : Move the selected column to a position, starting at 1.
+    :cast [col] as <dtype>
+                        : Re-parse the column, or the selected one, as i64, f64,
+                          bool, str, date or datetime, e.g. :cast ts as
+                          datetime('%Y-%m-%d'). Values that can not be parsed are
+                          empty, their text is shown crossed out.
+                          Numbers take their decimal separator, e.g. f64(',')
+                          for 1.234,56. --decimal-comma makes it the default.
+    :unparsed           : Filter to the rows with cells a cast could not parse, in
+                          the selected column or in any column.
+    :diffcols <a>[,<b>] : Highlight the cells where two columns differ, or a column
+                          and the selected one, e.g. :diffcols expected,actual.
+    :fdiffcols <a>[,<b>]: Filter to the rows where the columns differ.
+    :sample <n> [seed]  : Show n random rows of the view, in their order. Esc shows
+                          all rows again, the seed draws the same rows again.
+    :shuffle [seed]     : Show the rows of the view in random order.
+    :cols [pattern]     : Only show the columns whose name matches the regex,
+                          ignoring case, e.g. :cols price|qty. All without one.
+    :rename <name>      : Rename the selected column. Files without header row get
+                          the names c1, c2, ..., their new names are remembered.
+    :group [column]     : Show the rows in sections of equal values of the column,
+                          or the selected one, headed by the value and row count.
+                          - folds the section of the curser row, + all sections,
+                          Esc shows the rows ungrouped.
+    :open <file>        : Load another file as additional table. Tab completes the
+                          path, or finds files below the working directory whose
+                          path fuzzily matches, repeated Tabs cycle the matches.
+    :split [file]       : Show a file, or the current one again, next to this table.
+    :only               : Close the split, only showing the focused table.
+    :scrollbind [columns]
+                        : Toggle scrolling the panes of a split together, with
+                          their columns too if given.
+    :bins <n>           : Group numeric histograms into n bins, 0 counts every value.
+    :messages           : List the warnings and errors shown so far.
+    :malformed          : List the csv lines padded or skipped by --lenient.
+    :header             : Reload a csv file, flipping if the first row is the header.
+    :reload [key]       : Reload the file and browse the rows added, removed or
+                          changed since it was loaded, Esc shows all rows. Rows
+                          are matched by the key column, or the first if unique.
+    :watch [key]        : Toggle reloading the file like :reload once it changes.
+    :w                  : Write edits back to the loaded csv/parquet file.
+    :q / :wq            : Quit, asking about unsaved edits / write and quit.
+    :q!                 : Quit and discard unsaved edits.
+
 
     Question? Write to manuel.pasieka@protonmail.ch
 ";
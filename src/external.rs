@@ -0,0 +1,98 @@
+use std::env;
+use std::fs;
+use std::io::{Write, stdout};
+use std::process::{Command, Stdio};
+
+use ratatui::crossterm::{
+    execute,
+    terminal::{EnterAlternateScreen, enable_raw_mode},
+};
+use tracing::info;
+
+use crate::domain::TVError;
+
+const DEFAULT_PAGER: &str = "less";
+const DEFAULT_EDITOR: &str = "vi";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalProgram {
+    Pager,
+    Editor,
+}
+
+// Suspend the tui, show the content in the users pager or editor and resume the tui afterwards.
+// The caller has to redraw the whole terminal afterwards.
+pub fn open(program: ExternalProgram, content: &str) -> Result<(), TVError> {
+    ratatui::restore();
+    let result = match program {
+        ExternalProgram::Pager => run_pager(content),
+        ExternalProgram::Editor => run_editor(content),
+    };
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    result
+}
+
+// Pipe the content into $PAGER
+fn run_pager(content: &str) -> Result<(), TVError> {
+    let pager = env::var("PAGER").unwrap_or(DEFAULT_PAGER.to_string());
+    info!("Opening cell in pager \"{pager}\"");
+
+    // Run through the shell, so that e.g. PAGER="less -S" works
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager might quit before reading everything, ignore the broken pipe
+        let _ = stdin.write_all(content.as_bytes());
+    }
+    check_exit(&pager, child.wait()?)
+}
+
+// Open the content as temporary file in $VISUAL or $EDITOR
+fn run_editor(content: &str) -> Result<(), TVError> {
+    let editor = env::var("VISUAL")
+        .or(env::var("EDITOR"))
+        .unwrap_or(DEFAULT_EDITOR.to_string());
+    info!("Opening cell in editor \"{editor}\"");
+
+    let path = env::temp_dir().join(format!("tv-cell-{}.txt", std::process::id()));
+    fs::write(&path, content)?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    let _ = fs::remove_file(&path);
+    check_exit(&editor, status?)
+}
+
+// Open the url in the default browser, without waiting for it
+pub fn open_url(url: &str) -> Result<(), TVError> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    info!("Opening url \"{url}\" with {opener}");
+    Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+fn check_exit(program: &str, status: std::process::ExitStatus) -> Result<(), TVError> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(TVError::LoadingFailed(format!(
+            "\"{program}\" exited with {status}"
+        )))
+    }
+}
@@ -0,0 +1,253 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use base64::prelude::*;
+use flate2::{Compression, Crc, write::ZlibEncoder};
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+
+use crate::capabilities::Capabilities;
+
+// Pixels per terminal cell of the drawn images, the terminal scales them to the cells they cover
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 16;
+// Bytes of base64 per escape sequence of the kitty protocol
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+static NEXT_IMAGE_ID: AtomicU32 = AtomicU32::new(1);
+
+// Protocols of terminals to show images inline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    ITerm,
+}
+
+impl Protocol {
+    pub fn detect(capabilities: &Capabilities) -> Option<Self> {
+        if capabilities.kitty_graphics {
+            Some(Protocol::Kitty)
+        } else if capabilities.iterm_images {
+            Some(Protocol::ITerm)
+        } else {
+            None
+        }
+    }
+
+    // Escape sequence showing the image in the cells of the area, the cursor is kept in place
+    pub fn show(&self, image: &Image, area: Rect, id: u32) -> String {
+        let png = BASE64_STANDARD.encode(image.png());
+        let (cols, rows) = (area.width, area.height);
+        let mut sequence = format!("\x1b7\x1b[{};{}H", area.y + 1, area.x + 1);
+        match self {
+            Protocol::Kitty => {
+                let chunks: Vec<&[u8]> = png.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+                for (idx, chunk) in chunks.iter().enumerate() {
+                    let more = (idx + 1 < chunks.len()) as u8;
+                    let chunk = std::str::from_utf8(chunk).unwrap();
+                    if idx == 0 {
+                        sequence.push_str(&format!(
+                            "\x1b_Ga=T,f=100,q=2,C=1,i={id},c={cols},r={rows},m={more};{chunk}\x1b\\"
+                        ));
+                    } else {
+                        sequence.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+                    }
+                }
+            }
+            Protocol::ITerm => sequence.push_str(&format!(
+                "\x1b]1337;File=inline=1;width={cols};height={rows};preserveAspectRatio=0:{png}\x07"
+            )),
+        }
+        sequence.push_str("\x1b8");
+        sequence
+    }
+
+    // Escape sequence removing the image, None if the cells have to be drawn again instead
+    pub fn remove(&self, id: u32) -> Option<String> {
+        match self {
+            Protocol::Kitty => Some(format!("\x1b_Ga=d,d=I,i={id},q=2\x1b\\")),
+            Protocol::ITerm => None,
+        }
+    }
+}
+
+// Id of an image, images with the same id replace each other
+pub fn image_id() -> u32 {
+    NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Rgb pixels, row by row
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    // Image covering the cells of the area
+    pub fn for_area(area: Rect, background: Color) -> Self {
+        let (width, height) = (
+            area.width as usize * CELL_WIDTH,
+            area.height as usize * CELL_HEIGHT,
+        );
+        let pixels = rgb(background).repeat(width * height);
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let idx = (y as usize * self.width + x as usize) * 3;
+        self.pixels[idx..idx + 3].copy_from_slice(&color);
+    }
+
+    fn fill(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64), color: [u8; 3]) {
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    // Two pixels wide line, see Bresenham's line algorithm
+    fn line(&mut self, (mut x0, mut y0): (i64, i64), (x1, y1): (i64, i64), color: [u8; 3]) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let mut error = dx + dy;
+        loop {
+            self.fill((x0, y0), (x0 + 2, y0 + 2), color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            if 2 * error >= dy {
+                error += dy;
+                x0 += sx;
+            }
+            if 2 * error <= dx {
+                error += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    // Line through the points, scaled from the bounds to the image, with axes at the left and bottom
+    pub fn plot(&mut self, points: &[(f64, f64)], x: [f64; 2], y: [f64; 2], colors: [Color; 2]) {
+        let [line, axes] = colors.map(rgb);
+        let (width, height) = (self.width as i64, self.height as i64);
+        self.fill((0, 0), (2, height), axes);
+        self.fill((0, height - 2), (width, height), axes);
+        let scale = |v: f64, [min, max]: [f64; 2], size: i64| {
+            let ratio = if max > min {
+                (v - min) / (max - min)
+            } else {
+                0.5
+            };
+            (ratio.clamp(0.0, 1.0) * (size - 3) as f64).round() as i64
+        };
+        let pixels: Vec<(i64, i64)> = points
+            .iter()
+            .map(|&(px, py)| (scale(px, x, width) + 1, height - 3 - scale(py, y, height)))
+            .collect();
+        for pair in pixels.windows(2) {
+            self.line(pair[0], pair[1], line);
+        }
+        if let [single] = pixels.as_slice() {
+            self.line(*single, *single, line);
+        }
+    }
+
+    // One horizontal bar per row of cells, scaled to the largest value
+    pub fn bars(&mut self, values: &[f64], color: Color) {
+        let color = rgb(color);
+        let max = values.iter().fold(0.0, |max: f64, v| max.max(v.abs()));
+        let margin = (CELL_HEIGHT / 8) as i64;
+        for (row, value) in values.iter().enumerate() {
+            if max <= 0.0 {
+                break;
+            }
+            let length = (value.max(0.0) / max * self.width as f64).round() as i64;
+            let top = (row * CELL_HEIGHT) as i64;
+            self.fill(
+                (0, top + margin),
+                (length, top + CELL_HEIGHT as i64 - margin),
+                color,
+            );
+        }
+    }
+
+    // Uncompressed size is no problem for zlib, the images are mostly background
+    pub fn png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width * 3) {
+            raw.push(0); // No filter
+            raw.extend_from_slice(row);
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&raw).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let mut header = Vec::new();
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]); // 8 bit rgb, no interlacing
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, content) in [(b"IHDR", header), (b"IDAT", data), (b"IEND", Vec::new())] {
+            png.extend((content.len() as u32).to_be_bytes());
+            let mut crc = Crc::new();
+            crc.update(kind);
+            crc.update(&content);
+            png.extend(kind);
+            png.extend(content);
+            png.extend(crc.sum().to_be_bytes());
+        }
+        png
+    }
+}
+
+fn rgb(color: Color) -> [u8; 3] {
+    match color {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::White => [255, 255, 255],
+        _ => [0, 0, 0],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+
+    #[test]
+    fn encode_images() {
+        let area = Rect::new(2, 1, 3, 2);
+        let mut image = Image::for_area(area, Color::Rgb(1, 2, 3));
+        image.bars(&[2.0, 1.0], Color::White);
+        let second_row = 2 * 24 * 3;
+        assert_eq!(image.pixels[second_row..second_row + 3], [255, 255, 255]);
+        assert_eq!(image.pixels[image.pixels.len() - 3..], [1, 2, 3]);
+
+        let png = image.png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(png[16..24], [0, 0, 0, 24, 0, 0, 0, 32]);
+        let length = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&png[41..41 + length])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw.len(), (24 * 3 + 1) * 32);
+
+        let shown = Protocol::Kitty.show(&image, area, 7);
+        assert!(shown.starts_with("\x1b7\x1b[2;3H\x1b_Ga=T,f=100,q=2,C=1,i=7,c=3,r=2,m=0;"));
+        assert!(shown.ends_with("\x1b\\\x1b8"));
+        assert_eq!(Protocol::ITerm.remove(7), None);
+    }
+}
@@ -31,7 +31,7 @@ impl Inputter {
 
     pub fn set(&mut self, s: &str) {
         self.current_input = s.to_string();
-        self.curser_pos += s.to_ascii_lowercase().len();
+        self.curser_pos = s.chars().count();
     }
 
     pub fn get(&self) -> InputResult {
@@ -66,10 +66,11 @@ impl Inputter {
         self.get()
     }
 
+    // Remove the character before the curser, which is not the last one after moving left
     fn backspace(&mut self) -> InputResult {
         if self.curser_pos > 0 {
-            self.current_input.pop();
             self.curser_pos -= 1;
+            self.current_input.remove(self.getbytepos());
         }
         self.get()
     }
@@ -80,7 +81,8 @@ impl Inputter {
     }
 
     fn right(&mut self) -> InputResult {
-        if self.curser_pos < self.current_input.len() {
+        // The curser counts characters, multi byte ones included
+        if self.curser_pos < self.current_input.chars().count() {
             self.curser_pos += 1;
         }
         self.get()
@@ -94,6 +96,16 @@ impl Inputter {
         self.get()
     }
 
+    // Insert pasted text at the curser, line breaks are replaced by spaces
+    pub fn insert(&mut self, text: &str) -> InputResult {
+        for chr in text.chars() {
+            let chr = if chr == '\n' || chr == '\r' { ' ' } else { chr };
+            self.current_input.insert(self.getbytepos(), chr);
+            self.curser_pos += 1;
+        }
+        self.get()
+    }
+
     fn getbytepos(&self) -> usize {
         self.current_input
             .char_indices()
@@ -102,3 +114,74 @@ impl Inputter {
             .unwrap_or(self.current_input.len())
     }
 }
+
+// Keys typed by property tests, mostly characters including multi byte ones, and the keys editing the input
+#[cfg(test)]
+pub fn key_strategy() -> impl proptest::strategy::Strategy<Value = event::KeyEvent> {
+    use proptest::prelude::*;
+    let chars = ['a', 'Z', '0', ' ', '/', '.', 'ä', 'ß', '€', '日', '🦀', '∅'];
+    let code = prop_oneof![
+        6 => proptest::sample::select(chars.to_vec()).prop_map(KeyCode::Char),
+        3 => proptest::sample::select(vec![KeyCode::Backspace, KeyCode::Left, KeyCode::Right]),
+        1 => proptest::sample::select(vec![KeyCode::Enter, KeyCode::Esc, KeyCode::Tab]),
+    ];
+    code.prop_map(|code| event::KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use ratatui::crossterm::event::KeyEvent;
+
+    use super::*;
+
+    #[test]
+    fn edit_multi_byte_input() {
+        let mut inputter = Inputter::default();
+        for c in ['a', '€', '🦀'] {
+            inputter.read(KeyEvent::from(KeyCode::Char(c)));
+        }
+        inputter.read(KeyEvent::from(KeyCode::Left));
+        inputter.read(KeyEvent::from(KeyCode::Backspace));
+        let result = inputter.read(KeyEvent::from(KeyCode::Char('ß')));
+        assert_eq!(result.input, "aß🦀");
+        assert_eq!(result.curser_pos, 2);
+    }
+
+    proptest! {
+        // Random keys edit the input like a vector of characters with a curser would
+        #[test]
+        fn random_keys_keep_the_curser_in_the_input(keys in prop::collection::vec(key_strategy(), 0..100)) {
+            let mut inputter = Inputter::default();
+            let (mut chars, mut curser) = (Vec::new(), 0);
+            for key in keys {
+                let result = inputter.read(key);
+                match key.code {
+                    KeyCode::Char(c) => {
+                        chars.insert(curser, c);
+                        curser += 1;
+                    }
+                    KeyCode::Backspace if curser > 0 => {
+                        curser -= 1;
+                        chars.remove(curser);
+                    }
+                    KeyCode::Left => curser = curser.saturating_sub(1),
+                    KeyCode::Right => curser = (curser + 1).min(chars.len()),
+                    KeyCode::Esc => {
+                        chars.clear();
+                        curser = 0;
+                    }
+                    _ => {}
+                }
+                let expected: String = chars.iter().collect();
+                prop_assert_eq!(result.input, expected);
+                prop_assert_eq!(result.curser_pos, curser);
+                if result.finished {
+                    inputter.clear();
+                    chars.clear();
+                    curser = 0;
+                }
+            }
+        }
+    }
+}
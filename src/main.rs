@@ -1,34 +1,47 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use clap::Parser;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
 use tracing::info;
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{self, EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
+mod capabilities;
+mod config;
 mod controller;
 mod domain;
+mod external;
+mod graphics;
 mod inputter;
 mod model;
 mod popup;
+mod remote;
+mod renderer;
+mod report;
+mod table_widget;
 mod tui;
 
+use capabilities::Capabilities;
 use controller::Controller;
-use domain::{TVConfig, TVError};
-use model::{Model, Status};
+use domain::{Message, TVConfig, TVError};
+use model::{Directory, Model, Workspace};
 use tui::TableUI;
 
+// How often the loading progress is redrawn at most
+const LOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
 fn main() -> ExitCode {
     match run() {
         Err(e) => {
-            ratatui::restore();
             eprintln!("Error: {:?}", e);
             ExitCode::FAILURE
         }
-        Ok(_) => {
-            ratatui::restore();
-            ExitCode::SUCCESS
-        }
+        Ok(_) => ExitCode::SUCCESS,
     }
 }
 
@@ -89,9 +102,17 @@ pub fn initialize_logging(_cfg: &TVConfig, args: &TVArguments) -> Result<(), std
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "TUI Table viewer", long_about = None)]
 struct Cli {
-    /// Location of file to open
+    /// Location of file to open, a directory to browse its files, a postgres:// or mysql:// connection url, or - to read csv or newline delimited json from stdin
     file: PathBuf,
 
+    /// Table to load from a database url
+    #[arg(long, value_name = "TABLE")]
+    table: Option<String>,
+
+    /// Query to run against a database url
+    #[arg(long, value_name = "SQL")]
+    query: Option<String>,
+
     /// Sets location of log file
     #[arg(short, long, value_name = "LOG", default_value = "~/.cache/tv/tv.log")]
     log: PathBuf,
@@ -103,13 +124,130 @@ struct Cli {
     /// Enable bright color scheme
     #[arg(short, long, default_value = "false")]
     bright_colors: bool,
+
+    /// Color values matching a regex, e.g. --render '^ERR.*=red'. Can be given multiple times
+    #[arg(long, value_name = "REGEX=COLOR")]
+    render: Vec<String>,
+
+    /// Url to open cells without a url with, `{}` is replaced by the value. E.g. https://issues.example.com/browse/{}
+    #[arg(long, value_name = "URL")]
+    url_template: Option<String>,
+
+    /// Number of rows per stripe of alternating background color, 0 disables striping
+    #[arg(long, value_name = "ROWS", default_value = "1")]
+    stripe: usize,
+
+    /// Show an overlay with load, collect and draw times
+    #[arg(long, default_value = "false")]
+    profile: bool,
+
+    /// Load csv files with ragged rows or bad quotes, padding or skipping the malformed rows
+    #[arg(long, default_value = "false")]
+    lenient: bool,
+
+    /// Print a profile of every column (dtype, nulls, distinct, min/max/mean, top values) and exit
+    #[arg(long, default_value = "false")]
+    stats: bool,
+
+    /// Print the column names and inferred dtypes and exit
+    #[arg(long, default_value = "false")]
+    schema: bool,
+
+    /// Parse numbers with a comma as decimal separator when casting text columns, e.g. 1.234,56
+    #[arg(long, default_value = "false")]
+    decimal_comma: bool,
+
+    /// Only draw ascii characters, e.g. for limited terminals and screen readers. Colors are disabled by setting NO_COLOR
+    #[arg(long, default_value = "false")]
+    no_unicode: bool,
+
+    /// Milliseconds to wait for input before updating the view, overrides `event_poll_time` of the config file
+    #[arg(long, value_name = "MS")]
+    poll_time: Option<usize>,
+
+    /// Milliseconds without typing before the matches of a filter are counted, overrides `filter_preview_delay` of the config file
+    #[arg(long, value_name = "MS")]
+    filter_delay: Option<usize>,
+
+    /// Milliseconds between checks of a file for changes with --watch, overrides `watch_interval` of the config file
+    #[arg(long, value_name = "MS")]
+    watch_interval: Option<usize>,
+
+    /// Milliseconds after which rows read with --stream are shown, overrides `stream_interval` of the config file
+    #[arg(long, value_name = "MS")]
+    stream_interval: Option<usize>,
+
+    /// Rows read with --stream that are shown at once at most, overrides `stream_batch_rows` of the config file
+    #[arg(long, value_name = "ROWS")]
+    stream_batch_rows: Option<usize>,
+
+    /// Searches and filters over more rows run in the background showing their progress, overrides `background_search_rows` of the config file
+    #[arg(long, value_name = "ROWS")]
+    background_search_rows: Option<usize>,
+
+    /// Load all rows of files over `max_rows` of the config file, instead of only their first rows
+    #[arg(long, default_value = "false")]
+    force_full: bool,
+
+    /// Load a random sample of the rows of csv files over `max_rows`, instead of their first rows
+    #[arg(long, default_value = "false")]
+    sample: bool,
+
+    /// Accept json commands on a unix socket, e.g. {"cmd": "goto", "row": 42}, see the README
+    #[arg(long, value_name = "SOCKET")]
+    listen: Option<PathBuf>,
+
+    /// Keep reading stdin after the first rows, appending rows as they arrive, e.g. `tail -f log.csv | tv --stream -`
+    #[arg(long, default_value = "false")]
+    stream: bool,
+
+    /// Only keep the latest rows read with --stream, dropping the oldest ones
+    #[arg(long, value_name = "ROWS", requires = "stream")]
+    ring: Option<usize>,
+
+    /// Reload the file once it changes, showing the rows added, removed or changed like :reload
+    #[arg(long, default_value = "false")]
+    watch: bool,
+
+    /// Show the schema and first rows of the file, to adjust how it is parsed before it is loaded
+    #[arg(long, default_value = "false")]
+    preview: bool,
+
+    /// Print --schema as json
+    #[arg(long, default_value = "false", requires = "schema")]
+    json: bool,
 }
 
 struct TVArguments {
     filepath: PathBuf,
+    table: Option<String>,
+    query: Option<String>,
     log: PathBuf,
     verbose: u8,
     light_colors: bool,
+    render_patterns: Vec<String>,
+    url_template: Option<String>,
+    stripe: usize,
+    profile: bool,
+    lenient: bool,
+    stats: bool,
+    schema: bool,
+    json: bool,
+    decimal_comma: bool,
+    no_unicode: bool,
+    poll_time: Option<usize>,
+    filter_delay: Option<usize>,
+    watch_interval: Option<usize>,
+    stream_interval: Option<usize>,
+    stream_batch_rows: Option<usize>,
+    background_search_rows: Option<usize>,
+    force_full: bool,
+    sample: bool,
+    listen: Option<PathBuf>,
+    stream: bool,
+    ring: Option<usize>,
+    watch: bool,
+    preview: bool,
 }
 
 fn arg_parser() -> TVArguments {
@@ -117,51 +255,316 @@ fn arg_parser() -> TVArguments {
 
     TVArguments {
         filepath: cli.file,
+        table: cli.table,
+        query: cli.query,
         log: cli.log,
         verbose: cli.verbose,
         light_colors: cli.bright_colors,
+        render_patterns: cli.render,
+        url_template: cli.url_template,
+        stripe: cli.stripe,
+        profile: cli.profile,
+        lenient: cli.lenient,
+        stats: cli.stats,
+        schema: cli.schema,
+        json: cli.json,
+        decimal_comma: cli.decimal_comma,
+        no_unicode: cli.no_unicode,
+        poll_time: cli.poll_time,
+        filter_delay: cli.filter_delay,
+        watch_interval: cli.watch_interval,
+        stream_interval: cli.stream_interval,
+        stream_batch_rows: cli.stream_batch_rows,
+        background_search_rows: cli.background_search_rows,
+        force_full: cli.force_full,
+        sample: cli.sample,
+        listen: cli.listen,
+        stream: cli.stream,
+        ring: cli.ring,
+        watch: cli.watch,
+        preview: cli.preview,
     }
 }
 
 fn run() -> Result<(), TVError> {
     let mut cfg = TVConfig {
-        event_poll_time: 100,
+        event_poll_time: config::DEFAULT_EVENT_POLL_TIME,
+        filter_preview_delay: config::DEFAULT_FILTER_PREVIEW_DELAY,
+        watch_interval: config::DEFAULT_WATCH_INTERVAL,
+        stream_interval: config::DEFAULT_STREAM_INTERVAL,
+        stream_batch_rows: config::DEFAULT_STREAM_BATCH_ROWS,
+        background_search_rows: config::DEFAULT_BACKGROUND_SEARCH_ROWS,
         max_column_width: 25,
         column_margin: 1,
         light_colors: true,
+        render_patterns: Vec::new(),
+        capabilities: Capabilities::probe(),
+        url_template: None,
+        stripe: 1,
+        profile: false,
+        scroll_acceleration: Default::default(),
+        lenient_csv: false,
+        histogram_bins: config::DEFAULT_HISTOGRAM_BINS,
+        decimal_comma: false,
+        no_color: false,
+        ascii: false,
+        stats_cache: true,
+        max_rows: config::DEFAULT_MAX_ROWS,
+        sample_large_files: false,
+        files: Vec::new(),
     };
 
     let args = arg_parser();
     cfg.light_colors = args.light_colors;
+    cfg.render_patterns = args.render_patterns.clone();
+    cfg.url_template = args.url_template.clone();
+    cfg.stripe = args.stripe;
+    cfg.profile = args.profile;
+    cfg.lenient_csv = args.lenient;
+    let config_file = config::load_config()?;
+    cfg.scroll_acceleration = config_file.scroll_acceleration;
+    cfg.histogram_bins = config_file.histogram_bins;
+    cfg.decimal_comma = args.decimal_comma || config_file.decimal_comma;
+    cfg.stats_cache = config_file.stats_cache;
+    cfg.capabilities = cfg.capabilities.with_overrides(&config_file.capabilities);
+    cfg.max_rows = if args.force_full {
+        0
+    } else {
+        config_file.max_rows
+    };
+    cfg.sample_large_files = args.sample;
+    cfg.files = config_file.files;
+    cfg.event_poll_time = args.poll_time.unwrap_or(config_file.event_poll_time);
+    cfg.filter_preview_delay = args
+        .filter_delay
+        .unwrap_or(config_file.filter_preview_delay);
+    cfg.watch_interval = args.watch_interval.unwrap_or(config_file.watch_interval);
+    cfg.stream_interval = args.stream_interval.unwrap_or(config_file.stream_interval);
+    cfg.stream_batch_rows = args
+        .stream_batch_rows
+        .unwrap_or(config_file.stream_batch_rows);
+    cfg.background_search_rows = args
+        .background_search_rows
+        .unwrap_or(config_file.background_search_rows);
+    // See https://no-color.org
+    cfg.no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    cfg.ascii = args.no_unicode;
     initialize_logging(&cfg, &args)?;
     info!("Starting tv!");
+    info!("Terminal capabilities: {}", cfg.capabilities.describe());
+
+    if args.stream && args.filepath != Path::new("-") {
+        return Err(TVError::LoadingFailed(
+            "--stream reads from stdin, give - instead of a file!".into(),
+        ));
+    }
+
+    // Reports are printed without starting the ui
+    if args.stats || args.schema {
+        let source = args.filepath.to_string_lossy();
+        let sheets = Model::read_source(&source, args.table, args.query, args.lenient)?;
+        if args.schema {
+            print!("{}", report::schema(&sheets, args.json));
+        } else {
+            print!("{}", report::stats(&sheets)?);
+        }
+        return Ok(());
+    }
 
     let mut terminal = ratatui::init();
-    let mut ui = TableUI::new(&cfg);
+    cfg.capabilities.enable_features()?;
+    let result = run_tui(&cfg, args, &mut terminal);
+    let disabled = cfg.capabilities.disable_features();
+    ratatui::restore();
+    disabled?;
+    result
+}
+
+fn run_tui(
+    cfg: &TVConfig,
+    args: TVArguments,
+    terminal: &mut ratatui::DefaultTerminal,
+) -> Result<(), TVError> {
+    let mut ui = TableUI::new(cfg);
 
     // Start by telling the model about the actual ui size
     let area = terminal.get_frame().area();
 
-    let mut model = Model::init(&cfg, area.width as usize, area.height as usize)?;
+    let mut model = Model::init(cfg, area.width as usize, area.height as usize)?;
     let uidata = model.get_uidata();
     terminal.draw(|f| ui.draw(uidata, f))?;
 
-    model.load_data_file(args.filepath)?;
+    // A directory is browsed in a sidebar, starting with its first file
+    let directory = match args.filepath.is_dir() {
+        true => Some(Directory::scan(&args.filepath)?),
+        false => None,
+    };
+    let filepath = directory
+        .as_ref()
+        .map_or(args.filepath.clone(), Directory::selected_path);
+
+    // With --preview the start of the file is shown first, to adjust how it is parsed
+    let source = args.filepath.to_string_lossy().to_string();
+    let previewed = args.preview && !model::is_database_url(&source) && source != "-";
+    if previewed && !preview_file(&mut model, filepath.clone(), &mut ui, terminal)? {
+        return Ok(());
+    }
+
+    // Load on a separate thread, so the progress can be drawn meanwhile
+    let (progress_sender, progress) = mpsc::channel();
+    model.set_load_progress(Some(progress_sender));
+    thread::scope(|s| -> Result<(), TVError> {
+        let loader = s.spawn(|| {
+            if model::is_database_url(&source) {
+                model.load_database(&source, args.table, args.query)
+            } else if source == "-" {
+                model.load_stdin(args.stream, args.ring)
+            } else if previewed {
+                model.load_previewed_file()
+            } else {
+                model.load_data_file(filepath)
+            }
+        });
+        let mut preview = None;
+        while !loader.is_finished() {
+            if let Ok(mut latest) = progress.recv_timeout(LOAD_PROGRESS_INTERVAL) {
+                // Only draw the latest progress, the preview is only sent once
+                preview = latest.preview.take().or(preview);
+                while let Ok(mut next) = progress.try_recv() {
+                    preview = next.preview.take().or(preview);
+                    latest = next;
+                }
+                latest.preview = preview.clone();
+                terminal.draw(|f| ui.draw_loading(&latest, f))?;
+            }
+        }
+        loader.join().expect("Loading thread panicked")?;
+        Ok(())
+    })?;
+    model.set_load_progress(None);
+    if args.watch {
+        model.watch("");
+    }
+
+    // Narrow terminals show the record view right away
+    model.update(Some(Message::Resize(
+        area.width as usize,
+        area.height as usize,
+    )))?;
+    let mut workspace = Workspace::new(cfg, model);
+    if let Some(directory) = directory {
+        workspace.browse(directory)?;
+    }
+    let controller = Controller::new(cfg);
+    workspace.set_event_sender(controller.event_sender());
+    let _listener = match args.listen.as_ref() {
+        Some(path) => Some(remote::listen(path, controller.event_sender())?),
+        None => None,
+    };
+    // Draws the right pane of a split, the left one is drawn by `ui`
+    let mut split_ui = TableUI::new(cfg);
+    while !workspace.is_quitting() {
+        let message = controller.handle_event(workspace.active())?;
+        let received = message.is_some().then(Instant::now);
+        workspace.update(message)?;
 
-    let controller = Controller::new(&cfg);
-    while model.status != Status::QUITTING {
-        let message = controller.handle_event(&model)?;
-        model.update(message)?;
+        let model = workspace.active_mut();
+        if let Some((program, content)) = model.take_external_request() {
+            controller.pause();
+            cfg.capabilities.disable_features()?;
+            let result = external::open(program, &content);
+            cfg.capabilities.enable_features()?;
+            controller.resume();
+            terminal.clear()?;
+            model.external_program_finished(result);
+        }
 
-        let uidata = model.get_uidata();
-        if ui.needs_redrawing(uidata) {
-            terminal.draw(|f| ui.draw(uidata, f))?;
+        let start_time = Instant::now();
+        let drawn = if let Some((left, right)) = workspace.panes() {
+            let (left, right) = (left.get_uidata(), right.get_uidata());
+            let redraw = ui.needs_redrawing(left) || split_ui.needs_redrawing(right);
+            if redraw {
+                terminal.draw(|f| {
+                    let area = draw_sidebar(&ui, &workspace, f);
+                    let left_width = Workspace::left_pane_width(area.width as usize) as u16;
+                    let [left_area, right_area] =
+                        Layout::horizontal([Constraint::Length(left_width), Constraint::Fill(1)])
+                            .areas(area);
+                    ui.draw_in(left, f, left_area);
+                    split_ui.draw_in(right, f, right_area);
+                })?;
+            }
+            redraw
+        } else {
+            let uidata = workspace.active().get_uidata();
+            let redraw = ui.needs_redrawing(uidata);
+            if redraw {
+                terminal.draw(|f| {
+                    let area = draw_sidebar(&ui, &workspace, f);
+                    ui.draw_in(uidata, f, area);
+                })?;
+            }
+            redraw
+        };
+        if drawn {
+            // Images of charts and plots are written after the frame they are part of
+            let mut stdout = std::io::stdout();
+            let clear = ui.write_graphics(&mut stdout)?;
+            if split_ui.write_graphics(&mut stdout)? || clear {
+                terminal.clear()?;
+                ui.invalidate();
+                split_ui.invalidate();
+            }
+            workspace
+                .active_mut()
+                .record_frame(start_time.elapsed(), received.map(|r| r.elapsed()));
         }
     }
 
     Ok(())
 }
 
+// Show the schema and first rows of the file until it is loaded with Enter. Returns false if tv was quit instead.
+fn preview_file(
+    model: &mut Model,
+    path: PathBuf,
+    ui: &mut TableUI,
+    terminal: &mut ratatui::DefaultTerminal,
+) -> Result<bool, TVError> {
+    let mut preview = model.preview_file(path)?;
+    loop {
+        terminal.draw(|f| ui.draw_preview(preview.as_ref(), f))?;
+        let TermEvent::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let adjusted = match key.code {
+            KeyCode::Enter => return Ok(true),
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(false),
+            KeyCode::Char('d') => model.cycle_preview_delimiter(),
+            KeyCode::Char('h') => model.toggle_preview_header(),
+            _ => None,
+        };
+        preview = adjusted.or(preview);
+    }
+}
+
+// Draw the sidebar of a browsed directory, returns the area left for the tables
+fn draw_sidebar(ui: &TableUI, workspace: &Workspace, frame: &mut ratatui::Frame) -> Rect {
+    let Some(directory) = workspace.directory() else {
+        return frame.area();
+    };
+    let [sidebar_area, area] = Layout::horizontal([
+        Constraint::Length(workspace.sidebar_width() as u16),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    ui.draw_sidebar(directory, frame, sidebar_area);
+    area
+}
+
 // #[cfg(test)]
 // mod tests {
 
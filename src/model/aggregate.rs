@@ -0,0 +1,83 @@
+use polars::prelude::*;
+use regex::Regex;
+
+// Functions of an aggregation spec, e.g. `sum(amount), mean(latency), count()`
+const FUNCTIONS: [&str; 10] = [
+    "count", "sum", "mean", "median", "min", "max", "std", "nunique", "first", "last",
+];
+
+// A single `<function>(<column>)` of an aggregation spec, the column is empty for `count()`
+#[derive(Debug, PartialEq)]
+pub struct Aggregation {
+    pub function: String,
+    pub column: String,
+}
+
+impl Aggregation {
+    // Polars expression named like the spec, e.g. `sum(amount)`
+    pub fn expr(&self) -> Expr {
+        let name = format!("{}({})", self.function, self.column);
+        let column = col(self.column.as_str());
+        let expr = match self.function.as_str() {
+            "count" if self.column.is_empty() => len(),
+            "count" => column.count(),
+            "sum" => column.sum(),
+            "mean" => column.mean(),
+            "median" => column.median(),
+            "min" => column.min(),
+            "max" => column.max(),
+            "std" => column.std(1),
+            "nunique" => column.n_unique(),
+            "first" => column.first(),
+            _ => column.last(),
+        };
+        expr.alias(name)
+    }
+}
+
+// Parse a comma separated list of aggregations
+pub fn parse(spec: &str) -> Result<Vec<Aggregation>, String> {
+    let pattern = Regex::new(r"^\s*(\w+)\(\s*([^()]*?)\s*\)\s*(,|$)").unwrap();
+    let mut aggregations = Vec::new();
+    let mut rest = spec.trim();
+    while !rest.is_empty() {
+        let captures = pattern.captures(rest).ok_or(format!(
+            "Expected an aggregation like sum(<column>) at: {rest}"
+        ))?;
+        let function = captures[1].to_lowercase();
+        if !FUNCTIONS.contains(&function.as_str()) {
+            return Err(format!(
+                "Unknown aggregation {function}, use one of {}",
+                FUNCTIONS.join(", ")
+            ));
+        }
+        let column = captures[2].to_string();
+        if column.is_empty() && function != "count" {
+            return Err(format!("{function}() needs a column"));
+        }
+        aggregations.push(Aggregation { function, column });
+        rest = &rest[captures[0].len()..];
+    }
+    Ok(aggregations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec() {
+        let aggregations = parse("sum(amount), MEAN( latency ),count()").unwrap();
+        let parsed: Vec<(&str, &str)> = aggregations
+            .iter()
+            .map(|a| (a.function.as_str(), a.column.as_str()))
+            .collect();
+        assert_eq!(
+            parsed,
+            [("sum", "amount"), ("mean", "latency"), ("count", "")]
+        );
+        assert!(parse("sum()").is_err());
+        assert!(parse("avg(amount)").is_err());
+        assert!(parse("sum(amount) mean(latency)").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, Sender, TryRecvError},
+};
+use std::thread;
+
+use crate::domain::Event;
+
+// Rows scanned between two progress reports
+const CHUNK_ROWS: usize = 200_000;
+
+// Rows scanned so far and the matches found in them, as mask position and column index
+type Progress = (usize, Vec<(usize, usize)>);
+
+// Search for a term in columns on a worker thread, reporting matches as they are found
+pub struct BackgroundSearch {
+    pub term: String,
+    pub filter: Option<(String, String)>, // Column name and term of the filtered table the matches go to, None for a search
+    pub depth: usize, // Number of tables when the matches are shown, other tables stop the search
+    pub mask: Arc<Vec<usize>>, // Rows searched
+    pub scanned: usize,
+    pub found: usize,
+    receiver: Receiver<Progress>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl BackgroundSearch {
+    pub fn start(
+        term: &str,
+        columns: Vec<(usize, Arc<[Arc<str>]>)>,
+        mask: Arc<Vec<usize>>,
+        wakeup: Option<Sender<Event>>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (search_term, rows, stop) = (term.to_string(), Arc::clone(&mask), cancelled.clone());
+        thread::spawn(move || {
+            for (chunk_idx, chunk) in rows.chunks(CHUNK_ROWS).enumerate() {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let start = chunk_idx * CHUNK_ROWS;
+                let mut matches = Vec::new();
+                for (offset, &ridx) in chunk.iter().enumerate() {
+                    for (col_idx, values) in columns.iter() {
+                        if values[ridx].contains(search_term.as_str()) {
+                            matches.push((start + offset, *col_idx));
+                        }
+                    }
+                }
+                if sender.send((start + chunk.len(), matches)).is_err() {
+                    return;
+                }
+                if let Some(wakeup) = wakeup.as_ref() {
+                    let _ = wakeup.send(Event::Wakeup);
+                }
+            }
+        });
+        BackgroundSearch {
+            term: term.to_string(),
+            filter: None,
+            depth: 0,
+            mask,
+            scanned: 0,
+            found: 0,
+            receiver,
+            cancelled,
+            done: false,
+        }
+    }
+
+    // Matches found since the last poll, in the order of the rows
+    pub fn poll(&mut self) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok((scanned, found)) => {
+                    self.scanned = scanned;
+                    matches.extend(found);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        self.found += matches.len();
+        matches
+    }
+
+    // All rows were scanned
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // E.g. `scanned 2.4M/10M rows`
+    pub fn progress(&self) -> String {
+        format!(
+            "scanned {}/{} rows",
+            short_count(self.scanned),
+            short_count(self.mask.len())
+        )
+    }
+}
+
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+// Counts shortened to thousands or millions, e.g. `2.4M`
+fn short_count(n: usize) -> String {
+    let (value, unit) = match n {
+        0..1_000 => return n.to_string(),
+        1_000..1_000_000 => (n as f64 / 1e3, "k"),
+        _ => (n as f64 / 1e6, "M"),
+    };
+    let value = format!("{value:.1}");
+    format!("{}{unit}", value.trim_end_matches(".0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_matches_in_chunks() {
+        let values: Arc<[Arc<str>]> = (0..500_000)
+            .map(|i| Arc::from(format!("row {}", i % 100_000)))
+            .collect();
+        let mask = Arc::new((0..values.len()).rev().collect::<Vec<usize>>());
+        let mut search = BackgroundSearch::start("row 99999", vec![(3, values)], mask, None);
+        let mut matches = Vec::new();
+        while !search.is_done() {
+            matches.extend(search.poll());
+        }
+        assert_eq!(search.found, 5);
+        assert_eq!(matches.first(), Some(&(0, 3)));
+        assert!(matches.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(search.progress(), "scanned 500k/500k rows");
+        assert_eq!(short_count(2_400_000), "2.4M");
+        assert_eq!(short_count(10_000_000), "10M");
+    }
+}
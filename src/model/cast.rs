@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+
+// Type a column is re-parsed as
+#[derive(Debug, PartialEq)]
+enum Target {
+    Dtype(DataType),
+    Boolean,
+    Date(Option<String>),     // With a strftime format, otherwise it is inferred
+    Datetime(Option<String>), // With a strftime format, otherwise it is inferred
+}
+
+// A `:cast <column> as <dtype>`, e.g. `amount as f64`, `amount as f64(',')` or `ts as datetime('%Y-%m-%d %H:%M')`
+#[derive(Debug, PartialEq)]
+pub struct Cast {
+    pub column: Option<String>, // None for the current column
+    target: Target,
+    decimal: Option<char>, // Decimal separator of numbers, other separators are thousands separators
+}
+
+const USAGE: &str = "Usage: cast [column] as <i64|f64|bool|str|date|datetime>, e.g. cast ts as datetime('%Y-%m-%d')";
+
+// Grouping digits of numbers, e.g. `1.234,56`, `1 234,56` or `1'234.56`
+const THOUSANDS_SEPARATORS: [char; 5] = ['.', ',', ' ', '\u{a0}', '\''];
+
+impl Cast {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let args = args.trim();
+        let (column, dtype) = match args.strip_prefix("as ") {
+            Some(dtype) => (None, dtype),
+            None => {
+                let (column, dtype) = args.rsplit_once(" as ").ok_or(USAGE)?;
+                (Some(column.trim().to_string()), dtype)
+            }
+        };
+        let dtype = dtype.trim();
+        let (name, format) = match dtype.split_once('(') {
+            Some((name, format)) => {
+                let format = format.strip_suffix(')').ok_or(USAGE)?.trim();
+                let format = format.trim_matches(|c| c == '\'' || c == '"');
+                (
+                    name.trim(),
+                    (!format.is_empty()).then(|| format.to_string()),
+                )
+            }
+            None => (dtype, None),
+        };
+        let has_format = format.is_some();
+        let decimal = match format.as_deref() {
+            Some(separator @ ("," | ".")) => separator.chars().next(),
+            _ => None,
+        };
+        let target = match name.to_lowercase().as_str() {
+            "i8" => Target::Dtype(DataType::Int8),
+            "i16" => Target::Dtype(DataType::Int16),
+            "i32" => Target::Dtype(DataType::Int32),
+            "i64" | "int" => Target::Dtype(DataType::Int64),
+            "u8" => Target::Dtype(DataType::UInt8),
+            "u16" => Target::Dtype(DataType::UInt16),
+            "u32" => Target::Dtype(DataType::UInt32),
+            "u64" => Target::Dtype(DataType::UInt64),
+            "f32" => Target::Dtype(DataType::Float32),
+            "f64" | "float" => Target::Dtype(DataType::Float64),
+            "str" | "string" => Target::Dtype(DataType::String),
+            "bool" | "boolean" => Target::Boolean,
+            "date" => Target::Date(format),
+            "datetime" => Target::Datetime(format),
+            other => return Err(format!("Unknown dtype {other}! {USAGE}")),
+        };
+        let is_number = matches!(&target, Target::Dtype(dtype) if dtype.is_primitive_numeric());
+        if is_number && has_format && decimal.is_none() {
+            return Err(format!(
+                "Numbers take their decimal separator, e.g. f64(','). {USAGE}"
+            ));
+        }
+        if has_format && !is_number && !matches!(target, Target::Date(_) | Target::Datetime(_)) {
+            return Err(format!(
+                "Only numbers, date and datetime take a format! {USAGE}"
+            ));
+        }
+        Ok(Cast {
+            column,
+            target,
+            decimal,
+        })
+    }
+
+    // The column as the target type, values that can not be parsed become null.
+    // Numbers use a comma as decimal separator with `decimal_comma`, unless the cast names the separator.
+    pub fn apply(&self, column: &Column, decimal_comma: bool) -> PolarsResult<Series> {
+        let series = column.as_materialized_series();
+        if series.dtype() != &DataType::String {
+            let dtype = match &self.target {
+                Target::Dtype(dtype) => dtype.clone(),
+                Target::Boolean => DataType::Boolean,
+                Target::Date(_) => DataType::Date,
+                Target::Datetime(_) => DataType::Datetime(TimeUnit::Microseconds, None),
+            };
+            return series.cast(&dtype);
+        }
+
+        // Surrounding whitespace would make parsing fail
+        let values = series.str()?.apply_values(|v| v.trim().into());
+        let cast = match &self.target {
+            Target::Dtype(dtype) if dtype.is_primitive_numeric() => {
+                match self.decimal.or(decimal_comma.then_some(',')) {
+                    Some(decimal) => values
+                        .apply_values(|v| normalize_number(v, decimal).into())
+                        .into_series()
+                        .cast(dtype)?,
+                    None => values.into_series().cast(dtype)?,
+                }
+            }
+            Target::Dtype(dtype) => values.into_series().cast(dtype)?,
+            Target::Boolean => values
+                .into_iter()
+                .map(|v| match v?.to_lowercase().as_str() {
+                    "true" | "t" | "yes" | "y" | "1" => Some(true),
+                    "false" | "f" | "no" | "n" | "0" => Some(false),
+                    _ => None,
+                })
+                .collect::<BooleanChunked>()
+                .into_series(),
+            Target::Date(format) => values.as_date(format.as_deref(), true)?.into_series(),
+            Target::Datetime(format) => {
+                let ambiguous = StringChunked::from_slice("ambiguous".into(), &["raise"]);
+                values
+                    .as_datetime(
+                        format.as_deref(),
+                        TimeUnit::Microseconds,
+                        true,
+                        false,
+                        None,
+                        &ambiguous,
+                    )?
+                    .into_series()
+            }
+        };
+        Ok(cast.with_name(series.name().clone()))
+    }
+}
+
+// Text of the values that were set before the cast, but are null after it, per row
+pub fn unparsed(before: &Column, after: &Series) -> PolarsResult<HashMap<usize, String>> {
+    let text = before.cast(&DataType::String)?;
+    Ok(text
+        .str()?
+        .into_iter()
+        .zip(after.is_null().into_no_null_iter())
+        .enumerate()
+        .filter_map(|(ridx, (value, is_null))| Some((ridx, value.filter(|_| is_null)?.to_string())))
+        .collect())
+}
+
+// The number with `.` as decimal separator and without thousands separators
+fn normalize_number(value: &str, decimal: char) -> String {
+    value
+        .chars()
+        .filter(|&c| c == decimal || !THOUSANDS_SEPARATORS.contains(&c))
+        .map(|c| if c == decimal { '.' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_apply() {
+        let cast = Cast::parse("unit price as f64").unwrap();
+        assert_eq!(cast.column.as_deref(), Some("unit price"));
+        let column = Column::new("unit price".into(), [" 1.5", "2", "n/a"]);
+        let values = cast.apply(&column, false).unwrap();
+        assert_eq!(values.dtype(), &DataType::Float64);
+        assert_eq!(
+            unparsed(&column, &values).unwrap(),
+            HashMap::from([(2, "n/a".to_string())])
+        );
+
+        let cast = Cast::parse("as datetime('%d.%m.%Y %H:%M')").unwrap();
+        assert_eq!(cast.column, None);
+        let column = Column::new("ts".into(), ["24.12.2024 18:30"]);
+        let values = cast.apply(&column, false).unwrap();
+        assert_eq!(values.str_value(0).unwrap(), "2024-12-24 18:30:00");
+        let cast = Cast::parse("ts as datetime('%Y-%m-%d')").unwrap();
+        let column = Column::new("ts".into(), ["2024-12-24", "soon"]);
+        let values = cast.apply(&column, false).unwrap();
+        assert_eq!(values.str_value(0).unwrap(), "2024-12-24 00:00:00");
+        assert_eq!(values.null_count(), 1);
+
+        assert!(Cast::parse("amount as money").is_err());
+        assert!(Cast::parse("amount as f64('%d')").is_err());
+    }
+
+    #[test]
+    fn decimal_separators() {
+        let column = Column::new("amount".into(), ["1.234,56", "1 234,5", "-7,25"]);
+        let parsed = |cast: &str, decimal_comma| {
+            let values = Cast::parse(cast)
+                .unwrap()
+                .apply(&column, decimal_comma)
+                .unwrap();
+            values.f64().unwrap().into_iter().collect::<Vec<_>>()
+        };
+        let expected = vec![Some(1234.56), Some(1234.5), Some(-7.25)];
+        assert_eq!(parsed("amount as f64(',')", false), expected);
+        assert_eq!(parsed("amount as f64", true), expected);
+        // The separator of the cast wins over the global setting
+        assert_eq!(
+            parsed("amount as f64('.')", true),
+            vec![Some(1.23456), Some(12345.0), Some(-725.0)]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use super::Column;
+
+// Shared prefix of structured column names, e.g. `user` of `user.id` and `user.name`
+pub fn prefix(name: &str) -> Option<&str> {
+    match name.split_once('.') {
+        Some((prefix, rest)) if !prefix.is_empty() && !rest.is_empty() => Some(prefix),
+        _ => None,
+    }
+}
+
+// Folded columns following a folded column of the same group, they are shown by its summary column
+pub fn is_hidden(data: &[Column], idx: usize) -> bool {
+    idx > 0 && idx < data.len() && data[idx].folded && same_group(&data[idx - 1], &data[idx])
+}
+
+// The column showing the given one, the summary column if it is folded into one
+pub fn head(data: &[Column], mut idx: usize) -> usize {
+    while is_hidden(data, idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+// Columns shown by the summary column starting at `head`
+pub fn members(data: &[Column], head: usize) -> Range<usize> {
+    let mut end = head + 1;
+    while is_hidden(data, end) {
+        end += 1;
+    }
+    head..end
+}
+
+// Header of a summary column, e.g. `user.* (3)`
+pub fn summary_name(data: &[Column], head: usize) -> String {
+    let prefix = prefix(&data[head].name).unwrap_or_default();
+    format!("{prefix}.* ({})", members(data, head).len())
+}
+
+// Fold or unfold the adjacent columns sharing the prefix of the given one.
+// Returns the prefix, the number of columns and if they were folded, None if the column has no group.
+pub fn toggle(data: &mut [Column], idx: usize) -> Option<(String, usize, bool)> {
+    let group = group_of(data, idx)?;
+    let fold = !data[idx].folded;
+    let prefix = prefix(&data[idx].name)?.to_string();
+    for column in data[group.clone()].iter_mut() {
+        column.folded = fold;
+    }
+    Some((prefix, group.len(), fold))
+}
+
+// Fold every group, or unfold all of them if any is folded. Returns the number of groups and if they were folded.
+pub fn toggle_all(data: &mut [Column]) -> (usize, bool) {
+    let fold = !data.iter().any(|c| c.folded);
+    let mut groups = 0;
+    let mut idx = 0;
+    while idx < data.len() {
+        match group_of(data, idx) {
+            Some(group) => {
+                for column in data[group.clone()].iter_mut() {
+                    column.folded = fold;
+                }
+                groups += 1;
+                idx = group.end;
+            }
+            None => {
+                data[idx].folded = false;
+                idx += 1;
+            }
+        }
+    }
+    (groups, fold)
+}
+
+fn same_group(a: &Column, b: &Column) -> bool {
+    a.folded == b.folded && prefix(&a.name).is_some() && prefix(&a.name) == prefix(&b.name)
+}
+
+// Adjacent columns around the given one that share its prefix, if there are at least two
+fn group_of(data: &[Column], idx: usize) -> Option<Range<usize>> {
+    let prefix = prefix(&data[idx].name)?;
+    let shares_prefix = |c: &Column| self::prefix(&c.name) == Some(prefix);
+    let start = data[..idx]
+        .iter()
+        .rposition(|c| !shares_prefix(c))
+        .map_or(0, |p| p + 1);
+    let end = data[idx..]
+        .iter()
+        .position(|c| !shares_prefix(c))
+        .map_or(data.len(), |p| idx + p);
+    (end - start > 1).then_some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::{NamedFrom, Series};
+
+    use super::*;
+
+    #[test]
+    fn fold_groups() {
+        let names = [
+            "id",
+            "user.id",
+            "user.name",
+            "payment.amount",
+            "payment.currency",
+        ];
+        let mut data: Vec<Column> = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| Column::new(idx, Series::new((*name).into(), [1])))
+            .collect();
+        assert_eq!(toggle(&mut data, 0), None);
+        assert_eq!(toggle(&mut data, 2), Some(("user".to_string(), 2, true)));
+        assert!(!is_hidden(&data, 1) && is_hidden(&data, 2));
+        assert_eq!(head(&data, 2), 1);
+        assert_eq!(members(&data, 1), 1..3);
+        assert_eq!(summary_name(&data, 1), "user.* (2)");
+
+        // Expanding all as one group is folded
+        assert_eq!(toggle_all(&mut data), (2, false));
+        assert!(data.iter().all(|c| !c.folded));
+        assert_eq!(toggle_all(&mut data), (2, true));
+        assert_eq!(head(&data, 4), 3);
+        assert!(!data[0].folded);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::domain::TVError;
+
+const COLUMN_NAMES_PATH: &str = "~/.config/tv/column_names.json";
+
+// Names given to the columns of files without header row, by file and synthetic name, e.g. `c3`
+type GivenNames = BTreeMap<String, BTreeMap<String, String>>;
+
+fn column_names_path() -> PathBuf {
+    PathBuf::from(shellexpand::tilde(COLUMN_NAMES_PATH).to_string())
+}
+
+// Column names of a file without header row: `c1` to `cn`, unless they were renamed before
+pub fn names(file: &Path, ncolumns: usize) -> Vec<String> {
+    names_in(&column_names_path(), file, ncolumns)
+}
+
+// Remember the new name of a column of a file without header row, for the next time it is opened
+pub fn rename(file: &Path, old: &str, new: &str) -> Result<(), TVError> {
+    rename_in(&column_names_path(), file, old, new)
+}
+
+// Unique names for the columns, repeated names get a suffix: `name`, `name_2`, `name_3`.
+// Returns them with the names in the file of the columns that got a suffix.
+pub fn disambiguate(names: &[String]) -> (Vec<String>, HashMap<String, String>) {
+    let mut taken: HashSet<String> = names.iter().cloned().collect();
+    let mut seen = HashSet::new();
+    let mut unique = Vec::with_capacity(names.len());
+    let mut originals = HashMap::new();
+    for name in names {
+        if seen.insert(name) {
+            unique.push(name.clone());
+            continue;
+        }
+        let suffixed = (2..)
+            .map(|n| format!("{name}_{n}"))
+            .find(|s| !taken.contains(s))
+            .unwrap();
+        taken.insert(suffixed.clone());
+        originals.insert(suffixed.clone(), name.clone());
+        unique.push(suffixed);
+    }
+    (unique, originals)
+}
+
+fn names_in(store: &Path, file: &Path, ncolumns: usize) -> Vec<String> {
+    let given = read(store).remove(&key(file)).unwrap_or_default();
+    (1..=ncolumns)
+        .map(|i| {
+            let synthetic = format!("c{i}");
+            given.get(&synthetic).cloned().unwrap_or(synthetic)
+        })
+        .collect()
+}
+
+fn rename_in(store: &Path, file: &Path, old: &str, new: &str) -> Result<(), TVError> {
+    let mut all = read(store);
+    let given = all.entry(key(file)).or_default();
+    let synthetic = given
+        .iter()
+        .find(|(_, name)| *name == old)
+        .map_or(old.to_string(), |(synthetic, _)| synthetic.clone());
+    if synthetic == new {
+        given.remove(&synthetic);
+    } else {
+        given.insert(synthetic, new.to_string());
+    }
+    if let Some(parent) = store.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(&all)
+        .map_err(|e| TVError::LoadingFailed(format!("Storing column names failed: {e}")))?;
+    fs::write(store, content)?;
+    Ok(())
+}
+
+fn read(store: &Path) -> GivenNames {
+    let Ok(content) = fs::read_to_string(store) else {
+        return GivenNames::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Ignoring invalid column names file {store:?}: {e}");
+        GivenNames::new()
+    })
+}
+
+fn key(file: &Path) -> String {
+    fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remember_renamed_columns() {
+        let store = std::env::temp_dir().join(format!("tv-names-{}.json", std::process::id()));
+        let file = Path::new("tests/fixtures/testdata_01.csv");
+        assert_eq!(names_in(&store, file, 3), ["c1", "c2", "c3"]);
+
+        rename_in(&store, file, "c2", "price").unwrap();
+        rename_in(&store, file, "price", "unit price").unwrap();
+        assert_eq!(names_in(&store, file, 3), ["c1", "unit price", "c3"]);
+        assert_eq!(names_in(&store, Path::new("other.csv"), 2), ["c1", "c2"]);
+
+        // Back to the synthetic name forgets it
+        rename_in(&store, file, "unit price", "c2").unwrap();
+        let restored = names_in(&store, file, 3);
+        fs::remove_file(&store).unwrap();
+        assert_eq!(restored, ["c1", "c2", "c3"]);
+    }
+
+    #[test]
+    fn suffix_repeated_names() {
+        let names = ["id", "name", "name", "name_2", "name"].map(String::from);
+        let (unique, originals) = disambiguate(&names);
+        assert_eq!(unique, ["id", "name", "name_3", "name_2", "name_4"]);
+        assert_eq!(originals.len(), 2);
+        assert_eq!(originals["name_4"], "name");
+    }
+}
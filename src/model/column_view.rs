@@ -1,4 +1,11 @@
-use polars::prelude::DataType;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use polars::prelude::{AnyValue, DataType, Float64Chunked, Series, StringChunked};
+use ratatui::style::{Style, palette::tailwind};
+use rayon::prelude::*;
+
+use crate::renderer::{CellRenderer, RenderedCell};
 
 pub struct Column {
     pub idx: u16,
@@ -6,33 +13,342 @@ pub struct Column {
     pub status: ColumnStatus,
     pub max_width: usize,
     pub render_width: usize,
-    pub data: Vec<String>,
+    values: ColumnValues,
     pub dtype: DataType,
+    pub base: NumberBase,
+    pub compact: bool, // Shorten uuids to their first block
+    pub renderer: Option<Arc<dyn CellRenderer>>,
+    pub unparsed: HashMap<usize, String>, // Text of cells a cast could not parse per row, shown instead of null
+    pub folded: bool, // Shown in the summary column of the columns sharing its name prefix
+    pub fill_down: bool, // Empty cells show the value above them, dimmed
+    pub hidden: bool, // The name does not match the pattern columns are filtered by
+    pub differs: HashSet<usize>, // Rows with another value than the column it is compared to, see :diffcols
 }
 
 impl Column {
+    pub fn new(idx: usize, series: Series) -> Self {
+        let mut column = Column {
+            idx: idx as u16,
+            name: series.name().to_string(),
+            status: ColumnStatus::NORMAL,
+            max_width: 0,
+            render_width: 0, // Will be set later
+            dtype: series.dtype().clone(),
+            values: ColumnValues::new(series),
+            base: NumberBase::Decimal,
+            compact: false,
+            renderer: None,
+            unparsed: HashMap::new(),
+            folded: false,
+            fill_down: false,
+            hidden: false,
+            differs: HashSet::new(),
+        };
+        column.update_max_width();
+        column
+    }
+
     pub fn as_string(&self) -> String {
         format!(
-            "{} \"{}\", {:?}, width_max: {}, render_width: {}, # rows {}",
+            "{} \"{}\", {:?}, width_max: {}, render_width: {}, # rows {}, renderer: {}",
             self.idx,
             self.name,
             self.status,
             self.max_width,
             self.render_width,
-            self.data.len(),
+            self.len(),
+            self.renderer.as_ref().map(|r| r.name()).unwrap_or("-"),
         )
     }
 
+    // Cell content and style as it should be displayed in the table
+    pub fn display_cell(&self, ridx: usize) -> RenderedCell {
+        if let Some(text) = self.unparsed.get(&ridx) {
+            return RenderedCell {
+                text: text.clone(),
+                style: Some(Style::new().fg(tailwind::RED.c500).crossed_out()),
+            };
+        }
+        let value = self.values.get(ridx);
+        let text = if self.compact && is_uuid(&value) {
+            format!("{}…", &value[..UUID_COMPACT_LENGTH])
+        } else {
+            self.full_value(ridx)
+        };
+        let mut cell = match self.renderer.as_ref() {
+            Some(renderer) => renderer.render(&text),
+            None => RenderedCell { text, style: None },
+        };
+        if self.differs.contains(&ridx) {
+            cell.style = Some(
+                cell.style
+                    .unwrap_or_default()
+                    .fg(tailwind::ORANGE.c400)
+                    .bold(),
+            );
+        }
+        cell
+    }
+
+    // Cell content as it should be displayed in the table
+    pub fn display_value(&self, ridx: usize) -> String {
+        self.display_cell(ridx).text
+    }
+
+    // Cell content as it should be displayed without any shortening
+    pub fn full_value(&self, ridx: usize) -> String {
+        self.base.format(&self.values.get(ridx))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.series.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Cell string of a single row, as loaded from the dataframe
+    pub fn value(&self, ridx: usize) -> Arc<str> {
+        self.values.get(ridx)
+    }
+
+    // Values of numeric columns as floats, None for other columns
+    pub fn numbers(&self) -> Option<Float64Chunked> {
+        if !self.dtype.is_primitive_numeric() {
+            return None;
+        }
+        let values = self.values.series.cast(&DataType::Float64).ok()?;
+        values.f64().ok().cloned()
+    }
+
+    // Cell strings of all rows, they are created on first use
+    pub fn values(&self) -> &[Arc<str>] {
+        self.values.all()
+    }
+
+    // Cell strings of all rows, to be used by another thread
+    pub fn shared_values(&self) -> Arc<[Arc<str>]> {
+        Arc::clone(self.values.all())
+    }
+
+    pub fn set_base(&mut self, base: NumberBase) {
+        self.base = base;
+        self.update_max_width();
+    }
+
+    pub fn set_renderer(&mut self, renderer: Option<Arc<dyn CellRenderer>>) {
+        self.renderer = renderer;
+        self.update_max_width();
+    }
+
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+        self.update_max_width();
+    }
+
+    // Show the modified data of the column, e.g. after editing cells
+    pub fn set_series(&mut self, series: Series) {
+        self.dtype = series.dtype().clone();
+        self.values = ColumnValues::new(series);
+        self.update_max_width();
+    }
+
+    // Estimated from the first rows, unless all values are loaded anyway
+    fn update_max_width(&mut self) {
+        let nrows = if self.values.all.get().is_some() {
+            self.len()
+        } else {
+            self.len().min(WIDTH_SAMPLE_ROWS)
+        };
+        self.max_width = (0..nrows)
+            .map(|ridx| self.display_value(ridx).len())
+            .max()
+            .unwrap_or(0);
+    }
+
+    // The cell is null or empty, cells a cast could not parse show their text
+    pub fn is_blank(&self, ridx: usize) -> bool {
+        let value = self.values.get(ridx);
+        (value.is_empty() || value.as_ref() == "∅") && !self.unparsed.contains_key(&ridx)
+    }
+
+    // A column holds uuids if all non null values look like one
+    pub fn is_uuid_column(&self) -> bool {
+        let mut values = self
+            .values()
+            .iter()
+            .filter(|v| v.as_ref() != "∅" && !v.is_empty())
+            .peekable();
+        values.peek().is_some() && values.all(|v| is_uuid(v))
+    }
+
     // Return mask index positions of rows in the column that match given term
     pub fn search(&self, term: &str, mask: &[usize]) -> Vec<usize> {
         let mut matches = Vec::new();
         for (midx, &m) in mask.iter().enumerate() {
-            if self.data[m].contains(term) {
+            if self.values()[m].contains(term) {
                 matches.push(midx)
             }
         }
         matches
     }
+
+    // Return up to n distinct values of the masked rows that are closest to the given term.
+    pub fn suggestions(&self, term: &str, mask: &[usize], n: usize) -> Vec<String> {
+        let term = term.to_lowercase();
+        let values = self.values();
+        let distinct: HashSet<&str> = mask.iter().map(|&m| &*values[m]).collect();
+
+        let mut ranked: Vec<(usize, &str)> = distinct
+            .into_iter()
+            .map(|v| {
+                let value = v.to_lowercase();
+                // Values containing the term ignoring case are the best suggestions
+                let distance = if value.contains(&term) {
+                    0
+                } else {
+                    edit_distance(&term, &value)
+                };
+                (distance, v)
+            })
+            .filter(|&(distance, _)| distance <= std::cmp::max(term.chars().count() / 2, 1))
+            .collect();
+        ranked.sort_unstable();
+        ranked
+            .into_iter()
+            .take(n)
+            .map(|(_, v)| v.to_string())
+            .collect()
+    }
+}
+
+// Number of rows that are converted to strings together when they are shown
+const BLOCK_ROWS: usize = 256;
+// Blocks of recently shown rows that are kept per column
+const MAX_CACHED_BLOCKS: usize = 32;
+// Number of rows that are converted together in one thread, when all values of a column are needed
+const LOAD_CHUNK_ROWS: usize = 100_000;
+// Rows the column width is estimated from
+const WIDTH_SAMPLE_ROWS: usize = 1000;
+
+type Block = Arc<Vec<Arc<str>>>; // Cell strings of BLOCK_ROWS consecutive rows
+
+// Cell strings of a column, created from the typed series only when needed.
+// Shown rows are converted in blocks around them, operations on the whole column
+// (search, sort, histograms, ...) convert all rows once.
+struct ColumnValues {
+    series: Series,
+    all: OnceLock<Arc<[Arc<str>]>>,
+    blocks: Mutex<VecDeque<(usize, Block)>>, // Most recently used last
+}
+
+impl ColumnValues {
+    fn new(series: Series) -> Self {
+        ColumnValues {
+            series,
+            all: OnceLock::new(),
+            blocks: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn get(&self, ridx: usize) -> Arc<str> {
+        if let Some(all) = self.all.get() {
+            return all[ridx].clone();
+        }
+        let block_idx = ridx / BLOCK_ROWS;
+        let mut blocks = self.blocks.lock().unwrap();
+        let block = match blocks.iter().position(|(idx, _)| *idx == block_idx) {
+            Some(pos) => blocks.remove(pos).unwrap().1,
+            None => Arc::new(to_strings(
+                &self
+                    .series
+                    .slice((block_idx * BLOCK_ROWS) as i64, BLOCK_ROWS),
+            )),
+        };
+        blocks.push_back((block_idx, block.clone()));
+        if blocks.len() > MAX_CACHED_BLOCKS {
+            blocks.pop_front();
+        }
+        block[ridx % BLOCK_ROWS].clone()
+    }
+
+    fn all(&self) -> &Arc<[Arc<str>]> {
+        self.all.get_or_init(|| {
+            let chunks: Vec<Vec<Arc<str>>> = (0..self.series.len().div_ceil(LOAD_CHUNK_ROWS))
+                .into_par_iter()
+                .map(|chunk| {
+                    to_strings(
+                        &self
+                            .series
+                            .slice((chunk * LOAD_CHUNK_ROWS) as i64, LOAD_CHUNK_ROWS),
+                    )
+                })
+                .collect();
+            chunks.concat().into()
+        })
+    }
+}
+
+// Display strings of the values, line breaks are shown as ↵ and nulls as ∅
+fn to_strings(series: &Series) -> Vec<Arc<str>> {
+    let strings = series
+        .cast(&DataType::String)
+        .ok()
+        .and_then(|strings| strings.str().ok().map(intern));
+    match strings {
+        Some(strings) => strings,
+        // E.g. nested types, that can not be cast
+        None => (0..series.len())
+            .map(|idx| match series.get(idx) {
+                Ok(AnyValue::Null) | Err(_) => Arc::from("∅"),
+                Ok(value) => Arc::from(value.to_string().replace('\n', " ↵ ")),
+            })
+            .collect(),
+    }
+}
+
+// Repeated values share one allocation
+fn intern(values: &StringChunked) -> Vec<Arc<str>> {
+    let mut pool: HashMap<&str, Arc<str>> = HashMap::new();
+    let null: Arc<str> = Arc::from("∅");
+    values
+        .into_iter()
+        .map(|value| match value {
+            Some(s) => pool
+                .entry(s)
+                .or_insert_with(|| Arc::from(s.replace("\r\n", " ↵ ").replace('\n', " ↵ ")))
+                .clone(),
+            None => null.clone(),
+        })
+        .collect()
+}
+
+const UUID_COMPACT_LENGTH: usize = 8;
+
+// Check for the canonical 8-4-4-4-12 hex digit form of a uuid
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(idx, c)| match idx {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
 }
 
 #[derive(Clone)]
@@ -40,6 +356,7 @@ pub struct ColumnView {
     pub name: String,
     pub width: usize,
     pub data: Vec<String>,
+    pub styles: Vec<Option<Style>>, // Per cell styles set by renderers, empty if unstyled
 }
 
 impl ColumnView {
@@ -48,6 +365,7 @@ impl ColumnView {
             name: "".to_string(),
             width: 0,
             data: Vec::new(),
+            styles: Vec::new(),
         }
     }
 }
@@ -58,3 +376,47 @@ pub enum ColumnStatus {
     EXPANDED,
     COLLAPSED,
 }
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NumberBase {
+    Decimal,
+    Hex,
+    Binary,
+    Octal,
+}
+
+impl NumberBase {
+    pub fn next(&self) -> Self {
+        match self {
+            NumberBase::Decimal => NumberBase::Hex,
+            NumberBase::Hex => NumberBase::Binary,
+            NumberBase::Binary => NumberBase::Octal,
+            NumberBase::Octal => NumberBase::Decimal,
+        }
+    }
+
+    // Format an integer value in this base, values that are no integers are returned unchanged
+    pub fn format(&self, value: &str) -> String {
+        if let NumberBase::Decimal = self {
+            return value.to_string();
+        }
+        let formatted = match value.parse::<u64>() {
+            Ok(v) => self.format_bits(v),
+            // Negative values are shown in their two's complement representation
+            Err(_) => value
+                .parse::<i64>()
+                .ok()
+                .and_then(|v| self.format_bits(v as u64)),
+        };
+        formatted.unwrap_or_else(|| value.to_string())
+    }
+
+    fn format_bits(&self, v: u64) -> Option<String> {
+        match self {
+            NumberBase::Decimal => None,
+            NumberBase::Hex => Some(format!("{v:#x}")),
+            NumberBase::Binary => Some(format!("{v:#b}")),
+            NumberBase::Octal => Some(format!("{v:#o}")),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::Cursor;
+
+use arrow::ipc::writer::FileWriter;
+use connectorx::get_arrow::get_arrow;
+use connectorx::source_router::SourceConn;
+use connectorx::sql::CXQuery;
+use polars::prelude::*;
+
+use crate::domain::TVError;
+
+// Databases are queried with connector-x, its arrow record batches are handed to polars as arrow ipc.
+
+pub fn is_database_url(source: &str) -> bool {
+    ["postgres://", "postgresql://", "mysql://"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+}
+
+// Query of all rows of the table, e.g. `SELECT * FROM "public"."orders"`
+pub fn select_table(url: &str, table: &str) -> String {
+    let quote = if url.starts_with("mysql://") {
+        '`'
+    } else {
+        '"'
+    };
+    let name = table
+        .split('.')
+        .map(|part| {
+            let escaped = part.replace(quote, &format!("{quote}{quote}"));
+            format!("{quote}{escaped}{quote}")
+        })
+        .collect::<Vec<String>>()
+        .join(".");
+    format!("SELECT * FROM {name}")
+}
+
+// Run the query against the database at the url and return the result
+pub fn read_query(url: &str, query: &str) -> Result<DataFrame, TVError> {
+    let failed = |e: String| TVError::LoadingFailed(format!("Database query failed! {e}"));
+    let source = SourceConn::try_from(url).map_err(|e| failed(e.to_string()))?;
+    let query = CXQuery::from(query.trim().trim_end_matches(';'));
+    let batches = get_arrow(&source, None, &[query], None)
+        .map_err(|e| failed(e.to_string()))?
+        .arrow()
+        .map_err(|e| failed(e.to_string()))?;
+    let Some(schema) = batches.first().map(|batch| batch.schema()) else {
+        return Ok(DataFrame::empty());
+    };
+
+    let mut ipc = Vec::new();
+    let mut writer = FileWriter::try_new(&mut ipc, &schema).map_err(|e| failed(e.to_string()))?;
+    for batch in batches.iter() {
+        writer.write(batch).map_err(|e| failed(e.to_string()))?;
+    }
+    writer.finish().map_err(|e| failed(e.to_string()))?;
+    drop(writer);
+    Ok(IpcReader::new(Cursor::new(ipc)).finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_table_names() {
+        assert_eq!(
+            select_table("postgres://localhost/db", "public.Orders"),
+            r#"SELECT * FROM "public"."Orders""#
+        );
+        assert_eq!(
+            select_table("mysql://localhost/db", "or`ders; DROP TABLE x"),
+            "SELECT * FROM `or``ders; DROP TABLE x`"
+        );
+    }
+}
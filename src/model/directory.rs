@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use super::Model;
+use crate::domain::TVError;
+
+// Widest the sidebar gets, longer file names are cut off
+const MAX_SIDEBAR_WIDTH: usize = 40;
+
+// Tabular files of a directory, listed in a sidebar to load them one by one
+pub struct Directory {
+    pub path: PathBuf,
+    pub files: Vec<(String, u64)>, // File name and size in bytes
+    pub selected: usize,
+    pub focused: bool, // Keys move the selection instead of the table
+}
+
+impl Directory {
+    // The csv, parquet, xlsx and arrow files of the directory, sorted by name
+    pub fn scan(path: &Path) -> Result<Self, TVError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && Model::is_tabular_file(&entry.path()) {
+                files.push((
+                    entry.file_name().to_string_lossy().to_string(),
+                    metadata.len(),
+                ));
+            }
+        }
+        if files.is_empty() {
+            return Err(TVError::LoadingFailed(format!(
+                "No csv, parquet, xlsx or arrow files in {}!",
+                path.display()
+            )));
+        }
+        files.sort();
+        Ok(Directory {
+            path: path.to_path_buf(),
+            files,
+            selected: 0,
+            focused: true,
+        })
+    }
+
+    pub fn selected_path(&self) -> PathBuf {
+        self.path.join(&self.files[self.selected].0)
+    }
+
+    pub fn select(&mut self, step: isize) {
+        self.selected = self
+            .selected
+            .saturating_add_signed(step)
+            .min(self.files.len() - 1);
+    }
+
+    // Columns needed to show the names and sizes, with a border
+    pub fn width(&self) -> usize {
+        let widest = self
+            .files
+            .iter()
+            .map(|(name, size)| name.chars().count() + format_size(*size).len() + 1)
+            .max()
+            .unwrap_or(0);
+        (widest + 2).min(MAX_SIDEBAR_WIDTH)
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_tabular_files() {
+        let mut directory = Directory::scan(Path::new("tests/fixtures")).unwrap();
+        assert_eq!(directory.files[0], ("testdata_01.csv".to_string(), 732));
+        directory.select(-1);
+        assert_eq!(directory.selected, 0);
+        directory.select(100);
+        assert_eq!(
+            directory.selected_path(),
+            PathBuf::from("tests/fixtures/testdata_06.csv")
+        );
+        assert_eq!(format_size(756297), "738.6 KB");
+    }
+}
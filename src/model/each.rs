@@ -0,0 +1,136 @@
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use crate::domain::Event;
+
+// Fill the values of the `{column}` placeholders into the template, quoted for the shell
+pub fn expand(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut command = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        let value = value(name).ok_or(format!("Unknown column: {name}"))?;
+        command.push_str(&rest[..start]);
+        command.push_str(&shell_quote(&value));
+        rest = &rest[start + end + 1..];
+    }
+    command.push_str(rest);
+    Ok(command)
+}
+
+// Single quoted, so values can not run commands of their own
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// Commands of :each, run one after the other on a worker thread
+pub struct EachRun {
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub log: String, // Output of the commands finished so far
+    receiver: Receiver<(String, bool)>,
+}
+
+impl EachRun {
+    pub fn start(commands: Vec<String>, wakeup: Option<Sender<Event>>) -> Self {
+        let total = commands.len();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for command in commands {
+                let result = run(&command);
+                if sender.send(result).is_err() {
+                    return;
+                }
+                if let Some(wakeup) = wakeup.as_ref() {
+                    let _ = wakeup.send(Event::Wakeup);
+                }
+            }
+        });
+        EachRun {
+            total,
+            done: 0,
+            failed: 0,
+            log: String::new(),
+            receiver,
+        }
+    }
+
+    // Collect the commands finished since the last poll. Returns true if there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok((log, success)) => {
+                    self.log.push_str(&log);
+                    self.done += 1;
+                    self.failed += !success as usize;
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // The worker stopped early, e.g. as it panicked
+                    self.total = self.done;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.total
+    }
+}
+
+// Log entry with the output of the command, and if it succeeded
+fn run(command: &str) -> (String, bool) {
+    let mut log = format!("$ {command}\n");
+    let success = match Command::new("sh").arg("-c").arg(command).output() {
+        Ok(output) => {
+            log.push_str(&String::from_utf8_lossy(&output.stdout));
+            log.push_str(&String::from_utf8_lossy(&output.stderr));
+            if !output.status.success() {
+                log.push_str(&format!("[{}]\n", output.status));
+            }
+            output.status.success()
+        }
+        Err(e) => {
+            log.push_str(&format!("[failed to run: {e}]\n"));
+            false
+        }
+    };
+    log.push('\n');
+    (log, success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_commands_per_row() {
+        let value = |name: &str| (name == "job id").then(|| "it's 42".to_string());
+        let command = expand("echo retry {job id}", value).unwrap();
+        assert_eq!(command, r"echo retry 'it'\''s 42'");
+        assert_eq!(
+            expand("echo {name}", value),
+            Err("Unknown column: name".to_string())
+        );
+
+        let mut each = EachRun::start(vec![command, "exit 3".to_string()], None);
+        while !each.is_done() {
+            each.poll();
+        }
+        assert_eq!(each.failed, 1);
+        assert!(
+            each.log
+                .starts_with("$ echo retry 'it'\\''s 42'\nretry it's 42\n")
+        );
+        assert!(each.log.contains("exit status: 3"));
+    }
+}
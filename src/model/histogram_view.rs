@@ -1,14 +1,33 @@
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+    time::Instant,
+};
 
+use polars::prelude::Float64Chunked;
 use tracing::trace;
 
+use crate::config::DEFAULT_HISTOGRAM_BINS;
+use crate::domain::Event;
 use crate::model::{Column, TableView, UIData};
 
 use super::ColumnView;
 
+// Larger tables get a histogram of their first rows first, the full one is counted in the background
+const HISTOGRAM_SAMPLE_ROWS: usize = 100_000;
+const NO_COLUMN: usize = 99999;
+
+pub type Histogram = (Vec<usize>, Vec<Arc<str>>); // Counts and values, most frequent first
+
 pub struct HistogramView {
-    pub value_data: Vec<String>,
-    pub column_histograms: HashMap<usize, (Vec<usize>, Vec<String>)>,
+    pub value_data: Vec<Arc<str>>,
+    pub column_histograms: HashMap<usize, Histogram>,
+    pending: HashMap<usize, Receiver<Histogram>>, // Full histograms still counted in the background
+    wakeup: Option<Sender<Event>>,                // Told when a background count finished
     pub value_width: usize,
     pub value_view: ColumnView,
     pub count_data: Vec<String>, // Count in absolute and relative values
@@ -21,6 +40,7 @@ pub struct HistogramView {
     pub last_column_idx: usize,
     pub height: usize, // UI height
     pub width: usize,  // UI Width
+    pub bins: usize,   // Bins of numeric columns, 0 counts every distinct value
 }
 
 impl HistogramView {
@@ -28,6 +48,8 @@ impl HistogramView {
         HistogramView {
             value_data: Vec::new(),
             column_histograms: HashMap::new(),
+            pending: HashMap::new(),
+            wakeup: None,
             value_width: 0,
             value_view: ColumnView::empty(),
             count_data: Vec::new(),
@@ -37,33 +59,113 @@ impl HistogramView {
             curser_row: 0,
             curser_offset: 0,
             last_update: Instant::now(),
-            last_column_idx: 99999,
+            last_column_idx: NO_COLUMN,
             height: 0,
             width: 0,
+            bins: DEFAULT_HISTOGRAM_BINS,
+        }
+    }
+
+    pub fn new(bins: usize) -> Self {
+        HistogramView {
+            bins,
+            ..HistogramView::empty()
         }
     }
 
+    // Forget all histograms, e.g. after the columns were reordered
+    pub fn clear(&mut self) {
+        self.column_histograms.clear();
+        self.pending.clear();
+        self.last_column_idx = NO_COLUMN;
+    }
+
+    pub fn set_wakeup(&mut self, wakeup: Option<Sender<Event>>) {
+        self.wakeup = wakeup;
+    }
+
+    pub fn set_bins(&mut self, bins: usize) {
+        self.bins = bins;
+        self.clear();
+    }
+
     fn calculate_column_histogram(
         &mut self,
         column_idx: usize,
         data: &mut Vec<Column>,
         table: &TableView,
     ) {
+        if self.column_histograms.contains_key(&column_idx) {
+            return;
+        }
         trace!("Calculate histogram for column {}", column_idx);
-        self.column_histograms.entry(column_idx).or_insert_with(|| {
-            let column_data = &data[column_idx].data;
+        if let Some(histogram) = data[column_idx]
+            .numbers()
+            .and_then(|numbers| bin_values(&numbers, &table.rows, self.bins))
+        {
+            self.column_histograms.insert(column_idx, histogram);
+            return;
+        }
+        let column_data = data[column_idx].values();
+        if table.rows.len() <= HISTOGRAM_SAMPLE_ROWS {
+            let histogram = count_values(table.rows.iter().map(|&ridx| &column_data[ridx]));
+            self.column_histograms.insert(column_idx, histogram);
+            return;
+        }
 
-            let mut counts: HashMap<String, usize> = HashMap::new();
-            for &ridx in table.rows.iter() {
-                *counts.entry(column_data[ridx].clone()).or_insert(0) += 1;
+        let sample = count_values(
+            table.rows[..HISTOGRAM_SAMPLE_ROWS]
+                .iter()
+                .map(|&ridx| &column_data[ridx]),
+        );
+        self.column_histograms.insert(column_idx, sample);
+
+        // Cloning the interned values only copies pointers
+        let values: Vec<Arc<str>> = table
+            .rows
+            .iter()
+            .map(|&ridx| column_data[ridx].clone())
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        let wakeup = self.wakeup.clone();
+        thread::spawn(move || {
+            let _ = sender.send(count_values(values.iter()));
+            if let Some(wakeup) = wakeup {
+                let _ = wakeup.send(Event::Wakeup);
             }
-            let mut sorted: Vec<(usize, String)> =
-                counts.iter().map(|(k, v)| (*v, k.clone())).collect();
-            sorted.sort_unstable();
-            sorted.reverse();
-            let (counts, values): (Vec<usize>, Vec<String>) = sorted.into_iter().unzip();
-            (counts, values)
         });
+        self.pending.insert(column_idx, receiver);
+    }
+
+    // The histogram of the column is a sample, its full count is still running
+    pub fn is_pending(&self, column_idx: usize) -> bool {
+        self.pending.contains_key(&column_idx)
+    }
+
+    // Replace sampled histograms by finished background counts. Returns true if the shown histogram changed.
+    pub fn poll_background(&mut self) -> bool {
+        let mut changed = false;
+        let mut finished = Vec::new();
+        for (&column_idx, receiver) in self.pending.iter() {
+            match receiver.try_recv() {
+                Ok(histogram) => {
+                    trace!("Full histogram for column {column_idx} is ready");
+                    self.column_histograms.insert(column_idx, histogram);
+                    finished.push(column_idx);
+                    changed |= column_idx == self.last_column_idx;
+                }
+                Err(TryRecvError::Disconnected) => finished.push(column_idx),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        for column_idx in finished {
+            self.pending.remove(&column_idx);
+        }
+        if changed {
+            // Rebuild the shown rows with the full counts
+            self.last_column_idx = NO_COLUMN;
+        }
+        changed
     }
 
     pub fn move_selection_up(
@@ -103,8 +205,10 @@ impl HistogramView {
                 // At the bottom of the table, need to shift table down
                 self.curser_offset =
                     std::cmp::min(self.curser_offset + size, self.value_data.len() - 1);
-                self.curser_row =
-                    std::cmp::min(self.height - 1, self.value_data.len() - self.curser_offset);
+                self.curser_row = std::cmp::min(
+                    self.height - 1,
+                    self.value_data.len() - self.curser_offset - 1,
+                );
             }
             self.update(self.last_column_idx, data, table, uidata);
         }
@@ -126,7 +230,7 @@ impl HistogramView {
             self.height = table.heigh;
             self.width = table.width;
 
-            let nrecords = table.rows.len();
+            let nrecords: usize = counts.0.iter().sum(); // Less than all rows while sampled
             self.count_data = counts
                 .0
                 .iter()
@@ -143,13 +247,18 @@ impl HistogramView {
             name: "Counts".to_string(),
             data: self.count_data[rbegin..rend].to_vec(),
             width: self.count_width,
+            styles: Vec::new(),
         };
 
-        self.value_width = self.width - self.count_width;
+        self.value_width = self.width.saturating_sub(self.count_width);
         self.value_view = ColumnView {
             name: "Values".to_string(),
-            data: self.value_data[rbegin..rend].to_vec(),
+            data: self.value_data[rbegin..rend]
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
             width: self.value_width,
+            styles: Vec::new(),
         };
 
         self.last_column_idx = column_idx;
@@ -157,12 +266,116 @@ impl HistogramView {
     }
 
     pub fn update_uidata(&self, table_name: &str, uidata: &mut UIData) {
-        uidata.name = format!("H[{}]", table_name);
+        if self.pending.contains_key(&self.column_idx) {
+            uidata.name = format!(
+                "H[{}] (first {HISTOGRAM_SAMPLE_ROWS} rows, counting ...)",
+                table_name
+            );
+        } else {
+            uidata.name = format!("H[{}]", table_name);
+        }
         uidata.table = vec![self.count_view.clone(), self.value_view.clone()];
         uidata.selected_column = 1;
         uidata.nrows = self.value_data.len();
         uidata.selected_row = self.curser_row;
         uidata.abs_selected_row = self.curser_row + self.curser_offset;
+        uidata.highlighted_rows.clear();
+        uidata.bookmarked_rows.clear();
+        uidata.marked_rows.clear();
         uidata.last_update = Instant::now();
     }
 }
+
+// Count numeric values in equally wide bins between their min and max, ordered by value.
+// Returns None if there are not more distinct values than bins. The bin labels are
+// `<from>..<to>` filter terms, the last bin includes its upper bound.
+fn bin_values(numbers: &Float64Chunked, rows: &[usize], mut bins: usize) -> Option<Histogram> {
+    if bins == 0 {
+        return None;
+    }
+    let values: Vec<Option<f64>> = rows
+        .iter()
+        .map(|&ridx| numbers.get(ridx).filter(|v| v.is_finite()))
+        .collect();
+    let mut distinct = HashSet::new();
+    for value in values.iter().flatten() {
+        distinct.insert(value.to_bits());
+        if distinct.len() > bins {
+            break;
+        }
+    }
+    if distinct.len() <= bins {
+        return None;
+    }
+
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((f64::MAX, f64::MIN), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    let is_integer = values.iter().flatten().all(|v| v.fract() == 0.0);
+    let (width, decimals) = if is_integer {
+        let width = ((max - min + 1.0) / bins as f64).ceil();
+        // Wider bins of integers can cover the range with fewer of them
+        bins = ((max - min + 1.0) / width).ceil() as usize;
+        (width, 0)
+    } else {
+        let width = (max - min) / bins as f64;
+        (width, (1 - width.log10().floor() as i32).max(0))
+    };
+    // Edges are rounded to the shown precision, so the labels match what is counted
+    let scale = 10f64.powi(decimals);
+    let edges: Vec<f64> = (0..=bins)
+        .map(|idx| match idx {
+            0 => (min * scale).floor() / scale,
+            idx if idx == bins && !is_integer => (max * scale).ceil() / scale,
+            idx => ((min + idx as f64 * width) * scale).round() / scale,
+        })
+        .collect();
+
+    let mut counts = vec![0; bins];
+    let mut nulls = 0;
+    for value in values.iter() {
+        match value {
+            Some(v) => {
+                let bin = edges[1..].partition_point(|&edge| edge <= *v).min(bins - 1);
+                counts[bin] += 1;
+            }
+            None => nulls += 1,
+        }
+    }
+
+    let decimals = decimals as usize;
+    let mut labels: Vec<Arc<str>> = (0..bins)
+        .map(|bin| {
+            let range = if bin == bins - 1 && !is_integer {
+                "..="
+            } else {
+                ".."
+            };
+            Arc::from(format!(
+                "{:.decimals$}{range}{:.decimals$}",
+                edges[bin],
+                edges[bin + 1]
+            ))
+        })
+        .collect();
+    if nulls > 0 {
+        counts.push(nulls);
+        labels.push(Arc::from("∅"));
+    }
+    Some((counts, labels))
+}
+
+fn count_values<'a>(values: impl Iterator<Item = &'a Arc<str>>) -> Histogram {
+    // Keyed by the interned cell values, so no strings are copied
+    let mut counts: HashMap<Arc<str>, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value.clone()).or_insert(0) += 1;
+    }
+    let mut sorted: Vec<(usize, Arc<str>)> = counts.into_iter().map(|(k, v)| (v, k)).collect();
+    sorted.sort_unstable();
+    sorted.reverse();
+    sorted.into_iter().unzip()
+}
@@ -0,0 +1,86 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use super::Column;
+
+const STYLESHEET: &str = "
+body { font-family: sans-serif; margin: 2em; }
+h1 { font-size: 1.2em; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { border: 1px solid #d4d4d8; padding: 0.3em 0.6em; text-align: left; white-space: pre-wrap; }
+th { background: #f4f4f5; position: sticky; top: 0; }
+tr:nth-child(even) td { background: #fafafa; }
+";
+
+// Standalone html document with the given rows as table, cells are styled like the renderers style them
+pub fn document(title: &str, columns: &[Column], rows: &[usize]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{STYLESHEET}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{} rows</p>\n<table>\n<tr>",
+        rows.len(),
+        title = escape(title)
+    );
+    for column in columns {
+        html.push_str(&format!("<th>{}</th>", escape(&column.name)));
+    }
+    html.push_str("</tr>\n");
+
+    for &ridx in rows {
+        html.push_str("<tr>");
+        for column in columns {
+            let text = column.full_value(ridx);
+            let style = column
+                .renderer
+                .as_ref()
+                .and_then(|renderer| renderer.render(&text).style)
+                .map(css)
+                .filter(|css| !css.is_empty());
+            match style {
+                Some(css) => html.push_str(&format!("<td style=\"{css}\">{}</td>", escape(&text))),
+                None => html.push_str(&format!("<td>{}</td>", escape(&text))),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css(style: Style) -> String {
+    let mut css = Vec::new();
+    if let Some(color) = style.fg.and_then(css_color) {
+        css.push(format!("color: {color}"));
+    }
+    if let Some(color) = style.bg.and_then(css_color) {
+        css.push(format!("background: {color}"));
+    }
+    if style.add_modifier.contains(Modifier::BOLD) {
+        css.push("font-weight: bold".to_string());
+    }
+    if style.add_modifier.contains(Modifier::UNDERLINED) {
+        css.push("text-decoration: underline".to_string());
+    }
+    css.join("; ")
+}
+
+fn css_color(color: Color) -> Option<String> {
+    let name = match color {
+        Color::Rgb(r, g, b) => return Some(format!("#{r:02x}{g:02x}{b:02x}")),
+        Color::Black => "black",
+        Color::Red | Color::LightRed => "red",
+        Color::Green | Color::LightGreen => "green",
+        Color::Yellow | Color::LightYellow => "goldenrod",
+        Color::Blue | Color::LightBlue => "blue",
+        Color::Magenta | Color::LightMagenta => "magenta",
+        Color::Cyan | Color::LightCyan => "darkcyan",
+        Color::Gray | Color::DarkGray => "gray",
+        Color::White => "white",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
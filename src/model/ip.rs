@@ -0,0 +1,79 @@
+use std::net::IpAddr;
+
+use super::Column;
+
+// Number of values checked when detecting if a column holds ip addresses
+const IP_DETECTION_SAMPLE_SIZE: usize = 100;
+
+// A network in CIDR notation, e.g. 10.0.0.0/8 or fe80::/10
+#[derive(Debug, Clone, Copy)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix: u32,
+}
+
+impl Cidr {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, prefix.parse().ok()?),
+            None => {
+                let address = s.parse::<IpAddr>().ok()?;
+                (address, Self::max_prefix(&address))
+            }
+        };
+        if prefix > Self::max_prefix(&address) {
+            return None;
+        }
+        Some(Cidr { address, prefix })
+    }
+
+    fn max_prefix(address: &IpAddr) -> u32 {
+        match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// Parse the ip address of a cell, ignoring an optional port or prefix length
+pub fn parse_ip(value: &str) -> Option<IpAddr> {
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.split_once('/')?.0.parse().ok())
+        .or_else(|| value.parse::<std::net::SocketAddr>().ok().map(|s| s.ip()))
+}
+
+// A column holds ip addresses if all sampled non null values can be parsed as one
+pub fn is_ip_column(column: &Column) -> bool {
+    let mut sample = column
+        .values()
+        .iter()
+        .filter(|v| v.as_ref() != "∅" && !v.is_empty())
+        .take(IP_DETECTION_SAMPLE_SIZE)
+        .peekable();
+    sample.peek().is_some() && sample.all(|v| parse_ip(v).is_some())
+}
+
+// Return mask index positions of rows in the column whose address is part of the network
+pub fn search_network(column: &Column, network: &Cidr, mask: &[usize]) -> Vec<usize> {
+    mask.iter()
+        .enumerate()
+        .filter(|(_, m)| parse_ip(&column.values()[**m]).is_some_and(|ip| network.contains(&ip)))
+        .map(|(midx, _)| midx)
+        .collect()
+}
@@ -0,0 +1,80 @@
+use std::io::Write;
+
+use polars::prelude::*;
+use serde_json::{Map, Number, Value};
+
+use crate::domain::TVError;
+
+// Write the frame as json array of objects, or as one object per line
+pub fn write(writer: &mut impl Write, frame: &DataFrame, lines: bool) -> Result<(), TVError> {
+    let columns = frame.get_columns();
+    let records = (0..frame.height()).map(|ridx| {
+        let record: Map<String, Value> = columns
+            .iter()
+            .map(|column| {
+                let value = column.get(ridx).map(json_value).unwrap_or(Value::Null);
+                (column.name().to_string(), value)
+            })
+            .collect();
+        Value::Object(record)
+    });
+
+    let to_error =
+        |e: serde_json::Error| TVError::LoadingFailed(format!("Writing json failed! {e}"));
+    if lines {
+        for record in records {
+            serde_json::to_writer(&mut *writer, &record).map_err(to_error)?;
+            writer.write_all(b"\n")?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut *writer, &Value::Array(records.collect()))
+            .map_err(to_error)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// Numbers and booleans keep their type, other values are written as shown by polars
+fn json_value(value: AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Bool(b),
+        AnyValue::UInt8(v) => v.into(),
+        AnyValue::UInt16(v) => v.into(),
+        AnyValue::UInt32(v) => v.into(),
+        AnyValue::UInt64(v) => v.into(),
+        AnyValue::Int8(v) => v.into(),
+        AnyValue::Int16(v) => v.into(),
+        AnyValue::Int32(v) => v.into(),
+        AnyValue::Int64(v) => v.into(),
+        // NaN and infinity have no json representation
+        AnyValue::Float32(v) => Number::from_f64(v as f64).map_or(Value::Null, Value::Number),
+        AnyValue::Float64(v) => Number::from_f64(v).map_or(Value::Null, Value::Number),
+        AnyValue::String(s) => Value::String(s.to_string()),
+        AnyValue::StringOwned(s) => Value::String(s.to_string()),
+        other => Value::String(other.str_value().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_dtypes() {
+        let frame = df!(
+            "id" => [1i64, 2],
+            "price" => [Some(19.99), None],
+            "ok" => [true, false],
+            "name" => ["a \"b\"", "c"],
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        write(&mut out, &frame, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":1,\"price\":19.99,\"ok\":true,\"name\":\"a \\\"b\\\"\"}\n\
+             {\"id\":2,\"price\":null,\"ok\":false,\"name\":\"c\"}\n"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use serde_json::Value;
+
+use super::ui::{JsonData, JsonToken};
+
+enum NodeKind {
+    Open { bracket: char, items: usize },
+    Close { bracket: char },
+    Scalar { text: String, token: JsonToken },
+}
+
+// One line of the pretty printed json document
+struct JsonNode {
+    depth: usize,
+    key: Option<String>,
+    kind: NodeKind,
+    end: usize, // Index of the node following the subtree of an opening node
+    comma: bool,
+    folded: bool,
+}
+
+// Indented json document in which nested objects and arrays can be folded
+pub struct JsonView {
+    title: String,
+    nodes: Vec<JsonNode>,
+    curser_row: usize, // Index in visible nodes
+    curser_offset: usize,
+    height: usize, // UI height
+}
+
+impl JsonView {
+    // Returns None if the value is no json object or array
+    pub fn parse(title: &str, value: &str) -> Option<Self> {
+        let value = value.trim();
+        if !(value.starts_with('{') || value.starts_with('[')) {
+            return None;
+        }
+        let json: Value = serde_json::from_str(value).ok()?;
+        let mut view = JsonView {
+            title: title.to_string(),
+            nodes: Vec::new(),
+            curser_row: 0,
+            curser_offset: 0,
+            height: 0,
+        };
+        view.add_node(&json, None, 0, false);
+        Some(view)
+    }
+
+    fn add_node(&mut self, value: &Value, key: Option<String>, depth: usize, comma: bool) {
+        let (bracket, children): (char, Vec<(Option<String>, &Value)>) = match value {
+            Value::Object(map) => ('{', map.iter().map(|(k, v)| (Some(k.clone()), v)).collect()),
+            Value::Array(values) => ('[', values.iter().map(|v| (None, v)).collect()),
+            _ => ('\0', Vec::new()),
+        };
+
+        if children.is_empty() {
+            // Scalars and empty objects or arrays are a single line
+            let token = match value {
+                Value::String(_) => JsonToken::String,
+                Value::Number(_) => JsonToken::Number,
+                Value::Bool(_) => JsonToken::Bool,
+                Value::Null => JsonToken::Null,
+                _ => JsonToken::Punctuation,
+            };
+            let idx = self.nodes.len();
+            self.nodes.push(JsonNode {
+                depth,
+                key,
+                kind: NodeKind::Scalar {
+                    text: value.to_string(),
+                    token,
+                },
+                end: idx + 1,
+                comma,
+                folded: false,
+            });
+            return;
+        }
+
+        let open_idx = self.nodes.len();
+        self.nodes.push(JsonNode {
+            depth,
+            key,
+            kind: NodeKind::Open {
+                bracket,
+                items: children.len(),
+            },
+            end: 0, // Set once the children are added
+            comma,
+            folded: false,
+        });
+        let nchildren = children.len();
+        for (idx, (child_key, child)) in children.into_iter().enumerate() {
+            self.add_node(child, child_key, depth + 1, idx + 1 < nchildren);
+        }
+        self.nodes.push(JsonNode {
+            depth,
+            key: None,
+            kind: NodeKind::Close {
+                bracket: if bracket == '{' { '}' } else { ']' },
+            },
+            end: self.nodes.len() + 1,
+            comma,
+            folded: false,
+        });
+        self.nodes[open_idx].end = self.nodes.len();
+    }
+
+    // Indices of nodes that are not hidden in a folded parent
+    fn visible_nodes(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut idx = 0;
+        while idx < self.nodes.len() {
+            visible.push(idx);
+            let node = &self.nodes[idx];
+            idx = if node.folded { node.end } else { idx + 1 };
+        }
+        visible
+    }
+
+    fn selected_node(&self) -> usize {
+        self.visible_nodes()[self.curser_offset + self.curser_row]
+    }
+
+    pub fn move_selection(&mut self, step: isize) {
+        let nvisible = self.visible_nodes().len();
+        let selected = (self.curser_offset + self.curser_row) as isize + step;
+        self.select(selected.clamp(0, nvisible as isize - 1) as usize);
+    }
+
+    fn select(&mut self, selected: usize) {
+        if selected < self.curser_offset {
+            self.curser_offset = selected;
+        } else if selected >= self.curser_offset + self.height {
+            self.curser_offset = selected + 1 - self.height.max(1);
+        }
+        self.curser_row = selected - self.curser_offset;
+    }
+
+    // Fold or unfold the object or array the selected line belongs to
+    pub fn set_folded(&mut self, folded: Option<bool>) {
+        let mut idx = self.selected_node();
+        if let NodeKind::Close { .. } = self.nodes[idx].kind {
+            // Closing brackets fold their opening node
+            idx = (0..idx)
+                .rev()
+                .find(|&i| self.nodes[i].end == idx + 1)
+                .unwrap_or(idx);
+        }
+        if let NodeKind::Open { .. } = self.nodes[idx].kind {
+            let node = &mut self.nodes[idx];
+            node.folded = folded.unwrap_or(!node.folded);
+            let position = self.visible_nodes().iter().position(|&i| i == idx);
+            self.select(position.unwrap_or(0));
+        }
+    }
+
+    // Document as indented json text
+    pub fn pretty(&self) -> String {
+        self.nodes
+            .iter()
+            .map(|node| {
+                self.line(node, false)
+                    .into_iter()
+                    .map(|(text, _)| text)
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn line(&self, node: &JsonNode, fold: bool) -> Vec<(String, JsonToken)> {
+        let mut line = vec![("  ".repeat(node.depth), JsonToken::Punctuation)];
+        if let Some(key) = node.key.as_ref() {
+            line.push((Value::String(key.clone()).to_string(), JsonToken::Key));
+            line.push((": ".to_string(), JsonToken::Punctuation));
+        }
+        match &node.kind {
+            NodeKind::Open { bracket, items } if fold && node.folded => {
+                let close = if *bracket == '{' { '}' } else { ']' };
+                line.push((format!("{bracket}…{close}"), JsonToken::Punctuation));
+                if node.comma {
+                    line.push((",".to_string(), JsonToken::Punctuation));
+                }
+                line.push((format!("  {items} items"), JsonToken::Null));
+                return line;
+            }
+            NodeKind::Open { bracket, .. } => {
+                line.push((bracket.to_string(), JsonToken::Punctuation));
+                return line;
+            }
+            NodeKind::Close { bracket } => line.push((bracket.to_string(), JsonToken::Punctuation)),
+            NodeKind::Scalar { text, token } => line.push((text.clone(), *token)),
+        }
+        if node.comma {
+            line.push((",".to_string(), JsonToken::Punctuation));
+        }
+        line
+    }
+
+    pub fn update_uidata(&mut self, height: usize) -> JsonData {
+        self.height = height;
+        self.move_selection(0);
+        let lines = self
+            .visible_nodes()
+            .iter()
+            .skip(self.curser_offset)
+            .take(height)
+            .map(|&idx| self.line(&self.nodes[idx], true))
+            .collect();
+        JsonData {
+            title: self.title.clone(),
+            lines,
+            selected: self.curser_row,
+        }
+    }
+}
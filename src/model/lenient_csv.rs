@@ -0,0 +1,191 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use polars::prelude::*;
+
+use super::sniff::decode;
+use crate::domain::TVError;
+
+// Characters of a malformed line kept for the report
+const MAX_REPORTED_TEXT: usize = 80;
+
+// A line that could not be loaded as is
+#[derive(Debug, Clone)]
+pub struct MalformedLine {
+    pub line: usize, // 1 based, as shown by editors
+    pub problem: String,
+    pub text: String,
+}
+
+// A record of the file, or the reason it could not be split into fields
+pub struct Record {
+    pub line: usize,
+    pub fields: Result<Vec<String>, String>,
+}
+
+// Read a csv file with ragged rows or bad quotes. Short rows are padded with nulls,
+// rows with too many fields or unterminated quotes are skipped. Both are reported.
+pub fn read(
+    path: &Path,
+    has_header: bool,
+    delimiter: char,
+) -> Result<(DataFrame, Vec<MalformedLine>), TVError> {
+    parse(&decode(&fs::read(path)?), has_header, delimiter)
+}
+
+// Like `read`, for csv content that is already decoded. Lines are reported as lines of the content.
+pub fn parse(
+    content: &str,
+    has_header: bool,
+    delimiter: char,
+) -> Result<(DataFrame, Vec<MalformedLine>), TVError> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut records = split_records(&lines, delimiter).into_iter().peekable();
+    let header = match records.peek() {
+        Some(Record {
+            fields: Ok(fields), ..
+        }) if !has_header => (1..=fields.len()).map(|i| format!("column_{i}")).collect(),
+        Some(Record {
+            fields: Ok(fields), ..
+        }) => {
+            let header = fields.clone();
+            records.next();
+            header
+        }
+        _ => {
+            return Err(TVError::LoadingFailed(
+                "The csv file has no readable first row!".into(),
+            ));
+        }
+    };
+
+    let mut malformed = Vec::new();
+    let mut cleaned = String::new();
+    write_record(&mut cleaned, &header);
+    for record in records {
+        let report = |problem: String| MalformedLine {
+            line: record.line + 1,
+            problem,
+            text: lines[record.line].chars().take(MAX_REPORTED_TEXT).collect(),
+        };
+        match record.fields {
+            Ok(fields) if fields.len() == header.len() => write_record(&mut cleaned, &fields),
+            Ok(mut fields) if fields.len() < header.len() => {
+                malformed.push(report(format!(
+                    "padded, {} of {} fields",
+                    fields.len(),
+                    header.len()
+                )));
+                fields.resize(header.len(), String::new());
+                write_record(&mut cleaned, &fields);
+            }
+            Ok(fields) => malformed.push(report(format!(
+                "skipped, {} fields instead of {}",
+                fields.len(),
+                header.len()
+            ))),
+            Err(problem) => malformed.push(report(format!("skipped, {problem}"))),
+        }
+    }
+
+    // Let polars infer the column types of the cleaned records
+    let frame = CsvReader::new(Cursor::new(cleaned.into_bytes()))
+        .with_options(CsvReadOptions::default().with_has_header(true))
+        .finish()?;
+    Ok((frame, malformed))
+}
+
+// Split lines into records. Quoted fields can span multiple lines, a quote that is
+// never closed only invalidates the record it starts in.
+pub fn split_records(lines: &[&str], delimiter: char) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let start = idx;
+        if lines[idx].trim().is_empty() {
+            idx += 1;
+            continue;
+        }
+
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        loop {
+            let mut chars = lines[idx].chars().peekable();
+            while let Some(c) = chars.next() {
+                match (in_quotes, c) {
+                    (true, '"') if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    (true, '"') => in_quotes = false,
+                    (false, '"') if field.is_empty() => in_quotes = true,
+                    (false, c) if c == delimiter => fields.push(std::mem::take(&mut field)),
+                    _ => field.push(c),
+                }
+            }
+            idx += 1;
+            if !in_quotes {
+                fields.push(field);
+                records.push(Record {
+                    line: start,
+                    fields: Ok(fields),
+                });
+                break;
+            }
+            if idx == lines.len() {
+                // Continue right after the line with the unterminated quote
+                records.push(Record {
+                    line: start,
+                    fields: Err("unterminated quote".to_string()),
+                });
+                idx = start + 1;
+                break;
+            }
+            field.push('\n');
+        }
+    }
+    records
+}
+
+pub fn write_record(out: &mut String, fields: &[String]) {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+    out.push_str(&fields.join(","));
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_ragged_and_quoted_records() {
+        let lines = [
+            "a,b",
+            "1,\"x,\"\"y\"\"\"",
+            "2,\"multi",
+            "line\"",
+            "",
+            "3,\"open",
+            "4,5",
+        ];
+        let records = split_records(&lines, ',');
+        let fields: Vec<(usize, Result<Vec<String>, String>)> =
+            records.into_iter().map(|r| (r.line, r.fields)).collect();
+        assert_eq!(fields[1], (1, Ok(vec!["1".into(), "x,\"y\"".into()])));
+        assert_eq!(fields[2], (2, Ok(vec!["2".into(), "multi\nline".into()])));
+        assert_eq!(fields[3], (5, Err("unterminated quote".into())));
+        assert_eq!(fields[4], (6, Ok(vec!["4".into(), "5".into()])));
+    }
+}
@@ -1,10 +1,17 @@
 pub mod model;
 pub use model::Model;
 pub use model::Status;
+pub use model::WorkspaceRequest;
 
 pub mod table_view;
 use table_view::TableView;
 
+mod workspace;
+pub use workspace::Workspace;
+
+mod directory;
+pub use directory::{Directory, format_size};
+
 pub mod column_view;
 use column_view::{Column, ColumnStatus, ColumnView};
 
@@ -14,5 +21,55 @@ use record_view::RecordView;
 mod histogram_view;
 use histogram_view::HistogramView;
 
+mod json_view;
+
+mod aggregate;
+
+mod plot_view;
+
+mod lenient_csv;
+
+mod sniff;
+
+mod views;
+
+mod substitute;
+
+mod cast;
+
+mod column_groups;
+
+mod column_names;
+
+mod row_groups;
+
+mod path_completion;
+
+mod sampling;
+
+mod background_search;
+
+mod each;
+
+mod stream;
+
+mod stats_cache;
+
 mod ui;
-pub use ui::{UIData, UILayout};
+pub use ui::{
+    ChartData, JsonToken, LoadPreview, LoadProgress, MessageLevel, PlotData, TopValuesData, UIData,
+    UILayout,
+};
+
+mod xlsx;
+
+mod html;
+
+mod json_export;
+
+mod snapshot_diff;
+
+mod ip;
+
+mod database;
+pub use database::is_database_url;
@@ -1,20 +1,51 @@
 use arboard::Clipboard;
+use base64::prelude::*;
 use polars::prelude::*;
-use ratatui::crossterm::event::KeyEvent;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
 use rayon::prelude::*;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
-use tracing::{debug, error, info, trace};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, trace, warn};
 
-use crate::domain::{CMDMode, HELP_TEXT, Message, TVConfig, TVError};
+use crate::config::{self, FileConfig};
+use crate::domain::{
+    CMDMode, Event, HELP_TEXT, InputMode, Message, RemoteCommand, TVConfig, TVError,
+};
+use crate::external::{self, ExternalProgram};
 use crate::inputter::{InputResult, Inputter};
+use crate::renderer::{PatternRenderer, RendererRegistry, find_url};
+use crate::tui::{STATUS_MESSAGE_DISPLAY_DURATION, TOP_VALUES_PANEL_ROWS};
 
-use super::{Column, ColumnStatus, HistogramView, RecordView, TableView, UIData, UILayout};
+use super::background_search::BackgroundSearch;
+use super::column_groups;
+use super::column_names;
+use super::each::{self, EachRun};
+use super::json_view::JsonView;
+use super::lenient_csv::{self, MalformedLine};
+use super::plot_view::PlotView;
+use super::row_groups::RowGroups;
+use super::sampling;
+use super::sniff::{self, Bom};
+use super::stats_cache::StatsCache;
+use super::stream::{Batching, Stream};
+use super::substitute::Substitution;
+use super::views::{self, SavedView};
+use super::{
+    ChartData, Column, ColumnStatus, HistogramView, LoadPreview, LoadProgress, MessageLevel,
+    RecordView, TableView, TopValuesData, UIData, UILayout,
+};
+use super::{
+    aggregate, cast, database, html, ip, json_export, path_completion, snapshot_diff, xlsx,
+};
 
 // A struct with different types
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum FileType {
     CSV,
     PARQUET,
@@ -34,8 +65,69 @@ pub struct FileInfo {
     path: PathBuf,
     file_size: u64,
     file_type: FileType,
+    has_header: bool, // If the first row of a csv file holds the column names
+    delimiter: char,
+    bom: Option<Bom>,
+}
+
+// Changes to the tables of the workspace, requested by commands of a table
+#[derive(Debug, PartialEq)]
+pub enum WorkspaceRequest {
+    Open(PathBuf),    // Load a file as additional table
+    Split(PathBuf),   // Load a file and show it next to the current table
+    Only,             // Close the split, only showing the current table
+    ScrollLock(bool), // Toggle scrolling the panes of a split together, with the columns if true
+}
+
+// Named frames of a source, one per sheet of a workbook
+type Sheets = Vec<(String, DataFrame)>;
+
+// Holds the state of a dataset while a derived view (e.g. melt) is shown on top of it
+struct Dataset {
+    frame: Arc<DataFrame>,
+    data: Vec<Column>,
+    tables: Vec<TableView>,
+    histogram_views: Vec<HistogramView>,
+}
+
+impl Dataset {
+    fn empty() -> Self {
+        Dataset {
+            frame: Arc::new(DataFrame::empty()),
+            data: Vec::new(),
+            tables: Vec::new(),
+            histogram_views: Vec::new(),
+        }
+    }
+}
+
+// An independently navigable table, e.g. one sheet of a workbook.
+// The active tab lives in the model fields, its entry in Model.tabs only keeps the name.
+struct Tab {
+    name: String,
+    dataset: Dataset,
+    parent_datasets: Vec<Dataset>,
+}
+
+// The loaded file is checked for changes, which are reloaded showing the rows that changed
+struct Watch {
+    key: String,                  // Column the rows are matched by, the first if empty
+    modified: Option<SystemTime>, // When the file was modified, as it was loaded last
+    due: Instant,                 // When the file is checked next
+}
+
+// A file that was read, before it replaces what the model shows
+struct FileToLoad {
+    file_info: FileInfo,
+    sheets: Sheets,
+    malformed: Vec<MalformedLine>,
+    partial_rows: Option<usize>,
+    sampled: bool,
+    original_names: HashMap<String, String>,
+    notes: Vec<String>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy)]
 enum Modus {
     TABLE,
@@ -43,6 +135,106 @@ enum Modus {
     POPUP,
     CMDINPUT,
     HISTOGRAM,
+    CHART,
+    PLOT,
+    JSON,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChartAggregation {
+    Count,
+    Sum,
+    Mean,
+}
+
+impl ChartAggregation {
+    fn next(&self, step: i32) -> Self {
+        let all = [
+            ChartAggregation::Count,
+            ChartAggregation::Sum,
+            ChartAggregation::Mean,
+        ];
+        let idx = all.iter().position(|a| a == self).unwrap_or(0) as i32;
+        all[(idx + step).rem_euclid(all.len() as i32) as usize]
+    }
+}
+
+// Maximum number of categories shown in a chart
+const MAX_CHART_BARS: usize = 50;
+
+// Counts the loaded rows and columns of a dataset, reporting them to the ui while loading
+struct LoadTracker {
+    sender: Sender<LoadProgress>,
+    rows_done: AtomicUsize,
+    columns_done: AtomicUsize,
+    rows_total: usize,
+    columns_total: usize,
+}
+
+impl LoadTracker {
+    fn new(sender: Sender<LoadProgress>, df: &DataFrame) -> Self {
+        LoadTracker {
+            sender,
+            rows_done: AtomicUsize::new(0),
+            columns_done: AtomicUsize::new(0),
+            rows_total: df.height() * df.width(),
+            columns_total: df.width(),
+        }
+    }
+
+    fn rows_loaded(&self, nrows: usize) {
+        self.rows_done.fetch_add(nrows, Ordering::Relaxed);
+        self.report();
+    }
+
+    fn column_loaded(&self) {
+        self.columns_done.fetch_add(1, Ordering::Relaxed);
+        self.report();
+    }
+
+    fn report(&self) {
+        // Nobody might be listening anymore, which is fine
+        let _ = self.sender.send(LoadProgress {
+            rows_done: self.rows_done.load(Ordering::Relaxed),
+            rows_total: self.rows_total,
+            columns_done: self.columns_done.load(Ordering::Relaxed),
+            columns_total: self.columns_total,
+            preview: None,
+        });
+    }
+}
+
+// Rows read ahead of loading the whole file, to show the start of the table meanwhile
+const PREVIEW_ROWS: usize = 100;
+
+// Longest time the main loop sleeps while waiting for input, when nothing is pending
+const IDLE_TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+// How deep macros can replay other macros, stops macros that replay themselves
+const MAX_MACRO_DEPTH: usize = 8;
+// Messages replayed at most by a macro and the macros it replays, a macro replaying itself more than once grows exponentially
+const MAX_MACRO_STEPS: usize = 10_000;
+
+// Rows of a string column that are checked for datetimes, before plotting over it
+const TIME_SAMPLE_ROWS: usize = 100;
+
+// Streamed rows are appended in chunks, merged into one once there are more
+const MAX_STREAM_CHUNKS: usize = 64;
+
+// Shown by the percentile popup of numeric columns
+const PERCENTILES: [u8; 7] = [1, 5, 25, 50, 75, 95, 99];
+
+// Number with thousands separators, e.g. 1,234,567
+fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (idx, chr) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(chr);
+    }
+    out
 }
 
 //#[derive(Debug)]
@@ -52,7 +244,11 @@ pub struct Model {
     pub status: Status,
     modus: Modus,
     previous_modus: Modus,
+    frame: Arc<DataFrame>,
     data: Vec<Column>,
+    parent_datasets: Vec<Dataset>,
+    tabs: Vec<Tab>,
+    active_tab: usize,
     pub tables: Vec<TableView>,
     record_view: RecordView,
     histogram_views: Vec<HistogramView>,
@@ -61,24 +257,71 @@ pub struct Model {
     last_data_change: Instant,
     pub uilayout: UILayout,
     uidata: UIData,
-    clipboard: Clipboard,
+    clipboard: Option<Clipboard>, // Not available e.g. without a display server
     input: Inputter,
     cmd_mode: Option<CMDMode>,
     last_input: InputResult,
     active_cmdinput: bool,
     status_message: String,
     last_status_message_update: Instant,
+    popup_pops_filter: bool,
+    chart_category: Option<usize>, // Category column selected for the next chart
+    chart_columns: (usize, usize), // Category and value column of the chart that is shown
+    chart_aggregation: ChartAggregation,
+    renderers: RendererRegistry,
+    external_request: Option<(ExternalProgram, String)>, // Cell content to show in a pager/editor
+    json_view: Option<JsonView>,
+    plot_view: Option<PlotView>,
+    filter_preview_due: Option<Instant>,
+    pending_substitution: Vec<(usize, Vec<(usize, String)>)>, // New values per column, applied once confirmed
+    dirty: bool, // Data was modified since it was loaded or exported
+    column_stats_key: Option<(usize, usize, usize)>, // Column, rows and row count the footer stats were computed for
+    load_progress: Option<Sender<LoadProgress>>,     // Receives the progress of loading datasets
+    events: Option<Sender<Event>>, // Main loop events, background workers post a wakeup when they finished
+    workspace_request: Option<WorkspaceRequest>,
+    completion: Option<(Vec<String>, usize)>, // Paths offered by Tab in the command line, and the one shown
+    macros: HashMap<char, Vec<Message>>,
+    recording: Option<(char, Vec<Message>)>, // Register and messages of the macro that is recorded
+    macro_depth: usize,                      // Number of nested macro replays in progress
+    macro_steps: usize, // Messages replayed by the outermost macro replay in progress
+    confirming_quit: bool, // The popup asking to write unsaved edits is shown
+    narrow_record_view: bool, // The record view replaced the table as the terminal got too narrow for it
+    messages: Vec<(Instant, MessageLevel, String)>, // Warnings and errors, listed by :messages
+    malformed_lines: Vec<MalformedLine>, // Csv lines padded or skipped when loading leniently
+    original_names: HashMap<String, String>, // Names in the file of columns suffixed as their name repeats
+    histogram_bins: usize,
+    top_values_panel: bool, // The most frequent values of the selected column are shown below the table
+    totals_row: bool,       // Sums and counts of the columns are shown below the table
+    stats_cache: Option<StatsCache>, // Stats of the loaded file computed before, None if it is not cached
+    totals: Option<((usize, usize), Vec<String>)>, // Rows the totals were computed for, and the total of each column
+    background_search: Option<BackgroundSearch>, // Search or filter over many rows still running on a worker thread
+    partial_rows: Option<usize>, // Estimated rows of the file, if it has more than max_rows and only part was loaded
+    pending_each: Vec<String>,   // Commands of :each, run once confirmed
+    each_run: Option<EachRun>,   // Commands of the last :each, still running or finished
+    stream: Option<Stream>,      // Rows piped to stdin with --stream, appended as they arrive
+    previewed_file: Option<FileInfo>, // File shown with --preview, loaded once its parse options are confirmed
+    watch: Option<Watch>, // The file is reloaded when it changes, with :watch or --watch
 }
 
 impl Model {
     pub fn init(config: &TVConfig, ui_width: usize, ui_height: usize) -> Result<Self, TVError> {
+        let mut renderers = RendererRegistry::new();
+        for spec in config.render_patterns.iter() {
+            let renderer = PatternRenderer::parse(spec).map_err(TVError::LoadingFailed)?;
+            renderers.register(Arc::new(renderer));
+        }
+
         let mut model = Self {
             file_info: None,
             config: config.clone(),
             modus: Modus::TABLE,
             previous_modus: Modus::TABLE,
             status: Status::READY,
+            frame: Arc::new(DataFrame::empty()),
             data: Vec::new(),
+            parent_datasets: Vec::new(),
+            tabs: Vec::new(),
+            active_tab: 0,
             tables: Vec::new(),
             record_view: RecordView::empty(),
             histogram_views: Vec::new(),
@@ -86,256 +329,1985 @@ impl Model {
             last_data_change: Instant::now(),
             uilayout: UILayout::from_values(0, ui_width, ui_height),
             uidata: UIData::empty(), // TODO: find out how to do this better. How can i in a factory function create an object that relies on self to exit?
-            clipboard: Clipboard::new().unwrap(),
+            clipboard: Clipboard::new().ok(),
             input: Inputter::default(),
             cmd_mode: None,
             last_input: InputResult::default(),
             active_cmdinput: false,
             status_message: "Started tv!".to_string(),
             last_status_message_update: Instant::now(),
+            popup_pops_filter: false,
+            chart_category: None,
+            chart_columns: (0, 0),
+            chart_aggregation: ChartAggregation::Count,
+            renderers,
+            external_request: None,
+            json_view: None,
+            plot_view: None,
+            filter_preview_due: None,
+            pending_substitution: Vec::new(),
+            dirty: false,
+            column_stats_key: None,
+            load_progress: None,
+            events: None,
+            workspace_request: None,
+            completion: None,
+            macros: HashMap::new(),
+            recording: None,
+            macro_depth: 0,
+            macro_steps: 0,
+            confirming_quit: false,
+            narrow_record_view: false,
+            messages: Vec::new(),
+            malformed_lines: Vec::new(),
+            original_names: HashMap::new(),
+            histogram_bins: config.histogram_bins,
+            top_values_panel: false,
+            totals_row: false,
+            stats_cache: None,
+            totals: None,
+            background_search: None,
+            partial_rows: None,
+            pending_each: Vec::new(),
+            each_run: None,
+            stream: None,
+            previewed_file: None,
+            watch: None,
         };
 
         model.uidata.layout = model.uilayout.clone();
+        model.uidata.profile.show = config.profile;
         model.set_status_message("Loading ...".to_string());
         Ok(model)
     }
 
-    pub fn load_data_file(&mut self, path: PathBuf) -> Result<bool, TVError> {
-        let file_info = Model::get_file_info(path)?;
-        let frame = match file_info.file_type {
-            FileType::CSV => Model::load_csv(&file_info.path)?,
-            FileType::PARQUET => Model::load_parquet(&file_info.path)?,
-            FileType::XLSX => todo!(),
-            FileType::ARROW => Model::load_arrow(&file_info.path)?,
-        };
+    // Report the progress of loading datasets, e.g. to show it while the ui is blocked
+    pub fn set_load_progress(&mut self, sender: Option<Sender<LoadProgress>>) {
+        self.load_progress = sender;
+    }
 
-        // Load dataframe using rayon with data parallelism.
-        // Each column is loaded in its own thread.
-        // This is a very intensive operation as the data is pre-processed.
-        // The returned columns hold all data as Strings in memory.
-        let start_time = Instant::now();
+    pub fn set_event_sender(&mut self, sender: Option<Sender<Event>>) {
+        for hist in self.histogram_views.iter_mut() {
+            hist.set_wakeup(sender.clone());
+        }
+        if let Some(stream) = self.stream.as_ref() {
+            stream.set_wakeup(sender.clone());
+        }
+        self.events = sender;
+    }
 
-        let df = Arc::new(frame);
-        let c_: Result<Vec<Column>, _> = df
-            .get_column_names()
-            .par_iter()
-            .enumerate()
-            .map(|(idx, name)| Self::load_columns(&df, idx, name))
-            .collect();
-        let columns = c_?;
+    fn new_histogram_view(&self) -> HistogramView {
+        let mut hist = HistogramView::new(self.histogram_bins);
+        hist.set_wakeup(self.events.clone());
+        hist
+    }
 
-        let data_loading_duration = start_time.elapsed().as_millis();
-        info!("Loading data took {data_loading_duration}ms ...");
-        for c in columns.iter() {
-            debug!("Column: {}", c.as_string());
+    pub fn load_data_file(&mut self, path: PathBuf) -> Result<bool, TVError> {
+        let mut file_info = Model::get_file_info(path)?;
+        let file_config = self.configure_parsing(&mut file_info);
+        let result = self.load_file(file_info)?;
+        if let Some((pattern, file_config)) = file_config {
+            self.apply_file_config(&pattern, &file_config);
         }
-        let mut table = TableView::empty();
-        // set default row mapping
-        table.rows = Arc::new((0..columns[0].data.len()).collect());
-        table.name = file_info
-            .path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("???")
-            .to_string();
-
-        self.tables.push(table);
+        Ok(result)
+    }
 
-        let hist = HistogramView::empty();
-        self.histogram_views.push(hist);
+    // Delimiter and header row of the first file config matching the file, returns the config
+    fn configure_parsing(&self, file_info: &mut FileInfo) -> Option<(String, FileConfig)> {
+        let (pattern, file_config) = config::file_config(&self.config.files, &file_info.path)?;
+        if matches!(file_info.file_type, FileType::CSV) {
+            file_info.delimiter = file_config.delimiter.unwrap_or(file_info.delimiter);
+            file_info.has_header = file_config.header.unwrap_or(file_info.has_header);
+        }
+        Some((pattern.clone(), file_config.clone()))
+    }
 
-        self.data = columns;
-        self.update_table_data();
-        self.set_status_message(format!("Loaded data in {}ms ...", data_loading_duration));
+    // Dates, pinned columns and sort of the file config matching the loaded file
+    fn apply_file_config(&mut self, pattern: &str, file_config: &FileConfig) {
+        let nmessages = self.messages.len();
+        for (column, format) in file_config.dates.iter() {
+            let has_time = ["%H", "%I", "%T", "%R", "%s"]
+                .iter()
+                .any(|spec| format.contains(spec));
+            let dtype = if has_time { "datetime" } else { "date" };
+            self.cast_column(&format!("{column} as {dtype}('{format}')"));
+        }
+        let mut position = 0;
+        for name in file_config.pinned.iter() {
+            match self.data.iter().position(|c| &c.name == name) {
+                Some(column_idx) => {
+                    self.select_cell(0, column_idx);
+                    self.move_column(position);
+                    position += 1;
+                }
+                None => self.show_warning(format!("Unknown pinned column: {name}")),
+            }
+        }
+        if let Some((name, ascending)) = file_config.sort_order() {
+            match self.data.iter().position(|c| c.name == name) {
+                Some(column_idx) => self.sort_column(column_idx, ascending),
+                None => self.show_warning(format!("Unknown sort column: {name}")),
+            }
+        }
+        self.select_cell(0, 0);
+        // The file is shown as configured, not edited
+        self.dirty = false;
+        self.uidata.dirty = false;
+        if self.messages.len() == nmessages {
+            self.set_status_message(format!("Applied the settings of {pattern}"));
+        }
+    }
 
-        Ok(true)
+    // Read the start of a file to show it before loading it, see --preview. None if there is
+    // no preview of the file, e.g. of a workbook.
+    pub fn preview_file(&mut self, path: PathBuf) -> Result<Option<LoadPreview>, TVError> {
+        let mut file_info = Model::get_file_info(path)?;
+        self.configure_parsing(&mut file_info);
+        let preview = Model::read_preview(&file_info);
+        self.previewed_file = Some(file_info);
+        Ok(preview)
     }
 
-    fn detect_file_type(path: &Path) -> Result<FileType, TVError> {
-        match path
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_uppercase())
-            .as_deref()
-        {
-            Some("CSV") => Ok(FileType::CSV),
-            Some("PARQUET") | Some("PQ") => Ok(FileType::PARQUET),
-            Some("XLSX") => Ok(FileType::XLSX),
-            Some("ARROW") | Some("IPC") | Some("FEATHER") => Ok(FileType::ARROW),
-            _ => Err(TVError::UnknownFileType),
+    // Parse the previewed csv file with the next delimiter it can be parsed with, None if it is no csv file
+    pub fn cycle_preview_delimiter(&mut self) -> Option<LoadPreview> {
+        let file_info = self
+            .previewed_file
+            .as_mut()
+            .filter(|f| matches!(f.file_type, FileType::CSV))?;
+        let start = file_info.delimiter;
+        loop {
+            file_info.delimiter = sniff::next_delimiter(file_info.delimiter);
+            let preview = Model::read_preview(file_info);
+            if preview.is_some() || file_info.delimiter == start {
+                return preview;
+            }
         }
     }
 
-    fn set_status_message(&mut self, message: impl Into<String>) {
-        self.status_message = message.into();
-        self.last_status_message_update = Instant::now();
-        self.uidata.status_message = self.status_message.clone();
-        self.uidata.last_status_message_update = self.last_status_message_update;
-        self.uidata.last_update = Instant::now();
+    // Parse the first row of the previewed csv file as header or as data, None if it is no csv file
+    pub fn toggle_preview_header(&mut self) -> Option<LoadPreview> {
+        let file_info = self
+            .previewed_file
+            .as_mut()
+            .filter(|f| matches!(f.file_type, FileType::CSV))?;
+        file_info.has_header = !file_info.has_header;
+        Model::read_preview(file_info)
     }
 
-    pub fn get_uidata(&self) -> &UIData {
-        &self.uidata
+    // Load the previewed file with the parse options chosen in the preview
+    pub fn load_previewed_file(&mut self) -> Result<bool, TVError> {
+        let Some(file_info) = self.previewed_file.take() else {
+            return Err(TVError::LoadingFailed("No file was previewed!".into()));
+        };
+        let file_config = config::file_config(&self.config.files, &file_info.path).cloned();
+        let result = self.load_file(file_info)?;
+        if let Some((pattern, file_config)) = file_config {
+            self.apply_file_config(&pattern, &file_config);
+        }
+        Ok(result)
     }
 
-    fn update_histogram(&mut self) {
-        let hist = self.histogram_views.last_mut().unwrap();
-        let table = self.tables.last().unwrap();
-        let column_idx = table.offset_column + table.curser_column;
-        self.uidata.layout = self.uilayout.clone();
-        hist.update(column_idx, &mut self.data, table, &mut self.uidata)
+    // Read a file or database without showing it, e.g. to print a report about it
+    pub fn read_source(
+        source: &str,
+        table: Option<String>,
+        query: Option<String>,
+        lenient: bool,
+    ) -> Result<Sheets, TVError> {
+        if database::is_database_url(source) {
+            return Ok(vec![Model::read_database(source, table, query)?]);
+        }
+        if source == "-" {
+            let input = std::io::BufReader::new(std::io::stdin());
+            let (stream, frame) = Stream::start(input, None, Batching::new(&TVConfig::default()))?;
+            return Ok(vec![("stdin".to_string(), stream.read_all(frame)?)]);
+        }
+        let file_info = Model::get_file_info(PathBuf::from(source))?;
+        let (sheets, _) = Model::read_file(&file_info, lenient)?;
+        Ok(sheets)
     }
 
-    fn update_table_data(&mut self) {
-        // If the model is empty, there is nothing to do.
-        if self.tables.is_empty() || self.data.is_empty() {
-            // Does self.uidata need some work?
-        } else {
-            let table = self.tables.last_mut().unwrap();
-            table.update(&mut self.data, &self.uilayout, &mut self.uidata);
+    // Read the sheets of a file, and the lines of a csv file that were padded or skipped
+    fn read_file(
+        file_info: &FileInfo,
+        lenient: bool,
+    ) -> Result<(Sheets, Vec<MalformedLine>), TVError> {
+        match Model::read_data_file(file_info) {
+            Err(e) if lenient && matches!(file_info.file_type, FileType::CSV) => {
+                warn!("Loading csv failed ({e:?}), loading it leniently.");
+                let (frame, malformed) =
+                    lenient_csv::read(&file_info.path, file_info.has_header, file_info.delimiter)?;
+                Ok((vec![(Model::file_name(file_info), frame)], malformed))
+            }
+            Err(e) if matches!(file_info.file_type, FileType::CSV) => {
+                Err(Model::strict_csv_error(e))
+            }
+            sheets => Ok((sheets?, Vec::new())),
         }
     }
 
-    fn is_numeric_type(dtype: &DataType) -> bool {
-        matches!(
-            dtype,
-            DataType::Int8
-                | DataType::Int16
-                | DataType::Int32
-                | DataType::Int64
-                | DataType::UInt8
-                | DataType::UInt16
-                | DataType::UInt32
-                | DataType::UInt64
-                | DataType::Float32
-                | DataType::Float64
-        )
+    fn strict_csv_error(e: impl std::fmt::Debug) -> TVError {
+        TVError::LoadingFailed(format!(
+            "{e:?}\nUse --lenient to pad or skip malformed rows."
+        ))
     }
 
-    fn load_columns(df: &DataFrame, idx: usize, col_name: &str) -> Result<Column, PolarsError> {
-        let original_dtype = df.column(col_name)?.dtype().clone();
-
-        let col = df.column(col_name)?.cast(&DataType::String)?;
-        let series = col.str()?;
-        let mut data = Vec::with_capacity(series.len());
+    fn load_file(&mut self, file_info: FileInfo) -> Result<bool, TVError> {
+        let start_time = Instant::now();
+        let file = self.read_for_load(file_info)?;
+        self.install_file(file, start_time)
+    }
 
-        let mut max_width = 0;
-        for value in series.into_iter() {
-            let ss = match value {
-                Some(s) => s.to_string().replace("\r\n", " ↵ ").replace("\n", " ↵ "),
-                None => String::from("∅"),
-            };
-            if ss.len() > max_width {
-                max_width = ss.len();
+    // Read a file to load, without changing the model, so a failed (re)load keeps what is shown
+    fn read_for_load(&self, file_info: FileInfo) -> Result<FileToLoad, TVError> {
+        if let Some(sender) = self.load_progress.as_ref()
+            && let Some(preview) = Model::read_preview(&file_info)
+        {
+            let _ = sender.send(LoadProgress {
+                preview: Some(Arc::new(preview)),
+                ..Default::default()
+            });
+        }
+        // Files with too many rows are only loaded in part, unless started with --force-full
+        let max_rows = self.config.max_rows;
+        let partial_rows =
+            Model::estimate_rows(&file_info).filter(|&n| max_rows > 0 && n > max_rows);
+        let (mut sheets, malformed) = match partial_rows {
+            Some(_) => Model::read_rows(
+                &file_info,
+                max_rows,
+                self.config.sample_large_files,
+                self.config.lenient_csv,
+            )?,
+            None => Model::read_file(&file_info, self.config.lenient_csv)?,
+        };
+        let sampled =
+            self.config.sample_large_files && matches!(file_info.file_type, FileType::CSV);
+        if !file_info.has_header {
+            for (_, frame) in sheets.iter_mut() {
+                frame.set_column_names(column_names::names(&file_info.path, frame.width()))?;
             }
-            data.push(ss);
         }
-
-        Ok(Column {
-            idx: idx as u16,
-            name: col_name.to_string(),
-            status: ColumnStatus::NORMAL,
-            max_width,
-            render_width: 0, // Will be set later
-            data,
-            dtype: original_dtype,
+        let mut notes = Vec::new();
+        if matches!(file_info.file_type, FileType::CSV) {
+            notes.push(format!(
+                "Delimiter: {}",
+                sniff::delimiter_name(file_info.delimiter)
+            ));
+        }
+        if let Some(bom @ (Bom::Utf16Le | Bom::Utf16Be)) = file_info.bom {
+            notes.push(format!("transcoded from {}", bom.name()));
+        }
+        let mut original_names = HashMap::new();
+        if file_info.has_header
+            && matches!(file_info.file_type, FileType::CSV)
+            && let Some(header) = sniff::header(&file_info.path, file_info.delimiter)
+            && header.len() == sheets[0].1.width()
+        {
+            let (unique, originals) = column_names::disambiguate(&header);
+            if !originals.is_empty() {
+                sheets[0].1.set_column_names(unique)?;
+                notes.push(format!(
+                    "{} repeated column names got a suffix",
+                    originals.len()
+                ));
+                original_names = originals;
+            }
+        }
+        if !file_info.has_header {
+            notes.push(
+                "no header row detected, :header to use the first row as header, r to rename columns"
+                    .into(),
+            );
+        }
+        Ok(FileToLoad {
+            file_info,
+            sheets,
+            malformed,
+            partial_rows,
+            sampled,
+            original_names,
+            notes,
         })
     }
 
-    fn get_file_info(path: PathBuf) -> Result<FileInfo, TVError> {
-        let metadata = fs::metadata(&path).map_err(|e| match e.kind() {
-            ErrorKind::NotFound => TVError::FileNotFound,
-            ErrorKind::PermissionDenied => TVError::PermissionDenied,
-            _ => TVError::IoError(e),
-        })?;
-        if !metadata.is_file() {
-            return Err(TVError::LoadingFailed("Not a file!".into()));
+    fn install_file(&mut self, file: FileToLoad, start_time: Instant) -> Result<bool, TVError> {
+        let FileToLoad {
+            file_info,
+            sheets,
+            malformed,
+            partial_rows,
+            sampled,
+            original_names,
+            notes,
+        } = file;
+        self.malformed_lines = malformed;
+        self.partial_rows = partial_rows;
+        self.original_names = original_names;
+        self.file_info = Some(file_info);
+        let result = self.load_sheets(sheets);
+        self.load_stats_cache();
+        self.uidata.profile.load_time = start_time.elapsed();
+        if !notes.is_empty() {
+            self.set_status_message(notes.join(", "));
         }
+        if !self.malformed_lines.is_empty() {
+            self.show_warning(format!(
+                "{} malformed lines were padded or skipped, see :malformed",
+                self.malformed_lines.len()
+            ));
+        }
+        if let Some(nrows) = self.partial_rows {
+            let loaded = self.tables[0].rows.len();
+            let part = match sampled {
+                true => "a random sample of",
+                false => "the first",
+            };
+            self.show_warning(format!(
+                "The file has about {} rows, loaded {part} {} of them (max_rows). Start with --force-full to load all rows.",
+                format_count(nrows),
+                format_count(loaded)
+            ));
+        }
+        result
+    }
 
-        let file_size = metadata.len();
-
-        let file_type = Model::detect_file_type(&path)?;
-
-        Ok(FileInfo {
-            path,
-            file_size,
-            file_type,
-        })
+    // Rows of csv and parquet files, estimated for csv files. None if they are unknown before reading the file.
+    fn estimate_rows(file_info: &FileInfo) -> Option<usize> {
+        match file_info.file_type {
+            FileType::CSV => {
+                let (lines, _) = sniff::estimate_lines(&file_info.path, file_info.file_size);
+                Some(lines.saturating_sub(file_info.has_header as usize))
+            }
+            FileType::PARQUET => ParquetReader::new(fs::File::open(&file_info.path).ok()?)
+                .num_rows()
+                .ok(),
+            _ => None,
+        }
     }
 
-    fn load_csv(path: &PathBuf) -> Result<DataFrame, PolarsError> {
-        CsvReadOptions::default()
-            .with_has_header(true)
-            .try_into_reader_with_file_path(Some(path.into()))?
-            .finish()
-            .or_else(|_| {
-                error!("Loading CSV failed! Fallback, trying to load in UTF8 lossy mode.");
-                // Fallback: read as bytes and replace invalid UTF-8
-                let bytes = std::fs::read(path).map_err(|e| PolarsError::IO {
-                    error: e.into(),
-                    msg: None,
-                })?;
-                let content = String::from_utf8_lossy(&bytes);
-                let cursor = std::io::Cursor::new(content.as_bytes());
-
-                let mut options = CsvReadOptions::default();
-                options.has_header = true;
-                CsvReader::new(cursor).with_options(options).finish()
-            })
+    // The first `n` rows of a csv or parquet file, or a random sample of the rows of a csv file
+    fn read_rows(
+        file_info: &FileInfo,
+        n: usize,
+        sample: bool,
+        lenient: bool,
+    ) -> Result<(Sheets, Vec<MalformedLine>), TVError> {
+        let path = &file_info.path;
+        let frame = match file_info.file_type {
+            FileType::CSV => return Model::read_csv_rows(file_info, n, sample, lenient),
+            FileType::PARQUET => ParquetReader::new(fs::File::open(path)?)
+                .with_slice(Some((0, n)))
+                .finish()?,
+            _ => return Ok((Model::read_data_file(file_info)?, Vec::new())),
+        };
+        Ok((vec![(Model::file_name(file_info), frame)], Vec::new()))
     }
 
-    fn load_parquet(path: &PathBuf) -> Result<DataFrame, PolarsError> {
-        let file = std::fs::File::open(path)?;
-        ParquetReader::new(file).finish()
+    // Rows of a csv file read by `read_rows`. They are decoded and parsed like whole files,
+    // only the selected records are kept in memory.
+    fn read_csv_rows(
+        file_info: &FileInfo,
+        n: usize,
+        sample: bool,
+        lenient: bool,
+    ) -> Result<(Sheets, Vec<MalformedLine>), TVError> {
+        let mut lines = sniff::decoded_lines(&file_info.path)?.enumerate();
+        // Lines of a record with an odd number of quotes continue on the next line
+        let mut records = std::iter::from_fn(|| {
+            let (idx, mut record) = lines.next()?;
+            while record.matches('"').count() % 2 == 1 {
+                let Some((_, line)) = lines.next() else { break };
+                record.push('\n');
+                record.push_str(&line);
+            }
+            Some((idx, record))
+        });
+        let header = match file_info.has_header {
+            true => records.next(),
+            false => None,
+        };
+        let selected = match sample {
+            true => sampling::reservoir(records, n, sampling::random_seed()),
+            false => records.take(n).collect(),
+        };
+
+        // Index of the line in the file of each line of the content
+        let mut file_lines = Vec::new();
+        let mut content = String::new();
+        for (idx, record) in header.into_iter().chain(selected) {
+            file_lines.extend(idx..=idx + record.matches('\n').count());
+            content.push_str(&record);
+            content.push('\n');
+        }
+        let options = CsvReadOptions::default()
+            .with_has_header(file_info.has_header)
+            .with_parse_options(
+                CsvParseOptions::default().with_separator(file_info.delimiter as u8),
+            );
+        let parsed = CsvReader::new(std::io::Cursor::new(content.as_bytes()))
+            .with_options(options)
+            .finish();
+        let (frame, mut malformed) = match parsed {
+            Ok(frame) => (frame, Vec::new()),
+            Err(e) if lenient => {
+                warn!("Loading csv failed ({e:?}), loading it leniently.");
+                lenient_csv::parse(&content, file_info.has_header, file_info.delimiter)?
+            }
+            Err(e) => return Err(Model::strict_csv_error(e)),
+        };
+        for line in malformed.iter_mut() {
+            line.line = file_lines
+                .get(line.line - 1)
+                .map_or(line.line, |idx| idx + 1);
+        }
+        Ok((vec![(Model::file_name(file_info), frame)], malformed))
     }
 
-    fn load_arrow(path: &PathBuf) -> Result<DataFrame, PolarsError> {
-        let file = std::fs::File::open(path)?;
-        IpcReader::new(file).finish()
+    // Load a table or the result of a query from a database given by its connection url
+    pub fn load_database(
+        &mut self,
+        url: &str,
+        table: Option<String>,
+        query: Option<String>,
+    ) -> Result<bool, TVError> {
+        let start_time = Instant::now();
+        let sheet = Model::read_database(url, table, query)?;
+        let result = self.load_sheets(vec![sheet]);
+        self.uidata.profile.load_time = start_time.elapsed();
+        result
     }
 
-    pub fn raw_keyevents(&self) -> bool {
-        self.active_cmdinput
+    // Load the rows piped to stdin. With follow, rows arriving later are appended while the table is shown.
+    pub fn load_stdin(&mut self, follow: bool, ring: Option<usize>) -> Result<bool, TVError> {
+        let start_time = Instant::now();
+        let input = std::io::BufReader::new(std::io::stdin());
+        let (stream, frame) = Stream::start(input, ring, Batching::new(&self.config))?;
+        let result = match follow {
+            true => self.load_stream(stream, frame),
+            false => self.load_sheets(vec![("stdin".to_string(), stream.read_all(frame)?)]),
+        };
+        self.uidata.profile.load_time = start_time.elapsed();
+        result
     }
 
-    pub fn quit(&mut self) {
-        self.status = Status::QUITTING;
+    fn load_stream(&mut self, stream: Stream, mut frame: DataFrame) -> Result<bool, TVError> {
+        if let Some(ring) = stream.ring {
+            frame = frame.tail(Some(ring));
+        }
+        let result = self.load_sheets(vec![("stdin".to_string(), frame)]);
+        stream.set_wakeup(self.events.clone());
+        self.stream = Some(stream);
+        result
     }
 
-    fn ui_resize(&mut self, width: usize, height: usize) {
-        trace!(
-            "UI was resized! w:{}->{}, h:{}->{}",
-            self.uilayout.width, width, self.uilayout.height, height
-        );
-        self.uilayout = UILayout::from_model(self, width, height);
-        self.input.set_width(self.uilayout.statusline_width);
-        match self.modus {
-            Modus::TABLE => self.update_table_data(),
-            Modus::RECORD => {
-                self.update_table_data();
-                let table = self.tables.last().unwrap();
-                self.record_view = RecordView::new(
-                    table,
-                    &self.data,
-                    &mut self.uidata,
-                    self.config.max_column_width,
-                )
-            }
-            Modus::HISTOGRAM => self.update_histogram(),
-            Modus::POPUP => {}
-            Modus::CMDINPUT => {}
+    // Append the rows that arrived on stdin since the last update
+    fn poll_stream(&mut self) {
+        // Derived tables show another frame, the rows wait until the streamed one is shown again
+        if self.active_tab != 0 || !self.parent_datasets.is_empty() {
+            return;
+        }
+        let Some(stream) = self.stream.as_mut() else {
+            return;
+        };
+        if stream.finished {
+            return;
+        }
+        let (batches, errors) = stream.poll();
+        let (ring, rows, finished) = (stream.ring, stream.rows, stream.finished);
+        for e in errors {
+            self.show_warning(format!("Rows on stdin could not be parsed: {e}"));
+        }
+        if !batches.is_empty()
+            && let Err(e) = self.append_rows(batches, ring)
+        {
+            self.show_error(format!("Appending rows failed! {e}"));
+        }
+        if finished {
+            self.set_status_message(format!(
+                "stdin was closed after {} rows",
+                format_count(rows)
+            ));
         }
     }
 
-    pub fn update(&mut self, message: Option<Message>) -> Result<(), TVError> {
-        if self.last_data_change - self.last_update > std::time::Duration::ZERO {
-            self.update_table_data();
+    // Append rows to the dataset, dropping the oldest rows beyond the ring size. Only the unfiltered
+    // table gets the new rows, its cursor follows them if it was on the last row.
+    fn append_rows(
+        &mut self,
+        batches: Vec<DataFrame>,
+        ring: Option<usize>,
+    ) -> Result<(), PolarsError> {
+        let old_height = self.frame.height();
+        let frame = Arc::make_mut(&mut self.frame);
+        for batch in batches {
+            // Columns keep the dtype of the first rows, added columns are null
+            let columns = frame
+                .get_columns()
+                .iter()
+                .map(|column| match batch.column(column.name()) {
+                    Ok(new) => new.cast(column.dtype()),
+                    Err(_) => Ok(polars::prelude::Column::full_null(
+                        column.name().clone(),
+                        batch.height(),
+                        column.dtype(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            frame.vstack_mut(&DataFrame::new(columns)?)?;
+        }
+        let dropped = match ring {
+            Some(ring) if frame.height() > ring => frame.height() - ring,
+            _ => 0,
+        };
+        if dropped > 0 {
+            *frame = frame.slice(dropped as i64, frame.height() - dropped);
+        }
+        if frame.max_n_chunks() > MAX_STREAM_CHUNKS {
+            frame.as_single_chunk_par();
+        }
+        for (column, series) in self.data.iter_mut().zip(frame.get_columns()) {
+            column.set_series(series.as_materialized_series().clone());
+            if dropped > 0 {
+                column.unparsed.clear();
+                column.differs.clear();
+            }
         }
 
+        let height = frame.height();
+        let shift = |rows: &mut dyn Iterator<Item = &usize>| -> Vec<usize> {
+            rows.filter(|&&r| r >= dropped)
+                .map(|&r| r - dropped)
+                .collect()
+        };
+        let following = self.tables.len() == 1 && {
+            let table = &self.tables[0];
+            table.offset_row + table.curser_row + 1 >= table.rows.len()
+        };
+        for (idx, table) in self.tables.iter_mut().enumerate() {
+            let mut rows = match dropped {
+                0 => std::mem::take(Arc::make_mut(&mut table.rows)),
+                _ => shift(&mut table.rows.iter()),
+            };
+            if idx == 0 {
+                rows.extend(old_height.saturating_sub(dropped)..height);
+            }
+            table.rows = Arc::new(rows);
+            if dropped > 0 {
+                table.marked_rows = shift(&mut table.marked_rows.iter()).into_iter().collect();
+                table.bookmarks =
+                    Arc::new(shift(&mut table.bookmarks.iter()).into_iter().collect());
+                table.highlighted_rows.clear();
+                table.search_results.clear();
+            }
+        }
+        self.column_stats_key = None;
+        self.totals = None;
+        for hist in self.histogram_views.iter_mut() {
+            hist.clear();
+        }
+
+        let table = self.tables.last().unwrap();
+        let (row, column) = (
+            table.offset_row + table.curser_row,
+            table.offset_column + table.curser_column,
+        );
+        let last_row = table.rows.len().saturating_sub(1);
+        match following {
+            true => self.select_cell(last_row, column),
+            false => self.select_cell(row.min(last_row), column),
+        }
+        self.update_table_data();
+        Ok(())
+    }
+
+    fn read_database(
+        url: &str,
+        table: Option<String>,
+        query: Option<String>,
+    ) -> Result<(String, DataFrame), TVError> {
+        let (name, query) = match (table, query) {
+            (_, Some(query)) => ("query".to_string(), query),
+            (Some(table), None) => (table.clone(), database::select_table(url, &table)),
+            (None, None) => {
+                return Err(TVError::LoadingFailed(
+                    "Database sources need a --table or --query!".into(),
+                ));
+            }
+        };
+        Ok((name, database::read_query(url, &query)?))
+    }
+
+    // Timings of the main loop for the profiling overlay. Does not trigger a redraw by itself.
+    pub fn record_frame(&mut self, draw_time: Duration, latency: Option<Duration>) {
+        self.uidata.profile.draw_time = draw_time;
+        if let Some(latency) = latency {
+            self.uidata.profile.latency = latency;
+        }
+    }
+
+    fn toggle_profile(&mut self) {
+        self.uidata.profile.show = !self.uidata.profile.show;
+        self.uidata.last_update = Instant::now();
+    }
+
+    fn load_sheets(&mut self, mut sheets: Vec<(String, DataFrame)>) -> Result<bool, TVError> {
+        let (name, frame) = sheets.remove(0);
+
+        let start_time = Instant::now();
+        self.tabs = vec![Tab {
+            name: name.clone(),
+            dataset: Dataset::empty(),
+            parent_datasets: Vec::new(),
+        }];
+        self.active_tab = 0;
+        self.set_frame(frame, name)?;
+
+        // Every further sheet of a workbook gets its own tab
+        for (name, frame) in sheets {
+            self.tabs.push(Tab {
+                name: name.clone(),
+                dataset: Model::build_dataset(
+                    frame,
+                    name,
+                    &self.renderers,
+                    self.load_progress.as_ref(),
+                )?,
+                parent_datasets: Vec::new(),
+            });
+        }
+        let data_loading_duration = start_time.elapsed().as_millis();
+
+        self.update_tab_names();
+        self.set_status_message(format!("Loaded data in {}ms ...", data_loading_duration));
+
+        Ok(true)
+    }
+
+    // Replace the current dataset with the given frame, returning the time needed to process it.
+    fn set_frame(&mut self, frame: DataFrame, name: String) -> Result<u128, TVError> {
+        let start_time = Instant::now();
+        let dataset =
+            Model::build_dataset(frame, name, &self.renderers, self.load_progress.as_ref())?;
+        let data_loading_duration = start_time.elapsed().as_millis();
+        self.install_dataset(dataset);
+        Ok(data_loading_duration)
+    }
+
+    fn build_dataset(
+        frame: DataFrame,
+        name: String,
+        renderers: &RendererRegistry,
+        progress: Option<&Sender<LoadProgress>>,
+    ) -> Result<Dataset, TVError> {
+        // Load dataframe using rayon with data parallelism.
+        // Each column is loaded in its own thread.
+        // Cell strings are created on demand, only the column widths are estimated here.
+        let start_time = Instant::now();
+
+        let df = Arc::new(frame);
+        let tracker = progress.map(|sender| LoadTracker::new(sender.clone(), &df));
+        let c_: Result<Vec<Column>, _> = df
+            .get_column_names()
+            .par_iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                Self::load_columns(&df, idx, name, tracker.as_ref()).map(|mut column| {
+                    column.set_renderer(renderers.find(&column));
+                    column
+                })
+            })
+            .collect();
+        let columns = c_?;
+
+        let data_loading_duration = start_time.elapsed().as_millis();
+        info!("Loading data took {data_loading_duration}ms ...");
+        for c in columns.iter() {
+            debug!("Column: {}", c.as_string());
+        }
+        let mut table = TableView::empty();
+        // set default row mapping
+        table.rows = Arc::new((0..df.height()).collect());
+        table.name = name;
+
+        Ok(Dataset {
+            frame: df,
+            data: columns,
+            tables: vec![table],
+            histogram_views: vec![HistogramView::empty()],
+        })
+    }
+
+    fn install_dataset(&mut self, dataset: Dataset) {
+        self.frame = dataset.frame;
+        self.column_stats_key = None;
+        self.totals = None;
+        self.data = dataset.data;
+        self.tables = dataset.tables;
+        self.background_search = None;
+        self.histogram_views = dataset.histogram_views;
+        for hist in self.histogram_views.iter_mut() {
+            if hist.bins != self.histogram_bins {
+                hist.set_bins(self.histogram_bins);
+            }
+            hist.set_wakeup(self.events.clone());
+        }
+        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+        self.update_table_data();
+    }
+
+    fn take_dataset(&mut self) -> Dataset {
+        Dataset {
+            frame: std::mem::replace(&mut self.frame, Arc::new(DataFrame::empty())),
+            data: std::mem::take(&mut self.data),
+            tables: std::mem::take(&mut self.tables),
+            histogram_views: std::mem::take(&mut self.histogram_views),
+        }
+    }
+
+    fn switch_tab(&mut self, step: isize) {
+        if self.tabs.len() < 2 {
+            self.set_status_message("No other tabs!");
+            return;
+        }
+        let next = (self.active_tab as isize + step).rem_euclid(self.tabs.len() as isize) as usize;
+
+        // Park the active tab and bring up the next one
+        let dataset = self.take_dataset();
+        let current = &mut self.tabs[self.active_tab];
+        current.parent_datasets = std::mem::take(&mut self.parent_datasets);
+        current.dataset = dataset;
+
+        let next_tab = &mut self.tabs[next];
+        let dataset = std::mem::replace(&mut next_tab.dataset, Dataset::empty());
+        self.parent_datasets = std::mem::take(&mut next_tab.parent_datasets);
+        self.active_tab = next;
+        self.install_dataset(dataset);
+        self.update_tab_names();
+    }
+
+    fn update_tab_names(&mut self) {
+        self.uidata.tab_names = self.tabs.iter().map(|t| t.name.clone()).collect();
+        self.uidata.active_tab = self.active_tab;
+    }
+
+    // Show the given frame as a new dataset on top of the current one. Leaving it with Esc restores the current one.
+    fn push_derived_frame(&mut self, frame: DataFrame, name: String) -> Result<(), TVError> {
+        // Built before the current dataset is put aside, so it is still shown if that fails
+        let dataset =
+            Model::build_dataset(frame, name, &self.renderers, self.load_progress.as_ref())?;
+        let parent = self.take_dataset();
+        self.parent_datasets.push(parent);
+        self.install_dataset(dataset);
+        Ok(())
+    }
+
+    fn pop_derived_frame(&mut self) {
+        if let Some(parent) = self.parent_datasets.pop() {
+            self.install_dataset(parent);
+        }
+    }
+
+    // Returns the rows of the current table view (filtered and sorted) as a polars DataFrame
+    fn current_view_frame(&self) -> Result<DataFrame, PolarsError> {
+        let table = self.tables.last().unwrap();
+        let indices = IdxCa::from_vec(
+            "idx".into(),
+            table.rows.iter().map(|&r| r as IdxSize).collect(),
+        );
+        self.frame.take(&indices)
+    }
+
+    // Read the file as a list of (name, frame). Workbooks contain one frame per sheet.
+    fn file_name(file_info: &FileInfo) -> String {
+        file_info
+            .path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("???")
+            .to_string()
+    }
+
+    fn read_data_file(file_info: &FileInfo) -> Result<Vec<(String, DataFrame)>, TVError> {
+        let name = Model::file_name(file_info);
+        let frame = match file_info.file_type {
+            FileType::CSV => Model::load_csv(file_info)?,
+            FileType::PARQUET => Model::load_parquet(&file_info.path)?,
+            FileType::XLSX => return xlsx::read_workbook(&file_info.path),
+            FileType::ARROW => Model::load_arrow(&file_info.path)?,
+        };
+        Ok(vec![(name, frame)])
+    }
+
+    // If the file can be loaded, judging by its extension
+    pub fn is_tabular_file(path: &Path) -> bool {
+        Model::detect_file_type(path).is_ok()
+    }
+
+    // Data was edited and not written yet
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    // The popup asking to write the edits before quitting is shown
+    pub fn is_confirming_quit(&self) -> bool {
+        self.confirming_quit
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_info.as_ref().map(|info| info.path.as_path())
+    }
+
+    fn detect_file_type(path: &Path) -> Result<FileType, TVError> {
+        match path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_uppercase())
+            .as_deref()
+        {
+            Some("CSV") | Some("TSV") | Some("PSV") => Ok(FileType::CSV),
+            Some("PARQUET") | Some("PQ") => Ok(FileType::PARQUET),
+            Some("XLSX") => Ok(FileType::XLSX),
+            Some("ARROW") | Some("IPC") | Some("FEATHER") => Ok(FileType::ARROW),
+            _ => Err(TVError::UnknownFileType),
+        }
+    }
+
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.notify(MessageLevel::Info, message.into());
+    }
+
+    pub fn show_warning(&mut self, message: impl Into<String>) {
+        self.notify(MessageLevel::Warning, message.into());
+    }
+
+    pub fn show_error(&mut self, message: impl Into<String>) {
+        self.notify(MessageLevel::Error, message.into());
+    }
+
+    fn notify(&mut self, level: MessageLevel, message: String) {
+        if level != MessageLevel::Info {
+            warn!("{message}");
+            self.messages.push((Instant::now(), level, message.clone()));
+        }
+        self.status_message = message;
+        self.last_status_message_update = Instant::now();
+        self.uidata.status_message = self.status_message.clone();
+        self.uidata.status_level = level;
+        self.uidata.last_status_message_update = self.last_status_message_update;
+        self.uidata.last_update = Instant::now();
+    }
+
+    // Warnings and errors stay in the status line until the next key press
+    fn dismiss_notification(&mut self) {
+        if self.uidata.status_level != MessageLevel::Info {
+            self.uidata.status_level = MessageLevel::Info;
+            self.uidata.last_update = Instant::now();
+        }
+    }
+
+    // Reload the csv file, flipping if its first row is used as header
+    fn toggle_header(&mut self) {
+        let Some(file_info) = self.file_info.as_ref() else {
+            self.show_warning("Not loaded from a file!");
+            return;
+        };
+        if !matches!(file_info.file_type, FileType::CSV) {
+            self.show_warning("Only csv files can be loaded without header!");
+            return;
+        }
+        if self.dirty {
+            self.show_warning("Reloading would discard the edits, :w or :export them first!");
+            return;
+        }
+        let file_info = FileInfo {
+            path: file_info.path.clone(),
+            file_size: file_info.file_size,
+            file_type: FileType::CSV,
+            has_header: !file_info.has_header,
+            delimiter: file_info.delimiter,
+            bom: file_info.bom,
+        };
+        let has_header = file_info.has_header;
+        match self.load_file(file_info) {
+            Ok(_) if has_header => self.set_status_message("Using the first row as header"),
+            Ok(_) => self.set_status_message("Using the first row as data"),
+            Err(e) => self.show_error(format!("Reloading failed! {e:?}")),
+        }
+    }
+
+    // The loaded file as it is on disk now, None if there are edits that reloading would discard
+    fn reloadable_file(&mut self) -> Option<FileInfo> {
+        let Some(file_info) = self.file_info.as_ref() else {
+            self.show_warning("Not loaded from a file!");
+            return None;
+        };
+        if self.dirty {
+            self.show_warning("Reloading would discard the edits, :w or :export them first!");
+            return None;
+        }
+        Some(FileInfo {
+            path: file_info.path.clone(),
+            file_size: fs::metadata(&file_info.path).map_or(file_info.file_size, |m| m.len()),
+            file_type: file_info.file_type,
+            has_header: file_info.has_header,
+            delimiter: file_info.delimiter,
+            bom: file_info.bom,
+        })
+    }
+
+    // Load the file again, the derived views are only dropped once it could be read
+    fn reload_file(&mut self, file_info: FileInfo) -> bool {
+        let start_time = Instant::now();
+        let result = self.read_for_load(file_info).and_then(|file| {
+            self.parent_datasets.clear();
+            self.install_file(file, start_time)
+        });
+        if let Err(e) = result {
+            self.show_error(format!("Reloading failed! {e:?}"));
+            return false;
+        }
+        let modified = self.file_modified();
+        if let Some(watch) = self.watch.as_mut() {
+            watch.modified = modified;
+        }
+        true
+    }
+
+    fn watch_interval(&self) -> Duration {
+        Duration::from_millis(self.config.watch_interval as u64)
+    }
+
+    fn file_modified(&self) -> Option<SystemTime> {
+        let file_info = self.file_info.as_ref()?;
+        fs::metadata(&file_info.path).ok()?.modified().ok()
+    }
+
+    // Start reloading the file when it changes, matching the rows by the key column
+    pub fn watch(&mut self, key: &str) {
+        let Some(file_info) = self.file_info.as_ref() else {
+            self.show_warning("Not loaded from a file!");
+            return;
+        };
+        let name = Model::file_name(file_info);
+        self.watch = Some(Watch {
+            key: key.to_string(),
+            modified: self.file_modified(),
+            due: Instant::now() + self.watch_interval(),
+        });
+        self.set_status_message(format!("Watching {name}, changes are shown once reloaded"));
+    }
+
+    fn toggle_watch(&mut self, key: &str) {
+        match self.watch.take() {
+            Some(_) => self.set_status_message("Stopped watching the file"),
+            None => self.watch(key),
+        }
+    }
+
+    // Reload a watched file that was modified, a file that is missing meanwhile is waited for
+    fn poll_watch(&mut self) {
+        let Some(watch) = self.watch.as_ref() else {
+            return;
+        };
+        if watch.due > Instant::now() {
+            return;
+        }
+        let key = watch.key.clone();
+        let modified = self.file_modified();
+        let changed = modified.is_some() && modified != watch.modified;
+        let due = Instant::now() + self.watch_interval();
+        let watch = self.watch.as_mut().unwrap();
+        watch.due = due;
+        if changed {
+            watch.modified = modified;
+            self.reload(&key);
+        }
+    }
+
+    // Reload the file and show the rows that changed since it was loaded, Esc shows all rows
+    fn reload(&mut self, key: &str) {
+        let Some(file_info) = self.reloadable_file() else {
+            return;
+        };
+
+        // The first sheet as loaded, without derived views on top of it
+        let previous = match self.active_tab {
+            0 => self
+                .parent_datasets
+                .first()
+                .map_or(&self.frame, |d| &d.frame),
+            _ => self.tabs[0]
+                .parent_datasets
+                .first()
+                .map_or(&self.tabs[0].dataset.frame, |d| &d.frame),
+        }
+        .clone();
+        if !self.reload_file(file_info) {
+            return;
+        }
+
+        let key = (!key.is_empty()).then_some(key);
+        let delta = match snapshot_diff::diff(&previous, &self.frame, key) {
+            Ok(delta) => delta,
+            Err(e) => {
+                self.show_error(format!("Comparing with the previous load failed! {e:?}"));
+                return;
+            }
+        };
+        if delta.is_empty() {
+            self.set_status_message("Reloaded, nothing changed");
+            return;
+        }
+        let name = format!("Δ[{}]", self.tables[0].name);
+        let summary = format!(
+            "{} added, {} removed, {} changed{}",
+            delta.added,
+            delta.removed,
+            delta.changed,
+            delta
+                .key
+                .map(|key| format!(" by {key}"))
+                .unwrap_or_default()
+        );
+        match self.push_derived_frame(delta.frame, name) {
+            Ok(_) => self.set_status_message(format!("Reloaded: {summary}, Esc shows all rows")),
+            Err(e) => self.show_error(format!("Showing the changes failed! {e:?}")),
+        }
+    }
+
+    // Reload the file, re-applying the filters and sort, with the curser on the same row of the file
+    fn reload_view(&mut self) {
+        let Some(file_info) = self.reloadable_file() else {
+            return;
+        };
+        let view = self.current_view();
+        let table = self.tables.last().unwrap();
+        let position = table.offset_row + table.curser_row;
+        let ridx = table.rows.get(position).copied();
+        let column = self.data[table.offset_column + table.curser_column]
+            .name
+            .clone();
+
+        if !self.reload_file(file_info) {
+            return;
+        }
+        self.apply_view("", &view);
+
+        // Rows of the file that are filtered out now keep the curser where it was on the screen
+        let table = self.tables.last_mut().unwrap();
+        let row = ridx
+            .and_then(|ridx| table.rows.iter().position(|&r| r == ridx))
+            .unwrap_or(position)
+            .min(table.rows.len().saturating_sub(1));
+        let column = self.data.iter().position(|c| c.name == column).unwrap_or(0);
+        table.select_cell(
+            row,
+            column,
+            &mut self.data,
+            &self.uilayout,
+            &mut self.uidata,
+        );
+        let shown = table.rows.len();
+        self.set_status_message(format!(
+            "Reloaded {} rows, {shown} shown",
+            self.frame.height()
+        ));
+    }
+
+    fn show_malformed_lines(&mut self) {
+        let mut message = String::from("\n");
+        if self.malformed_lines.is_empty() {
+            message.push_str("  No malformed lines.\n");
+        }
+        for malformed in self.malformed_lines.iter() {
+            message.push_str(&format!(
+                "  line {}: {}\n    {}\n",
+                malformed.line, malformed.problem, malformed.text
+            ));
+        }
+        self.show_popup("Malformed Lines", &message);
+    }
+
+    fn show_messages(&mut self) {
+        let mut message = String::from("\n");
+        if self.messages.is_empty() {
+            message.push_str("  No warnings or errors.\n");
+        }
+        for (time, level, text) in self.messages.iter().rev() {
+            message.push_str(&format!(
+                "  {:>5}s ago {level:?}: {text}\n",
+                time.elapsed().as_secs()
+            ));
+        }
+        self.show_popup("Messages", &message);
+    }
+
+    pub fn get_uidata(&self) -> &UIData {
+        &self.uidata
+    }
+
+    fn update_histogram(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let cache_key = self.caches_stats().then(|| {
+            let column = &self.data[column_idx];
+            (column.name.clone(), column.dtype.to_string())
+        });
+        let hist = self.histogram_views.last_mut().unwrap();
+        if let (Some((name, dtype)), Some(cache)) = (&cache_key, self.stats_cache.as_ref())
+            && !hist.column_histograms.contains_key(&column_idx)
+            && let Some(histogram) = cache.histogram(name, dtype, hist.bins)
+        {
+            hist.column_histograms.insert(column_idx, histogram);
+        }
+        self.uidata.layout = self.uilayout.clone();
+        hist.update(column_idx, &mut self.data, table, &mut self.uidata);
+
+        // Sampled histograms are cached once their full count is done
+        if let (Some((name, dtype)), Some(cache)) = (cache_key, self.stats_cache.as_mut())
+            && !hist.is_pending(column_idx)
+            && !cache.has_histogram(&name, &dtype, hist.bins)
+            && cache.set_histogram(
+                &name,
+                &dtype,
+                hist.bins,
+                &hist.column_histograms[&column_idx],
+            )
+        {
+            self.store_stats_cache();
+        }
+    }
+
+    // Widths of the columns as they were when the file was opened before
+    fn load_stats_cache(&mut self) {
+        let path = self.file_info.as_ref().map(|f| f.path.clone());
+        self.stats_cache = path
+            .filter(|_| self.config.stats_cache)
+            .and_then(|path| StatsCache::load(&path));
+        let Some(cache) = self.stats_cache.as_ref() else {
+            return;
+        };
+        for column in self.data.iter_mut() {
+            if let Some(&width) = cache.widths.get(&column.name) {
+                column.max_width = width;
+            }
+        }
+        self.update_table_data();
+    }
+
+    fn store_stats_cache(&mut self) {
+        let Some(cache) = self.stats_cache.as_mut() else {
+            return;
+        };
+        cache.widths = self
+            .data
+            .iter()
+            .map(|c| (c.name.clone(), c.max_width))
+            .collect();
+        if let Err(e) = cache.store() {
+            warn!("Storing the stats cache failed: {e:?}");
+        }
+    }
+
+    // Only stats over all rows of the loaded file are cached, not the ones of filtered, derived, edited
+    // or partially loaded tables
+    fn caches_stats(&self) -> bool {
+        self.stats_cache.is_some()
+            && !self.dirty
+            && self.partial_rows.is_none()
+            && self.tables.len() == 1
+            && self.parent_datasets.is_empty()
+            && self.active_tab == 0
+    }
+
+    fn update_table_data(&mut self) {
+        // If the model is empty, there is nothing to do.
+        if self.tables.is_empty() || self.data.is_empty() {
+            // Does self.uidata need some work?
+        } else {
+            let start_time = Instant::now();
+            let table = self.tables.last_mut().unwrap();
+            table.update(&mut self.data, &self.uilayout, &mut self.uidata);
+            self.uidata.profile.collect_time = start_time.elapsed();
+        }
+    }
+
+    // Full value of the selected cell with its column name and dtype
+    fn update_cell_line(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let (Some(&ridx), Some(column)) = (
+            table.rows.get(table.offset_row + table.curser_row),
+            self.data.get(column_idx),
+        ) else {
+            self.uidata.cell_line.clear();
+            return;
+        };
+        let value = self.raw_value(column_idx, ridx).replace('\n', "⏎");
+        self.uidata.cell_line = format!("{} ({}): {value}", column.name, column.dtype);
+    }
+
+    // Quick stats of the selected column over the rows of the current table, shown in the footer
+    fn update_column_stats(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let key = (
+            column_idx,
+            Arc::as_ptr(&table.rows) as usize,
+            table.rows.len(),
+        );
+        if self.column_stats_key == Some(key) {
+            return;
+        }
+        self.column_stats_key = Some(key);
+        let Some(column) = self.data.get(column_idx) else {
+            self.uidata.column_stats.clear();
+            self.uidata.top_values = None;
+            return;
+        };
+        self.uidata.top_values = self
+            .top_values_panel
+            .then(|| Model::top_values(column, &table.rows));
+
+        self.uidata.last_update = Instant::now();
+        let dtype = column.dtype.to_string();
+        let cached = match self.caches_stats() {
+            true => self
+                .stats_cache
+                .as_ref()
+                .and_then(|c| c.stats(&column.name, &dtype)),
+            false => None,
+        };
+        if let Some(stats) = cached {
+            self.uidata.column_stats = stats.clone();
+            return;
+        }
+        self.uidata.column_stats = if Model::is_numeric_type(&column.dtype) {
+            match self.numeric_values(column_idx) {
+                Ok(values) if values.len() > values.null_count() => {
+                    let sum = values.sum().unwrap_or(0.0);
+                    let min = values.min().unwrap_or(0.0);
+                    let max = values.max().unwrap_or(0.0);
+                    let mean = values.mean().unwrap_or(0.0);
+                    if Model::is_integer_type(&column.dtype) {
+                        format!("min {min} | mean {mean:.2} | max {max} | sum {sum}")
+                    } else {
+                        format!("min {min:.2} | mean {mean:.2} | max {max:.2} | sum {sum:.2}")
+                    }
+                }
+                _ => String::new(),
+            }
+        } else {
+            let values = column.values();
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for &ridx in table.rows.iter() {
+                *counts.entry(&values[ridx]).or_default() += 1;
+            }
+            match counts
+                .iter()
+                .max_by_key(|(value, count)| (**count, std::cmp::Reverse(**value)))
+            {
+                Some((mode, count)) => {
+                    let mode: String = mode.chars().take(20).collect();
+                    format!(
+                        "distinct {} | mode {mode} ({})",
+                        format_count(counts.len()),
+                        format_count(*count)
+                    )
+                }
+                None => String::new(),
+            }
+        };
+        if self.caches_stats() {
+            let name = self.data[column_idx].name.clone();
+            let stats = self.uidata.column_stats.clone();
+            self.stats_cache
+                .as_mut()
+                .unwrap()
+                .set_stats(&name, &dtype, stats);
+            self.store_stats_cache();
+        }
+    }
+
+    fn top_values(column: &Column, rows: &[usize]) -> TopValuesData {
+        let values = column.values();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for &ridx in rows {
+            *counts.entry(&values[ridx]).or_default() += 1;
+        }
+        let title = format!(
+            "Top values of {} ({} distinct)",
+            column.name,
+            format_count(counts.len())
+        );
+        let mut top: Vec<(&str, usize)> = counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        TopValuesData {
+            title,
+            values: top
+                .into_iter()
+                .take(TOP_VALUES_PANEL_ROWS)
+                .map(|(value, count)| (value.to_string(), count))
+                .collect(),
+            total: rows.len(),
+        }
+    }
+
+    pub fn shows_top_values(&self) -> bool {
+        self.top_values_panel
+    }
+
+    fn toggle_top_values(&mut self) {
+        self.top_values_panel = !self.top_values_panel;
+        self.column_stats_key = None;
+        self.totals = None;
+        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+        self.update_table_data();
+    }
+
+    pub fn shows_totals(&self) -> bool {
+        self.totals_row
+    }
+
+    fn toggle_totals(&mut self) {
+        self.totals_row = !self.totals_row;
+        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+        self.update_table_data();
+    }
+
+    // Sum of numeric columns, count of values of other columns, over the rows of the current table.
+    // Computed for all columns once per filter, the visible ones are passed to the ui.
+    fn update_totals(&mut self) {
+        if !self.totals_row {
+            self.uidata.totals = None;
+            return;
+        }
+        let table = self.tables.last().unwrap();
+        let key = (Arc::as_ptr(&table.rows) as usize, table.rows.len());
+        if self
+            .totals
+            .as_ref()
+            .is_none_or(|(computed, _)| *computed != key)
+        {
+            let totals = (0..self.data.len())
+                .map(|idx| self.column_total(idx))
+                .collect();
+            self.totals = Some((key, totals));
+        }
+        let (_, totals) = self.totals.as_ref().unwrap();
+        let table = self.tables.last().unwrap();
+        let visible = table.visible_columns.iter().map(|&idx| {
+            let column = &self.data[idx];
+            match column.folded || column.status == ColumnStatus::COLLAPSED {
+                true => String::new(),
+                false => totals[idx].clone(),
+            }
+        });
+        self.uidata.totals = Some(visible.collect());
+    }
+
+    fn column_total(&self, column_idx: usize) -> String {
+        let column = &self.data[column_idx];
+        if !Model::is_numeric_type(&column.dtype) {
+            let rows = self.tables.last().unwrap().rows.iter();
+            let count = rows.filter(|&&ridx| !column.is_blank(ridx)).count();
+            return format!("count {count}");
+        }
+        let sum = self
+            .numeric_values(column_idx)
+            .ok()
+            .and_then(|values| values.sum())
+            .unwrap_or(0.0);
+        match Model::is_integer_type(&column.dtype) {
+            true => format!("{sum}"),
+            false => format!("{sum:.2}"),
+        }
+    }
+
+    // Typed values of the rows in the current table, without creating the cell strings
+    fn numeric_values(&self, column_idx: usize) -> PolarsResult<Float64Chunked> {
+        let table = self.tables.last().unwrap();
+        let indices = IdxCa::from_vec(
+            "idx".into(),
+            table.rows.iter().map(|&r| r as IdxSize).collect(),
+        );
+        let values = self.frame.get_columns()[column_idx]
+            .take(&indices)?
+            .cast(&DataType::Float64)?;
+        Ok(values.f64()?.clone())
+    }
+
+    // Show a popup with the percentiles of the selected numeric column, of the rows in the current table
+    fn show_percentiles(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let column = &self.data[column_idx];
+        if !Model::is_numeric_type(&column.dtype) {
+            self.show_warning("Percentiles need a numeric column!");
+            return;
+        }
+        let name = column.name.clone();
+        let values = match self.numeric_values(column_idx) {
+            Ok(values) if values.len() > values.null_count() => values.sort(false),
+            Ok(_) => {
+                self.show_warning("The column has no values!");
+                return;
+            }
+            Err(e) => {
+                self.show_error(format!("Computing percentiles failed! {e}"));
+                return;
+            }
+        };
+
+        let mut message = format!(
+            "\n  {name}: {} values, {} nulls\n\n",
+            format_count(values.len() - values.null_count()),
+            format_count(values.null_count())
+        );
+        let mut add_line = |label: &str, value: Option<f64>| {
+            let value = value.map(|v| format!("{v:.2}")).unwrap_or_default();
+            message.push_str(&format!("  {label:<4} {value:>16}\n"));
+        };
+        add_line("min", values.min());
+        for percentile in PERCENTILES {
+            let value = values
+                .quantile(percentile as f64 / 100.0, QuantileMethod::Linear)
+                .ok()
+                .flatten();
+            add_line(&format!("p{percentile}"), value);
+        }
+        add_line("max", values.max());
+        self.show_popup("Percentiles", &message);
+    }
+
+    fn is_numeric_type(dtype: &DataType) -> bool {
+        matches!(
+            dtype,
+            DataType::Int8
+                | DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64
+                | DataType::Float32
+                | DataType::Float64
+        )
+    }
+
+    fn is_integer_type(dtype: &DataType) -> bool {
+        matches!(
+            dtype,
+            DataType::Int8
+                | DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64
+        )
+    }
+
+    fn load_columns(
+        df: &DataFrame,
+        idx: usize,
+        col_name: &str,
+        tracker: Option<&LoadTracker>,
+    ) -> Result<Column, PolarsError> {
+        // Cell strings are only created when they are needed, e.g. when shown
+        let column = Column::new(idx, df.column(col_name)?.as_materialized_series().clone());
+        if let Some(tracker) = tracker {
+            tracker.rows_loaded(column.len());
+            tracker.column_loaded();
+        }
+        Ok(column)
+    }
+
+    fn get_file_info(path: PathBuf) -> Result<FileInfo, TVError> {
+        let metadata = fs::metadata(&path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => TVError::FileNotFound,
+            ErrorKind::PermissionDenied => TVError::PermissionDenied,
+            _ => TVError::IoError(e),
+        })?;
+        if !metadata.is_file() {
+            return Err(TVError::LoadingFailed("Not a file!".into()));
+        }
+
+        let file_size = metadata.len();
+
+        let file_type = Model::detect_file_type(&path)?;
+        let (has_header, delimiter, bom) = match file_type {
+            FileType::CSV => {
+                let delimiter = sniff::delimiter(&path);
+                (
+                    sniff::has_header(&path, delimiter),
+                    delimiter,
+                    Bom::read(&path),
+                )
+            }
+            _ => (true, ',', None),
+        };
+
+        Ok(FileInfo {
+            path,
+            file_size,
+            file_type,
+            has_header,
+            delimiter,
+            bom,
+        })
+    }
+
+    fn load_csv(file_info: &FileInfo) -> Result<DataFrame, PolarsError> {
+        let path = &file_info.path;
+        let options = CsvReadOptions::default()
+            .with_has_header(file_info.has_header)
+            .with_parse_options(
+                CsvParseOptions::default().with_separator(file_info.delimiter as u8),
+            );
+        // Decoded in memory: replaces invalid UTF-8, drops the BOM and transcodes UTF-16
+        let read_decoded = |options: CsvReadOptions| {
+            let bytes = std::fs::read(path).map_err(|e| PolarsError::IO {
+                error: e.into(),
+                msg: None,
+            })?;
+            let content = sniff::decode(&bytes);
+            let cursor = std::io::Cursor::new(content.as_bytes());
+            CsvReader::new(cursor).with_options(options).finish()
+        };
+        if matches!(file_info.bom, Some(Bom::Utf16Le | Bom::Utf16Be)) {
+            return read_decoded(options);
+        }
+        options
+            .clone()
+            .try_into_reader_with_file_path(Some(path.into()))?
+            .finish()
+            .or_else(|_| {
+                error!("Loading CSV failed! Fallback, trying to load in UTF8 lossy mode.");
+                read_decoded(options)
+            })
+    }
+
+    // Schema, row count and first rows, without reading the whole file. Row counts of csv
+    // files are estimated, arrow files are shown without.
+    fn read_preview(file_info: &FileInfo) -> Option<LoadPreview> {
+        let path = &file_info.path;
+        let (frame, nrows, nrows_exact) = match file_info.file_type {
+            FileType::CSV if !matches!(file_info.bom, Some(Bom::Utf16Le | Bom::Utf16Be)) => {
+                let frame = CsvReadOptions::default()
+                    .with_has_header(file_info.has_header)
+                    .with_n_rows(Some(PREVIEW_ROWS))
+                    .with_parse_options(
+                        CsvParseOptions::default().with_separator(file_info.delimiter as u8),
+                    )
+                    .try_into_reader_with_file_path(Some(path.into()))
+                    .ok()?
+                    .finish()
+                    .ok()?;
+                let (lines, exact) = sniff::estimate_lines(path, file_info.file_size);
+                let nrows = lines.saturating_sub(file_info.has_header as usize);
+                (frame, Some(nrows), exact)
+            }
+            FileType::PARQUET => {
+                let mut reader = ParquetReader::new(fs::File::open(path).ok()?);
+                let nrows = reader.num_rows().ok()?;
+                let frame = reader.with_slice(Some((0, PREVIEW_ROWS))).finish().ok()?;
+                (frame, Some(nrows), true)
+            }
+            FileType::ARROW => {
+                let frame = IpcReader::new(fs::File::open(path).ok()?)
+                    .with_n_rows(Some(PREVIEW_ROWS))
+                    .finish()
+                    .ok()?;
+                (frame, None, false)
+            }
+            _ => return None,
+        };
+
+        let columns = frame.get_columns();
+        let names: Vec<String> = match file_info.has_header {
+            true => columns.iter().map(|c| c.name().to_string()).collect(),
+            false => column_names::names(path, columns.len()),
+        };
+        let parsing = matches!(file_info.file_type, FileType::CSV).then(|| {
+            let encoding = file_info.bom.map_or("UTF-8", Bom::name);
+            let header = match file_info.has_header {
+                true => "header row",
+                false => "no header row",
+            };
+            format!(
+                "{} delimited, {encoding}, {header}",
+                sniff::delimiter_name(file_info.delimiter)
+            )
+        });
+        Some(LoadPreview {
+            columns: names
+                .into_iter()
+                .zip(columns)
+                .map(|(name, c)| (name, c.dtype().to_string()))
+                .collect(),
+            rows: (0..frame.height())
+                .map(|ridx| {
+                    columns
+                        .iter()
+                        .map(|c| match c.get(ridx) {
+                            Ok(AnyValue::Null) | Err(_) => "∅".to_string(),
+                            Ok(value) => value.str_value().to_string(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            nrows,
+            nrows_exact,
+            parsing,
+        })
+    }
+
+    fn load_parquet(path: &PathBuf) -> Result<DataFrame, PolarsError> {
+        let file = std::fs::File::open(path)?;
+        ParquetReader::new(file).finish()
+    }
+
+    fn load_arrow(path: &PathBuf) -> Result<DataFrame, PolarsError> {
+        let file = std::fs::File::open(path)?;
+        IpcReader::new(file).finish()
+    }
+
+    fn record_macro(&mut self, register: char) {
+        self.recording = Some((register, Vec::new()));
+        self.uidata.recording_macro = Some(register);
+        self.set_status_message(format!("Recording @{register}, Q to stop"));
+    }
+
+    fn stop_macro(&mut self) {
+        if let Some((register, recorded)) = self.recording.take() {
+            self.set_status_message(format!("Recorded {} keys into @{register}", recorded.len()));
+            self.macros.insert(register, recorded);
+        }
+        self.uidata.recording_macro = None;
+    }
+
+    pub fn recording_macro(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    fn replay_macro(&mut self, register: char, count: usize) -> Result<(), TVError> {
+        let Some(messages) = self.macros.get(&register).cloned() else {
+            self.show_warning(format!("Register @{register} is empty!"));
+            return Ok(());
+        };
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            self.show_warning(format!("Macro @{register} replays itself too often!"));
+            return Ok(());
+        }
+        if self.macro_depth == 0 {
+            self.macro_steps = 0;
+        }
+        // A recording keeps the replay instead of the replayed messages
+        let recording = self.recording.take();
+        self.macro_depth += 1;
+        let mut result = Ok(());
+        'replay: for _ in 0..count {
+            for message in messages.iter() {
+                if self.macro_steps >= MAX_MACRO_STEPS {
+                    if self.macro_depth == 1 {
+                        self.show_warning(format!("Macro @{register} replays too many keys!"));
+                    }
+                    break 'replay;
+                }
+                self.macro_steps += 1;
+                result = self.update(Some(message.clone()));
+                if result.is_err() || self.status == Status::QUITTING {
+                    break 'replay;
+                }
+            }
+        }
+        self.macro_depth -= 1;
+        self.recording = recording;
+        if let Some((_, recorded)) = self.recording.as_mut() {
+            recorded.push(Message::ReplayMacro(register, count));
+        }
+        result
+    }
+
+    pub fn input_mode(&self) -> InputMode {
+        if self.active_cmdinput {
+            InputMode::from_cmd_mode(self.cmd_mode)
+        } else {
+            InputMode::Normal
+        }
+    }
+
+    // Cell content the main loop should show in an external program
+    pub fn take_external_request(&mut self) -> Option<(ExternalProgram, String)> {
+        self.external_request.take()
+    }
+
+    fn expand_path(path: &str) -> PathBuf {
+        PathBuf::from(
+            shellexpand::full(path.trim())
+                .map(|p| p.to_string())
+                .unwrap_or(path.trim().to_string()),
+        )
+    }
+
+    // The workspace loads the file, as it owns all tables
+    fn request_open(&mut self, path: &str) {
+        if path.trim().is_empty() {
+            self.show_warning("Usage: open <file>, Tab completes the file");
+            return;
+        }
+        self.workspace_request = Some(WorkspaceRequest::Open(Model::expand_path(path)));
+    }
+
+    // Show another file, or the current one again, next to this table
+    fn request_split(&mut self, path: &str) {
+        let path = if !path.trim().is_empty() {
+            Model::expand_path(path)
+        } else if let Some(file_info) = self.file_info.as_ref() {
+            file_info.path.clone()
+        } else {
+            self.show_warning("Not loaded from a file, use :split <file>");
+            return;
+        };
+        self.workspace_request = Some(WorkspaceRequest::Split(path));
+    }
+
+    pub fn take_workspace_request(&mut self) -> Option<WorkspaceRequest> {
+        self.workspace_request.take()
+    }
+
+    // Offsets and curser of the current table as (row offset, row, column offset, column)
+    pub fn scroll_position(&self) -> (usize, usize, usize, usize) {
+        let table = self.tables.last().unwrap();
+        (
+            table.offset_row,
+            table.curser_row,
+            table.offset_column,
+            table.curser_column,
+        )
+    }
+
+    // Scroll like another table, e.g. of the other pane. The rows and columns are kept within this table.
+    pub fn scroll_to(&mut self, position: (usize, usize, usize, usize), columns: bool) {
+        let (offset_row, curser_row, mut offset_column, mut curser_column) = position;
+        let (_, _, current_offset_column, current_column) = self.scroll_position();
+        if !columns {
+            (offset_column, curser_column) = (current_offset_column, current_column);
+        }
+        let position = (offset_row, curser_row, offset_column, curser_column);
+        if self.scroll_position() == position || self.data.is_empty() {
+            return;
+        }
+        let table = self.tables.last_mut().unwrap();
+        table.offset_row = offset_row;
+        table.curser_row = curser_row;
+        table.offset_column = offset_column.min(self.data.len() - 1);
+        table.curser_column = curser_column;
+        self.update_table_data();
+    }
+
+    pub fn external_program_finished(&mut self, result: Result<(), TVError>) {
+        match result {
+            // The terminal was cleared, make sure everything is redrawn
+            Ok(_) => self.uidata.last_update = Instant::now(),
+            Err(e) => self.show_error(format!("Opening cell failed! {e:?}")),
+        }
+    }
+
+    // Asks what to do with unsaved edits before quitting, a second quit discards them
+    pub fn quit(&mut self) {
+        if self.dirty && !self.confirming_quit {
+            if matches!(self.modus, Modus::POPUP) {
+                self.exit();
+            }
+            let target = match self.file_info.as_ref() {
+                Some(file_info) => format!("{:?}", file_info.path),
+                None => "the file".to_string(),
+            };
+            self.show_popup(
+                "Unsaved Changes",
+                &format!(
+                    "\n  The data was edited since it was loaded.\n\n  ENTER : Write to {target} and quit\n  q     : Discard the changes and quit\n  ESC   : Cancel\n"
+                ),
+            );
+            self.confirming_quit = true;
+            return;
+        }
+        self.status = Status::QUITTING;
+    }
+
+    // Write the edited data back to the file it was loaded from. Returns if it was written.
+    fn write_file(&mut self) -> bool {
+        let path = match self.file_info.as_ref() {
+            None => {
+                self.show_warning("Not loaded from a file, use :export <file>");
+                return false;
+            }
+            Some(FileInfo {
+                file_type: FileType::XLSX | FileType::ARROW,
+                ..
+            }) => {
+                self.show_warning("Can only write csv and parquet files, use :export <file>");
+                return false;
+            }
+            Some(file_info) => file_info.path.to_string_lossy().to_string(),
+        };
+        if !self.parent_datasets.is_empty() {
+            self.show_warning("Leave the derived view before writing the file!");
+            return false;
+        }
+        if self.partial_rows.is_some() {
+            self.show_warning("Only part of the rows were loaded, use :export <file>");
+            return false;
+        }
+        // All rows in the order of the file, sorting only reorders the rows of the tables
+        let rows: Vec<usize> = (0..self.frame.height()).collect();
+        match self.export_rows(&path, &rows, true) {
+            Ok(n) => {
+                self.dirty = false;
+                self.uidata.dirty = false;
+                self.set_status_message(format!("Wrote {n} rows to {path}"));
+                true
+            }
+            Err(e) => {
+                self.show_error(format!("Writing {path} failed! {e:?}"));
+                false
+            }
+        }
+    }
+
+    fn ui_resize(&mut self, width: usize, height: usize) {
+        trace!(
+            "UI was resized! w:{}->{}, h:{}->{}",
+            self.uilayout.width, width, self.uilayout.height, height
+        );
+        self.uilayout = UILayout::from_model(self, width, height);
+        self.input.set_width(self.uilayout.statusline_width);
+        // Popups and the command line are drawn on top of the previous view, which has to follow the new size
+        let modus = match self.modus {
+            Modus::POPUP | Modus::CMDINPUT => self.previous_modus,
+            modus => modus,
+        };
+        match modus {
+            Modus::TABLE => self.update_table_data(),
+            Modus::RECORD => {
+                self.update_table_data();
+                let table = self.tables.last().unwrap();
+                self.record_view = RecordView::new(
+                    table,
+                    &self.data,
+                    &mut self.uidata,
+                    self.config.max_column_width,
+                )
+            }
+            Modus::HISTOGRAM => self.update_histogram(),
+            Modus::CHART => self.update_table_data(),
+            Modus::PLOT => self.update_plot(),
+            Modus::JSON => self.update_json(),
+            Modus::POPUP => {}
+            Modus::CMDINPUT => {}
+        }
+        // Terminals narrower than the selected column show its record instead of a broken table,
+        // until they are wide enough again
+        let table = self.tables.last().unwrap();
+        let too_narrow = self.uilayout.table_width < table.selected_column_width(&self.data);
+        match self.modus {
+            Modus::TABLE if too_narrow && !table.rows.is_empty() => {
+                self.enter();
+                self.narrow_record_view = true;
+            }
+            Modus::RECORD if self.narrow_record_view && !too_narrow => self.exit(),
+            _ => {}
+        }
+        // The whole screen has to be redrawn at the new size, whatever the modus
+        self.uidata.last_update = Instant::now();
+    }
+
+    // How long the controller may wait for input before the model needs to update again
+    pub fn tick_interval(&self, poll_time: Duration) -> Duration {
+        if self.last_data_change > self.last_update {
+            return poll_time;
+        }
+        // Background workers post a wakeup, so only timers are waited for
+        let now = Instant::now();
+        let status_message_hidden =
+            self.last_status_message_update + STATUS_MESSAGE_DISPLAY_DURATION;
+        let filter_preview_due = self.filter_preview_due.filter(|_| self.active_cmdinput);
+        [
+            filter_preview_due,
+            self.watch.as_ref().map(|watch| watch.due),
+            (status_message_hidden > now).then_some(status_message_hidden),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|due| due.saturating_duration_since(now))
+        .fold(IDLE_TICK_INTERVAL, Duration::min)
+    }
+
+    pub fn update(&mut self, message: Option<Message>) -> Result<(), TVError> {
+        if self.last_data_change - self.last_update > std::time::Duration::ZERO {
+            self.update_table_data();
+        }
+        if self.active_cmdinput {
+            self.update_filter_preview();
+        }
+        self.poll_background_search();
+        self.poll_each();
+        self.poll_stream();
+        self.poll_watch();
+        if let Some(hist) = self.histogram_views.last_mut()
+            && hist.poll_background()
+            && matches!(self.modus, Modus::HISTOGRAM)
+        {
+            self.update_histogram();
+        }
+
+        if message
+            .as_ref()
+            .is_some_and(|m| !matches!(m, Message::Resize(..)))
+        {
+            self.dismiss_notification();
+            self.uidata.key_hints.clear();
+        }
+        let message = match message {
+            Some(Message::RecordMacro(register)) => {
+                self.record_macro(register);
+                None
+            }
+            Some(Message::StopMacro) => {
+                self.stop_macro();
+                None
+            }
+            Some(Message::ReplayMacro(register, count)) => {
+                self.replay_macro(register, count)?;
+                None
+            }
+            Some(Message::Remote(command, reply)) => {
+                reply.send(self.remote(command));
+                None
+            }
+            Some(Message::KeyHints(hints)) => {
+                self.uidata.key_hints = hints;
+                None
+            }
+            message => {
+                if let (Some((_, recorded)), Some(msg)) = (self.recording.as_mut(), &message)
+                    && !matches!(msg, Message::Resize(..))
+                {
+                    recorded.push(msg.clone());
+                }
+                message
+            }
+        };
+
         //trace!("Update: Modus {:?}, Message {:?}", self.modus, message);
         if let Some(msg) = message {
             if self.data.is_empty()
-                || self.data[0].data.is_empty()
+                || self.data[0].is_empty()
                 || self.tables.is_empty()
                 || self.tables.last().unwrap().rows.is_empty()
             {
@@ -351,502 +2323,4164 @@ impl Model {
                         _ => (),
                     }
                 }
+                match (self.modus, msg) {
+                    (Modus::CMDINPUT, Message::RawKey(key)) => self.raw_input(key),
+                    (Modus::CMDINPUT, Message::Paste(text)) => self.paste_input(&text),
+                    (Modus::POPUP, Message::Quit) => self.quit(),
+                    (Modus::POPUP, Message::Enter) => self.enter(),
+                    (Modus::POPUP, Message::Exit) => self.exit(),
+                    _ => (),
+                }
+            } else {
+                match self.modus {
+                    Modus::TABLE => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveDown(step) => self.move_table_selection_down(step),
+                        Message::MoveLeft => self.move_table_selection_left(),
+                        Message::MoveRight => self.move_table_selection_right(),
+                        Message::MoveUp(step) => self.move_table_selection_up(step),
+                        Message::MovePageUp => {
+                            self.move_table_selection_up(self.uilayout.table_height + 1)
+                        }
+                        Message::MovePageDown => {
+                            self.move_table_selection_down(self.uilayout.table_height + 1)
+                        }
+                        Message::MoveBeginning => self.move_table_selection_beginning(),
+                        Message::MoveEnd => self.move_table_selection_end(),
+                        Message::ToggleColumnState => self.toggle_column_status(false),
+                        Message::ToggleExpandColumnState => self.toggle_column_status(true),
+                        Message::ToggleColumnGroup => self.toggle_column_group(),
+                        Message::ToggleRowGroup => self.toggle_row_group(false),
+                        Message::ToggleAllRowGroups => self.toggle_row_group(true),
+                        Message::ToggleAllColumnGroups => self.toggle_all_column_groups(),
+                        Message::ToggleIndex => self.toggle_table_index(),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::CopyCell => self.copy_table_cell(),
+                        Message::CopyRow => self.copy_table_row(),
+                        Message::Help => self.show_help(),
+                        Message::EnterCommand => self.enter_cmd_mode(CMDMode::Raw),
+                        Message::Search => self.enter_cmd_mode(CMDMode::SearchTable),
+                        Message::Filter => self.enter_cmd_mode(CMDMode::FilterByColumn),
+                        Message::SearchInColumn => self.enter_cmd_mode(CMDMode::SearchInColumn),
+                        Message::Enter => self.enter(),
+                        Message::Exit => self.exit(),
+                        Message::Histogram => {
+                            self.previous_modus = self.modus;
+                            self.modus = Modus::HISTOGRAM;
+                            self.update_histogram()
+                        }
+                        Message::SearchNext => self.search_next(1),
+                        Message::SearchPrev => self.search_next(-1),
+                        Message::SortAscending => self.sort_current_column(true),
+                        Message::SortDescending => self.sort_current_column(false),
+                        Message::CycleNumberBase => self.cycle_number_base(),
+                        Message::ShowBitfield => self.show_bitfield(),
+                        Message::Percentiles => self.show_percentiles(),
+                        Message::ToggleUuidCompaction => self.toggle_uuid_compaction(),
+                        Message::ToggleFillDown => self.toggle_fill_down(),
+                        Message::ToggleTopValues => self.toggle_top_values(),
+                        Message::ToggleTotals => self.toggle_totals(),
+                        Message::FilterColumns => self.enter_column_filter(),
+                        Message::RenameColumn => self.enter_column_rename(),
+                        Message::ToggleCrosshair => self.toggle_crosshair(),
+                        Message::ToggleProfile => self.toggle_profile(),
+                        Message::OpenInPager => self.open_table_cell(ExternalProgram::Pager),
+                        Message::OpenInEditor => self.open_table_cell(ExternalProgram::Editor),
+                        Message::ToggleBookmark => self.toggle_bookmark(),
+                        Message::ToggleMark => self.toggle_mark(),
+                        Message::VisualSelect => self.visual_select(),
+                        Message::Reload => self.reload_view(),
+                        Message::EditCell => self.edit_cell(),
+                        Message::NextBookmark => self.jump_to_bookmark(true),
+                        Message::PreviousBookmark => self.jump_to_bookmark(false),
+                        Message::NextNull => self.jump_to_null(true),
+                        Message::PreviousNull => self.jump_to_null(false),
+                        Message::OpenUrl => {
+                            let table = self.tables.last().unwrap();
+                            let ridx = table.rows[table.offset_row + table.curser_row];
+                            self.open_url(table.offset_column + table.curser_column, ridx);
+                        }
+                        Message::Chart => self.chart(),
+                        Message::Plot => self.plot(),
+                        Message::NextTab => self.switch_tab(1),
+                        Message::PreviousTab => self.switch_tab(-1),
+                        Message::MoveToFirstColumn => {
+                            self.select_cell(
+                                self.tables.last().unwrap().curser_row
+                                    + self.tables.last().unwrap().offset_row,
+                                0,
+                            );
+                        }
+                        Message::MoveToLastColumn => {
+                            let table = self.tables.last().unwrap();
+                            self.select_cell(
+                                table.curser_row + table.offset_row,
+                                self.data.len() - 1,
+                            );
+                        }
+                        Message::MoveColumnLeft => {
+                            let table = self.tables.last().unwrap();
+                            let column = table.offset_column + table.curser_column;
+                            self.move_column(column.saturating_sub(1));
+                        }
+                        Message::MoveColumnRight => {
+                            let table = self.tables.last().unwrap();
+                            self.move_column(table.offset_column + table.curser_column + 1);
+                        }
+                        _ => (),
+                    },
+                    Modus::RECORD => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveDown(step) => self.move_record_selection_down(step),
+                        Message::MoveLeft => self.previous_record(),
+                        Message::MoveRight => self.next_record(),
+                        Message::MoveUp(step) => self.move_record_selection_up(step),
+                        Message::MovePageUp => self.move_record_selection_up(10),
+                        Message::MovePageDown => self.move_record_selection_down(10),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::CopyCell => self.copy_record_cell(),
+                        Message::OpenInPager => self.open_record_cell(ExternalProgram::Pager),
+                        Message::OpenInEditor => self.open_record_cell(ExternalProgram::Editor),
+                        Message::OpenUrl => {
+                            let record = &self.record_view;
+                            let ridx = self.tables.last().unwrap().rows[record.last_record_idx];
+                            self.open_url(record.curser_offset + record.curser_row, ridx);
+                        }
+                        Message::Help => self.show_help(),
+                        Message::Enter => self.enter(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+                    Modus::HISTOGRAM => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveDown(step) => self.move_histogram_selection_down(step),
+                        Message::MoveUp(step) => self.move_histogram_selection_up(step),
+                        Message::MovePageUp => self.move_histogram_selection_up(10),
+                        Message::MovePageDown => self.move_histogram_selection_down(10),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::Help => self.show_help(),
+                        Message::Enter => self.enter(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+                    Modus::CHART => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveLeft => self.cycle_chart_aggregation(-1),
+                        Message::MoveRight => self.cycle_chart_aggregation(1),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::Help => self.show_help(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+                    Modus::PLOT => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveUp(_) => self.zoom_plot(true),
+                        Message::MoveDown(_) => self.zoom_plot(false),
+                        Message::MoveLeft => self.pan_plot(-1),
+                        Message::MoveRight => self.pan_plot(1),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::Help => self.show_help(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+                    Modus::JSON => match msg {
+                        Message::Quit => self.quit(),
+                        Message::MoveDown(step) => self.move_json_selection(step as isize),
+                        Message::MoveUp(step) => self.move_json_selection(-(step as isize)),
+                        Message::MovePageDown => self.move_json_selection(10),
+                        Message::MovePageUp => self.move_json_selection(-10),
+                        Message::MoveLeft => self.fold_json(Some(true)),
+                        Message::MoveRight => self.fold_json(Some(false)),
+                        Message::Enter => self.fold_json(None),
+                        Message::CopyCell => self.copy_json(),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::Help => self.show_help(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+
+                    Modus::POPUP => match msg {
+                        Message::Quit => self.quit(),
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::Enter => self.enter(),
+                        Message::Exit => self.exit(),
+                        _ => (),
+                    },
+                    Modus::CMDINPUT => match msg {
+                        Message::Resize(width, height) => self.ui_resize(width, height),
+                        Message::RawKey(key) => self.raw_input(key),
+                        Message::Paste(text) => self.paste_input(&text),
+                        _ => (),
+                    },
+                }
+            }
+        }
+
+        // The curser is moved by the table views directly, keep the footer in sync
+        if !self.tables.is_empty() && !self.data.is_empty() {
+            self.update_column_stats();
+            self.update_totals();
+            self.update_cell_line();
+        }
+        self.last_update = Instant::now();
+        Ok(())
+    }
+
+    // -------------------- Control handling functions ---------------------- //
+
+    fn enter(&mut self) {
+        match self.modus {
+            Modus::TABLE => {
+                let table = self.tables.last_mut().unwrap();
+                table.show_index = false;
+                let table = self.tables.last().unwrap();
+                // Disabling the index will change the ui layout. Recalculate it
+                self.uilayout =
+                    UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+                //self.build_record_view(record_idx);
+                self.record_view = RecordView::new(
+                    table,
+                    &self.data,
+                    &mut self.uidata,
+                    self.config.max_column_width,
+                );
+                self.modus = Modus::RECORD;
+                self.previous_modus = Modus::TABLE;
+            }
+            Modus::RECORD => self.show_json(),
+            Modus::HISTOGRAM => {
+                let hist = self.histogram_views.last().unwrap();
+                let table = self.tables.last().unwrap();
+                let term = hist.value_data[hist.curser_offset + hist.curser_row].to_string();
+                let matches = self
+                    .filter_matches(hist.column_idx, &term, &table.rows)
+                    .map(|(_, matches)| matches)
+                    .unwrap_or_default();
+                let filter = (self.data[hist.column_idx].name.clone(), term);
+                self.filter_table(matches, Some(filter));
+                self.modus = Modus::TABLE;
+                self.previous_modus = Modus::HISTOGRAM;
+            }
+            Modus::POPUP => {
+                if self.confirming_quit {
+                    self.exit();
+                    if self.write_file() {
+                        self.quit();
+                    }
+                } else if self.popup_pops_filter {
+                    // Close the popup, then leave the empty filtered table
+                    self.exit();
+                    self.exit();
+                } else if !self.pending_substitution.is_empty() {
+                    self.apply_substitution();
+                    self.exit();
+                } else if !self.pending_each.is_empty() {
+                    self.run_each();
+                    self.exit();
+                }
+            }
+            Modus::CMDINPUT => {}
+            Modus::CHART => {}
+            Modus::PLOT => {}
+            Modus::JSON => {}
+        }
+    }
+
+    fn exit(&mut self) {
+        match self.modus {
+            Modus::TABLE => {
+                // Nothing todo, there is no exit from table, only quit
+                if self.tables.last().unwrap().visual_anchor.is_some() {
+                    self.tables.last_mut().unwrap().visual_anchor = None;
+                    self.update_table_data();
+                } else if self.tables.len() > 1 {
+                    self.tables.pop();
+                    self.histogram_views.pop();
+                    // E.g. grouped rows show their sections in the index column
+                    self.uilayout =
+                        UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+                    self.update_table_data();
+                } else {
+                    self.pop_derived_frame();
+                }
+            }
+            Modus::RECORD => {
+                // Switch back to table mode
+                self.narrow_record_view = false;
+                self.previous_modus = Modus::RECORD;
+                self.modus = Modus::TABLE;
+                self.update_table_data();
+            }
+            Modus::POPUP => {
+                trace!("Close popup ...");
+                self.modus = self.previous_modus;
+                self.previous_modus = Modus::POPUP;
+                self.popup_pops_filter = false;
+                self.confirming_quit = false;
+                self.pending_substitution.clear();
+                self.pending_each.clear();
+                self.uidata.show_popup = false;
+                self.uidata.last_update = Instant::now();
+            }
+            Modus::CMDINPUT => {}
+            Modus::HISTOGRAM => {
+                // Switch back to table mode
+                self.previous_modus = Modus::HISTOGRAM;
+                self.modus = Modus::TABLE;
+                self.update_table_data();
+            }
+            Modus::CHART => {
+                self.previous_modus = Modus::CHART;
+                self.modus = Modus::TABLE;
+                self.uidata.chart = None;
+                self.update_table_data();
+            }
+            Modus::PLOT => {
+                self.previous_modus = Modus::PLOT;
+                self.modus = Modus::TABLE;
+                self.plot_view = None;
+                self.uidata.plot = None;
+                self.update_table_data();
+            }
+            Modus::JSON => {
+                // Return to the record view the json was opened from
+                self.previous_modus = Modus::JSON;
+                self.modus = Modus::RECORD;
+                self.json_view = None;
+                self.uidata.json = None;
+                self.uidata.last_update = Instant::now();
+            }
+        }
+    }
+
+    fn show_help(&mut self) {
+        self.show_popup("Key Bindings", HELP_TEXT);
+    }
+
+    fn show_popup(&mut self, title: &str, message: &str) {
+        self.previous_modus = self.modus;
+        self.modus = Modus::POPUP;
+        self.uidata.popup_title = title.to_string();
+        self.uidata.popup_message = message.to_string();
+        self.uidata.show_popup = true;
+        self.uidata.last_update = Instant::now();
+    }
+
+    // Explain an empty search or filter result and suggest values close to the term
+    fn show_empty_result(
+        &mut self,
+        term: &str,
+        column_idx: usize,
+        mask: &[usize],
+        is_filter: bool,
+    ) {
+        let column = &self.data[column_idx];
+        let suggestions = column.suggestions(term, mask, 5);
+
+        let mut message = format!("\n  No rows in \"{}\" match \"{}\".\n", column.name, term);
+        if suggestions.is_empty() {
+            message.push_str("\n  No similar values found.\n");
+        } else {
+            message.push_str("\n  Did you mean:\n");
+            for s in suggestions.iter() {
+                message.push_str(&format!("    {s}\n"));
+            }
+        }
+        if is_filter {
+            message.push_str("\n  ENTER : Remove the filter\n  ESC   : Keep the empty table\n");
+        } else {
+            message.push_str("\n  ESC   : Close\n");
+        }
+
+        let title = if is_filter {
+            "Empty Filter"
+        } else {
+            "Empty Search"
+        };
+        self.show_popup(title, &message);
+        self.popup_pops_filter = is_filter;
+    }
+
+    fn raw_input(&mut self, key: KeyEvent) {
+        if self.active_cmdinput
+            && key.code == KeyCode::Tab
+            && matches!(self.cmd_mode, Some(CMDMode::Raw))
+        {
+            return self.complete_path();
+        }
+        if self.active_cmdinput {
+            self.completion = None;
+            self.last_input = self.input.read(key);
+            if self.last_input.finished {
+                self.filter_preview_due = None;
+                self.uidata.cmd_preview.clear();
+                self.handle_cmd_input();
+            } else if let Some(CMDMode::FilterByColumn) = self.cmd_mode {
+                // Debounce counting the matches while typing
+                let delay =
+                    std::time::Duration::from_millis(self.config.filter_preview_delay as u64);
+                self.filter_preview_due = Some(Instant::now() + delay);
+            }
+            self.uidata.cmdinput = self.last_input.clone();
+            self.uidata.cmd_mode = self.cmd_mode;
+            self.uidata.last_update = Instant::now();
+        }
+    }
+
+    // Complete the file of :open and :split, repeated Tabs cycle through the candidates
+    fn complete_path(&mut self) {
+        let input = self.last_input.input.clone();
+        let Some((cmd, typed)) = input.trim_start().split_once(' ') else {
+            return;
+        };
+        if !matches!(cmd, "open" | "split" | "vsplit") {
+            return;
+        }
+        let (candidates, idx) = match self.completion.take() {
+            Some((candidates, idx)) if candidates.len() > 1 && candidates[idx] == typed => {
+                let next = (idx + 1) % candidates.len();
+                (candidates, next)
+            }
+            _ => (path_completion::candidates(typed), 0),
+        };
+        if candidates.is_empty() {
+            self.uidata.cmd_preview = "no matching files".to_string();
+            self.uidata.last_update = Instant::now();
+            return;
+        }
+
+        self.input.clear();
+        self.last_input = self.input.insert(&format!("{cmd} {}", candidates[idx]));
+        self.uidata.cmdinput = self.last_input.clone();
+        self.uidata.cmd_preview = match candidates.len() {
+            1 => String::new(),
+            n => format!("{}/{n}, Tab for the next", idx + 1),
+        };
+        self.uidata.last_update = Instant::now();
+        self.completion = Some((candidates, idx));
+    }
+
+    fn paste_input(&mut self, text: &str) {
+        if self.active_cmdinput {
+            self.last_input = self.input.insert(text);
+            self.uidata.cmdinput = self.last_input.clone();
+            self.uidata.last_update = Instant::now();
+        }
+    }
+
+    fn enter_cmd_mode(&mut self, mode: CMDMode) {
+        trace!("Entering command mode ...");
+        self.previous_modus = self.modus;
+        self.modus = Modus::CMDINPUT;
+        self.cmd_mode = Some(mode);
+
+        self.active_cmdinput = true;
+        self.input.clear();
+        self.uidata.cmd_preview.clear();
+        self.last_input = self.input.get();
+
+        self.last_input = self.input.get();
+        self.uidata.cmdinput = self.last_input.clone();
+        self.uidata.active_cmdinput = self.active_cmdinput;
+        self.uidata.last_update = Instant::now();
+        self.uidata.cmd_mode = self.cmd_mode;
+    }
+
+    fn handle_cmd_input(&mut self) {
+        // TODO: process self.last_input
+        trace!("Handle cmd input {}", self.last_input.input);
+
+        self.active_cmdinput = false;
+        self.modus = self.previous_modus;
+        self.previous_modus = Modus::CMDINPUT;
+
+        self.uidata.active_cmdinput = self.active_cmdinput;
+        self.last_update = Instant::now();
+
+        let cmd_input = self.last_input.input.clone();
+        match self.cmd_mode {
+            Some(CMDMode::SearchTable) => {
+                self.search(&cmd_input, false);
+            }
+            Some(CMDMode::FilterByColumn) => {
+                self.filter(&cmd_input);
+            }
+            Some(CMDMode::SearchInColumn) => {
+                self.search(&cmd_input, true);
+            }
+            Some(CMDMode::Raw) => {
+                info!("Raw cmd mode {cmd_input}");
+                self.run_command(&cmd_input);
+            }
+            Some(CMDMode::EditCell) => {
+                if !self.last_input.canceled {
+                    self.write_cell(&cmd_input);
+                }
+            }
+            None => {
+                info!("Cmd mode is none!")
+            }
+        }
+
+        self.cmd_mode = None;
+    }
+
+    fn run_command(&mut self, input: &str) {
+        // Substitutions can contain spaces, `s/foo/bar/g` for the current column, `%s/..` for all
+        if let Some(expr) = input.trim().strip_prefix("%s") {
+            return self.substitute(expr, true);
+        }
+        if let Some(expr) = input.trim().strip_prefix('s')
+            && expr.starts_with(|c: char| !c.is_alphanumeric() && !c.is_whitespace())
+        {
+            return self.substitute(expr, false);
+        }
+
+        let (cmd, args) = input.trim().split_once(' ').unwrap_or((input.trim(), ""));
+        match cmd {
+            "" => {}
+            "melt" => self.melt(args),
+            "groupby" => self.group_by(args),
+            "dups" => self.duplicates(args, false),
+            "fdups" => self.duplicates(args, true),
+            "diffcols" => self.diff_columns(args, false),
+            "fdiffcols" => self.diff_columns(args, true),
+            "nohl" => self.clear_highlights(),
+            "relnum" => self.toggle_relative_numbers(),
+            "min" => self.jump_to_extreme(false),
+            "max" => self.jump_to_extreme(true),
+            "join" => self.join(args),
+            "view" => self.view(args),
+            "marks" => self.show_bookmarks(),
+            "export-marks" => self.export_bookmarks(args),
+            "sel" => self.selection_action(args),
+            "each" => self.each(args.trim()),
+            "eachlog" => self.show_each_log(),
+            "export" => self.export_view(args),
+            "append" => self.append_row(),
+            "delete" => self.delete_rows(),
+            "open" => self.request_open(args),
+            "split" | "vsplit" => self.request_split(args),
+            "only" => self.workspace_request = Some(WorkspaceRequest::Only),
+            "scrollbind" | "scrolllock" => {
+                let columns = args.trim() == "columns";
+                self.workspace_request = Some(WorkspaceRequest::ScrollLock(columns));
+            }
+            "bins" => self.set_histogram_bins(args),
+            "mvcol" => match args.trim().parse::<usize>() {
+                Ok(position) if position > 0 => self.move_column(position - 1),
+                _ => self.show_warning("Usage: mvcol <position>"),
+            },
+            "messages" => self.show_messages(),
+            "malformed" => self.show_malformed_lines(),
+            "header" => self.toggle_header(),
+            "reload" => self.reload(args.trim()),
+            "watch" => self.toggle_watch(args.trim()),
+            "cast" => self.cast_column(args),
+            "unparsed" => self.filter_unparsed(),
+            "group" => self.group_rows(args.trim()),
+            "cols" => self.filter_columns(args.trim()),
+            "sample" => self.sample_rows(args, false),
+            "shuffle" => self.sample_rows(args, true),
+            "rename" => self.rename_column(args.trim()),
+            // Like vim, writing to another file leaves the loaded one untouched
+            "w" | "write" if !args.trim().is_empty() => self.export_view(args),
+            "w" | "write" => {
+                self.write_file();
+            }
+            "q" => self.quit(),
+            "q!" => self.status = Status::QUITTING,
+            "wq" => {
+                if self.write_file() {
+                    self.quit();
+                }
+            }
+            _ => self.show_warning(format!("Unknown command: {cmd}")),
+        }
+    }
+
+    // Unpivot the given comma separated columns of the current view into variable/value columns.
+    // Without arguments, all columns except the selected one are unpivoted.
+    fn melt(&mut self, args: &str) {
+        let table = self.tables.last().unwrap();
+        let selected = &self.data[table.offset_column + table.curser_column].name;
+        let requested: Vec<&str> = args
+            .split(',')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .collect();
+
+        if let Some(unknown) = requested
+            .iter()
+            .find(|&&r| !self.data.iter().any(|c| c.name == r))
+        {
+            self.show_warning(format!("Unknown column: {unknown}"));
+            return;
+        }
+
+        let (on, index): (Vec<&Column>, Vec<&Column>) = if requested.is_empty() {
+            self.data.iter().partition(|c| &c.name != selected)
+        } else {
+            self.data
+                .iter()
+                .partition(|c| requested.contains(&c.name.as_str()))
+        };
+        if on.is_empty() {
+            self.set_status_message("Nothing to melt!");
+            return;
+        }
+
+        // Unpivoting requires a common dtype, fall back to strings for mixed columns
+        let common_dtype = on.iter().all(|c| c.dtype == on[0].dtype);
+        let on: Vec<String> = on.iter().map(|c| c.name.clone()).collect();
+        let index: Vec<String> = index.iter().map(|c| c.name.clone()).collect();
+        let name = format!("M[{}]", table.name);
+
+        let melted = self.current_view_frame().and_then(|mut frame| {
+            if !common_dtype {
+                for c in on.iter() {
+                    let casted = frame.column(c)?.cast(&DataType::String)?;
+                    frame.with_column(casted)?;
+                }
+            }
+            frame.unpivot(
+                on.iter().map(|s| s.as_str()),
+                index.iter().map(|s| s.as_str()),
+            )
+        });
+
+        match melted {
+            Ok(frame) => {
+                let nrows = frame.height();
+                match self.push_derived_frame(frame, name) {
+                    Ok(_) => self.set_status_message(format!("Melted into {nrows} rows")),
+                    Err(e) => self.show_error(format!("Melt failed! {e:?}")),
+                }
+            }
+            Err(e) => self.show_error(format!("Melt failed! {e}")),
+        }
+    }
+
+    // Aggregate the current view per group of the key columns: `[c1,c2,..] [sum(c3), mean(c4), count()]`.
+    // Without key columns the selected column is used, without aggregations the rows are counted.
+    fn group_by(&mut self, args: &str) {
+        let table = self.tables.last().unwrap();
+        let args = args.trim();
+        // The first word holds the key columns, unless it is already an aggregation
+        let (keys, spec) = match args.split_once(char::is_whitespace) {
+            Some((keys, spec)) if !keys.contains('(') => (keys, spec),
+            None if !args.contains('(') => (args, ""),
+            _ => ("", args),
+        };
+        let mut keys: Vec<String> = keys
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if keys.is_empty() {
+            keys.push(
+                self.data[table.offset_column + table.curser_column]
+                    .name
+                    .clone(),
+            );
+        }
+        let spec = if spec.trim().is_empty() {
+            "count()"
+        } else {
+            spec
+        };
+        let aggregations = match aggregate::parse(spec) {
+            Ok(aggregations) => aggregations,
+            Err(e) => {
+                self.show_warning(e);
+                return;
+            }
+        };
+        let columns = keys
+            .iter()
+            .chain(aggregations.iter().map(|a| &a.column))
+            .filter(|c| !c.is_empty());
+        if let Some(unknown) = columns
+            .into_iter()
+            .find(|&c| !self.data.iter().any(|column| column.name == *c))
+        {
+            self.show_warning(format!("Unknown column: {unknown}"));
+            return;
+        }
+
+        let name = format!("G[{}]", table.name);
+        let grouped = self.current_view_frame().and_then(|frame| {
+            frame
+                .lazy()
+                .group_by(keys.iter().map(|k| col(k.as_str())).collect::<Vec<_>>())
+                .agg(aggregations.iter().map(|a| a.expr()).collect::<Vec<_>>())
+                .sort(
+                    keys.iter().map(|k| k.as_str()).collect::<Vec<_>>(),
+                    SortMultipleOptions::default().with_nulls_last(true),
+                )
+                .collect()
+        });
+        match grouped {
+            Ok(frame) => {
+                let ngroups = frame.height();
+                match self.push_derived_frame(frame, name) {
+                    Ok(_) => self.set_status_message(format!("Aggregated into {ngroups} groups")),
+                    Err(e) => self.show_error(format!("Group by failed! {e:?}")),
+                }
+            }
+            Err(e) => self.show_error(format!("Group by failed! {e}")),
+        }
+    }
+
+    // Join another file into the current view: `<path> on <col>[,<col>..] [left|inner]`
+    fn join(&mut self, args: &str) {
+        let Some((path, spec)) = args.rsplit_once(" on ") else {
+            self.show_warning("Usage: join <file> on <col>[,<col>..] [left|inner]");
+            return;
+        };
+        let mut spec = spec.split_whitespace();
+        let keys: Vec<String> = spec
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+        let how = match spec.next() {
+            None | Some("left") => JoinType::Left,
+            Some("inner") => JoinType::Inner,
+            Some(other) => {
+                self.show_warning(format!("Unknown join type: {other}"));
+                return;
+            }
+        };
+        if keys.is_empty() {
+            self.set_status_message("No join columns given!");
+            return;
+        }
+
+        let path = PathBuf::from(
+            shellexpand::full(path.trim())
+                .map(|p| p.to_string())
+                .unwrap_or(path.trim().to_string()),
+        );
+        let other = match Model::get_file_info(path).and_then(|fi| {
+            // Workbooks are joined with their first sheet
+            let (_, frame) = Model::read_data_file(&fi)?.remove(0);
+            Ok((fi, frame))
+        }) {
+            Ok(other) => other,
+            Err(e) => {
+                self.show_error(format!("Loading join file failed! {e:?}"));
+                return;
+            }
+        };
+        let (file_info, other_frame) = other;
+        let prefix = file_info
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("other")
+            .to_string();
+        let name = format!("J[{}]", self.tables.last().unwrap().name);
+
+        match self.join_frame(other_frame, &keys, how, &prefix) {
+            Ok(frame) => {
+                let nrows = frame.height();
+                match self.push_derived_frame(frame, name) {
+                    Ok(_) => self.set_status_message(format!("Joined {prefix}, {nrows} rows")),
+                    Err(e) => self.show_error(format!("Join failed! {e:?}")),
+                }
+            }
+            Err(e) => self.show_error(format!("Join failed! {e}")),
+        }
+    }
+
+    // Join the other frame on the key columns into the current view.
+    // All non key columns of the other frame are prefixed with `<prefix>.` to avoid name clashes.
+    fn join_frame(
+        &self,
+        mut other: DataFrame,
+        keys: &[String],
+        how: JoinType,
+        prefix: &str,
+    ) -> Result<DataFrame, PolarsError> {
+        let current = self.current_view_frame()?;
+
+        let other_columns: Vec<String> = other
+            .get_column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        for c in other_columns.iter() {
+            if keys.contains(c) {
+                // Keys need matching types on both sides
+                let dtype = current.column(c)?.dtype().clone();
+                let casted = other.column(c)?.cast(&dtype)?;
+                other.with_column(casted)?;
+            } else {
+                other.rename(c, format!("{prefix}.{c}").into())?;
+            }
+        }
+
+        let mut join_args = JoinArgs::new(how);
+        join_args.maintain_order = MaintainOrderJoin::Left;
+        current.join(&other, keys, keys, join_args, None)
+    }
+
+    // Resolve a comma separated list of column names into column indices
+    fn parse_column_list(&self, args: &str) -> Result<Vec<usize>, String> {
+        args.split(',')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                self.data
+                    .iter()
+                    .position(|col| col.name == c)
+                    .ok_or(format!("Unknown column: {c}"))
+            })
+            .collect()
+    }
+
+    // Highlight or filter rows that are duplicated in the given comma separated columns (all columns if empty).
+    fn duplicates(&mut self, args: &str, filter: bool) {
+        let mut key_columns = match self.parse_column_list(args) {
+            Ok(columns) => columns,
+            Err(e) => {
+                self.show_warning(e);
+                return;
+            }
+        };
+        if key_columns.is_empty() {
+            key_columns = (0..self.data.len()).collect();
+        }
+
+        let table = self.tables.last_mut().unwrap();
+        let (matches, ngroups) = table.duplicate_rows(&self.data, &key_columns);
+        let nrows = matches.len();
+
+        if filter {
+            if !matches.is_empty() {
+                self.filter_table(matches, None);
+            }
+        } else {
+            table.highlighted_rows = matches.iter().map(|&midx| table.rows[midx]).collect();
+            self.update_table_data();
+        }
+        self.set_status_message(format!("Found {ngroups} duplicate groups in {nrows} rows"));
+    }
+
+    // Highlight the cells of two columns where they differ, e.g. `expected,actual`, or filter to these rows.
+    // A single column is compared to the selected one.
+    fn diff_columns(&mut self, args: &str, filter: bool) {
+        let table = self.tables.last().unwrap();
+        let selected = table.offset_column + table.curser_column;
+        let (a, b) = match self.parse_column_list(args).as_deref() {
+            Ok(&[a, b]) => (a, b),
+            Ok(&[other]) => (selected, other),
+            Ok(_) => {
+                self.show_warning("Usage: diffcols <column>[,<column>]");
+                return;
+            }
+            Err(e) => {
+                self.show_warning(e.clone());
+                return;
+            }
+        };
+        if a == b {
+            self.show_warning("Compare the column to another one!");
+            return;
+        }
+
+        // Numbers are compared by value, so 1 equals 1.0
+        let (left, right) = (&self.data[a], &self.data[b]);
+        let numeric = Model::is_numeric_type(&left.dtype) && Model::is_numeric_type(&right.dtype);
+        let differs = |ridx: usize| {
+            let (x, y) = (left.value(ridx), right.value(ridx));
+            match numeric {
+                true => x.parse::<f64>().ok() != y.parse::<f64>().ok(),
+                false => x != y,
+            }
+        };
+        let matches: Vec<usize> = (0..table.rows.len())
+            .filter(|&midx| differs(table.rows[midx]))
+            .collect();
+        let (left, right) = (left.name.clone(), right.name.clone());
+        let rows: HashSet<usize> = matches.iter().map(|&midx| table.rows[midx]).collect();
+        for column in self.data.iter_mut() {
+            column.differs.clear();
+        }
+        self.data[a].differs = rows.clone();
+        self.data[b].differs = rows.clone();
+
+        let ndiffs = matches.len();
+        if filter && !matches.is_empty() {
+            self.filter_table(matches, None);
+        } else {
+            self.tables.last_mut().unwrap().highlighted_rows = rows;
+            self.update_table_data();
+        }
+        self.set_status_message(match ndiffs {
+            0 => format!("{left} and {right} are equal in all rows"),
+            n => format!("{left} and {right} differ in {n} rows, :nohl clears the highlights"),
+        });
+    }
+
+    fn toggle_bookmark(&mut self) {
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[table.offset_row + table.curser_row];
+        let mut bookmarks = (*table.bookmarks).clone();
+        if !bookmarks.remove(&ridx) {
+            bookmarks.insert(ridx);
+        }
+        self.set_status_message(format!("{} bookmarked rows", bookmarks.len()));
+
+        // Bookmarks are kept on all tables, so they survive filtering and sorting
+        let bookmarks = Arc::new(bookmarks);
+        for table in self.tables.iter_mut() {
+            table.bookmarks = Arc::clone(&bookmarks);
+        }
+        self.update_table_data();
+    }
+
+    // Compute the cells changed by a substitution and ask for confirmation before applying it
+    fn substitute(&mut self, expr: &str, all_columns: bool) {
+        let substitution = match Substitution::parse(expr) {
+            Ok(substitution) => substitution,
+            Err(e) => {
+                self.show_warning(e);
+                return;
+            }
+        };
+        let table = self.tables.last().unwrap();
+        let columns: Vec<usize> = if all_columns {
+            (0..self.data.len()).collect()
+        } else {
+            vec![table.offset_column + table.curser_column]
+        };
+
+        let mut changes = Vec::new();
+        for column_idx in columns {
+            let values = match self.frame.get_columns()[column_idx].cast(&DataType::String) {
+                Ok(values) => values,
+                Err(e) => {
+                    self.show_error(format!("Substitution failed! {e}"));
+                    return;
+                }
+            };
+            let Ok(values) = values.str() else {
+                continue;
+            };
+            let column_changes: Vec<(usize, String)> = table
+                .rows
+                .iter()
+                .filter_map(|&ridx| {
+                    let new = substitution.apply(values.get(ridx)?)?;
+                    Some((ridx, new))
+                })
+                .collect();
+            if !column_changes.is_empty() {
+                changes.push((column_idx, column_changes));
+            }
+        }
+
+        let ncells: usize = changes.iter().map(|(_, c)| c.len()).sum();
+        if ncells == 0 {
+            self.set_status_message("Pattern not found!");
+            return;
+        }
+        let ncolumns = changes.len();
+        self.pending_substitution = changes;
+        self.show_popup(
+            "Substitute",
+            &format!(
+                "\n  Replace {} cells in {ncolumns} columns?\n\n  Enter to apply, ESC to cancel.\n",
+                format_count(ncells)
+            ),
+        );
+    }
+
+    // Write the confirmed substitution into the dataframe and the loaded columns
+    fn apply_substitution(&mut self) {
+        let changes = std::mem::take(&mut self.pending_substitution);
+        match self.write_values(&changes) {
+            Ok(ncells) => {
+                self.set_status_message(format!("Replaced {} cells", format_count(ncells)))
+            }
+            Err(e) => self.show_error(format!("Substitution failed! {e}")),
+        }
+        self.update_table_data();
+    }
+
+    // Write new values per column into the dataframe and the loaded columns, returning the number of changed cells
+    fn write_values(
+        &mut self,
+        changes: &[(usize, Vec<(usize, String)>)],
+    ) -> Result<usize, PolarsError> {
+        let frame = Arc::make_mut(&mut self.frame);
+        let mut ncells = 0;
+        for (column_idx, values) in changes.iter() {
+            let column = &mut self.data[*column_idx];
+            let result = frame.get_columns()[*column_idx]
+                .cast(&DataType::String)
+                .and_then(|c| {
+                    let mut new_values: Vec<Option<String>> = c
+                        .str()?
+                        .into_iter()
+                        .map(|v| v.map(str::to_string))
+                        .collect();
+                    for (ridx, value) in values.iter() {
+                        new_values[*ridx] = Some(value.clone());
+                    }
+                    let series = Series::new(column.name.as_str().into(), new_values);
+                    // Keep the original type if all new values can be converted to it
+                    let typed = series.cast(&column.dtype)?;
+                    if typed.null_count() == series.null_count() {
+                        Ok(typed)
+                    } else {
+                        column.dtype = DataType::String;
+                        Ok(series)
+                    }
+                })
+                .and_then(|series| frame.with_column(series).map(|_| ()));
+            result?;
+            column.set_series(
+                frame.get_columns()[*column_idx]
+                    .as_materialized_series()
+                    .clone(),
+            );
+            // Edited cells show their new value
+            for (ridx, _) in values.iter() {
+                column.unparsed.remove(ridx);
+            }
+            ncells += values.len();
+            self.dirty = true;
+            self.uidata.dirty = true;
+            self.column_stats_key = None;
+            self.totals = None;
+        }
+        Ok(ncells)
+    }
+
+    // Re-parse a column as another dtype, e.g. numbers that were loaded as text
+    fn cast_column(&mut self, args: &str) {
+        let cast = match cast::Cast::parse(args) {
+            Ok(cast) => cast,
+            Err(e) => {
+                self.show_warning(e);
+                return;
+            }
+        };
+        let column_idx = match cast.column.as_deref() {
+            Some(name) => match self.data.iter().position(|c| c.name == name) {
+                Some(column_idx) => column_idx,
+                None => {
+                    self.show_warning(format!("Unknown column: {name}"));
+                    return;
+                }
+            },
+            None => {
+                let table = self.tables.last().unwrap();
+                table.offset_column + table.curser_column
+            }
+        };
+
+        let before = self.frame.get_columns()[column_idx].clone();
+        let result = cast
+            .apply(&before, self.config.decimal_comma)
+            .and_then(|series| {
+                let unparsed = cast::unparsed(&before, &series)?;
+                let frame = Arc::make_mut(&mut self.frame);
+                frame.with_column(series)?;
+                let mut column =
+                    Model::load_columns(frame, column_idx, &self.data[column_idx].name, None)?;
+                column.unparsed = unparsed;
+                Ok(column)
+            });
+        let mut column = match result {
+            Ok(column) => column,
+            Err(e) => {
+                self.show_error(format!("Casting failed! {e}"));
+                return;
+            }
+        };
+        column.set_renderer(self.renderers.find(&column));
+        column.status = std::mem::replace(&mut self.data[column_idx].status, ColumnStatus::NORMAL);
+        column.folded = self.data[column_idx].folded;
+        column.fill_down = self.data[column_idx].fill_down;
+        column.hidden = self.data[column_idx].hidden;
+        let message = format!("Cast {} to {}", column.name, column.dtype);
+        let nunparsed = column.unparsed.len();
+        self.data[column_idx] = column;
+        self.column_stats_key = None;
+        self.totals = None;
+        self.update_table_data();
+        match nunparsed {
+            0 => self.set_status_message(message),
+            n => self.show_warning(format!(
+                "{message}, {n} values could not be parsed, :unparsed shows their rows"
+            )),
+        }
+    }
+
+    // Filter to the rows with cells a cast could not parse, in the current column if it has any
+    fn filter_unparsed(&mut self) {
+        let table = self.tables.last().unwrap();
+        let current = &self.data[table.offset_column + table.curser_column];
+        let columns: Vec<&Column> = if current.unparsed.is_empty() {
+            self.data.iter().collect()
+        } else {
+            vec![current]
+        };
+        let matches: Vec<usize> = table
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, ridx)| columns.iter().any(|c| c.unparsed.contains_key(ridx)))
+            .map(|(midx, _)| midx)
+            .collect();
+        if matches.is_empty() {
+            self.set_status_message("No cells failed to parse!");
+            return;
+        }
+        let nrows = matches.len();
+        self.filter_table(matches, None);
+        self.set_status_message(format!(
+            "{nrows} rows with unparsed cells, Esc shows all rows"
+        ));
+    }
+
+    // Only show the columns whose name matches the pattern, ignoring case. All columns without a pattern.
+    fn filter_columns(&mut self, pattern: &str) {
+        if pattern.is_empty() {
+            self.data.iter_mut().for_each(|c| c.hidden = false);
+            self.set_status_message("Showing all columns");
+            self.update_table_data();
+            return;
+        }
+        let regex = match Regex::new(&format!("(?i){pattern}")) {
+            Ok(regex) => regex,
+            Err(e) => {
+                self.show_warning(format!("Invalid pattern: {e}"));
+                return;
+            }
+        };
+        let nmatches = self.data.iter().filter(|c| regex.is_match(&c.name)).count();
+        if nmatches == 0 {
+            self.show_warning(format!("No column name matches {pattern}!"));
+            return;
+        }
+        for column in self.data.iter_mut() {
+            column.hidden = !regex.is_match(&column.name);
+        }
+        self.set_status_message(format!(
+            "Showing {nmatches} of {} columns, :cols shows all",
+            self.data.len()
+        ));
+        self.update_table_data();
+    }
+
+    // Show `n` random rows of the view in their order, or all rows shuffled. Esc shows the rows as before.
+    // The seed is shown, passing it again draws the same rows.
+    fn sample_rows(&mut self, args: &str, shuffle: bool) {
+        let mut args = args.split_whitespace();
+        let n = match shuffle {
+            true => None,
+            false => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Some(n),
+                _ => {
+                    self.show_warning("Usage: sample <rows> [seed]");
+                    return;
+                }
+            },
+        };
+        let seed = match args.next().map(|seed| seed.parse::<u64>()) {
+            None => sampling::random_seed(),
+            Some(Ok(seed)) => seed,
+            Some(Err(_)) => {
+                self.show_warning("The seed has to be a number!");
+                return;
+            }
+        };
+        let table = self.tables.last().unwrap();
+        let nrows = table.rows.len();
+        let name = table.name.clone();
+        let (positions, message, prefix) = match n {
+            Some(n) => (
+                sampling::sample(nrows, n, seed),
+                format!(
+                    "{} of {nrows} rows sampled (seed {seed}), Esc shows all rows",
+                    n.min(nrows)
+                ),
+                "S",
+            ),
+            None => (
+                sampling::shuffle(nrows, seed),
+                format!("{nrows} rows shuffled (seed {seed}), Esc shows them in order"),
+                "R",
+            ),
+        };
+        self.filter_table(positions, None);
+        self.tables.last_mut().unwrap().name = format!("{prefix}[{name}]");
+        self.set_status_message(message);
+    }
+
+    // Open the command line to filter the columns by name
+    fn enter_column_filter(&mut self) {
+        self.enter_cmd_mode(CMDMode::Raw);
+        self.last_input = self.input.insert("cols ");
+        self.uidata.cmdinput = self.last_input.clone();
+    }
+
+    // Open the command line to edit the name of the current column
+    fn enter_column_rename(&mut self) {
+        let table = self.tables.last().unwrap();
+        let command = format!(
+            "rename {}",
+            self.data[table.offset_column + table.curser_column].name
+        );
+        self.enter_cmd_mode(CMDMode::Raw);
+        self.last_input = self.input.insert(&command);
+        self.uidata.cmdinput = self.last_input.clone();
+    }
+
+    // Give the current column a new name. Files without header row remember it for the next time they are opened.
+    fn rename_column(&mut self, name: &str) {
+        if name.is_empty() {
+            self.show_warning("Usage: rename <name>");
+            return;
+        }
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let old = self.data[column_idx].name.clone();
+        if name == old {
+            return;
+        }
+        if self.data.iter().any(|c| c.name == name) {
+            self.show_warning(format!("There is a column {name} already!"));
+            return;
+        }
+        if let Err(e) = Arc::make_mut(&mut self.frame).rename(&old, name.into()) {
+            self.show_error(format!("Renaming failed! {e}"));
+            return;
+        }
+        self.data[column_idx].name = name.to_string();
+        self.original_names.remove(&old);
+        self.update_table_data();
+
+        // Names of derived views, e.g. of :melt, are not the ones of the file
+        let headerless = self.file_info.as_ref().filter(|f| !f.has_header);
+        if let Some(file_info) = headerless
+            && self.tabs[self.active_tab].parent_datasets.is_empty()
+            && let Err(e) = column_names::rename(&file_info.path, &old, name)
+        {
+            self.show_warning(format!("Remembering the column name failed! {e:?}"));
+            return;
+        }
+        self.set_status_message(format!("Renamed {old} to {name}"));
+    }
+
+    // Show the rows of the view in sections of equal values of a column, Esc shows them ungrouped
+    fn group_rows(&mut self, name: &str) {
+        let table = self.tables.last().unwrap();
+        let column_idx = match name {
+            "" => table.offset_column + table.curser_column,
+            name => match self.data.iter().position(|c| c.name == name) {
+                Some(column_idx) => column_idx,
+                None => {
+                    self.show_warning(format!("Unknown column: {name}"));
+                    return;
+                }
+            },
+        };
+        let groups = RowGroups::new(&self.data[column_idx], &table.rows);
+        let message = format!(
+            "{} groups of {}, - folds a group, + all of them, Esc ungroups",
+            groups.len(),
+            self.data[column_idx].name
+        );
+        let mut new_table = TableView::empty();
+        new_table.name = format!("G[{}]", table.name);
+        new_table.bookmarks = Arc::clone(&table.bookmarks);
+        new_table.filter = table.filter.clone();
+        new_table.rows = Arc::new(groups.rows());
+        new_table.groups = Some(groups);
+        new_table.show_index = true;
+        new_table.offset_column = table.offset_column;
+        new_table.curser_column = table.curser_column;
+        self.tables.push(new_table);
+        self.histogram_views.push(self.new_histogram_view());
+
+        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+        self.update_table_data();
+        self.set_status_message(message);
+    }
+
+    // Fold or unfold the section of the selected row, or all sections
+    fn toggle_row_group(&mut self, all: bool) {
+        let table = self.tables.last_mut().unwrap();
+        let Some(groups) = table.groups.as_mut() else {
+            self.show_warning("Group the rows with :group [column] first!");
+            return;
+        };
+        let message = if all {
+            table.offset_row = 0;
+            table.curser_row = 0;
+            match groups.toggle_all() {
+                true => "Folded all groups".to_string(),
+                false => "Unfolded all groups".to_string(),
+            }
+        } else {
+            let Some((key, folded, row)) = groups.toggle(table.offset_row + table.curser_row)
+            else {
+                return;
+            };
+            // Keep the curser on the first row of the section
+            if row < table.offset_row {
+                table.offset_row = row;
+            }
+            table.curser_row = row - table.offset_row;
+            match folded {
+                true => format!("Folded group {key}"),
+                false => format!("Unfolded group {key}"),
+            }
+        };
+        table.rows = Arc::new(groups.rows());
+        self.update_table_data();
+        self.set_status_message(message);
+    }
+
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.uidata.dirty = true;
+    }
+
+    // Edit the selected cell in the command line, prefilled with its current value
+    fn edit_cell(&mut self) {
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[table.offset_row + table.curser_row];
+        let value = self.raw_value(table.offset_column + table.curser_column, ridx);
+        self.enter_cmd_mode(CMDMode::EditCell);
+        self.last_input = self.input.insert(&value);
+        self.uidata.cmdinput = self.last_input.clone();
+    }
+
+    fn write_cell(&mut self, value: &str) {
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[table.offset_row + table.curser_row];
+        let column_idx = table.offset_column + table.curser_column;
+        match self.write_values(&[(column_idx, vec![(ridx, value.to_string())])]) {
+            Ok(_) => self.set_status_message("Changed cell"),
+            Err(e) => self.show_error(format!("Changing cell failed! {e}")),
+        }
+        self.update_table_data();
+    }
+
+    // Insert an empty row after the curser, it is added at the end of the dataframe
+    fn append_row(&mut self) {
+        let frame = Arc::make_mut(&mut self.frame);
+        let ridx = frame.height();
+        let empty_row = frame
+            .get_columns()
+            .iter()
+            .map(|c| Series::full_null(c.name().clone(), 1, c.dtype()).into())
+            .collect::<Vec<_>>();
+        if let Err(e) = DataFrame::new(empty_row).and_then(|row| frame.vstack_mut(&row).map(|_| ()))
+        {
+            self.show_error(format!("Adding row failed! {e}"));
+            return;
+        }
+        for (column, series) in self.data.iter_mut().zip(frame.get_columns()) {
+            column.set_series(series.as_materialized_series().clone());
+        }
+
+        let table = self.tables.last().unwrap();
+        let current = table.rows[table.offset_row + table.curser_row];
+        for table in self.tables.iter_mut() {
+            let mut rows = table.rows.to_vec();
+            match rows.iter().position(|&r| r == current) {
+                Some(pos) => rows.insert(pos + 1, ridx),
+                None => rows.push(ridx),
+            }
+            table.rows = Arc::new(rows);
+        }
+        self.mark_dirty();
+        self.move_table_selection_down(1);
+        self.update_table_data();
+        self.set_status_message("Added empty row, edit its cells with i");
+    }
+
+    // Delete the selected rows, or the row under the curser, from all views of the data
+    fn delete_rows(&mut self) {
+        let table = self.tables.last().unwrap();
+        let deleted: BTreeSet<usize> = if table.marked_rows.is_empty() {
+            BTreeSet::from([table.rows[table.offset_row + table.curser_row]])
+        } else {
+            table.marked_rows.iter().copied().collect()
+        };
+        let position = table.offset_row + table.curser_row;
+
+        for table in self.tables.iter_mut() {
+            let rows = table
+                .rows
+                .iter()
+                .filter(|r| !deleted.contains(r))
+                .copied()
+                .collect::<Vec<usize>>();
+            table.rows = Arc::new(rows);
+            table.marked_rows.retain(|r| !deleted.contains(r));
+            table.highlighted_rows.retain(|r| !deleted.contains(r));
+            table.bookmarks = Arc::new(
+                table
+                    .bookmarks
+                    .iter()
+                    .filter(|r| !deleted.contains(r))
+                    .copied()
+                    .collect(),
+            );
+        }
+        self.mark_dirty();
+
+        let table = self.tables.last_mut().unwrap();
+        if !table.rows.is_empty() {
+            let row = position.min(table.rows.len() - 1);
+            let column = table.offset_column + table.curser_column;
+            table.select_cell(
+                row,
+                column,
+                &mut self.data,
+                &self.uilayout,
+                &mut self.uidata,
+            );
+        }
+        self.update_table_data();
+        self.set_status_message(format!("Deleted {} rows", deleted.len()));
+    }
+
+    // Write the rows of the current view to a csv or parquet file
+    fn export_view(&mut self, args: &str) {
+        let (original_names, path) = match args.trim().strip_prefix("--original-names") {
+            Some(path) => (true, path),
+            None => (false, args),
+        };
+        let rows = self.tables.last().unwrap().rows.to_vec();
+        match self.export_rows(path.trim(), &rows, original_names) {
+            Ok(n) => {
+                // A report can not be loaded again, the edits are not saved by it
+                if !path.trim().ends_with(".html") && !path.trim().ends_with(".htm") {
+                    self.dirty = false;
+                    self.uidata.dirty = false;
+                }
+                self.set_status_message(format!("Exported {n} rows to {}", path.trim()));
+            }
+            Err(e) => self.show_error(format!("Export failed! {e:?}")),
+        }
+    }
+
+    // Add or remove the selected row from the row selection and move on to the next row
+    fn toggle_mark(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        let ridx = table.rows[table.offset_row + table.curser_row];
+        if !table.marked_rows.remove(&ridx) {
+            table.marked_rows.insert(ridx);
+        }
+        let nmarked = table.marked_rows.len();
+        self.move_table_selection_down(1);
+        self.update_table_data();
+        self.set_status_message(format!("{nmarked} rows selected"));
+    }
+
+    // Start a visual selection at the current row, or add the visually selected rows to the selection
+    fn visual_select(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        if table.visual_anchor.is_some() {
+            table.end_visual();
+            let nmarked = table.marked_rows.len();
+            self.set_status_message(format!("{nmarked} rows selected"));
+        } else {
+            table.visual_anchor = Some(table.offset_row + table.curser_row);
+            self.set_status_message("Visual selection, y to copy, V to select, Esc to cancel");
+        }
+        self.update_table_data();
+    }
+
+    // Copy rows with headers, quoted for pasting into a spreadsheet
+    fn copy_rows(&mut self, rows: &[usize], delimiter: char) {
+        let content = TableView::get_rows(&self.data, rows, delimiter);
+        match self.set_clipboard(content) {
+            Ok(_) => self.set_status_message(format!("Copied {} rows!", rows.len())),
+            Err(e) => self.show_error(format!("Copying to clipboard failed! {e}")),
+        }
+    }
+
+    // Bulk actions on the selected rows, `:sel copy [csv]|export <file>|delete|stats|clear`
+    fn selection_action(&mut self, args: &str) {
+        let (action, args) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+        self.tables.last_mut().unwrap().end_visual();
+        let table = self.tables.last().unwrap();
+        // Keep the order of the current table
+        let rows: Vec<usize> = table
+            .rows
+            .iter()
+            .filter(|ridx| table.marked_rows.contains(ridx))
+            .copied()
+            .collect();
+        if rows.is_empty() && action != "clear" {
+            self.set_status_message("No rows selected, select rows with space!");
+            return;
+        }
+
+        match action {
+            "copy" if args.trim() == "csv" => self.copy_rows(&rows, ','),
+            "copy" => self.copy_rows(&rows, '\t'),
+            "export" => match self.export_rows(args.trim(), &rows, false) {
+                Ok(n) => self.set_status_message(format!("Exported {n} rows to {}", args.trim())),
+                Err(e) => self.show_error(format!("Export failed! {e:?}")),
+            },
+            "delete" => {
+                // Removing rows from the view is a filter on all other rows
+                let remaining = (0..table.rows.len())
+                    .filter(|&midx| !table.marked_rows.contains(&table.rows[midx]))
+                    .collect();
+                self.filter_table(remaining, None);
+                self.set_status_message(format!("Removed {} rows from the view", rows.len()));
+            }
+            "stats" => self.show_selection_stats(&rows),
+            "clear" => {
+                self.tables.last_mut().unwrap().marked_rows.clear();
+                self.update_table_data();
+            }
+            _ => self.show_warning(format!("Unknown selection action: {action}")),
+        }
+    }
+
+    // Commands received on the control socket act on the table view, closing popups, charts etc.
+    // Run a command of the control socket, it failed if it showed a warning or error
+    fn remote(&mut self, command: RemoteCommand) -> Result<(), String> {
+        debug!("Remote command {command:?}");
+        let nmessages = self.messages.len();
+        if matches!(self.modus, Modus::CMDINPUT) {
+            self.active_cmdinput = false;
+            self.uidata.active_cmdinput = false;
+            self.modus = self.previous_modus;
+        }
+        // E.g. json of a record, over the record view, over the table
+        for _ in 0..3 {
+            if !matches!(self.modus, Modus::TABLE) {
+                self.exit();
+            }
+        }
+        let column_idx = |model: &Model, name: Option<String>| match name {
+            Some(name) => model.data.iter().position(|c| c.name == name).ok_or(name),
+            None => {
+                let table = model.tables.last().unwrap();
+                Ok(table.offset_column + table.curser_column)
+            }
+        };
+        match command {
+            RemoteCommand::Goto { row, column } => match column_idx(self, column) {
+                Ok(column_idx) => {
+                    let nrows = self.tables.last().unwrap().rows.len();
+                    self.select_cell(row.clamp(1, nrows.max(1)) - 1, column_idx);
+                }
+                Err(name) => self.show_warning(format!("Unknown column: {name}")),
+            },
+            RemoteCommand::Filter { column, term } => match column_idx(self, column) {
+                Ok(column_idx) => self.filter_column(column_idx, &term),
+                Err(name) => self.show_warning(format!("Unknown column: {name}")),
+            },
+            RemoteCommand::Load { file } => {
+                self.workspace_request = Some(WorkspaceRequest::Open(file));
+            }
+            RemoteCommand::Command { input } => {
+                self.run_command(input.strip_prefix(':').unwrap_or(&input))
+            }
+        }
+        match self.messages.get(nmessages) {
+            Some((_, _, error)) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    // Fill the values of the selected rows, or the current one, into the command and ask to run it
+    fn each(&mut self, template: &str) {
+        if template.is_empty() {
+            self.show_warning("Usage: each <command with {column}>");
+            return;
+        }
+        if self.each_run.as_ref().is_some_and(|run| !run.is_done()) {
+            self.show_warning("The commands of the last :each are still running!");
+            return;
+        }
+        self.tables.last_mut().unwrap().end_visual();
+        let table = self.tables.last().unwrap();
+        let mut rows: Vec<usize> = table
+            .rows
+            .iter()
+            .filter(|ridx| table.marked_rows.contains(ridx))
+            .copied()
+            .collect();
+        if rows.is_empty() && !table.rows.is_empty() {
+            rows.push(table.rows[table.offset_row + table.curser_row]);
+        }
+        let selected = table.offset_column + table.curser_column;
+        let commands: Result<Vec<String>, String> = rows
+            .iter()
+            .map(|&ridx| {
+                each::expand(template, |name| {
+                    let column_idx = match name {
+                        "" => Some(selected),
+                        name => self.data.iter().position(|c| c.name == name),
+                    };
+                    column_idx.map(|cidx| self.raw_value(cidx, ridx))
+                })
+            })
+            .collect();
+        let commands = match commands {
+            Ok(commands) if !commands.is_empty() => commands,
+            Ok(_) => return self.set_status_message("No rows to run the command for!"),
+            Err(e) => return self.show_warning(e),
+        };
+
+        let mut message = format!("\n  Run {} commands?\n\n", format_count(commands.len()));
+        for command in commands.iter().take(5) {
+            message.push_str(&format!("  $ {command}\n"));
+        }
+        if commands.len() > 5 {
+            message.push_str("  ...\n");
+        }
+        message.push_str("\n  Enter to run, ESC to cancel.\n");
+        self.pending_each = commands;
+        self.show_popup("Each", &message);
+    }
+
+    fn run_each(&mut self) {
+        let commands = std::mem::take(&mut self.pending_each);
+        self.set_status_message(format!("Running {} commands ...", commands.len()));
+        self.each_run = Some(EachRun::start(commands, self.events.clone()));
+    }
+
+    // Show the progress of the commands of :each
+    fn poll_each(&mut self) {
+        let Some(run) = self.each_run.as_mut() else {
+            return;
+        };
+        if run.is_done() || !run.poll() {
+            return;
+        }
+        let (done, total, failed) = (run.done, run.total, run.failed);
+        if !run.is_done() {
+            self.set_status_message(format!("Ran {done}/{total} commands, {failed} failed"));
+        } else if failed > 0 {
+            self.show_warning(format!(
+                "{failed} of {total} commands failed, :eachlog shows their output"
+            ));
+        } else {
+            self.set_status_message(format!("Ran {total} commands, :eachlog shows their output"));
+        }
+    }
+
+    fn show_each_log(&mut self) {
+        match self.each_run.as_ref() {
+            Some(run) if !run.log.is_empty() => {
+                self.external_request = Some((ExternalProgram::Pager, run.log.clone()))
+            }
+            _ => self.set_status_message("No output of :each yet"),
+        }
+    }
+
+    // Popup with count, sum, mean, min and max of the numeric columns over the given rows
+    fn show_selection_stats(&mut self, rows: &[usize]) {
+        let mut message = format!("\n  {} rows selected\n\n", rows.len());
+        message.push_str(&format!(
+            "  {:<20} {:>12} {:>12} {:>12} {:>12}\n",
+            "column", "sum", "mean", "min", "max"
+        ));
+        for column in self
+            .data
+            .iter()
+            .filter(|c| Model::is_numeric_type(&c.dtype))
+        {
+            let values: Vec<f64> = rows
+                .iter()
+                .filter_map(|&ridx| column.value(ridx).parse::<f64>().ok())
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+            let sum: f64 = values.iter().sum();
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let name: String = column.name.chars().take(20).collect();
+            message.push_str(&format!(
+                "  {name:<20} {sum:>12.2} {:>12.2} {min:>12.2} {max:>12.2}\n",
+                sum / values.len() as f64
+            ));
+        }
+        self.show_popup("Selection", &message);
+    }
+
+    // Move the curser to the next or previous bookmarked row of the current table
+    fn jump_to_bookmark(&mut self, forward: bool) {
+        let table = self.tables.last_mut().unwrap();
+        let current = table.offset_row + table.curser_row;
+        let positions: Vec<usize> = table
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, ridx)| table.bookmarks.contains(ridx))
+            .map(|(midx, _)| midx)
+            .collect();
+        let next = if forward {
+            positions
+                .iter()
+                .find(|&&p| p > current)
+                .or(positions.first())
+        } else {
+            positions
+                .iter()
+                .rev()
+                .find(|&&p| p < current)
+                .or(positions.last())
+        };
+        let Some(&row) = next else {
+            self.set_status_message("No bookmarked rows in this table!");
+            return;
+        };
+        let column = table.offset_column + table.curser_column;
+        table.select_cell(
+            row,
+            column,
+            &mut self.data,
+            &self.uilayout,
+            &mut self.uidata,
+        );
+        let idx = positions.iter().position(|&p| p == row).unwrap_or(0);
+        self.set_status_message(format!("Bookmark {}/{}", idx + 1, positions.len()));
+    }
+
+    // Move the curser to the next or previous row of the current table, where the current column is null
+    fn jump_to_null(&mut self, forward: bool) {
+        let table = self.tables.last_mut().unwrap();
+        let current = table.offset_row + table.curser_row;
+        let column = table.offset_column + table.curser_column;
+        let nulls = self.frame.get_columns()[column].is_null();
+        let positions: Vec<usize> = table
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, ridx)| nulls.get(**ridx) == Some(true))
+            .map(|(midx, _)| midx)
+            .collect();
+        let next = if forward {
+            positions
+                .iter()
+                .find(|&&p| p > current)
+                .or(positions.first())
+        } else {
+            positions
+                .iter()
+                .rev()
+                .find(|&&p| p < current)
+                .or(positions.last())
+        };
+        let name = &self.data[column].name;
+        let Some(&row) = next else {
+            let message = format!("No nulls in {name}!");
+            self.set_status_message(message);
+            return;
+        };
+        let idx = positions.iter().position(|&p| p == row).unwrap_or(0);
+        let message = format!("Null {}/{} in {name}", idx + 1, positions.len());
+        table.select_cell(
+            row,
+            column,
+            &mut self.data,
+            &self.uilayout,
+            &mut self.uidata,
+        );
+        self.set_status_message(message);
+    }
+
+    fn show_bookmarks(&mut self) {
+        let table = self.tables.last().unwrap();
+        if table.bookmarks.is_empty() {
+            self.set_status_message("No bookmarked rows!");
+            return;
+        }
+        let mut message = String::from("\n");
+        for &ridx in table.bookmarks.iter() {
+            let preview = self
+                .data
+                .iter()
+                .take(3)
+                .map(|c| c.value(ridx).to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            message.push_str(&format!("  row {ridx:>6}: {preview}\n"));
+        }
+        message.push_str("\n  Jump between bookmarks with ' and `\n");
+        self.show_popup("Bookmarks", &message);
+    }
+
+    fn export_bookmarks(&mut self, path: &str) {
+        let rows: Vec<usize> = self
+            .tables
+            .last()
+            .unwrap()
+            .bookmarks
+            .iter()
+            .copied()
+            .collect();
+        if rows.is_empty() {
+            self.set_status_message("No bookmarked rows!");
+            return;
+        }
+        match self.export_rows(path.trim(), &rows, false) {
+            Ok(n) => self.set_status_message(format!("Exported {n} rows to {}", path.trim())),
+            Err(e) => self.show_error(format!("Export failed! {e:?}")),
+        }
+    }
+
+    // Write the given data rows to a csv, parquet, json, xlsx or html file, depending on the file extension
+    // Csv files get the names of the loaded file, with repeated names, if `original_names` is set.
+    fn export_rows(
+        &self,
+        path: &str,
+        rows: &[usize],
+        original_names: bool,
+    ) -> Result<usize, TVError> {
+        if path.is_empty() {
+            return Err(TVError::LoadingFailed("Missing file name!".into()));
+        }
+        let path = PathBuf::from(shellexpand::tilde(path).to_string());
+        let idx = IdxCa::from_vec("idx".into(), rows.iter().map(|&r| r as IdxSize).collect());
+        let mut frame = self.frame.take(&idx)?;
+        let mut file = fs::File::create(&path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("parquet") => {
+                ParquetWriter::new(&mut file).finish(&mut frame)?;
+            }
+            Some("html" | "htm") => {
+                let title = &self.tables.last().unwrap().name;
+                file.write_all(html::document(title, &self.data, rows).as_bytes())?;
+            }
+            Some("xlsx") => {
+                let sheet_name = &self.tables.last().unwrap().name;
+                xlsx::write_workbook(&mut file, &frame, sheet_name)?;
+            }
+            Some("json") => json_export::write(&mut file, &frame, false)?,
+            Some("ndjson" | "jsonl") => json_export::write(&mut file, &frame, true)?,
+            _ if original_names && !self.original_names.is_empty() => {
+                let header: Vec<String> = frame
+                    .get_column_names()
+                    .iter()
+                    .map(|name| {
+                        self.original_names
+                            .get(name.as_str())
+                            .map_or(name.to_string(), |original| original.clone())
+                    })
+                    .collect();
+                let mut line = String::new();
+                lenient_csv::write_record(&mut line, &header);
+                file.write_all(line.as_bytes())?;
+                CsvWriter::new(&mut file)
+                    .include_header(false)
+                    .finish(&mut frame)?;
+            }
+            _ => CsvWriter::new(&mut file).finish(&mut frame)?,
+        }
+        Ok(frame.height())
+    }
+
+    // Save, load, delete or list named views, e.g. `:view save staging-errors`
+    fn view(&mut self, args: &str) {
+        let (action, name) = args
+            .trim()
+            .split_once(' ')
+            .map(|(action, name)| (action, name.trim()))
+            .unwrap_or((args.trim(), ""));
+        let mut saved_views = match views::load_views() {
+            Ok(saved_views) => saved_views,
+            Err(e) => {
+                self.show_error(format!("Loading views failed! {e:?}"));
+                return;
+            }
+        };
+
+        match (action, name) {
+            ("list" | "", _) => {
+                let mut message = String::from("\n");
+                if saved_views.is_empty() {
+                    message.push_str("  No saved views.\n");
+                }
+                for (name, view) in saved_views.iter() {
+                    message.push_str(&format!(
+                        "  {name}: {} filters{}\n",
+                        view.filters.len(),
+                        view.sort
+                            .as_ref()
+                            .map(|(column, _)| format!(", sorted by {column}"))
+                            .unwrap_or_default()
+                    ));
+                }
+                self.show_popup("Saved Views", &message);
+            }
+            (_, "") => self.set_status_message("Missing view name!"),
+            ("save", name) => {
+                let view = self.current_view();
+                // Filters like duplicates can not be re-applied by column and term
+                let skipped = self.tables.len() - 1 - view.filters.len();
+                saved_views.insert(name.to_string(), view);
+                match views::store_views(&saved_views) {
+                    Ok(_) if skipped > 0 => self.set_status_message(format!(
+                        "Saved view {name}, skipped {skipped} filters that can not be saved"
+                    )),
+                    Ok(_) => self.set_status_message(format!("Saved view {name}")),
+                    Err(e) => self.show_error(format!("Saving view failed! {e:?}")),
+                }
+            }
+            ("load", name) => match saved_views.get(name) {
+                Some(view) => self.apply_view(name, &view.clone()),
+                None => self.show_warning(format!("Unknown view: {name}")),
+            },
+            ("delete", name) => {
+                if saved_views.remove(name).is_none() {
+                    self.show_warning(format!("Unknown view: {name}"));
+                    return;
+                }
+                match views::store_views(&saved_views) {
+                    Ok(_) => self.set_status_message(format!("Deleted view {name}")),
+                    Err(e) => self.show_error(format!("Deleting view failed! {e:?}")),
+                }
+            }
+            _ => self.show_warning(format!("Unknown view action: {action}")),
+        }
+    }
+
+    fn current_view(&self) -> SavedView {
+        let names_with_status = |status| {
+            self.data
+                .iter()
+                .filter(|c| c.status == status)
+                .map(|c| c.name.clone())
+                .collect::<Vec<String>>()
+        };
+        SavedView {
+            filters: self
+                .tables
+                .iter()
+                .filter_map(|t| t.filter.clone())
+                .collect(),
+            sort: self.tables.iter().rev().find_map(|t| t.sort.clone()),
+            collapsed: names_with_status(ColumnStatus::COLLAPSED),
+            expanded: names_with_status(ColumnStatus::EXPANDED),
+        }
+    }
+
+    // Re-apply a saved view on the unfiltered table, skipping columns that do not exist
+    fn apply_view(&mut self, name: &str, view: &SavedView) {
+        self.tables.truncate(1);
+        self.histogram_views.truncate(1);
+
+        let mut missing = Vec::new();
+        for (column, term) in view.filters.iter() {
+            match self.data.iter().position(|c| &c.name == column) {
+                Some(idx) => self.filter_column(idx, term),
+                None => missing.push(column.clone()),
+            }
+        }
+        if let Some((column, ascending)) = view.sort.as_ref() {
+            match self.data.iter().position(|c| &c.name == column) {
+                Some(idx) => self.sort_column(idx, *ascending),
+                None => missing.push(column.clone()),
+            }
+        }
+        for column in self.data.iter_mut() {
+            column.status = if view.collapsed.contains(&column.name) {
+                ColumnStatus::COLLAPSED
+            } else if view.expanded.contains(&column.name) {
+                ColumnStatus::EXPANDED
             } else {
-                match self.modus {
-                    Modus::TABLE => match msg {
-                        Message::Quit => self.quit(),
-                        Message::MoveDown => self.move_table_selection_down(1),
-                        Message::MoveLeft => self.move_table_selection_left(),
-                        Message::MoveRight => self.move_table_selection_right(),
-                        Message::MoveUp => self.move_table_selection_up(1),
-                        Message::MovePageUp => {
-                            self.move_table_selection_up(self.uilayout.table_height + 1)
-                        }
-                        Message::MovePageDown => {
-                            self.move_table_selection_down(self.uilayout.table_height + 1)
-                        }
-                        Message::MoveBeginning => self.move_table_selection_beginning(),
-                        Message::MoveEnd => self.move_table_selection_end(),
-                        Message::ToggleColumnState => self.toggle_column_status(false),
-                        Message::ToggleExpandColumnState => self.toggle_column_status(true),
-                        Message::ToggleIndex => self.toggle_table_index(),
-                        Message::Resize(width, height) => self.ui_resize(width, height),
-                        Message::CopyCell => self.copy_table_cell(),
-                        Message::CopyRow => self.copy_table_row(),
-                        Message::Help => self.show_help(),
-                        Message::EnterCommand => self.enter_cmd_mode(CMDMode::Raw),
-                        Message::Search => self.enter_cmd_mode(CMDMode::SearchTable),
-                        Message::Filter => self.enter_cmd_mode(CMDMode::FilterByColumn),
-                        Message::SearchInColumn => self.enter_cmd_mode(CMDMode::SearchInColumn),
-                        Message::Enter => self.enter(),
-                        Message::Exit => self.exit(),
-                        Message::Histogram => {
-                            self.previous_modus = self.modus;
-                            self.modus = Modus::HISTOGRAM;
-                            self.update_histogram()
-                        }
-                        Message::SearchNext => self.search_next(1),
-                        Message::SearchPrev => self.search_next(-1),
-                        Message::SortAscending => self.sort_current_column(true),
-                        Message::SortDescending => self.sort_current_column(false),
-                        Message::MoveToFirstColumn => {
-                            self.select_cell(
-                                self.tables.last().unwrap().curser_row
-                                    + self.tables.last().unwrap().offset_row,
-                                0,
-                            );
-                        }
-                        Message::MoveToLastColumn => {
-                            let table = self.tables.last().unwrap();
-                            self.select_cell(
-                                table.curser_row + table.offset_row,
-                                self.data.len() - 1,
-                            );
+                ColumnStatus::NORMAL
+            };
+        }
+        self.update_table_data();
+
+        if missing.is_empty() {
+            self.set_status_message(format!("Applied view {name}"));
+        } else {
+            self.set_status_message(format!(
+                "Applied view {name}, missing columns: {}",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    // Move the curser to the row with the smallest or largest value of the numeric column,
+    // keeping the order of the rows. Ties go to the first of the rows.
+    fn jump_to_extreme(&mut self, largest: bool) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let column = &self.data[column_idx];
+        let Some(numbers) = column.numbers() else {
+            self.show_warning(format!("{} is not numeric!", column.name));
+            return;
+        };
+        let extreme = table
+            .rows
+            .iter()
+            .enumerate()
+            .filter_map(|(row, &ridx)| numbers.get(ridx).filter(|v| !v.is_nan()).map(|v| (row, v)))
+            .reduce(|best, next| match largest {
+                true if next.1 > best.1 => next,
+                false if next.1 < best.1 => next,
+                _ => best,
+            });
+        let name = column.name.clone();
+        let Some((row, _)) = extreme else {
+            self.set_status_message(format!("No values in {name}"));
+            return;
+        };
+        let value = column.display_value(table.rows[row]);
+        self.select_cell(row, column_idx);
+        let kind = if largest { "Maximum" } else { "Minimum" };
+        self.set_status_message(format!("{kind} {value} of {name} in row {}", row + 1));
+    }
+
+    fn clear_highlights(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.highlighted_rows.clear();
+        for column in self.data.iter_mut() {
+            column.differs.clear();
+        }
+        self.update_table_data();
+    }
+
+    fn search(&mut self, term: &str, current_column_only: bool) {
+        trace!("Starting search for {} ...", term);
+        let table = self.tables.last_mut().unwrap();
+        if table.rows.len() > self.config.background_search_rows {
+            let column_idxs: Vec<usize> = if current_column_only {
+                vec![table.offset_column + table.curser_column]
+            } else {
+                (0..self.data.len()).collect()
+            };
+            let columns = column_idxs
+                .into_iter()
+                .map(|idx| (idx, self.data[idx].shared_values()))
+                .collect();
+            table.search_results.clear();
+            table.search_idx = 0;
+            let mut search = BackgroundSearch::start(
+                term,
+                columns,
+                Arc::clone(&table.rows),
+                self.events.clone(),
+            );
+            search.depth = self.tables.len();
+            self.background_search = Some(search);
+            self.set_status_message(format!("Searching for {term} ..."));
+            return;
+        }
+        let num_matches = table.search(
+            term,
+            current_column_only,
+            &mut self.data,
+            &self.uilayout,
+            &mut self.uidata,
+        );
+        if num_matches == 0 {
+            self.set_status_message("Found no matches!".to_string());
+            let table = self.tables.last().unwrap();
+            let column_idx = table.offset_column + table.curser_column;
+            let mask = Arc::clone(&table.rows);
+            self.show_empty_result(term, column_idx, &mask, false);
+        } else {
+            self.set_status_message(format!("Found {} results", num_matches));
+        }
+    }
+
+    // Show the matches found so far by the search running in the background, and its progress
+    fn poll_background_search(&mut self) {
+        let Some(search) = self.background_search.as_mut() else {
+            return;
+        };
+        // The table searched was closed or another one opened
+        if search.depth != self.tables.len() {
+            self.background_search = None;
+            return;
+        }
+        let matches = search.poll();
+        let mask = Arc::clone(&search.mask);
+        let is_filter = search.filter.is_some();
+        let table = self.tables.last_mut().unwrap();
+        if !matches.is_empty() && is_filter {
+            Arc::make_mut(&mut table.rows).extend(matches.iter().map(|&(midx, _)| mask[midx]));
+            self.histogram_views.last_mut().unwrap().clear();
+            self.column_stats_key = None;
+            self.totals = None;
+            self.update_table_data();
+        } else if !matches.is_empty() {
+            // Jump to the first match after the curser, or the first one found
+            let first_matches = table.search_results.is_empty();
+            table.search_results.extend(matches);
+            if first_matches {
+                let curser_ridx = table.offset_row + table.curser_row;
+                table.search_idx = table
+                    .search_results
+                    .iter()
+                    .position(|&(row, _)| row >= curser_ridx)
+                    .unwrap_or(0);
+                table.search_next(0, &mut self.data, &self.uilayout, &mut self.uidata);
+            }
+        }
+
+        let search = self.background_search.as_ref().unwrap();
+        if !search.is_done() {
+            let verb = if is_filter { "Filtering" } else { "Searching" };
+            self.set_status_message(format!(
+                "{verb} for {}: {} found, {}",
+                search.term,
+                format_count(search.found),
+                search.progress()
+            ));
+            return;
+        }
+        let search = self.background_search.take().unwrap();
+        let table = self.tables.last().unwrap();
+        let column_idx = match &search.filter {
+            Some((name, _)) => self.data.iter().position(|c| &c.name == name),
+            None => Some(table.offset_column + table.curser_column),
+        };
+        if search.found > 0 {
+            let what = if is_filter {
+                "matching rows"
+            } else {
+                "results"
+            };
+            self.set_status_message(format!("Found {} {what}", format_count(search.found)));
+        } else if let Some(column_idx) = column_idx {
+            let message = if is_filter {
+                "Empty table!"
+            } else {
+                "Found no matches!"
+            };
+            self.set_status_message(message);
+            self.show_empty_result(&search.term, column_idx, &mask, is_filter);
+        }
+    }
+
+    // Sets the curser to the next search result
+    fn search_next(&mut self, step: i32) {
+        let table = self.tables.last_mut().unwrap();
+        if table.search_results.is_empty() {
+            self.set_status_message("Empty search results!".to_string());
+        } else {
+            let next_match_idx =
+                table.search_next(step, &mut self.data, &self.uilayout, &mut self.uidata);
+
+            let table = self.tables.last().unwrap();
+            self.set_status_message(format!(
+                "Search result {}/{}",
+                next_match_idx + 1,
+                table.search_results.len()
+            ));
+        }
+    }
+
+    fn sort_current_column(&mut self, ascending: bool) {
+        let table = self.tables.last().unwrap();
+        self.sort_column(table.curser_column + table.offset_column, ascending);
+    }
+
+    fn sort_column(&mut self, column_idx: usize, ascending: bool) {
+        let table = self.tables.last_mut().unwrap();
+        // Rows of folded sections are sorted as well
+        if let Some(groups) = &table.groups {
+            table.rows = Arc::new(groups.all_rows());
+        }
+        let data = self.data[column_idx].values();
+        let is_numeric = Model::is_numeric_type(&self.data[column_idx].dtype);
+
+        // Create a vector of (original_index, value) pairs
+        let mut indexed_rows: Vec<(usize, &Arc<str>)> = table
+            .rows
+            .iter()
+            .map(|&row_idx| (row_idx, &data[row_idx]))
+            .collect();
+
+        //indexed_rows.sort_unstable_by_key(|(idx, &data)| data);
+
+        // Sort by the data values
+        if ip::is_ip_column(&self.data[column_idx]) {
+            // Ip addresses are sorted numerically, values that are no addresses come last
+            indexed_rows.sort_by(|(_, a), (_, b)| match (ip::parse_ip(a), ip::parse_ip(b)) {
+                (Some(a_ip), Some(b_ip)) => {
+                    if ascending {
+                        a_ip.cmp(&b_ip)
+                    } else {
+                        b_ip.cmp(&a_ip)
+                    }
+                }
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => a.cmp(b),
+            });
+        } else if is_numeric {
+            // If the column originally was a numeric, try to convert each value to a float representation and compare it.
+            // LLM generated matches will order partial float conversion, giving order preference to successful converted floats
+            // Falling back to string sorting if nothing can be converted
+            indexed_rows.sort_by(|(_, a), (_, b)| {
+                let a_val: Result<f64, _> = a.parse();
+                let b_val: Result<f64, _> = b.parse();
+
+                match (a_val, b_val) {
+                    (Ok(a_float), Ok(b_float)) => {
+                        if ascending {
+                            a_float
+                                .partial_cmp(&b_float)
+                                .unwrap_or(std::cmp::Ordering::Equal)
+                        } else {
+                            b_float
+                                .partial_cmp(&a_float)
+                                .unwrap_or(std::cmp::Ordering::Equal)
                         }
-                        _ => (),
-                    },
-                    Modus::RECORD => match msg {
-                        Message::Quit => self.quit(),
-                        Message::MoveDown => self.move_record_selection_down(1),
-                        Message::MoveLeft => self.previous_record(),
-                        Message::MoveRight => self.next_record(),
-                        Message::MoveUp => self.move_record_selection_up(1),
-                        Message::MovePageUp => self.move_record_selection_up(10),
-                        Message::MovePageDown => self.move_record_selection_down(10),
-                        Message::Resize(width, height) => self.ui_resize(width, height),
-                        Message::CopyCell => self.copy_record_cell(),
-                        Message::Help => self.show_help(),
-                        Message::Enter => self.enter(),
-                        Message::Exit => self.exit(),
-                        _ => (),
-                    },
-                    Modus::HISTOGRAM => match msg {
-                        Message::Quit => self.quit(),
-                        Message::MoveDown => self.move_histogram_selection_down(1),
-                        Message::MoveUp => self.move_histogram_selection_up(1),
-                        Message::MovePageUp => self.move_histogram_selection_up(10),
-                        Message::MovePageDown => self.move_histogram_selection_down(10),
-                        Message::Resize(width, height) => self.ui_resize(width, height),
-                        Message::Help => self.show_help(),
-                        Message::Enter => self.enter(),
-                        Message::Exit => self.exit(),
-                        _ => (),
-                    },
+                    }
+                    (Ok(_), Err(_)) => std::cmp::Ordering::Less, // Valid numbers come first
+                    (Err(_), Ok(_)) => std::cmp::Ordering::Greater, // Invalid strings come last
+                    (Err(_), Err(_)) => {
+                        // Both invalid, sort as strings
+                        if ascending { a.cmp(b) } else { b.cmp(a) }
+                    }
+                }
+            });
+        } else {
+            // Sort as Strings
+            if ascending {
+                indexed_rows.sort_by(|(_, a), (_, b)| a.cmp(b));
+            } else {
+                indexed_rows.sort_by(|(_, a), (_, b)| b.cmp(a));
+            }
+        }
+
+        // Overwrite the table rows with the new ordered index
+        table.rows = Arc::new(indexed_rows.into_iter().map(|(i, _)| i).collect());
+        table.sort = Some((self.data[column_idx].name.clone(), ascending));
+        if let Some(groups) = table.groups.as_mut()
+            && let Some(column) = self.data.iter().find(|c| c.name == groups.column)
+        {
+            groups.regroup(column, &table.rows);
+            table.rows = Arc::new(groups.rows());
+        }
+        self.update_table_data();
+    }
+
+    fn cycle_number_base(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column = &mut self.data[table.offset_column + table.curser_column];
+        if !Model::is_integer_type(&column.dtype) {
+            self.set_status_message("Number bases are only supported for integer columns!");
+            return;
+        }
+        let base = column.base.next();
+        column.set_base(base);
+        self.set_status_message(format!("Showing column in {base:?}"));
+        self.update_table_data();
+    }
+
+    // First call selects the category column, the second the column that is aggregated per category
+    fn chart(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+
+        let Some(category) = self.chart_category.take() else {
+            self.chart_category = Some(column_idx);
+            self.set_status_message(format!(
+                "Chart categories \"{}\", press c on a numeric column or again for counts",
+                self.data[column_idx].name
+            ));
+            return;
+        };
+
+        self.chart_aggregation = if category == column_idx {
+            ChartAggregation::Count
+        } else if Model::is_numeric_type(&self.data[column_idx].dtype) {
+            ChartAggregation::Sum
+        } else {
+            self.set_status_message("Chart values need a numeric column!");
+            return;
+        };
+        self.chart_columns = (category, column_idx);
+        self.update_chart();
+    }
+
+    fn cycle_chart_aggregation(&mut self, step: i32) {
+        self.chart_aggregation = self.chart_aggregation.next(step);
+        // Counting does not need a numeric column, but sum and mean do
+        if self.chart_aggregation != ChartAggregation::Count
+            && !Model::is_numeric_type(&self.data[self.chart_columns.1].dtype)
+        {
+            self.chart_aggregation = ChartAggregation::Count;
+        }
+        self.update_chart();
+    }
+
+    fn update_chart(&mut self) {
+        let (category, value) = self.chart_columns;
+        let category_name = self.data[category].name.clone();
+        let value_name = self.data[value].name.clone();
+
+        match self.aggregate_chart(&category_name, &value_name) {
+            Ok(bars) => {
+                let title = match self.chart_aggregation {
+                    ChartAggregation::Count => format!("count() by {category_name}"),
+                    ChartAggregation::Sum => format!("sum({value_name}) by {category_name}"),
+                    ChartAggregation::Mean => format!("mean({value_name}) by {category_name}"),
+                };
+                self.uidata.chart = Some(ChartData { title, bars });
+                if !matches!(self.modus, Modus::CHART) {
+                    self.previous_modus = self.modus;
+                    self.modus = Modus::CHART;
+                }
+                self.uidata.last_update = Instant::now();
+            }
+            Err(e) => self.show_error(format!("Chart failed! {e}")),
+        }
+    }
+
+    // Aggregate the value column per category of the current view, largest first
+    fn aggregate_chart(
+        &self,
+        category: &str,
+        value: &str,
+    ) -> Result<Vec<(String, f64)>, PolarsError> {
+        let aggregation = match self.chart_aggregation {
+            ChartAggregation::Count => len(),
+            ChartAggregation::Sum => col(value).sum(),
+            ChartAggregation::Mean => col(value).mean(),
+        };
+        let frame = self
+            .current_view_frame()?
+            .lazy()
+            .group_by([col(category).cast(DataType::String)])
+            .agg([aggregation.cast(DataType::Float64).alias("value")])
+            .sort(
+                ["value"],
+                SortMultipleOptions::default()
+                    .with_order_descending(true)
+                    .with_nulls_last(true),
+            )
+            .limit(MAX_CHART_BARS as IdxSize)
+            .collect()?;
+
+        let labels = frame.column(category)?.str()?.clone();
+        let values = frame.column("value")?.f64()?.clone();
+        Ok(labels
+            .into_iter()
+            .zip(&values)
+            .map(|(l, v)| (l.unwrap_or("∅").to_string(), v.unwrap_or(0.0)))
+            .collect())
+    }
+
+    fn toggle_crosshair(&mut self) {
+        self.uidata.crosshair = !self.uidata.crosshair;
+        self.uidata.last_update = Instant::now();
+        if self.uidata.crosshair {
+            self.set_status_message("Crosshair mode on");
+        } else {
+            self.set_status_message("Crosshair mode off");
+        }
+    }
+
+    // Toggle shortening all uuid columns to their first block
+    // Move the selected column to another position. The dataframe is reordered as well,
+    // so the record view and exports follow the new order.
+    fn move_column(&mut self, position: usize) {
+        let table = self.tables.last().unwrap();
+        let from = table.offset_column + table.curser_column;
+        let row = table.offset_row + table.curser_row;
+        let to = position.min(self.data.len() - 1);
+        if from == to {
+            return;
+        }
+
+        let mut names = self.frame.get_column_names_owned();
+        let name = names.remove(from);
+        names.insert(to, name.clone());
+        match self.frame.select(names) {
+            Ok(frame) => self.frame = Arc::new(frame),
+            Err(e) => {
+                self.show_error(format!("Moving column failed! {e}"));
+                return;
+            }
+        }
+        let column = self.data.remove(from);
+        self.data.insert(to, column);
+
+        // Drop state that refers to columns by their position
+        for table in self.tables.iter_mut() {
+            table.search_results.clear();
+        }
+        self.background_search = None;
+        for hist in self.histogram_views.iter_mut() {
+            hist.clear();
+        }
+        self.chart_category = None;
+        self.column_stats_key = None;
+        self.totals = None;
+        self.select_cell(row, to);
+        self.set_status_message(format!("Moved column {name} to position {}", to + 1));
+    }
+
+    fn toggle_uuid_compaction(&mut self) {
+        let compact = !self.data.iter().any(|c| c.compact);
+        let mut ncolumns = 0;
+        for column in self.data.iter_mut() {
+            if !compact {
+                column.set_compact(false);
+            } else if column.is_uuid_column() {
+                column.set_compact(true);
+                ncolumns += 1;
+            }
+        }
+        if !compact {
+            self.set_status_message("Showing full uuids");
+        } else if ncolumns == 0 {
+            self.set_status_message("No uuid columns found!");
+        } else {
+            self.set_status_message(format!("Compacted {ncolumns} uuid columns"));
+        }
+        self.update_table_data();
+    }
+
+    // Toggle showing the value above in empty cells of the current column, e.g. group keys of hierarchical exports
+    fn toggle_fill_down(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column = &mut self.data[table.offset_column + table.curser_column];
+        column.fill_down = !column.fill_down;
+        let message = match column.fill_down {
+            true => format!(
+                "Filling empty cells of {} with the value above",
+                column.name
+            ),
+            false => format!("Showing empty cells of {}", column.name),
+        };
+        self.set_status_message(message);
+        self.update_table_data();
+    }
+
+    // Show a popup listing all set bits of the selected integer cell
+    fn show_bitfield(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column = &self.data[table.offset_column + table.curser_column];
+        let cell = table.get_current_cell(&self.data);
+        let value = match cell.parse::<u64>() {
+            Ok(v) => Some(v),
+            Err(_) => cell.parse::<i64>().ok().map(|v| v as u64),
+        };
+        let Some(value) = value.filter(|_| Model::is_integer_type(&column.dtype)) else {
+            self.set_status_message("Selected cell is not an integer!");
+            return;
+        };
+
+        let mut message = format!(
+            "\n  {}: {cell}\n\n  hex: {value:#x}\n  oct: {value:#o}\n  bin: {value:#b}\n\n",
+            column.name
+        );
+        if value == 0 {
+            message.push_str("  No bits set\n");
+        }
+        for bit in (0..u64::BITS).filter(|b| value & (1 << b) != 0) {
+            message.push_str(&format!("  bit {bit:>2}  {:#x}\n", 1u64 << bit));
+        }
+        self.show_popup("Bitfield", &message);
+    }
+
+    fn select_cell(&mut self, row: usize, column: usize) {
+        let table = self.tables.last_mut().unwrap();
+        table.select_cell(
+            row,
+            column,
+            &mut self.data,
+            &self.uilayout,
+            &mut self.uidata,
+        );
+    }
+
+    fn filter(&mut self, term: &str) {
+        let table = self.tables.last().unwrap();
+        self.filter_column(table.offset_column + table.curser_column, term);
+    }
+
+    // Mask positions of rows matching the filter term, together with the column that was filtered
+    fn filter_matches(
+        &self,
+        mut column_idx: usize,
+        term: &str,
+        mask: &[usize],
+    ) -> Result<(usize, Vec<usize>), String> {
+        // Filters of the form `[column] in <network>` select ip addresses within a network
+        let words: Vec<&str> = term.split_whitespace().collect();
+        let network_filter = match words.as_slice() {
+            ["in", network] => Some((None, *network)),
+            [column, "in", network] => Some((Some(*column), *network)),
+            _ => None,
+        };
+
+        let Some((column, network)) = network_filter else {
+            if let Some(matches) = Model::range_matches(&self.data[column_idx], term, mask) {
+                return Ok((column_idx, matches));
+            }
+            return Ok((column_idx, self.data[column_idx].search(term, mask)));
+        };
+        if let Some(name) = column {
+            column_idx = self
+                .data
+                .iter()
+                .position(|c| c.name == name)
+                .ok_or(format!("Unknown column: {name}"))?;
+        }
+        let network = ip::Cidr::parse(network).ok_or(format!("Invalid network: {network}"))?;
+        if !ip::is_ip_column(&self.data[column_idx]) {
+            return Err("Column does not contain ip addresses!".to_string());
+        }
+        Ok((
+            column_idx,
+            ip::search_network(&self.data[column_idx], &network, mask),
+        ))
+    }
+
+    // Plain terms are matched as part of the values, see filter_matches
+    fn is_substring_filter(&self, column_idx: usize, term: &str) -> bool {
+        let words: Vec<&str> = term.split_whitespace().collect();
+        !matches!(words.as_slice(), ["in", _] | [_, "in", _])
+            && Model::range_matches(&self.data[column_idx], term, &[]).is_none()
+    }
+
+    // Mask positions of rows with numbers in a range like `10..20` or `10..=20`.
+    // None if the term is no range or the column is not numeric.
+    fn range_matches(column: &Column, term: &str, mask: &[usize]) -> Option<Vec<usize>> {
+        let (from, to) = term.trim().split_once("..")?;
+        let (to, inclusive) = match to.strip_prefix('=') {
+            Some(to) => (to, true),
+            None => (to, false),
+        };
+        let from = from.trim().parse::<f64>().ok()?;
+        let to = to.trim().parse::<f64>().ok()?;
+        let numbers = column.numbers()?;
+        let in_range = |v: f64| v >= from && (v < to || (inclusive && v == to));
+        Some(
+            mask.iter()
+                .enumerate()
+                .filter(|&(_, &ridx)| numbers.get(ridx).is_some_and(in_range))
+                .map(|(midx, _)| midx)
+                .collect(),
+        )
+    }
+
+    // Group numeric columns of histograms into the given number of bins
+    fn set_histogram_bins(&mut self, args: &str) {
+        let Ok(bins) = args.trim().parse::<usize>() else {
+            self.show_warning("Usage: bins <n>");
+            return;
+        };
+        self.histogram_bins = bins;
+        for hist in self.histogram_views.iter_mut() {
+            hist.set_bins(bins);
+        }
+        if bins == 0 {
+            self.set_status_message("Histograms count every distinct value");
+        } else {
+            self.set_status_message(format!("Histograms group numbers into {bins} bins"));
+        }
+    }
+
+    // Show how many rows the filter being typed would match
+    fn update_filter_preview(&mut self) {
+        let Some(due) = self.filter_preview_due else {
+            return;
+        };
+        if Instant::now() < due {
+            return;
+        }
+        self.filter_preview_due = None;
+
+        let term = self.last_input.input.clone();
+        let table = self.tables.last().unwrap();
+        self.uidata.cmd_preview = if term.trim().is_empty() {
+            String::new()
+        } else {
+            let column_idx = table.offset_column + table.curser_column;
+            match self.filter_matches(column_idx, &term, &table.rows) {
+                Ok((_, matches)) => format!(
+                    "would match {} / {} rows",
+                    format_count(matches.len()),
+                    format_count(table.rows.len())
+                ),
+                Err(e) => e,
+            }
+        };
+        self.uidata.last_update = Instant::now();
+    }
+
+    fn filter_column(&mut self, column_idx: usize, term: &str) {
+        trace!("Starting filter for {} ...", term);
+        let table = self.tables.last().unwrap();
+        let start_time = Instant::now();
+
+        let filter = (self.data[column_idx].name.clone(), term.to_string());
+        let mask = Arc::clone(&table.rows);
+
+        // Large tables are filtered on a worker thread, the rows are added as they are found
+        if mask.len() > self.config.background_search_rows
+            && self.is_substring_filter(column_idx, term)
+        {
+            let values = self.data[column_idx].shared_values();
+            let mut search = BackgroundSearch::start(
+                term,
+                vec![(column_idx, values)],
+                mask,
+                self.events.clone(),
+            );
+            self.filter_table(Vec::new(), Some(filter.clone()));
+            search.filter = Some(filter);
+            search.depth = self.tables.len();
+            self.background_search = Some(search);
+            self.set_status_message(format!("Filtering for {term} ..."));
+            return;
+        }
+
+        let (column_idx, matches) = match self.filter_matches(column_idx, term, &mask) {
+            Ok(result) => result,
+            Err(e) => {
+                self.show_warning(e);
+                return;
+            }
+        };
+
+        let search_duration = start_time.elapsed().as_millis();
+
+        trace!(
+            "Search found {} matching rows in {}ms",
+            matches.len(),
+            search_duration
+        );
+        let is_empty = matches.is_empty();
+        self.filter_table(matches, Some(filter));
+        if is_empty {
+            self.set_status_message("Empty table!".to_string());
+            self.show_empty_result(term, column_idx, &mask, true);
+        }
+    }
+
+    fn filter_table(&mut self, indices: Vec<usize>, filter: Option<(String, String)>) {
+        let table = self.tables.last().unwrap();
+        let mut new_table = TableView::empty();
+        new_table.name = format!("F[{}]", table.name);
+        new_table.filter = filter;
+        new_table.bookmarks = Arc::clone(&table.bookmarks);
+        let resolved_indices: Vec<usize> = indices.iter().map(|&midx| table.rows[midx]).collect();
+        new_table.rows = Arc::new(resolved_indices);
+        self.tables.push(new_table);
+
+        let new_hist = self.new_histogram_view();
+        self.histogram_views.push(new_hist);
+
+        self.update_table_data();
+    }
+
+    fn toggle_table_index(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.show_index = !table.show_index;
+
+        // Update ui layout and the underlying data
+        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
+        self.update_table_data();
+    }
+
+    // Relative numbers show the index, so count prefixed jumps like 17j can be read off it
+    fn toggle_relative_numbers(&mut self) {
+        self.uidata.relative_numbers = !self.uidata.relative_numbers;
+        let table = self.tables.last_mut().unwrap();
+        if self.uidata.relative_numbers && !table.show_index {
+            self.toggle_table_index();
+        } else {
+            self.update_table_data();
+        }
+        match self.uidata.relative_numbers {
+            true => self.set_status_message("Relative row numbers on"),
+            false => self.set_status_message("Relative row numbers off"),
+        }
+    }
+
+    fn copy_table_cell(&mut self) {
+        // Yanking a visual selection copies its rows
+        let table = self.tables.last_mut().unwrap();
+        if table.visual_anchor.is_some() {
+            let rows = table.visual_rows();
+            table.visual_anchor = None;
+            self.copy_rows(&rows, '\t');
+            self.update_table_data();
+            return;
+        }
+        let cell = table.get_current_cell(&self.data);
+
+        match self.set_clipboard(cell) {
+            Ok(_) => self.set_status_message("Copied cell to clipboard!"),
+            Err(e) => self.show_error(format!("Copying to clipboard failed! {e}")),
+        }
+    }
+
+    // Copy using the system clipboard, or the terminal if there is none
+    fn set_clipboard(&mut self, text: String) -> Result<(), String> {
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            return clipboard.set_text(text).map_err(|e| e.to_string());
+        }
+        if !self.config.capabilities.osc52 {
+            return Err("No clipboard available!".to_string());
+        }
+        let sequence = format!("\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text));
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn copy_table_row(&mut self) {
+        let table = self.tables.last().unwrap();
+        let row_content = table.get_current_row(&self.data);
+
+        match self.set_clipboard(row_content) {
+            Ok(_) => self.set_status_message("Copied row to clipboard!"),
+            Err(e) => self.show_error(format!("Copying to clipboard failed! {e}")),
+        }
+    }
+
+    fn toggle_column_status(&mut self, toggle_to_expand: bool) {
+        let table = self.tables.last_mut().unwrap();
+        table.toggle_column_status(&mut self.data, toggle_to_expand);
+        self.update_table_data();
+    }
+
+    fn toggle_column_group(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let name = self.data[column_idx].name.clone();
+        match column_groups::toggle(&mut self.data, column_idx) {
+            Some((prefix, ncolumns, true)) => self.set_status_message(format!(
+                "Folded {ncolumns} {prefix}.* columns, z unfolds them"
+            )),
+            Some((prefix, ncolumns, false)) => {
+                self.set_status_message(format!("Unfolded {ncolumns} {prefix}.* columns"))
+            }
+            None => self.show_warning(format!(
+                "No adjacent columns share a prefix with {name}, e.g. {name}.id"
+            )),
+        }
+        self.update_table_data();
+    }
+
+    fn toggle_all_column_groups(&mut self) {
+        match column_groups::toggle_all(&mut self.data) {
+            (0, _) => self.show_warning(
+                "No adjacent columns share a name prefix like user.id and user.name!",
+            ),
+            (ngroups, true) => self.set_status_message(format!("Folded {ngroups} column groups")),
+            (ngroups, false) => {
+                self.set_status_message(format!("Unfolded {ngroups} column groups"))
+            }
+        }
+        self.update_table_data();
+    }
+
+    fn move_table_selection_beginning(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_beginning(&mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn move_table_selection_end(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_end(&mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn move_table_selection_up(&mut self, size: usize) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_up(size, &mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn move_table_selection_down(&mut self, size: usize) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_down(size, &mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn move_table_selection_left(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_left(&mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn move_table_selection_right(&mut self) {
+        let table = self.tables.last_mut().unwrap();
+        table.move_selection_right(&mut self.data, &self.uilayout, &mut self.uidata);
+    }
+
+    fn copy_record_cell(&mut self) {
+        let record = &self.record_view;
+        let cell = record.row_data[record.curser_offset + record.curser_row].clone();
+        trace!("Cell content: {}", cell);
 
-                    Modus::POPUP => match msg {
-                        Message::Quit => self.quit(),
-                        Message::Resize(width, height) => self.ui_resize(width, height),
-                        Message::Exit => self.exit(),
-                        _ => (),
-                    },
-                    Modus::CMDINPUT => {
-                        if let Message::RawKey(key) = msg {
-                            self.raw_input(key)
-                        }
-                    }
-                }
-            }
+        match self.set_clipboard(cell) {
+            Ok(_) => trace!("Copied cell content to clipboard."),
+            Err(e) => trace!("Error copying to clipboard: {:?}", e),
         }
+    }
 
-        self.last_update = Instant::now();
-        Ok(())
+    // Unprocessed cell value, e.g. with line breaks
+    fn raw_value(&self, column_idx: usize, ridx: usize) -> String {
+        match self.frame.get_columns()[column_idx].get(ridx) {
+            Ok(AnyValue::Null) | Err(_) => String::new(),
+            Ok(value) => value
+                .get_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string()),
+        }
     }
 
-    // -------------------- Control handling functions ---------------------- //
+    fn open_table_cell(&mut self, program: ExternalProgram) {
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[table.offset_row + table.curser_row];
+        let column_idx = table.offset_column + table.curser_column;
+        self.external_request = Some((program, self.raw_value(column_idx, ridx)));
+    }
 
-    fn enter(&mut self) {
-        match self.modus {
-            Modus::TABLE => {
-                let table = self.tables.last_mut().unwrap();
-                table.show_index = false;
-                let table = self.tables.last().unwrap();
-                // Disabling the index will change the ui layout. Recalculate it
-                self.uilayout =
-                    UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
-                //self.build_record_view(record_idx);
-                self.record_view = RecordView::new(
-                    table,
-                    &self.data,
-                    &mut self.uidata,
-                    self.config.max_column_width,
-                );
-                self.modus = Modus::RECORD;
-                self.previous_modus = Modus::TABLE;
-            }
-            Modus::RECORD => {}
-            Modus::HISTOGRAM => {
-                let hist = self.histogram_views.last().unwrap();
-                let table = self.tables.last().unwrap();
-                let term = hist.value_data[hist.curser_offset + hist.curser_row].clone();
-                let matches = self.data[hist.column_idx].search(&term, &table.rows);
-                self.filter_table(matches);
-                self.modus = Modus::TABLE;
-                self.previous_modus = Modus::HISTOGRAM;
+    fn open_record_cell(&mut self, program: ExternalProgram) {
+        let record = &self.record_view;
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[record.last_record_idx];
+        let column_idx = record.curser_offset + record.curser_row;
+        self.external_request = Some((program, self.raw_value(column_idx, ridx)));
+    }
+
+    // Open the url in a cell, or the cell value filled into the url template
+    fn open_url(&mut self, column_idx: usize, ridx: usize) {
+        let value = self.raw_value(column_idx, ridx);
+        let url = match (find_url(&value), self.config.url_template.as_ref()) {
+            (Some(url), _) if url.starts_with("www.") => format!("https://{url}"),
+            (Some(url), _) => url.to_string(),
+            (None, Some(template)) if !value.is_empty() => template.replace("{}", value.trim()),
+            _ => {
+                self.set_status_message("No url in the selected cell!");
+                return;
             }
-            Modus::POPUP => {}
-            Modus::CMDINPUT => {}
+        };
+        match external::open_url(&url) {
+            Ok(_) => self.set_status_message(format!("Opened {url}")),
+            Err(e) => self.show_error(format!("Opening url failed! {e:?}")),
         }
     }
 
-    fn exit(&mut self) {
-        match self.modus {
-            Modus::TABLE => {
-                // Nothing todo, there is no exit from table, only quit
-                if self.tables.len() > 1 {
-                    self.tables.pop();
-                    self.histogram_views.pop();
-                    self.update_table_data();
-                }
-            }
-            Modus::RECORD => {
-                // Switch back to table mode
-                self.previous_modus = Modus::RECORD;
-                self.modus = Modus::TABLE;
-                self.update_table_data();
-            }
-            Modus::POPUP => {
-                trace!("Close popup ...");
-                self.modus = self.previous_modus;
-                self.previous_modus = Modus::POPUP;
-                self.uidata.show_popup = false;
-                self.uidata.last_update = Instant::now();
+    // Plot the selected numeric column of the current view over the first datetime column,
+    // or over the row order if there is none
+    fn plot(&mut self) {
+        let table = self.tables.last().unwrap();
+        let column_idx = table.offset_column + table.curser_column;
+        let name = self.data[column_idx].name.clone();
+        if !Model::is_numeric_type(&self.data[column_idx].dtype) {
+            self.show_warning("Plots need a numeric column!");
+            return;
+        }
+        let values = match self.numeric_values(column_idx) {
+            Ok(values) => values,
+            Err(e) => {
+                self.show_error(format!("Plot failed! {e}"));
+                return;
             }
-            Modus::CMDINPUT => {}
-            Modus::HISTOGRAM => {
-                // Switch back to table mode
-                self.previous_modus = Modus::HISTOGRAM;
-                self.modus = Modus::TABLE;
-                self.update_table_data();
+        };
+
+        let time = (0..self.data.len()).find_map(|idx| Some((idx, self.time_values(idx)?)));
+        let (title, points, time_axis) = match time {
+            Some((time_idx, times)) => (
+                format!("{name} over {}", self.data[time_idx].name),
+                times
+                    .into_iter()
+                    .zip(&values)
+                    .filter_map(|(x, y)| Some((x? as f64, y?)))
+                    .collect(),
+                true,
+            ),
+            None => (
+                format!("{name} over row order"),
+                values
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(idx, y)| Some(((idx + 1) as f64, y?)))
+                    .collect(),
+                false,
+            ),
+        };
+        let Some(view) = PlotView::new(title, points, time_axis) else {
+            self.show_warning("The column has no values to plot!");
+            return;
+        };
+        self.plot_view = Some(view);
+        self.previous_modus = self.modus;
+        self.modus = Modus::PLOT;
+        self.update_plot();
+    }
+
+    // Milliseconds since the epoch of the rows in the current view, for datetime columns
+    // and string columns that hold datetimes
+    fn time_values(&self, column_idx: usize) -> Option<Int64Chunked> {
+        let datetime = DataType::Datetime(TimeUnit::Milliseconds, None);
+        // The format is inferred from the first value
+        let parse = |column: &polars::prelude::Column| -> Option<Series> {
+            let ambiguous = StringChunked::from_slice("ambiguous".into(), &["raise"]);
+            let parsed = column.str().ok()?.as_datetime(
+                None,
+                TimeUnit::Milliseconds,
+                false,
+                false,
+                None,
+                &ambiguous,
+            );
+            parsed.ok().map(|times| times.into_series())
+        };
+
+        let table = self.tables.last().unwrap();
+        let column = &self.frame.get_columns()[column_idx];
+        let is_string = match column.dtype() {
+            DataType::Date | DataType::Datetime(..) => false,
+            DataType::String => {
+                // Most values of a sample have to be datetimes
+                let sample = column.head(Some(TIME_SAMPLE_ROWS));
+                let parsed = parse(&sample)?;
+                let values = sample.len() - sample.null_count();
+                if values == 0 || (values - (parsed.len() - parsed.null_count())) * 10 > values {
+                    return None;
+                }
+                true
             }
+            _ => return None,
+        };
+        let indices = IdxCa::from_vec(
+            "idx".into(),
+            table.rows.iter().map(|&r| r as IdxSize).collect(),
+        );
+        let values = column.take(&indices).ok()?;
+        let times = if is_string {
+            parse(&values)?
+        } else {
+            values.as_materialized_series().clone()
+        };
+        let millis = times.cast(&datetime).ok()?.cast(&DataType::Int64).ok()?;
+        millis.i64().ok().cloned()
+    }
+
+    fn update_plot(&mut self) {
+        if let Some(view) = self.plot_view.as_ref() {
+            // Chart border and axis labels
+            let width = self.uilayout.table_width.saturating_sub(16);
+            self.uidata.plot = Some(view.update_uidata(width));
+            self.uidata.last_update = Instant::now();
         }
     }
 
-    fn show_help(&mut self) {
-        self.previous_modus = self.modus;
-        self.modus = Modus::POPUP;
-        self.uidata.popup_message = HELP_TEXT.to_string();
-        self.uidata.show_popup = true;
-        self.uidata.last_update = Instant::now();
+    fn zoom_plot(&mut self, zoom_in: bool) {
+        if let Some(view) = self.plot_view.as_mut() {
+            view.zoom(zoom_in);
+        }
+        self.update_plot();
     }
 
-    fn raw_input(&mut self, key: KeyEvent) {
-        if self.active_cmdinput {
-            self.last_input = self.input.read(key);
-            if self.last_input.finished {
-                self.handle_cmd_input();
+    fn pan_plot(&mut self, step: i32) {
+        if let Some(view) = self.plot_view.as_mut() {
+            view.pan(step);
+        }
+        self.update_plot();
+    }
+
+    // Show the selected record value as foldable json document
+    fn show_json(&mut self) {
+        let record = &self.record_view;
+        let table = self.tables.last().unwrap();
+        let ridx = table.rows[record.last_record_idx];
+        let column_idx = record.curser_offset + record.curser_row;
+        let value = self.raw_value(column_idx, ridx);
+        match JsonView::parse(&self.data[column_idx].name, &value) {
+            Some(view) => {
+                self.json_view = Some(view);
+                self.previous_modus = self.modus;
+                self.modus = Modus::JSON;
+                self.update_json();
             }
-            self.uidata.cmdinput = self.last_input.clone();
-            self.uidata.cmd_mode = self.cmd_mode;
+            None => self.set_status_message("Selected value is no json object or array!"),
+        }
+    }
+
+    fn update_json(&mut self) {
+        if let Some(view) = self.json_view.as_mut() {
+            // Popup border and margin
+            let height = self.uilayout.table_height.saturating_sub(4).max(1);
+            self.uidata.json = Some(view.update_uidata(height));
             self.uidata.last_update = Instant::now();
         }
     }
 
-    fn enter_cmd_mode(&mut self, mode: CMDMode) {
-        trace!("Entering command mode ...");
-        self.previous_modus = self.modus;
-        self.modus = Modus::CMDINPUT;
-        self.cmd_mode = Some(mode);
+    fn move_json_selection(&mut self, step: isize) {
+        if let Some(view) = self.json_view.as_mut() {
+            view.move_selection(step);
+        }
+        self.update_json();
+    }
 
-        self.active_cmdinput = true;
-        self.input.clear();
-        self.last_input = self.input.get();
+    fn fold_json(&mut self, folded: Option<bool>) {
+        if let Some(view) = self.json_view.as_mut() {
+            view.set_folded(folded);
+        }
+        self.update_json();
+    }
 
-        self.last_input = self.input.get();
-        self.uidata.cmdinput = self.last_input.clone();
-        self.uidata.active_cmdinput = self.active_cmdinput;
-        self.uidata.last_update = Instant::now();
-        self.uidata.cmd_mode = self.cmd_mode;
+    fn copy_json(&mut self) {
+        let Some(pretty) = self.json_view.as_ref().map(|v| v.pretty()) else {
+            return;
+        };
+        match self.set_clipboard(pretty) {
+            Ok(_) => self.set_status_message("Copied json to clipboard!"),
+            Err(e) => self.show_error(format!("Copying to clipboard failed! {e}")),
+        }
     }
 
-    fn handle_cmd_input(&mut self) {
-        // TODO: process self.last_input
-        trace!("Handle cmd input {}", self.last_input.input);
+    fn move_record_selection_up(&mut self, size: usize) {
+        let record = &mut self.record_view;
+        let table = self.tables.last().unwrap();
+        record.move_selection_up(size, table, &mut self.data, &mut self.uidata);
+    }
 
-        self.active_cmdinput = false;
-        self.modus = self.previous_modus;
-        self.previous_modus = Modus::CMDINPUT;
+    fn move_histogram_selection_up(&mut self, size: usize) {
+        let hist = self.histogram_views.last_mut().unwrap();
+        hist.move_selection_up(
+            size,
+            &mut self.data,
+            self.tables.last().unwrap(),
+            &mut self.uidata,
+        );
+    }
 
-        self.uidata.active_cmdinput = self.active_cmdinput;
-        self.last_update = Instant::now();
+    fn move_record_selection_down(&mut self, size: usize) {
+        let record = &mut self.record_view;
+        let table = self.tables.last().unwrap();
+        record.move_selection_down(size, table, &mut self.data, &mut self.uidata);
+    }
 
-        let cmd_input = self.last_input.input.clone();
-        match self.cmd_mode {
-            Some(CMDMode::SearchTable) => {
-                self.search(&cmd_input, false);
-            }
-            Some(CMDMode::FilterByColumn) => {
-                self.filter(&cmd_input);
-            }
-            Some(CMDMode::SearchInColumn) => {
-                self.search(&cmd_input, true);
-            }
-            Some(CMDMode::Raw) => {
-                info!("Raw cmd mode {cmd_input}")
-            }
-            None => {
-                info!("Cmd mode is none!")
-            }
+    fn move_histogram_selection_down(&mut self, size: usize) {
+        let hist = self.histogram_views.last_mut().unwrap();
+        hist.move_selection_down(
+            size,
+            &mut self.data,
+            self.tables.last().unwrap(),
+            &mut self.uidata,
+        )
+    }
+
+    fn previous_record(&mut self) {
+        let record = &mut self.record_view;
+        let table = self.tables.last().unwrap();
+        record.previous_record(table, &mut self.data, &mut self.uidata);
+    }
+
+    fn next_record(&mut self) {
+        let record = &mut self.record_view;
+        let table = self.tables.last().unwrap();
+        record.next_record(table, &mut self.data, &mut self.uidata);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::RemoteReply;
+    use crate::inputter::key_strategy;
+    use crate::tui::TOTALS_ROW_HEIGHT;
+    use proptest::prelude::*;
+    use ratatui::crossterm::event::KeyCode;
+
+    const FIXTURE: &str = "tests/fixtures/testdata_01.csv";
+
+    fn model() -> Model {
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(PathBuf::from(FIXTURE)).unwrap();
+        model
+    }
+
+    // File in the temp directory, removed when dropped so failing tests do not leave it behind
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("tv-{}-{name}", std::process::id())))
         }
 
-        self.cmd_mode = None;
+        fn with(name: &str, content: &str) -> Self {
+            let file = TempFile::new(name);
+            fs::write(&file.0, content).unwrap();
+            file
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn send(model: &mut Model, messages: Vec<Message>) {
+        for message in messages {
+            model.update(Some(message)).unwrap();
+        }
+    }
+
+    // Type into the command line and confirm with enter
+    fn type_input(model: &mut Model, text: &str) {
+        for c in text.chars() {
+            send(model, vec![Message::RawKey(KeyCode::Char(c).into())]);
+        }
+        send(model, vec![Message::RawKey(KeyCode::Enter.into())]);
+    }
+
+    fn selected_column(model: &Model) -> usize {
+        let table = model.tables.last().unwrap();
+        table.offset_column + table.curser_column
+    }
+
+    fn selected_value(model: &Model, column: &str) -> String {
+        let table = model.tables.last().unwrap();
+        let cidx = model.data.iter().position(|c| c.name == column).unwrap();
+        model.raw_value(cidx, table.rows[model.uidata.abs_selected_row])
+    }
+
+    #[test]
+    fn move_rows() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveDown(1)]);
+        assert_eq!(model.uidata.abs_selected_row, 1);
+        send(&mut model, vec![Message::MoveDown(3), Message::MoveUp(2)]);
+        assert_eq!(model.uidata.abs_selected_row, 2);
+        send(&mut model, vec![Message::MoveEnd]);
+        assert_eq!(model.uidata.abs_selected_row, 9);
+        send(&mut model, vec![Message::MoveDown(5)]);
+        assert_eq!(model.uidata.abs_selected_row, 9);
+        send(&mut model, vec![Message::MoveBeginning]);
+        assert_eq!(model.uidata.abs_selected_row, 0);
+        send(&mut model, vec![Message::MovePageDown, Message::MovePageUp]);
+        assert_eq!(model.uidata.abs_selected_row, 0);
+    }
+
+    #[test]
+    fn move_columns() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        assert_eq!(selected_column(&model), 2);
+        send(&mut model, vec![Message::MoveLeft]);
+        assert_eq!(selected_column(&model), 1);
+        send(&mut model, vec![Message::MoveToLastColumn]);
+        assert_eq!(selected_column(&model), model.data.len() - 1);
+        send(&mut model, vec![Message::MoveToFirstColumn]);
+        assert_eq!(selected_column(&model), 0);
+    }
+
+    #[test]
+    fn reorder_columns() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveColumnRight]);
+        assert_eq!(model.data[1].name, "id");
+        assert_eq!(model.frame.get_column_names()[1].as_str(), "id");
+        assert_eq!(selected_column(&model), 1);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "mvcol 4");
+        assert_eq!(model.data[3].name, "id");
+        assert_eq!(model.data[1].name, "price");
+        send(&mut model, vec![Message::MoveColumnLeft]);
+        assert_eq!(selected_column(&model), 2);
+        assert_eq!(selected_value(&model, "id"), "1");
+    }
+
+    #[test]
+    fn jump_to_nulls() {
+        let mut model = model();
+        send(&mut model, vec![Message::NextNull]);
+        assert_eq!(model.status_message, "No nulls in id!");
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        send(&mut model, vec![Message::NextNull]);
+        assert_eq!(model.uidata.abs_selected_row, 3);
+        assert_eq!(model.status_message, "Null 1/1 in price");
+        send(&mut model, vec![Message::MoveEnd, Message::PreviousNull]);
+        assert_eq!(model.uidata.abs_selected_row, 3);
+    }
+
+    #[test]
+    fn fill_down() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        assert_eq!(model.uidata.table[2].data[3], "∅");
+        send(&mut model, vec![Message::ToggleFillDown]);
+        assert_eq!(model.uidata.table[2].data[3], "99.99");
+        assert!(model.uidata.table[2].styles[3].is_some());
+        assert_eq!(model.uidata.table[2].data[4], "100.00");
+        send(&mut model, vec![Message::ToggleFillDown]);
+        assert_eq!(model.uidata.table[2].data[3], "∅");
+    }
+
+    #[test]
+    fn group_rows() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "group available");
+        assert_eq!(model.uidata.index.data[0], "▾ true (7)");
+        assert_eq!(model.uidata.index.data[7], "▾ false (3)");
+        assert_eq!(selected_value(&model, "id"), "1");
+        send(
+            &mut model,
+            vec![Message::MoveDown(2), Message::ToggleRowGroup],
+        );
+        assert_eq!(model.uidata.nrows, 4);
+        assert_eq!(model.uidata.abs_selected_row, 0);
+        assert_eq!(model.uidata.index.data[..2], ["▸ true (7)", "▾ false (3)"]);
+        // Sorting keeps the sections
+        send(
+            &mut model,
+            vec![Message::SortDescending, Message::ToggleAllRowGroups],
+        );
+        assert_eq!(model.uidata.nrows, 10);
+        assert_eq!(selected_value(&model, "id"), "10");
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.uilayout.index_width, 0);
+        assert_eq!(model.uidata.nrows, 10);
+    }
+
+    #[test]
+    fn top_values_panel() {
+        let mut model = model();
+        let table_height = model.uilayout.table_height;
+        send(&mut model, vec![Message::ToggleTopValues]);
+        assert_eq!(model.uilayout.table_height, table_height - 6);
+        for _ in 0..5 {
+            send(&mut model, vec![Message::MoveRight]);
+        }
+        let top = model.uidata.top_values.clone().unwrap();
+        assert_eq!(top.title, "Top values of available (2 distinct)");
+        assert_eq!(
+            top.values,
+            [("true".to_string(), 7), ("false".to_string(), 3)]
+        );
+        // Follows filters
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "delete");
+        let top = model.uidata.top_values.clone().unwrap();
+        assert_eq!(top.total, 9);
+        send(&mut model, vec![Message::ToggleTopValues]);
+        assert!(model.uidata.top_values.is_none());
+        assert_eq!(model.uilayout.table_height, table_height);
+    }
+
+    #[test]
+    fn filter_columns() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "cols PRICE|quan");
+        let names: Vec<&str> = model.uidata.table.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["price", "quantity"]);
+        assert_eq!(selected_column(&model), 2);
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        assert_eq!(selected_column(&model), 4);
+
+        send(&mut model, vec![Message::FilterColumns]);
+        type_input(&mut model, "^n");
+        let names: Vec<&str> = model.uidata.table.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["name", "notes"]);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "cols");
+        assert_eq!(model.uidata.table.len(), model.data.len());
+    }
+
+    #[test]
+    fn fold_column_groups() {
+        let mut model = model();
+        model.data[1].name = "item.name".into();
+        model.data[2].name = "item.price".into();
+        send(
+            &mut model,
+            vec![Message::MoveRight, Message::ToggleColumnGroup],
+        );
+        assert_eq!(model.uidata.table[1].name, "item.* (2)");
+        assert_eq!(model.uidata.table[1].data[0], "Basic Widget, 19.99");
+        assert_eq!(model.uidata.table[2].name, "category");
+        // The folded columns are skipped
+        send(&mut model, vec![Message::MoveRight]);
+        assert_eq!(selected_column(&model), 3);
+        assert_eq!(model.uidata.selected_column, 2);
+        send(&mut model, vec![Message::MoveLeft]);
+        assert_eq!(selected_column(&model), 1);
+        send(&mut model, vec![Message::ToggleAllColumnGroups]);
+        assert_eq!(model.uidata.table[2].name, "item.price");
+    }
+
+    #[test]
+    fn search_in_column() {
+        let mut model = model();
+        send(
+            &mut model,
+            vec![Message::MoveRight, Message::SearchInColumn],
+        );
+        assert!(matches!(model.modus, Modus::CMDINPUT));
+        type_input(&mut model, "Widget");
+        assert!(matches!(model.modus, Modus::TABLE));
+        assert_eq!(selected_value(&model, "id"), "1");
+        send(&mut model, vec![Message::SearchNext]);
+        assert_eq!(selected_value(&model, "id"), "2");
+        send(&mut model, vec![Message::SearchPrev]);
+        assert_eq!(selected_value(&model, "id"), "1");
     }
 
-    fn search(&mut self, term: &str, current_column_only: bool) {
-        trace!("Starting search for {} ...", term);
-        let table = self.tables.last_mut().unwrap();
-        let num_matches = table.search(
-            term,
-            current_column_only,
-            &mut self.data,
-            &self.uilayout,
-            &mut self.uidata,
-        );
-        if num_matches == 0 {
-            self.set_status_message("Found no matches!".to_string());
-        } else {
-            self.set_status_message(format!("Found {} results", num_matches));
-        }
+    #[test]
+    fn filter_and_exit() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveRight, Message::Filter]);
+        type_input(&mut model, "Widget");
+        assert_eq!(model.uidata.nrows, 2);
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.uidata.nrows, 10);
     }
 
-    // Sets the curser to the next search result
-    fn search_next(&mut self, step: i32) {
-        let table = self.tables.last_mut().unwrap();
-        if table.search_results.is_empty() {
-            self.set_status_message("Empty search results!".to_string());
-        } else {
-            let next_match_idx =
-                table.search_next(step, &mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn binned_histogram() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "bins 3");
+        send(&mut model, vec![Message::Histogram]);
+        let hist = model.histogram_views.last().unwrap();
+        let labels: Vec<&str> = hist.value_data.iter().map(|v| v.as_ref()).collect();
+        assert_eq!(labels, ["1..5", "5..9", "9..13"]);
+        send(&mut model, vec![Message::MoveDown(1), Message::Enter]);
+        assert_eq!(model.uidata.nrows, 4);
+        assert_eq!(selected_value(&model, "id"), "5");
+    }
 
-            let table = self.tables.last().unwrap();
-            self.set_status_message(format!(
-                "Search result {}/{}",
-                next_match_idx + 1,
-                table.search_results.len()
-            ));
-        }
+    #[test]
+    fn group_by() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "groupby available sum(quantity), count()");
+        let names: Vec<&str> = model.data.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["available", "sum(quantity)", "count()"]);
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.data[0].name, "id");
     }
 
-    fn sort_current_column(&mut self, ascending: bool) {
-        let table = self.tables.last_mut().unwrap();
-        let data = &(self.data[table.curser_column + table.offset_column]).data;
-        let is_numeric =
-            Model::is_numeric_type(&self.data[table.curser_column + table.offset_column].dtype);
+    #[test]
+    fn export_html() {
+        let mut model = model();
+        let file = TempFile::new("report.html");
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, &format!("write {}", file.0.display()));
+        let html = fs::read_to_string(&file.0).unwrap();
+        assert!(html.contains("<th>id</th>"));
+        assert!(html.contains("Deluxe &quot;Pro&quot; Widget"));
+        assert!(html.contains("<td style=\"color: #ef4444\">false</td>"));
+    }
 
-        // Create a vector of (original_index, value) pairs
-        let mut indexed_rows: Vec<(usize, &String)> = table
-            .rows
+    #[test]
+    fn sort() {
+        let mut model = model();
+        let quantity = model
+            .data
             .iter()
-            .map(|&row_idx| (row_idx, &data[row_idx]))
-            .collect();
+            .position(|c| c.name == "quantity")
+            .unwrap();
+        send(&mut model, vec![Message::MoveRight; quantity]);
+        send(&mut model, vec![Message::SortDescending]);
+        assert_eq!(selected_value(&model, "quantity"), "999");
+        send(&mut model, vec![Message::SortAscending]);
+        assert_eq!(selected_value(&model, "quantity"), "-5");
+    }
 
-        //indexed_rows.sort_unstable_by_key(|(idx, &data)| data);
+    #[test]
+    fn switch_views() {
+        let mut model = model();
+        send(&mut model, vec![Message::Enter]);
+        assert!(matches!(model.modus, Modus::RECORD));
+        send(&mut model, vec![Message::Exit]);
+        assert!(matches!(model.modus, Modus::TABLE));
+        send(&mut model, vec![Message::Histogram]);
+        assert!(matches!(model.modus, Modus::HISTOGRAM));
+        send(&mut model, vec![Message::Exit, Message::Help]);
+        assert!(matches!(model.modus, Modus::POPUP));
+        send(&mut model, vec![Message::Exit]);
+        assert!(matches!(model.modus, Modus::TABLE));
+    }
 
-        // Sort by the data values
-        if is_numeric {
-            // If the column originally was a numeric, try to convert each value to a float representation and compare it.
-            // LLM generated matches will order partial float conversion, giving order preference to successful converted floats
-            // Falling back to string sorting if nothing can be converted
-            indexed_rows.sort_by(|(_, a), (_, b)| {
-                let a_val: Result<f64, _> = a.parse();
-                let b_val: Result<f64, _> = b.parse();
+    #[test]
+    fn toggles() {
+        let mut model = model();
+        send(
+            &mut model,
+            vec![Message::ToggleCrosshair, Message::ToggleProfile],
+        );
+        assert!(model.uidata.crosshair);
+        assert!(model.uidata.profile.show);
+        send(&mut model, vec![Message::ToggleBookmark]);
+        assert!(model.uidata.bookmarked_rows[0]);
+        send(&mut model, vec![Message::ToggleMark]);
+        assert!(model.uidata.marked_rows[0]);
+    }
 
-                match (a_val, b_val) {
-                    (Ok(a_float), Ok(b_float)) => {
-                        if ascending {
-                            a_float
-                                .partial_cmp(&b_float)
-                                .unwrap_or(std::cmp::Ordering::Equal)
-                        } else {
-                            b_float
-                                .partial_cmp(&a_float)
-                                .unwrap_or(std::cmp::Ordering::Equal)
-                        }
-                    }
-                    (Ok(_), Err(_)) => std::cmp::Ordering::Less, // Valid numbers come first
-                    (Err(_), Ok(_)) => std::cmp::Ordering::Greater, // Invalid strings come last
-                    (Err(_), Err(_)) => {
-                        // Both invalid, sort as strings
-                        if ascending { a.cmp(b) } else { b.cmp(a) }
-                    }
-                }
-            });
-        } else {
-            // Sort as Strings
-            if ascending {
-                indexed_rows.sort_by(|(_, a), (_, b)| a.cmp(b));
-            } else {
-                indexed_rows.sort_by(|(_, a), (_, b)| b.cmp(a));
-            }
-        }
+    #[test]
+    fn visual_selection() {
+        let mut model = model();
+        send(
+            &mut model,
+            vec![Message::VisualSelect, Message::MoveDown(1)],
+        );
+        assert_eq!(model.uidata.marked_rows[..3], [true, true, false]);
+        let table = model.tables.last().unwrap();
+        let copied = TableView::get_rows(&model.data[..3], &table.visual_rows(), '\t');
+        assert_eq!(
+            copied,
+            "id\tname\tprice\n1\tBasic Widget\t19.99\n2\t\"Deluxe \"\"Pro\"\" Widget\"\t45.50"
+        );
+        send(&mut model, vec![Message::VisualSelect]);
+        assert_eq!(model.tables.last().unwrap().marked_rows.len(), 2);
+    }
 
-        // Overwrite the table rows with the new ordered index
-        table.rows = Arc::new(indexed_rows.into_iter().map(|(i, _)| i).collect());
-        self.update_table_data();
+    #[test]
+    fn reload_shows_changes() {
+        let file = TempFile::with("reload.csv", "id,qty\n1,10\n2,20\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        fs::write(&file.0, "id,qty\n1,11\n3,30\n").unwrap();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "reload");
+        assert_eq!(model.frame.height(), 3);
+        assert_eq!(model.data[0].value(0).as_ref(), "changed: qty");
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.frame.height(), 2);
     }
 
-    fn select_cell(&mut self, row: usize, column: usize) {
-        let table = self.tables.last_mut().unwrap();
-        table.select_cell(
-            row,
-            column,
-            &mut self.data,
-            &self.uilayout,
-            &mut self.uidata,
+    #[test]
+    fn watch_reloads_changes() {
+        let file = TempFile::with("watch.csv", "id,qty\n1,10\n2,20\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "watch");
+        assert!(model.tick_interval(IDLE_TICK_INTERVAL) <= model.watch_interval());
+
+        // Nothing happens until the file is modified and the check is due
+        model.watch.as_mut().unwrap().due = Instant::now();
+        model.update(None).unwrap();
+        assert_eq!(model.frame.height(), 2);
+        fs::write(&file.0, "id,qty\n1,11\n2,20\n3,30\n").unwrap();
+        let modified = SystemTime::now() + Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&file.0)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        model.update(None).unwrap();
+        assert_eq!(model.frame.height(), 2);
+        model.watch.as_mut().unwrap().due = Instant::now();
+        model.update(None).unwrap();
+        assert_eq!(model.frame.height(), 2);
+        assert_eq!(model.data[0].value(1).as_ref(), "added");
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.frame.height(), 3);
+
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "watch");
+        assert!(model.watch.is_none());
+    }
+
+    #[test]
+    fn failed_reload_keeps_views() {
+        let file = TempFile::with("reload-deleted.csv", "id,kind\n1,a\n2,b\n3,a\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "groupby kind count()");
+        assert_eq!(model.parent_datasets.len(), 1);
+        fs::remove_file(&file.0).unwrap();
+
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "reload");
+        assert!(
+            model
+                .messages
+                .last()
+                .unwrap()
+                .2
+                .starts_with("Reloading failed!")
         );
+        send(&mut model, vec![Message::Reload]);
+        assert_eq!(model.messages.len(), 2);
+        assert_eq!(model.frame.height(), 2);
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.frame.height(), 3);
     }
 
-    fn filter(&mut self, term: &str) {
-        trace!("Starting filter for {} ...", term);
-        let table = self.tables.last_mut().unwrap();
-        let start_time = Instant::now();
+    #[test]
+    fn reload_keeps_view() {
+        let file = TempFile::with("reload-view.csv", "id,kind\n1,a\n2,b\n3,a\n4,a\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        send(
+            &mut model,
+            vec![Message::SortDescending, Message::MoveDown(1)],
+        );
+        fs::write(&file.0, "id,kind\n1,a\n2,b\n3,a\n4,a\n5,b\n").unwrap();
+        send(&mut model, vec![Message::Reload]);
 
-        let matches =
-            self.data[table.offset_column + table.curser_column].search(term, &table.rows);
+        // Still sorted descending, with the curser on the third row of the file
+        assert_eq!(model.frame.height(), 5);
+        let table = model.tables.last().unwrap();
+        assert_eq!(table.rows[..], [4, 3, 2, 1, 0]);
+        assert_eq!(model.uidata.abs_selected_row, 2);
+    }
 
-        let search_duration = start_time.elapsed().as_millis();
+    #[test]
+    fn macros() {
+        let mut model = model();
+        send(
+            &mut model,
+            vec![
+                Message::RecordMacro('a'),
+                Message::MoveDown(1),
+                Message::MoveRight,
+                Message::StopMacro,
+            ],
+        );
+        assert_eq!(model.uidata.abs_selected_row, 1);
+        send(
+            &mut model,
+            vec![Message::MoveToFirstColumn, Message::ReplayMacro('a', 3)],
+        );
+        assert_eq!(model.uidata.abs_selected_row, 4);
+        assert_eq!(selected_column(&model), 3);
+        assert!(model.uidata.recording_macro.is_none());
 
-        trace!(
-            "Search found {} matching rows in {}ms",
-            matches.len(),
-            search_duration
+        // A macro replaying itself stops instead of replaying for ages
+        send(
+            &mut model,
+            vec![
+                Message::RecordMacro('b'),
+                Message::MoveDown(1),
+                Message::ReplayMacro('b', 1),
+                Message::StopMacro,
+                Message::RecordMacro('b'),
+                Message::MoveUp(1),
+                Message::ReplayMacro('b', 3),
+                Message::ReplayMacro('b', 3),
+                Message::StopMacro,
+                Message::ReplayMacro('b', 3),
+            ],
         );
-        if matches.is_empty() {
-            self.set_status_message("Empty table!".to_string());
-        }
-        self.filter_table(matches);
+        assert_eq!(model.macro_steps, MAX_MACRO_STEPS);
     }
 
-    fn filter_table(&mut self, indices: Vec<usize>) {
-        let table = self.tables.last().unwrap();
-        let mut new_table = TableView::empty();
-        new_table.name = format!("F[{}]", table.name);
-        let resolved_indices: Vec<usize> = indices.iter().map(|&midx| table.rows[midx]).collect();
-        new_table.rows = Arc::new(resolved_indices);
-        self.tables.push(new_table);
+    #[test]
+    fn confirm_quit_with_edits() {
+        let mut model = model();
+        send(&mut model, vec![Message::EditCell]);
+        type_input(&mut model, "42");
+        assert_eq!(selected_value(&model, "id"), "142");
+        send(&mut model, vec![Message::Quit]);
+        assert!(matches!(model.modus, Modus::POPUP));
+        send(&mut model, vec![Message::Exit]);
+        assert_eq!(model.status, Status::READY);
+        send(&mut model, vec![Message::Quit, Message::Quit]);
+        assert_eq!(model.status, Status::QUITTING);
+    }
 
-        let new_hist = HistogramView::empty();
-        self.histogram_views.push(new_hist);
+    #[test]
+    fn write_keeps_file_order() {
+        let file = TempFile::with("write.csv", "id,qty\n1,10\n2,20\n3,30\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        send(&mut model, vec![Message::SortDescending, Message::EditCell]);
+        type_input(&mut model, "4");
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "w");
+        let written = fs::read_to_string(&file.0).unwrap();
+        assert_eq!(written, "id,qty\n1,10\n2,20\n34,30\n");
+        assert!(!model.dirty);
+    }
 
-        self.update_table_data();
+    #[test]
+    fn warnings_until_key_press() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "foo");
+        assert_eq!(model.uidata.status_level, MessageLevel::Warning);
+        assert_eq!(model.messages.len(), 1);
+        send(&mut model, vec![Message::MoveDown(1)]);
+        assert_eq!(model.uidata.status_level, MessageLevel::Info);
     }
 
-    fn toggle_table_index(&mut self) {
-        let table = self.tables.last_mut().unwrap();
-        table.show_index = !table.show_index;
+    #[test]
+    fn resize_and_quit() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveEnd, Message::Resize(60, 8)]);
+        assert_eq!(model.uilayout.width, 60);
+        assert_eq!(model.uidata.abs_selected_row, 9);
+        assert!(model.uidata.selected_row < model.uilayout.table_height);
+        send(&mut model, vec![Message::Quit]);
+        assert_eq!(model.status, Status::QUITTING);
+    }
 
-        // Update ui layout and the underlying data
-        self.uilayout = UILayout::from_model(self, self.uilayout.width, self.uilayout.height);
-        self.update_table_data();
+    #[test]
+    fn complete_open_path() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        for c in "open tests/fix".chars() {
+            send(&mut model, vec![Message::RawKey(KeyCode::Char(c).into())]);
+        }
+        let tab = Message::RawKey(KeyCode::Tab.into());
+        send(&mut model, vec![tab.clone()]);
+        assert_eq!(model.last_input.input, "open tests/fixtures/");
+        send(&mut model, vec![tab.clone(), tab]);
+        assert_eq!(
+            model.last_input.input,
+            "open tests/fixtures/testdata_02.csv"
+        );
+        assert_eq!(model.uidata.cmd_preview, "2/5, Tab for the next");
+
+        send(&mut model, vec![Message::RawKey(KeyCode::Enter.into())]);
+        assert_eq!(
+            model.take_workspace_request(),
+            Some(WorkspaceRequest::Open(PathBuf::from(
+                "tests/fixtures/testdata_02.csv"
+            )))
+        );
     }
 
-    fn copy_table_cell(&mut self) {
-        let table = self.tables.last().unwrap();
-        let cell = table.get_current_cell(&self.data);
+    #[test]
+    fn cast_column() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "cast quantity as f64");
+        let column = model.data.iter().find(|c| c.name == "quantity").unwrap();
+        assert_eq!(column.dtype, DataType::Float64);
+        assert_eq!(column.value(0).as_ref(), "25.0");
+        // Only edited cells are unsaved changes
+        assert!(!model.dirty);
 
-        match self.clipboard.set_text(cell) {
-            Ok(_) => self.set_status_message("Copied cell to clipboard!"),
-            Err(e) => self.set_status_message(format!("Copying to clipboard failed! {e}")),
-        }
+        // Cells that can not be parsed keep their text, and can be filtered for
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "cast name as f64");
+        let column = model.data.iter().find(|c| c.name == "name").unwrap();
+        assert_eq!(column.unparsed.len(), 10);
+        assert_eq!(column.display_value(0), "Basic Widget");
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "unparsed");
+        assert_eq!(model.tables.last().unwrap().rows.len(), 10);
     }
 
-    fn copy_table_row(&mut self) {
-        let table = self.tables.last().unwrap();
-        let row_content = table.get_current_row(&self.data);
+    #[test]
+    fn tick_interval_follows_timers() {
+        let mut model = model();
+        let poll_time = Duration::from_millis(100);
+        model.update(None).unwrap();
+        model.last_status_message_update = Instant::now() - STATUS_MESSAGE_DISPLAY_DURATION;
+        assert_eq!(model.tick_interval(poll_time), IDLE_TICK_INTERVAL);
 
-        match self.clipboard.set_text(row_content) {
-            Ok(_) => self.set_status_message("Copied row to clipboard!"),
-            Err(e) => self.set_status_message(format!("Copying to clipboard failed! {e}")),
+        // Waits until the status message is hidden
+        model.set_status_message("Hello");
+        let interval = model.tick_interval(poll_time);
+        assert!(interval > poll_time && interval <= STATUS_MESSAGE_DISPLAY_DURATION);
+
+        // Typing a filter waits for the preview
+        send(&mut model, vec![Message::Filter]);
+        send(&mut model, vec![Message::RawKey(KeyCode::Char('a').into())]);
+        let delay = Duration::from_millis(model.config.filter_preview_delay as u64);
+        assert!(model.tick_interval(poll_time) <= delay);
+    }
+
+    #[test]
+    fn rename_columns() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveRight, Message::RenameColumn]);
+        assert_eq!(model.last_input.input, "rename name");
+        for _ in 0.."name".len() {
+            send(&mut model, vec![Message::RawKey(KeyCode::Backspace.into())]);
         }
+        type_input(&mut model, "product");
+        assert_eq!(model.data[selected_column(&model)].name, "product");
+        assert!(model.frame.column("product").is_ok());
+
+        // Names stay unique
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "rename id");
+        assert_eq!(model.data[selected_column(&model)].name, "product");
+
+        // Headerless files get synthetic names
+        let file = TempFile::with("headerless.csv", "1,foo,2.5\n2,bar,3.5\n3,baz,4.5\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        let names: Vec<&str> = model.data.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["c1", "c2", "c3"]);
     }
 
-    fn toggle_column_status(&mut self, toggle_to_expand: bool) {
-        let table = self.tables.last_mut().unwrap();
-        table.toggle_column_status(&mut self.data, toggle_to_expand);
-        self.update_table_data();
+    #[test]
+    fn repeated_column_names() {
+        let file = TempFile::with("repeated.csv", "id,name,name,name\n1,a,b,c\n2,d,e,f\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        let names: Vec<&str> = model.data.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "name", "name_2", "name_3"]);
+
+        let exported = TempFile::new("repeated-export.csv");
+        model.export_view(exported.0.to_str().unwrap());
+        let unique = fs::read_to_string(&exported.0).unwrap();
+        model.export_view(&format!("--original-names {}", exported.0.display()));
+        let original = fs::read_to_string(&exported.0).unwrap();
+        assert!(unique.starts_with("id,name,name_2,name_3\n"));
+        assert!(original.starts_with("id,name,name,name\n1,a,b,c\n"));
     }
 
-    fn move_table_selection_beginning(&mut self) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_beginning(&mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn totals_row() {
+        let mut model = model();
+        send(&mut model, vec![Message::ToggleTotals]);
+        let height = model.uilayout.table_height;
+        let totals = model.uidata.totals.clone().unwrap();
+        assert_eq!(totals[0], "55");
+        assert_eq!(totals[1], "count 10");
+        assert_eq!(totals[4], "1144");
+
+        // Only the rows of the filtered view are summed up
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        send(&mut model, vec![Message::MoveRight, Message::MoveRight]);
+        send(&mut model, vec![Message::Filter]);
+        type_input(&mut model, "999");
+        assert_eq!(model.uidata.totals.as_ref().unwrap()[4], "999");
+
+        send(&mut model, vec![Message::ToggleTotals]);
+        assert_eq!(model.uidata.totals, None);
+        assert_eq!(model.uilayout.table_height, height + TOTALS_ROW_HEIGHT);
     }
 
-    fn move_table_selection_end(&mut self) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_end(&mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn diff_columns() {
+        let file = TempFile::with(
+            "diffcols.csv",
+            "case,expected,actual\na,1,1.0\nb,2,3\nc,4,4\nd,5,\n",
+        );
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "diffcols expected,actual");
+        let table = model.tables.last().unwrap();
+        assert_eq!(table.highlighted_rows, HashSet::from([1, 3]));
+        assert!(model.data[2].display_cell(1).style.is_some());
+        assert!(model.data[2].display_cell(0).style.is_none());
+
+        // A single column is compared to the selected one
+        send(&mut model, vec![Message::MoveRight, Message::EnterCommand]);
+        type_input(&mut model, "fdiffcols actual");
+        assert_eq!(model.tables.last().unwrap().rows.len(), 2);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "nohl");
+        assert!(model.data.iter().all(|c| c.differs.is_empty()));
     }
 
-    fn move_table_selection_up(&mut self, size: usize) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_up(size, &mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn sample_and_shuffle() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "sample 4 42");
+        let sampled = model.tables.last().unwrap().rows.to_vec();
+        assert_eq!(sampled.len(), 4);
+        assert!(sampled.windows(2).all(|w| w[0] < w[1]));
+        send(&mut model, vec![Message::Exit, Message::EnterCommand]);
+        type_input(&mut model, "sample 4 42");
+        assert_eq!(model.tables.last().unwrap().rows.to_vec(), sampled);
+
+        // Shuffling the sample keeps its rows
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "shuffle");
+        let mut shuffled = model.tables.last().unwrap().rows.to_vec();
+        shuffled.sort_unstable();
+        assert_eq!(shuffled, sampled);
+        send(&mut model, vec![Message::Exit, Message::Exit]);
+        assert_eq!(model.tables.len(), 1);
     }
 
-    fn move_table_selection_down(&mut self, size: usize) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_down(size, &mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn load_part_of_large_files() {
+        let config = TVConfig {
+            max_rows: 4,
+            ..TVConfig::default()
+        };
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(PathBuf::from(FIXTURE)).unwrap();
+        assert!(model.partial_rows.is_some_and(|n| n >= 10));
+        assert_eq!(model.tables[0].rows.len(), 4);
+        assert_eq!(model.data[0].value(3).as_ref(), "4");
+        assert!(!model.write_file());
+
+        let config = TVConfig {
+            sample_large_files: true,
+            ..config
+        };
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(PathBuf::from(FIXTURE)).unwrap();
+        assert_eq!(model.tables[0].rows.len(), 4);
+        assert_eq!(model.data.len(), 8);
     }
 
-    fn move_table_selection_left(&mut self) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_left(&mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn load_part_of_large_files_like_whole_ones() {
+        let config = TVConfig {
+            max_rows: 4,
+            ..TVConfig::default()
+        };
+        let rows: String = (1..=10).map(|i| format!("{i};ä{i}\r\n")).collect();
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(
+            format!("id;name\r\n{rows}")
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        );
+        let file = TempFile::new("partial-utf16.csv");
+        fs::write(&file.0, utf16).unwrap();
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        assert!(model.partial_rows.is_some());
+        assert_eq!(model.tables[0].rows.len(), 4);
+        assert_eq!(model.data[1].value(3).as_ref(), "ä4");
+
+        // Malformed lines are reported with their line in the file
+        let file = TempFile::with(
+            "partial-lenient.csv",
+            "id,name\n1,a\n2,\"b\nc\"\n3,c,extra\n4,d\n5,e\n6,f\n",
+        );
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        assert!(model.load_data_file(file.0.clone()).is_err());
+        let config = TVConfig {
+            lenient_csv: true,
+            ..config
+        };
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+        assert_eq!(model.tables[0].rows.len(), 3);
+        assert_eq!(model.malformed_lines.len(), 1);
+        assert_eq!(model.malformed_lines[0].line, 5);
     }
 
-    fn move_table_selection_right(&mut self) {
-        let table = self.tables.last_mut().unwrap();
-        table.move_selection_right(&mut self.data, &self.uilayout, &mut self.uidata);
+    #[test]
+    fn each_row() {
+        let mut model = model();
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "each echo {unknown}");
+        assert!(model.pending_each.is_empty());
+
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "each echo {name} costs {price}");
+        assert_eq!(model.pending_each, ["echo 'Basic Widget' costs '19.99'"]);
+        send(&mut model, vec![Message::Enter]);
+        assert!(model.pending_each.is_empty());
+        while model.each_run.as_ref().is_some_and(|run| !run.is_done()) {
+            model.update(None).unwrap();
+        }
+        let log = &model.each_run.as_ref().unwrap().log;
+        assert!(log.contains("Basic Widget costs 19.99"));
     }
 
-    fn copy_record_cell(&mut self) {
-        let record = &self.record_view;
-        let cell = record.row_data[record.curser_offset + record.curser_row].clone();
-        trace!("Cell content: {}", cell);
+    #[test]
+    fn remote_commands() {
+        let mut model = model();
+        let (sender, replies) = std::sync::mpsc::channel();
+        let remote = |command| Message::Remote(command, RemoteReply::new(sender.clone()));
+        send(&mut model, vec![Message::Histogram]);
+        let goto = RemoteCommand::Goto {
+            row: 4,
+            column: Some("category".into()),
+        };
+        send(&mut model, vec![remote(goto)]);
+        assert_eq!(replies.recv().unwrap(), Ok(()));
+        assert!(matches!(model.modus, Modus::TABLE));
+        assert_eq!(selected_column(&model), 3);
+        assert_eq!(selected_value(&model, "category"), "Utilities");
 
-        match self.clipboard.set_text(cell) {
-            Ok(_) => trace!("Copied cell content to clipboard."),
-            Err(e) => trace!("Error copying to clipboard: {:?}", e),
+        let filter = RemoteCommand::Filter {
+            column: None,
+            term: "Tools".into(),
+        };
+        send(&mut model, vec![remote(filter)]);
+        assert_eq!(model.tables.last().unwrap().rows.len(), 2);
+        assert_eq!(replies.recv().unwrap(), Ok(()));
+
+        let command = RemoteCommand::Command {
+            input: ":rename kind".into(),
+        };
+        send(&mut model, vec![remote(command)]);
+        assert!(model.data.iter().any(|c| c.name == "kind"));
+        assert_eq!(replies.recv().unwrap(), Ok(()));
+
+        // Failures are answered with the warning shown
+        let goto = RemoteCommand::Goto {
+            row: 1,
+            column: Some("nope".into()),
+        };
+        send(&mut model, vec![remote(goto)]);
+        assert_eq!(replies.recv().unwrap(), Err("Unknown column: nope".into()));
+        let command = RemoteCommand::Command {
+            input: "foo".into(),
+        };
+        send(&mut model, vec![remote(command)]);
+        assert!(replies.recv().unwrap().is_err());
+    }
+
+    #[test]
+    fn append_streamed_rows() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        writeln!(writer, "{{\"n\": 1, \"level\": \"info\"}}").unwrap();
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        let (stream, frame) = Stream::start(
+            std::io::BufReader::new(reader),
+            Some(3),
+            Batching::new(&TVConfig::default()),
+        )
+        .unwrap();
+        model.load_stream(stream, frame).unwrap();
+        assert_eq!(model.tables[0].rows.len(), 1);
+
+        for n in 2..=5 {
+            writeln!(writer, "{{\"n\": {n}, \"level\": \"warn\"}}").unwrap();
         }
+        drop(writer);
+        while !model.stream.as_ref().unwrap().finished {
+            model.update(None).unwrap();
+        }
+        // Only the latest 3 rows are kept, the cursor followed them to the last one
+        assert_eq!(model.tables[0].rows.as_slice(), [0, 1, 2]);
+        assert_eq!(model.data[0].value(0).as_ref(), "3");
+        assert_eq!(selected_value(&model, "n"), "5");
+        assert_eq!(model.stream.as_ref().unwrap().rows, 5);
     }
 
-    fn move_record_selection_up(&mut self, size: usize) {
-        let record = &mut self.record_view;
-        let table = self.tables.last().unwrap();
-        record.move_selection_up(size, table, &mut self.data, &mut self.uidata);
+    #[test]
+    fn adjust_parsing_in_preview() {
+        let file = TempFile::with("preview.csv", "a;b;c,d\n1;2;3,4\n");
+        let mut model = Model::init(&TVConfig::default(), 120, 40).unwrap();
+        let preview = model.preview_file(file.0.clone()).unwrap().unwrap();
+        assert_eq!(preview.columns.len(), 3);
+        assert_eq!(
+            preview.parsing.as_deref(),
+            Some("semicolon delimited, UTF-8, header row")
+        );
+
+        let preview = model.cycle_preview_delimiter().unwrap();
+        assert!(preview.parsing.unwrap().starts_with("tab delimited"));
+        let preview = model.cycle_preview_delimiter().unwrap();
+        assert_eq!(preview.columns.len(), 1);
+        let preview = model.cycle_preview_delimiter().unwrap();
+        assert_eq!(preview.columns[1].0, "d");
+        let preview = model.toggle_preview_header().unwrap();
+        assert_eq!(preview.columns[0].0, "c1");
+        assert_eq!(preview.rows[0], ["a;b;c", "d"]);
+
+        model.load_previewed_file().unwrap();
+        assert_eq!(model.tables[0].rows.len(), 2);
+        assert!(model.previewed_file.is_none());
     }
 
-    fn move_histogram_selection_up(&mut self, size: usize) {
-        let hist = self.histogram_views.last_mut().unwrap();
-        hist.move_selection_up(
-            size,
-            &mut self.data,
-            self.tables.last().unwrap(),
-            &mut self.uidata,
+    #[test]
+    fn configure_files_by_pattern() {
+        let file = TempFile::with(
+            "files.csv",
+            "id;day;amount\n1;03.02.2024;5\n2;01.02.2024;7\n3;15.01.2024;6\n",
         );
+        let config = TVConfig {
+            files: serde_json::from_str::<config::ConfigFile>(
+                r#"{"files": {"tv-*-files.csv": {"delimiter": ";", "dates": {"day": "%d.%m.%Y"}, "pinned": ["amount"], "sort": "day"}}}"#,
+            )
+            .unwrap()
+            .files,
+            ..TVConfig::default()
+        };
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(file.0.clone()).unwrap();
+
+        let names: Vec<&str> = model.data.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["amount", "id", "day"]);
+        assert_eq!(model.data[2].dtype, DataType::Date);
+        assert_eq!(selected_value(&model, "id"), "3");
+        assert!(!model.dirty);
     }
 
-    fn move_record_selection_down(&mut self, size: usize) {
-        let record = &mut self.record_view;
-        let table = self.tables.last().unwrap();
-        record.move_selection_down(size, table, &mut self.data, &mut self.uidata);
+    #[test]
+    fn jump_to_min_and_max() {
+        let mut model = model();
+        let quantity = model
+            .data
+            .iter()
+            .position(|c| c.name == "quantity")
+            .unwrap();
+        model.select_cell(0, quantity);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "max");
+        assert_eq!(selected_value(&model, "id"), "8");
+        assert_eq!(model.status_message, "Maximum 999 of quantity in row 8");
+
+        // Only the rows of the filtered view count
+        model.filter_column(quantity, "1");
+        model.select_cell(0, quantity);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "min");
+        assert_eq!(selected_value(&model, "id"), "7");
+        assert_eq!(selected_column(&model), quantity);
+
+        model.select_cell(0, 1);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "min");
+        assert_eq!(model.status_message, "name is not numeric!");
     }
 
-    fn move_histogram_selection_down(&mut self, size: usize) {
-        let hist = self.histogram_views.last_mut().unwrap();
-        hist.move_selection_down(
-            size,
-            &mut self.data,
-            self.tables.last().unwrap(),
-            &mut self.uidata,
-        )
+    #[test]
+    fn relative_row_numbers() {
+        let mut model = model();
+        send(&mut model, vec![Message::MoveDown(2)]);
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "relnum");
+        assert!(model.tables[0].show_index);
+        assert_eq!(model.uidata.index.data[..4], ["2", "1", "3", "1"]);
+
+        send(&mut model, vec![Message::MoveDown(1)]);
+        assert_eq!(model.uidata.index.data[..4], ["3", "2", "1", "4"]);
+
+        send(&mut model, vec![Message::EnterCommand]);
+        type_input(&mut model, "relnum");
+        assert_eq!(model.uidata.index.data[..4], ["1", "2", "3", "4"]);
     }
 
-    fn previous_record(&mut self) {
-        let record = &mut self.record_view;
-        let table = self.tables.last().unwrap();
-        record.previous_record(table, &mut self.data, &mut self.uidata);
+    #[test]
+    fn record_view_on_narrow_terminals() {
+        let mut model = model();
+        model.select_cell(3, 1);
+        send(&mut model, vec![Message::Resize(12, 20)]);
+        assert!(matches!(model.modus, Modus::RECORD));
+        assert_eq!(model.record_view.last_record_idx, 3);
+
+        send(&mut model, vec![Message::Resize(100, 20)]);
+        assert!(matches!(model.modus, Modus::TABLE));
+        assert_eq!(selected_value(&model, "id"), "4");
+
+        // Leaving the record view shows the table until the next resize
+        send(&mut model, vec![Message::Resize(12, 20), Message::Exit]);
+        assert!(matches!(model.modus, Modus::TABLE));
+        send(&mut model, vec![Message::Resize(11, 3)]);
+        assert!(matches!(model.modus, Modus::RECORD));
     }
 
-    fn next_record(&mut self) {
-        let record = &mut self.record_view;
-        let table = self.tables.last().unwrap();
-        record.next_record(table, &mut self.data, &mut self.uidata);
+    // Messages of the random message tests, the ones leaving the table like quitting or opening files are left out
+    fn message_strategy() -> impl Strategy<Value = Message> {
+        let messages = vec![
+            Message::MovePageUp,
+            Message::MovePageDown,
+            Message::MoveLeft,
+            Message::MoveRight,
+            Message::MoveEnd,
+            Message::MoveBeginning,
+            Message::MoveToFirstColumn,
+            Message::MoveToLastColumn,
+            Message::MoveColumnLeft,
+            Message::MoveColumnRight,
+            Message::ToggleColumnState,
+            Message::ToggleExpandColumnState,
+            Message::ToggleColumnGroup,
+            Message::ToggleAllColumnGroups,
+            Message::ToggleRowGroup,
+            Message::ToggleAllRowGroups,
+            Message::ToggleIndex,
+            Message::Help,
+            Message::Search,
+            Message::SearchInColumn,
+            Message::Filter,
+            Message::FilterColumns,
+            Message::RenameColumn,
+            Message::EditCell,
+            Message::Histogram,
+            Message::Enter,
+            Message::Exit,
+            Message::SearchNext,
+            Message::SearchPrev,
+            Message::SortAscending,
+            Message::SortDescending,
+            Message::CycleNumberBase,
+            Message::ShowBitfield,
+            Message::Percentiles,
+            Message::NextTab,
+            Message::PreviousTab,
+            Message::NextTable,
+            Message::PreviousTable,
+            Message::ToggleUuidCompaction,
+            Message::ToggleFillDown,
+            Message::ToggleTopValues,
+            Message::ToggleTotals,
+            Message::ToggleCrosshair,
+            Message::ToggleBookmark,
+            Message::NextBookmark,
+            Message::PreviousBookmark,
+            Message::NextNull,
+            Message::PreviousNull,
+            Message::ToggleMark,
+            Message::VisualSelect,
+            Message::Chart,
+            Message::Plot,
+            Message::Paste("日本 a\nb".to_string()),
+            Message::RecordMacro('a'),
+            Message::StopMacro,
+            Message::ReplayMacro('a', 2),
+        ];
+        prop_oneof![
+            3 => key_strategy().prop_map(Message::RawKey),
+            1 => (1..131usize, 1..46usize).prop_map(|(w, h)| Message::Resize(w, h)),
+            1 => (0..15usize).prop_map(Message::MoveDown),
+            1 => (0..15usize).prop_map(Message::MoveUp),
+            5 => proptest::sample::select(messages),
+        ]
+    }
+
+    proptest! {
+        // Random messages, and keys typed into the command line, never panic and
+        // leave the curser on a row and column of the table
+        #[test]
+        fn random_messages_keep_the_curser_in_bounds(messages in prop::collection::vec(message_strategy(), 0..300)) {
+            let mut model = model();
+            for (step, message) in messages.into_iter().enumerate() {
+                let context = format!("step {step}, {message:?}");
+                model.update(Some(message)).expect(&context);
+
+                let table = model.tables.last().unwrap();
+                if !table.rows.is_empty() {
+                    prop_assert!(
+                        table.offset_row + table.curser_row < table.rows.len(),
+                        "{}", context
+                    );
+                }
+                prop_assert!(
+                    table.offset_column + table.curser_column < model.data.len(),
+                    "{}", context
+                );
+                if matches!(model.modus, Modus::TABLE) && !model.uidata.table.is_empty() {
+                    let visible_rows = model.uidata.table[0].data.len();
+                    prop_assert!(model.uidata.selected_row < visible_rows.max(1), "{}", context);
+                }
+                let input = &model.uidata.cmdinput;
+                prop_assert!(input.curser_pos <= input.input.chars().count(), "{}", context);
+            }
+        }
     }
 }
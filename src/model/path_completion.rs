@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::Path;
+
+use super::Model;
+
+// Directory levels searched below the working directory for fuzzy matches
+const MAX_DEPTH: usize = 4;
+// Entries looked at by the fuzzy search, so large trees do not block the ui
+const MAX_ENTRIES: usize = 20_000;
+// Candidates offered for a single completion
+const MAX_CANDIDATES: usize = 50;
+
+// Paths completing the typed one: directories and tabular files starting with it, or if there are none,
+// tabular files below the working directory fuzzily matching it, best first
+pub fn candidates(typed: &str) -> Vec<String> {
+    let typed = shellexpand::tilde(typed).to_string();
+    let prefixed = prefix_matches(&typed);
+    if !prefixed.is_empty() || typed.is_empty() {
+        return prefixed;
+    }
+    fuzzy_matches(&typed)
+}
+
+fn prefix_matches(typed: &str) -> Vec<String> {
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(idx) => typed.split_at(idx + 1),
+        None => ("", typed),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Hidden files only if asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                Some(format!("{dir}{name}/"))
+            } else if Model::is_tabular_file(&path) {
+                Some(format!("{dir}{name}"))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort();
+    matches.truncate(MAX_CANDIDATES);
+    matches
+}
+
+fn fuzzy_matches(pattern: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    collect_files(Path::new("."), 0, &mut 0, &mut files);
+    let pattern = pattern.to_lowercase();
+    let mut scored: Vec<(usize, String)> = files
+        .into_iter()
+        .filter_map(|file| fuzzy_score(&pattern, &file.to_lowercase()).map(|score| (score, file)))
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(_, file)| file)
+        .collect()
+}
+
+// Tabular files below the directory, relative to the working directory
+fn collect_files(dir: &Path, depth: usize, visited: &mut usize, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        *visited += 1;
+        if *visited > MAX_ENTRIES {
+            return;
+        }
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                collect_files(&path, depth + 1, visited, files);
+            }
+        } else if Model::is_tabular_file(&path) {
+            let path = path.strip_prefix(".").unwrap_or(&path);
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+// If the characters of the pattern appear in order in the text, the number of characters spanned by
+// them, so compact matches rank first
+fn fuzzy_score(pattern: &str, text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    let mut start = None;
+    let mut end = 0;
+    for p in pattern.chars() {
+        let (idx, _) = chars.find(|(_, c)| *c == p)?;
+        start.get_or_insert(idx);
+        end = idx;
+    }
+    Some(end - start.unwrap_or(0) + text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_paths() {
+        assert_eq!(
+            candidates("tests/fix"),
+            ["tests/fixtures/".to_string()].to_vec()
+        );
+        assert_eq!(candidates("tests/fixtures/testdata_0").len(), 5);
+        // Not a prefix of any path, so files are matched fuzzily
+        assert_eq!(candidates("fixt05")[0], "tests/fixtures/testdata_05.csv");
+
+        assert!(fuzzy_score("tdc", "testdata.csv").is_some());
+        assert!(fuzzy_score("tdx", "testdata.csv").is_none());
+        assert!(fuzzy_score("dat", "data.csv") < fuzzy_score("dat", "d_a_t.csv"));
+    }
+}
@@ -0,0 +1,161 @@
+use polars::prelude::{DataType, NamedFrom, Series, TimeUnit};
+
+use super::ui::PlotData;
+
+// Zooming in shows this part of the previous window, zooming out the inverse
+const ZOOM_FACTOR: f64 = 0.5;
+// Part of the window a pan moves it by
+const PAN_FACTOR: f64 = 0.25;
+
+// Line chart of a numeric column over a datetime column, or over the row order
+pub struct PlotView {
+    title: String,
+    points: Vec<(f64, f64)>, // Sorted by x, datetimes are milliseconds since the epoch
+    time_axis: bool,
+    window: (f64, f64), // Shown range of x
+}
+
+impl PlotView {
+    // Returns None without points to plot
+    pub fn new(title: String, mut points: Vec<(f64, f64)>, time_axis: bool) -> Option<Self> {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let window = (points.first()?.0, points.last()?.0);
+        Some(PlotView {
+            title,
+            points,
+            time_axis,
+            window,
+        })
+    }
+
+    fn full_range(&self) -> (f64, f64) {
+        (self.points[0].0, self.points[self.points.len() - 1].0)
+    }
+
+    pub fn zoom(&mut self, zoom_in: bool) {
+        let (from, to) = self.window;
+        let center = (from + to) / 2.0;
+        let factor = if zoom_in {
+            ZOOM_FACTOR
+        } else {
+            1.0 / ZOOM_FACTOR
+        };
+        let half = (to - from) * factor / 2.0;
+        self.set_window(center - half, center + half);
+    }
+
+    pub fn pan(&mut self, step: i32) {
+        let (from, to) = self.window;
+        let shift = (to - from) * PAN_FACTOR * step as f64;
+        self.set_window(from + shift, to + shift);
+    }
+
+    // Keep the window inside the plotted range, without shrinking it while panning
+    fn set_window(&mut self, from: f64, to: f64) {
+        let (min, max) = self.full_range();
+        let width = (to - from).min(max - min);
+        let from = from.max(min).min(max - width);
+        self.window = (from, from + width);
+    }
+
+    // Points of the window, reduced to the min and max of every terminal column
+    pub fn update_uidata(&self, width: usize) -> PlotData {
+        let (from, to) = self.window;
+        let begin = self.points.partition_point(|p| p.0 < from);
+        let end = self.points.partition_point(|p| p.0 <= to);
+        let visible = &self.points[begin..end];
+
+        let buckets = width.max(1);
+        let points: Vec<(f64, f64)> = if visible.len() <= 2 * buckets {
+            visible.to_vec()
+        } else {
+            visible
+                .chunks(visible.len().div_ceil(buckets))
+                .flat_map(|chunk| {
+                    let min = chunk.iter().min_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+                    let max = chunk.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+                    // Keep the order of x, so the line does not jump back
+                    if min.0 <= max.0 {
+                        [*min, *max]
+                    } else {
+                        [*max, *min]
+                    }
+                })
+                .collect()
+        };
+
+        let (mut y_min, mut y_max) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+            (min.min(p.1), max.max(p.1))
+        });
+        if points.is_empty() {
+            (y_min, y_max) = (0.0, 1.0);
+        } else if y_min == y_max {
+            (y_min, y_max) = (y_min - 1.0, y_max + 1.0);
+        }
+
+        let x_ticks = [from, (from + to) / 2.0, to];
+        let x_labels = if self.time_axis {
+            format_times(&x_ticks)
+        } else {
+            x_ticks.iter().map(|x| format!("{x:.0}")).collect()
+        };
+        let y_labels = [y_min, (y_min + y_max) / 2.0, y_max]
+            .iter()
+            .map(|y| format!("{y:.2}"))
+            .collect();
+
+        let (min, max) = self.full_range();
+        let zoom = if to > from {
+            (max - min) / (to - from)
+        } else {
+            1.0
+        };
+        PlotData {
+            title: format!("{} ({} points, zoom {zoom:.0}x)", self.title, visible.len()),
+            points,
+            x_bounds: [from, to],
+            y_bounds: [y_min, y_max],
+            x_labels,
+            y_labels,
+        }
+    }
+}
+
+// Datetime strings of milliseconds since the epoch
+fn format_times(values: &[f64]) -> Vec<String> {
+    let millis: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+    Series::new("time".into(), millis)
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .and_then(|s| s.cast(&DataType::String))
+        .ok()
+        .and_then(|s| {
+            s.str().ok().map(|s| {
+                s.into_iter()
+                    .map(|v| v.unwrap_or("").trim_end_matches(".000").to_string())
+                    .collect()
+            })
+        })
+        .unwrap_or_else(|| values.iter().map(|v| v.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_pan_and_downsample() {
+        let points = (0..1000).map(|x| (x as f64, (x % 10) as f64)).collect();
+        let mut view = PlotView::new("y over x".into(), points, false).unwrap();
+        assert_eq!(view.update_uidata(50).points.len(), 100);
+        view.zoom(true);
+        assert_eq!(view.window, (249.75, 749.25));
+        view.pan(-4);
+        assert_eq!(view.window, (0.0, 499.5));
+        view.zoom(false);
+        view.zoom(false);
+        assert_eq!(view.window, (0.0, 999.0));
+        let data = view.update_uidata(1000);
+        assert_eq!(data.points.len(), 1000);
+        assert_eq!(data.y_bounds, [0.0, 9.0]);
+    }
+}
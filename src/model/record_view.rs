@@ -64,7 +64,7 @@ impl RecordView {
             .map(|h| h.len())
             .max()
             .unwrap_or(0);
-        record.row_width = record.width - record.header_width;
+        record.row_width = record.width.saturating_sub(record.header_width);
         record.update(table.curser_row + table.offset_row, table, data, uidata);
         record
     }
@@ -123,8 +123,10 @@ impl RecordView {
                 // At the bottom of the table, need to shift table down
                 self.curser_offset =
                     std::cmp::min(self.curser_offset + size, self.row_data.len() - 1);
-                self.curser_row =
-                    std::cmp::min(self.height - 1, self.row_data.len() - self.curser_offset);
+                self.curser_row = std::cmp::min(
+                    self.height - 1,
+                    self.row_data.len() - self.curser_offset - 1,
+                );
             }
             self.update(self.last_record_idx, table, data, uidata);
         }
@@ -140,7 +142,7 @@ impl RecordView {
         if self.last_record_idx != record_idx {
             self.row_data = data
                 .iter()
-                .map(|c| c.data[table.rows[record_idx]].clone())
+                .map(|c| c.full_value(table.rows[record_idx]))
                 .collect::<Vec<String>>();
         }
 
@@ -158,12 +160,14 @@ impl RecordView {
             name: "Headers".to_string(),
             data: self.header_data[rbegin..rend].to_vec(),
             width: self.header_width,
+            styles: Vec::new(),
         };
 
         self.row_view = ColumnView {
             name: "Values".to_string(),
             data: self.row_data[rbegin..rend].to_vec(),
             width: self.row_width,
+            styles: Vec::new(),
         };
         self.last_record_idx = record_idx;
         self.last_update = Instant::now();
@@ -177,6 +181,9 @@ impl RecordView {
         uidata.selected_column = 1;
         uidata.nrows = table.rows.len();
         uidata.abs_selected_row = self.last_record_idx; // In the record view, show which record we are looking at instead of line in record view.
+        uidata.highlighted_rows.clear();
+        uidata.bookmarked_rows.clear();
+        uidata.marked_rows.clear();
         uidata.last_update = Instant::now();
     }
 }
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use super::Column;
+
+// Widest a section header in the index column gets, longer keys are cut off
+const MAX_LABEL_WIDTH: usize = 30;
+
+// Rows with the same value in a column, shown as a section that can be folded to its first row
+struct RowGroup {
+    key: String,
+    rows: Vec<usize>, // Data indices
+    folded: bool,
+}
+
+// Rows of a table grouped by the values of a column, in the order the values first appear
+pub struct RowGroups {
+    pub column: String,
+    groups: Vec<RowGroup>,
+}
+
+impl RowGroups {
+    pub fn new(column: &Column, rows: &[usize]) -> Self {
+        let mut groups: Vec<RowGroup> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for &ridx in rows {
+            let key = column.display_value(ridx);
+            let pos = *positions.entry(key.clone()).or_insert_with(|| {
+                groups.push(RowGroup {
+                    key,
+                    rows: Vec::new(),
+                    folded: false,
+                });
+                groups.len() - 1
+            });
+            groups[pos].rows.push(ridx);
+        }
+        RowGroups {
+            column: column.name.clone(),
+            groups,
+        }
+    }
+
+    // Group the rows again, e.g. after they were sorted, keeping folded groups folded
+    pub fn regroup(&mut self, column: &Column, rows: &[usize]) {
+        let mut regrouped = RowGroups::new(column, rows);
+        for group in regrouped.groups.iter_mut() {
+            group.folded = self.groups.iter().any(|g| g.folded && g.key == group.key);
+        }
+        *self = regrouped;
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    // Data indices of all rows, also of folded groups
+    pub fn all_rows(&self) -> Vec<usize> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.rows.iter().copied())
+            .collect()
+    }
+
+    // Data indices of the rows shown, folded groups only show their first row
+    pub fn rows(&self) -> Vec<usize> {
+        self.groups
+            .iter()
+            .flat_map(|g| match g.folded {
+                true => &g.rows[..1],
+                false => &g.rows[..],
+            })
+            .copied()
+            .collect()
+    }
+
+    // Section header of the shown row at the position, empty for rows that do not start a group
+    pub fn label(&self, pos: usize) -> String {
+        let mut start = 0;
+        for group in self.groups.iter() {
+            if start == pos {
+                let marker = if group.folded { '▸' } else { '▾' };
+                let label = format!("{marker} {} ({})", group.key, group.rows.len());
+                return label.chars().take(MAX_LABEL_WIDTH).collect();
+            }
+            start += group.shown();
+            if start > pos {
+                break;
+            }
+        }
+        String::new()
+    }
+
+    pub fn label_width(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|g| g.key.chars().count() + g.rows.len().to_string().len() + 5)
+            .max()
+            .unwrap_or(0)
+            .min(MAX_LABEL_WIDTH)
+    }
+
+    // Fold or unfold the group of the shown row at the position.
+    // Returns the key of the group, if it got folded and the position of its first row.
+    pub fn toggle(&mut self, pos: usize) -> Option<(String, bool, usize)> {
+        let mut start = 0;
+        for group in self.groups.iter_mut() {
+            if pos < start + group.shown() {
+                group.folded = !group.folded;
+                return Some((group.key.clone(), group.folded, start));
+            }
+            start += group.shown();
+        }
+        None
+    }
+
+    // Fold all groups, or unfold them if any is folded. Returns if they got folded.
+    pub fn toggle_all(&mut self) -> bool {
+        let fold = !self.groups.iter().any(|g| g.folded);
+        for group in self.groups.iter_mut() {
+            group.folded = fold;
+        }
+        fold
+    }
+}
+
+impl RowGroup {
+    fn shown(&self) -> usize {
+        if self.folded { 1 } else { self.rows.len() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::{NamedFrom, Series};
+
+    use super::*;
+
+    #[test]
+    fn fold_sections() {
+        let column = Column::new(0, Series::new("request".into(), ["a", "b", "a", "c", "b"]));
+        let mut groups = RowGroups::new(&column, &[0, 1, 2, 3, 4]);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups.rows(), vec![0, 2, 1, 4, 3]);
+        assert_eq!(groups.label(0), "▾ a (2)");
+        assert_eq!(groups.label(1), "");
+        assert_eq!(groups.label(2), "▾ b (2)");
+
+        assert_eq!(groups.toggle(1), Some(("a".to_string(), true, 0)));
+        assert_eq!(groups.rows(), vec![0, 1, 4, 3]);
+        assert_eq!(groups.label(1), "▾ b (2)");
+        assert_eq!(groups.label(0), "▸ a (2)");
+
+        // Sorting keeps the fold of a group
+        groups.regroup(&column, &[4, 3, 2, 1, 0]);
+        assert_eq!(groups.rows(), vec![4, 1, 3, 2]);
+        assert!(!groups.toggle_all());
+        assert_eq!(groups.all_rows(), vec![4, 1, 3, 2, 0]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+// Small xorshift generator, good enough to pick rows, and reproducible for a given seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Uniform enough for the row counts of a table
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// A seed that differs between calls, shown to the user so a sample can be drawn again
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish() % 1_000_000
+}
+
+// Positions 0..len in random order
+pub fn shuffle(len: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    let mut positions: Vec<usize> = (0..len).collect();
+    for idx in (1..len).rev() {
+        positions.swap(idx, rng.below(idx + 1));
+    }
+    positions
+}
+
+// `n` random positions of 0..len, in ascending order
+pub fn sample(len: usize, n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    let mut positions: Vec<usize> = (0..len).collect();
+    let n = n.min(len);
+    for idx in 0..n {
+        positions.swap(idx, idx + rng.below(len - idx));
+    }
+    positions.truncate(n);
+    positions.sort_unstable();
+    positions
+}
+
+// `n` random items in the order they came, holding no more than `n` of them at a time
+pub fn reservoir<T>(items: impl Iterator<Item = T>, n: usize, seed: u64) -> Vec<T> {
+    let mut rng = Rng::new(seed);
+    let mut kept: Vec<(usize, T)> = Vec::with_capacity(n);
+    for (idx, item) in items.enumerate() {
+        if kept.len() < n {
+            kept.push((idx, item));
+            continue;
+        }
+        let pos = rng.below(idx + 1);
+        if pos < n {
+            kept[pos] = (idx, item);
+        }
+    }
+    kept.sort_unstable_by_key(|&(idx, _)| idx);
+    kept.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible_samples() {
+        let sampled = sample(1000, 10, 42);
+        assert_eq!(sampled.len(), 10);
+        assert!(sampled.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(sampled, sample(1000, 10, 42));
+        assert_ne!(sampled, sample(1000, 10, 43));
+        assert_eq!(sample(5, 10, 42), [0, 1, 2, 3, 4]);
+
+        let mut shuffled = shuffle(100, 7);
+        assert_ne!(shuffled, (0..100).collect::<Vec<_>>());
+        shuffled.sort_unstable();
+        assert_eq!(shuffled, (0..100).collect::<Vec<_>>());
+
+        let kept = reservoir(0..10_000, 20, 42);
+        assert_eq!(kept.len(), 20);
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+        assert!(kept.last().unwrap() > &5000);
+        assert_eq!(reservoir(0..3, 20, 42), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use polars::prelude::*;
+
+use crate::domain::TVError;
+
+// Name of the column telling how a row of the delta changed
+const CHANGE_COLUMN: &str = "Δ";
+
+// Rows that differ between two snapshots of a file
+pub struct Delta {
+    pub frame: DataFrame, // Added and changed rows of the new snapshot, then removed rows of the old one
+    pub key: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.added + self.removed + self.changed == 0
+    }
+}
+
+// Values of the given columns of every row, as strings
+fn row_values(frame: &DataFrame, names: &[&str]) -> Result<Vec<Vec<Option<String>>>, TVError> {
+    let columns = names
+        .iter()
+        .map(|&name| frame.column(name)?.cast(&DataType::String))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let columns = columns
+        .iter()
+        .map(|c| c.str().cloned())
+        .collect::<PolarsResult<Vec<StringChunked>>>()?;
+    Ok((0..frame.height())
+        .map(|ridx| {
+            columns
+                .iter()
+                .map(|c| c.get(ridx).map(str::to_string))
+                .collect()
+        })
+        .collect())
+}
+
+fn is_unique_key(frame: &DataFrame, key: &str) -> bool {
+    frame
+        .column(key)
+        .is_ok_and(|c| c.null_count() == 0 && c.n_unique().is_ok_and(|n| n == frame.height()))
+}
+
+// Compare the snapshots by the key column. Without a key the first column is used if it is unique
+// in both, otherwise rows are compared as a whole and can only be added or removed.
+pub fn diff(old: &DataFrame, new: &DataFrame, key: Option<&str>) -> Result<Delta, TVError> {
+    let common: Vec<&str> = new
+        .get_column_names()
+        .into_iter()
+        .filter(|name| old.schema().contains(name))
+        .map(|name| name.as_str())
+        .collect();
+    let key = match key {
+        Some(key) if !common.contains(&key) => {
+            return Err(TVError::LoadingFailed(format!(
+                "Key column {key} is not in both snapshots!"
+            )));
+        }
+        Some(key) => Some(key.to_string()),
+        None => common
+            .first()
+            .filter(|&&first| is_unique_key(old, first) && is_unique_key(new, first))
+            .map(|first| first.to_string()),
+    };
+
+    let old_rows = row_values(old, &common)?;
+    let new_rows = row_values(new, &common)?;
+    let mut changes: Vec<(usize, String)> = Vec::new(); // Row of the new snapshot and its change
+    let mut removed: Vec<usize> = Vec::new();
+    let mut nchanged = 0;
+
+    match &key {
+        Some(key) => {
+            let kidx = common.iter().position(|name| name == key).unwrap();
+            let old_keys: HashMap<&Option<String>, usize> = old_rows
+                .iter()
+                .enumerate()
+                .map(|(ridx, row)| (&row[kidx], ridx))
+                .collect();
+            let new_keys: HashSet<&Option<String>> =
+                new_rows.iter().map(|row| &row[kidx]).collect();
+            for (ridx, row) in new_rows.iter().enumerate() {
+                match old_keys.get(&row[kidx]) {
+                    None => changes.push((ridx, "added".to_string())),
+                    Some(&oidx) if old_rows[oidx] != *row => {
+                        let columns: Vec<&str> = common
+                            .iter()
+                            .zip(row.iter().zip(&old_rows[oidx]))
+                            .filter(|(_, (new, old))| new != old)
+                            .map(|(name, _)| *name)
+                            .collect();
+                        changes.push((ridx, format!("changed: {}", columns.join(", "))));
+                        nchanged += 1;
+                    }
+                    Some(_) => (),
+                }
+            }
+            removed.extend(
+                (0..old_rows.len()).filter(|&ridx| !new_keys.contains(&old_rows[ridx][kidx])),
+            );
+        }
+        None => {
+            // Count the rows, so duplicates are matched one by one
+            let mut counts: HashMap<&Vec<Option<String>>, isize> = HashMap::new();
+            for row in old_rows.iter() {
+                *counts.entry(row).or_default() += 1;
+            }
+            for (ridx, row) in new_rows.iter().enumerate() {
+                let count = counts.entry(row).or_default();
+                if *count > 0 {
+                    *count -= 1;
+                } else {
+                    changes.push((ridx, "added".to_string()));
+                }
+            }
+            for (ridx, row) in old_rows.iter().enumerate().rev() {
+                let count = counts.entry(row).or_default();
+                if *count > 0 {
+                    *count -= 1;
+                    removed.push(ridx);
+                }
+            }
+            removed.reverse();
+        }
+    }
+
+    let take = |frame: &DataFrame, rows: &[usize]| {
+        frame.take(&IdxCa::from_vec(
+            "idx".into(),
+            rows.iter().map(|&r| r as IdxSize).collect(),
+        ))
+    };
+    let mut frame = take(
+        new,
+        &changes.iter().map(|(ridx, _)| *ridx).collect::<Vec<_>>(),
+    )?;
+    let mut labels: Vec<String> = changes.into_iter().map(|(_, change)| change).collect();
+
+    // Removed rows get the columns of the new snapshot, missing ones are null
+    let old_removed = take(old, &removed)?;
+    let columns = new
+        .get_columns()
+        .iter()
+        .map(|c| match old_removed.column(c.name()) {
+            Ok(old) => old.cast(c.dtype()),
+            Err(_) => Ok(Column::full_null(
+                c.name().clone(),
+                removed.len(),
+                c.dtype(),
+            )),
+        })
+        .collect::<PolarsResult<Vec<Column>>>()?;
+    frame.vstack_mut(&DataFrame::new(columns)?)?;
+    labels.extend(removed.iter().map(|_| "removed".to_string()));
+
+    let added = labels.iter().filter(|l| *l == "added").count();
+    frame.insert_column(0, Column::new(CHANGE_COLUMN.into(), labels))?;
+    Ok(Delta {
+        frame,
+        key,
+        added,
+        removed: removed.len(),
+        changed: nchanged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_snapshots() {
+        let old = df!("id" => [1, 2, 3], "qty" => [10, 20, 30]).unwrap();
+        let new = df!("id" => [1, 3, 4], "qty" => [10, 31, 40]).unwrap();
+        let delta = diff(&old, &new, None).unwrap();
+        assert_eq!(delta.key.as_deref(), Some("id"));
+        assert_eq!((delta.added, delta.removed, delta.changed), (1, 1, 1));
+        let changes: Vec<&str> = delta
+            .frame
+            .column(CHANGE_COLUMN)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(changes, ["changed: qty", "added", "removed"]);
+        let ids: Vec<i32> = delta
+            .frame
+            .column("id")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(ids, [3, 4, 2]);
+
+        // Without a unique key whole rows are compared
+        let old = df!("kind" => ["a", "a", "b"]).unwrap();
+        let new = df!("kind" => ["a", "b", "b"]).unwrap();
+        let delta = diff(&old, &new, None).unwrap();
+        assert_eq!(delta.key, None);
+        assert_eq!((delta.added, delta.removed, delta.changed), (1, 1, 0));
+    }
+}
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use super::lenient_csv::split_records;
+
+// Bytes read from the start of a file to guess its format
+const SNIFF_BYTES: u64 = 16 * 1024;
+// Lines compared to find the delimiter
+const SNIFF_LINES: usize = 20;
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+// Byte order mark a text file starts with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Bom {
+    pub fn detect(bytes: &[u8]) -> Option<Bom> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some(Bom::Utf8),
+            [0xFF, 0xFE, ..] => Some(Bom::Utf16Le),
+            [0xFE, 0xFF, ..] => Some(Bom::Utf16Be),
+            _ => None,
+        }
+    }
+
+    pub fn read(path: &Path) -> Option<Bom> {
+        let mut bytes = [0; 3];
+        let n = File::open(path).ok()?.read(&mut bytes).ok()?;
+        Bom::detect(&bytes[..n])
+    }
+
+    fn len(self) -> usize {
+        match self {
+            Bom::Utf8 => 3,
+            Bom::Utf16Le | Bom::Utf16Be => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Bom::Utf8 => "UTF-8",
+            Bom::Utf16Le => "UTF-16LE",
+            Bom::Utf16Be => "UTF-16BE",
+        }
+    }
+}
+
+// Text without the byte order mark, UTF-16 is transcoded and invalid characters are replaced
+pub fn decode(bytes: &[u8]) -> String {
+    let bom = Bom::detect(bytes);
+    let text = &bytes[bom.map_or(0, Bom::len)..];
+    let utf16 = |to_u16: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = text
+            .chunks_exact(2)
+            .map(|pair| to_u16([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bom {
+        Some(Bom::Utf16Le) => utf16(u16::from_le_bytes),
+        Some(Bom::Utf16Be) => utf16(u16::from_be_bytes),
+        _ => String::from_utf8_lossy(text).to_string(),
+    }
+}
+
+// Lines of the file without their line break, decoded like `decode` while they are read
+pub fn decoded_lines(path: &Path) -> io::Result<Box<dyn Iterator<Item = String>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let bom = Bom::detect(reader.fill_buf()?);
+    reader.consume(bom.map_or(0, Bom::len));
+    let to_u16: fn([u8; 2]) -> u16 = match bom {
+        Some(Bom::Utf16Le) => u16::from_le_bytes,
+        Some(Bom::Utf16Be) => u16::from_be_bytes,
+        _ => {
+            let lines = reader
+                .split(b'\n')
+                .map_while(Result::ok)
+                .map(|line| String::from_utf8_lossy(&line).into_owned());
+            return Ok(Box::new(lines));
+        }
+    };
+    let mut bytes = reader.bytes().map_while(Result::ok);
+    let units = std::iter::from_fn(move || Some(to_u16([bytes.next()?, bytes.next()?])));
+    let mut chars = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .peekable();
+    let lines = std::iter::from_fn(move || {
+        chars.peek()?;
+        Some(chars.by_ref().take_while(|&c| c != '\n').collect())
+    });
+    Ok(Box::new(lines))
+}
+
+// Start of the file, without a line that was cut off
+fn sample(path: &Path) -> String {
+    sample_with_size(path).0
+}
+
+// Also the bytes of the file the start was decoded from
+fn sample_with_size(path: &Path) -> (String, usize) {
+    let mut bytes = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(SNIFF_BYTES).read_to_end(&mut bytes);
+    }
+    let mut content = decode(&bytes);
+    if bytes.len() as u64 == SNIFF_BYTES
+        && let Some(end) = content.rfind('\n')
+    {
+        content.truncate(end);
+    }
+    (content, bytes.len())
+}
+
+// Guess the delimiter, the candidate that appears equally often in most lines wins
+pub fn delimiter(path: &Path) -> char {
+    let content = sample(path);
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+    DELIMITERS
+        .iter()
+        .filter_map(|&delimiter| {
+            let counts: Vec<usize> = lines
+                .iter()
+                .map(|line| count_unquoted(line, delimiter))
+                .collect();
+            let first = *counts.first()?;
+            let consistent = counts.iter().filter(|&&c| c == first).count();
+            (first > 0).then_some((consistent, first, delimiter))
+        })
+        // Earlier candidates win ties
+        .rev()
+        .max_by_key(|&(consistent, count, _)| (consistent, count))
+        .map(|(_, _, delimiter)| delimiter)
+        .unwrap_or(',')
+}
+
+fn count_unquoted(line: &str, delimiter: char) -> usize {
+    let mut in_quotes = false;
+    line.chars()
+        .filter(|&c| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+            !in_quotes && c == delimiter
+        })
+        .count()
+}
+
+// Number of lines, estimated from the start of the file if it is not read completely
+pub fn estimate_lines(path: &Path, file_size: u64) -> (usize, bool) {
+    let (content, size) = sample_with_size(path);
+    let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
+    if file_size <= SNIFF_BYTES {
+        return (lines, true);
+    }
+    let estimate = file_size as f64 * lines as f64 / size.max(1) as f64;
+    (estimate.round() as usize, false)
+}
+
+// Candidate after the given delimiter, to try them one after the other
+pub fn next_delimiter(delimiter: char) -> char {
+    let idx = DELIMITERS.iter().position(|&d| d == delimiter);
+    DELIMITERS[idx.map_or(0, |idx| (idx + 1) % DELIMITERS.len())]
+}
+
+pub fn delimiter_name(delimiter: char) -> String {
+    match delimiter {
+        ',' => "comma".to_string(),
+        ';' => "semicolon".to_string(),
+        '\t' => "tab".to_string(),
+        '|' => "pipe".to_string(),
+        other => format!("'{other}'"),
+    }
+}
+
+// Fields of the first row, as they are written in the file
+pub fn header(path: &Path, delimiter: char) -> Option<Vec<String>> {
+    let content = sample(path);
+    let lines: Vec<&str> = content.lines().collect();
+    split_records(&lines, delimiter)
+        .into_iter()
+        .next()?
+        .fields
+        .ok()
+}
+
+// Guess if the first row is a header. Every column votes: numeric columns with a non numeric
+// first value, and columns of equally long values with a first value of another length are
+// a sign of a header. Without votes a header is assumed.
+pub fn has_header(path: &Path, delimiter: char) -> bool {
+    let content = sample(path);
+    let lines: Vec<&str> = content.lines().collect();
+    let records: Vec<Vec<String>> = split_records(&lines, delimiter)
+        .into_iter()
+        .filter_map(|r| r.fields.ok())
+        .collect();
+    let Some((first, rows)) = records.split_first() else {
+        return true;
+    };
+    if rows.is_empty() {
+        return true;
+    }
+
+    let mut votes = 0;
+    for (cidx, name) in first.iter().enumerate() {
+        let values: Vec<&str> = rows
+            .iter()
+            .filter_map(|row| row.get(cidx))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        let is_number = |v: &str| v.parse::<f64>().is_ok();
+        if values.iter().all(|v| is_number(v)) {
+            votes += if is_number(name.trim()) { -1 } else { 1 };
+        } else {
+            let len = values[0].chars().count();
+            if values.iter().all(|v| v.chars().count() == len) {
+                votes += if name.trim().chars().count() == len {
+                    -1
+                } else {
+                    1
+                };
+            }
+        }
+    }
+    votes >= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_lines_while_reading() {
+        let path = std::env::temp_dir().join(format!("tv-sniff-{}-16.csv", std::process::id()));
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("id;näme\r\n1;ä\n".encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(&path, &bytes).unwrap();
+        let lines: Vec<String> = decoded_lines(&path).unwrap().collect();
+        std::fs::write(&path, b"\xEF\xBB\xBFid\n\xFF\n").unwrap();
+        let utf8: Vec<String> = decoded_lines(&path).unwrap().collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines, ["id;näme\r", "1;ä"]);
+        assert_eq!(utf8, ["id", "\u{FFFD}"]);
+    }
+
+    #[test]
+    fn detect_header() {
+        assert!(has_header(Path::new("tests/fixtures/testdata_01.csv"), ','));
+        let path = std::env::temp_dir().join(format!("tv-sniff-{}.csv", std::process::id()));
+        std::fs::write(&path, "1,foo,2.5\n2,bar,3.5\n3,baz,4.5\n").unwrap();
+        let detected = has_header(&path, ',');
+        std::fs::remove_file(&path).unwrap();
+        assert!(!detected);
+    }
+
+    #[test]
+    fn detect_delimiter() {
+        assert_eq!(delimiter(Path::new("tests/fixtures/testdata_01.csv")), ',');
+        let path = std::env::temp_dir().join(format!("tv-delimiter-{}.csv", std::process::id()));
+        std::fs::write(&path, "a;b;c\n1,5;\"x;y\";2\n3;4;5\n").unwrap();
+        let detected = delimiter(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(detected, ';');
+    }
+
+    #[test]
+    fn decode_byte_order_marks() {
+        assert_eq!(decode(b"\xEF\xBB\xBFname,qty"), "name,qty");
+        let utf16: Vec<u8> = "\u{FEFF}näme;qty"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(Bom::detect(&utf16), Some(Bom::Utf16Le));
+        assert_eq!(decode(&utf16), "näme;qty");
+        assert_eq!(decode(b"plain"), "plain");
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::histogram_view::Histogram;
+use crate::domain::TVError;
+
+// Histograms with more values are not cached, reading them would take longer than counting
+const MAX_CACHED_VALUES: usize = 10_000;
+
+// Widths, footer stats and histograms of the columns of a file, computed over all rows.
+// Stored in the cache directory and used as long as the file keeps its size and modification time.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatsCache {
+    size: u64,
+    modified: (u64, u32), // Seconds and nanoseconds since the epoch
+    pub widths: HashMap<String, usize>,
+    stats: HashMap<String, String>, // By column name and dtype, e.g. `price:f64`
+    histograms: HashMap<String, (Vec<usize>, Vec<String>)>, // By column name, dtype and bins, e.g. `price:f64#20`
+    #[serde(skip)]
+    path: PathBuf,                        // The cache file
+}
+
+impl StatsCache {
+    // Cached stats of the file, empty if it changed since they were stored. None if the file can not be cached.
+    pub fn load(file: &Path) -> Option<Self> {
+        let (size, modified) = fingerprint(file)?;
+        let path = cache_path(file)?;
+        let cached = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<StatsCache>(&content).ok())
+            .filter(|cache| cache.size == size && cache.modified == modified);
+        if cached.is_some() {
+            debug!("Using cached stats of {file:?} from {path:?}");
+        }
+        Some(StatsCache {
+            path,
+            ..cached.unwrap_or(StatsCache {
+                size,
+                modified,
+                ..Default::default()
+            })
+        })
+    }
+
+    pub fn store(&self) -> Result<(), TVError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(self)
+            .map_err(|e| TVError::LoadingFailed(format!("Storing the stats cache failed: {e}")))?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    pub fn stats(&self, column: &str, dtype: &str) -> Option<&String> {
+        self.stats.get(&format!("{column}:{dtype}"))
+    }
+
+    pub fn set_stats(&mut self, column: &str, dtype: &str, stats: String) {
+        self.stats.insert(format!("{column}:{dtype}"), stats);
+    }
+
+    pub fn has_histogram(&self, column: &str, dtype: &str, bins: usize) -> bool {
+        self.histograms
+            .contains_key(&format!("{column}:{dtype}#{bins}"))
+    }
+
+    pub fn histogram(&self, column: &str, dtype: &str, bins: usize) -> Option<Histogram> {
+        let (counts, values) = self.histograms.get(&format!("{column}:{dtype}#{bins}"))?;
+        Some((
+            counts.clone(),
+            values.iter().map(|v| Arc::from(v.as_str())).collect(),
+        ))
+    }
+
+    // Returns false if the histogram is too large to be cached
+    pub fn set_histogram(
+        &mut self,
+        column: &str,
+        dtype: &str,
+        bins: usize,
+        histogram: &Histogram,
+    ) -> bool {
+        if histogram.1.len() > MAX_CACHED_VALUES {
+            return false;
+        }
+        let values = histogram.1.iter().map(|v| v.to_string()).collect();
+        self.histograms.insert(
+            format!("{column}:{dtype}#{bins}"),
+            (histogram.0.clone(), values),
+        );
+        true
+    }
+}
+
+// Size and modification time of the file
+fn fingerprint(file: &Path) -> Option<(u64, (u64, u32))> {
+    let metadata = fs::metadata(file).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((
+        metadata.len(),
+        (modified.as_secs(), modified.subsec_nanos()),
+    ))
+}
+
+// `$XDG_CACHE_HOME/tv/<hash of the path>.json`, or below `~/.cache` without it
+fn cache_path(file: &Path) -> Option<PathBuf> {
+    let file = fs::canonicalize(file).ok()?;
+    let dir = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(shellexpand::tilde("~/.cache").to_string()),
+    };
+    if !dir.is_absolute() {
+        warn!("Ignoring the relative cache directory {dir:?}");
+        return None;
+    }
+    // FNV-1a, stable across builds unlike the hasher of the standard library
+    let hash = file
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    Some(dir.join("tv").join(format!("{hash:016x}.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidate_changed_files() {
+        let dir = std::env::temp_dir().join(format!("tv-cache-{}", std::process::id()));
+        let file = dir.join("data.csv");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "a,b\n1,2\n").unwrap();
+        let mut cache = StatsCache::load(&file).unwrap();
+        cache.path = dir.join("cache.json");
+        cache.set_stats("a", "i64", "min 1".into());
+        let histogram = (vec![1], vec![Arc::from("1")]);
+        assert!(cache.set_histogram("a", "i64", 20, &histogram));
+        cache.store().unwrap();
+
+        let cached: StatsCache =
+            serde_json::from_str(&fs::read_to_string(&cache.path).unwrap()).unwrap();
+        assert_eq!(cached.stats.len(), 1);
+        assert_eq!(fingerprint(&file), Some((cached.size, cached.modified)));
+        assert_eq!(cache.histogram("a", "i64", 20), Some(histogram));
+        assert_eq!(cache.histogram("a", "i64", 10), None);
+
+        // A different size makes the cached stats stale
+        fs::write(&file, "a,b\n1,2\n3,4\n").unwrap();
+        let changed = fingerprint(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_ne!(changed, (cached.size, cached.modified));
+    }
+}
@@ -0,0 +1,325 @@
+use std::io::{BufRead, Cursor};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use polars::prelude::*;
+
+use crate::domain::{Event, TVConfig, TVError};
+
+// Records read since the last batch are shown after the interval, or once there are this many
+#[derive(Debug, Clone, Copy)]
+pub struct Batching {
+    interval: Duration,
+    rows: usize,
+}
+
+impl Batching {
+    pub fn new(config: &TVConfig) -> Self {
+        Batching {
+            interval: Duration::from_millis(config.stream_interval as u64),
+            rows: config.stream_batch_rows.max(1),
+        }
+    }
+}
+// Batches not yet shown, reading the input pauses while the queue is full
+const QUEUED_BATCHES: usize = 4;
+
+type Batch = Result<DataFrame, String>;
+
+// Newline delimited json if the first line is an object, csv with a header line otherwise
+#[derive(Debug, Clone, PartialEq)]
+enum Format {
+    Json,
+    Csv { header: String, delimiter: u8 },
+}
+
+impl Format {
+    fn detect(first_line: &str) -> Self {
+        if first_line.trim_start().starts_with('{') {
+            return Format::Json;
+        }
+        let delimiter = [b',', b'\t', b';', b'|']
+            .into_iter()
+            .max_by_key(|&d| first_line.bytes().filter(|&b| b == d).count())
+            .unwrap_or(b',');
+        Format::Csv {
+            header: first_line.to_string(),
+            delimiter,
+        }
+    }
+
+    // Frame of the records, with the columns and dtypes of the schema if given
+    fn parse(&self, records: &[String], schema: Option<&SchemaRef>) -> Batch {
+        match self {
+            Format::Json => Ok(json_frame(records, schema)),
+            Format::Csv { header, delimiter } => {
+                let mut content = header.clone();
+                for record in records {
+                    content.push('\n');
+                    content.push_str(record);
+                }
+                let options = CsvReadOptions::default()
+                    .with_has_header(true)
+                    .with_ignore_errors(true)
+                    .with_schema(schema.cloned())
+                    .with_parse_options(
+                        CsvParseOptions::default()
+                            .with_separator(*delimiter)
+                            .with_truncate_ragged_lines(true),
+                    );
+                CsvReader::new(Cursor::new(content))
+                    .with_options(options)
+                    .finish()
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+// Rows read from an input that keeps growing, e.g. stdin of `tail -f log.csv | tv --stream -`.
+// The dtypes of the columns are taken from the first batch of rows.
+pub struct Stream {
+    batches: Receiver<Batch>,
+    wakeup: Arc<Mutex<Option<Sender<Event>>>>,
+    pub ring: Option<usize>, // Only the latest rows are kept
+    pub rows: usize,         // Rows received so far, including dropped ones
+    pub finished: bool,      // The input was closed
+}
+
+impl Stream {
+    // Start reading the input, blocks until its first rows arrived. Returns them as frame.
+    pub fn start(
+        mut input: impl BufRead + Send + 'static,
+        ring: Option<usize>,
+        batching: Batching,
+    ) -> Result<(Self, DataFrame), TVError> {
+        let mut first_line = String::new();
+        while first_line.trim().is_empty() {
+            first_line.clear();
+            if input.read_line(&mut first_line)? == 0 {
+                return Err(TVError::LoadingFailed("No data on stdin!".into()));
+            }
+        }
+        let first_line = first_line.trim_end_matches(['\n', '\r']).to_string();
+        let format = Format::detect(&first_line);
+
+        let (record_sender, records) = mpsc::sync_channel(batching.rows);
+        let pending = match format {
+            Format::Json => Some(first_line),
+            Format::Csv { .. } => None,
+        };
+        let quoted = matches!(format, Format::Csv { .. });
+        thread::spawn(move || read_records(input, pending, quoted, record_sender));
+
+        let (batch_sender, batches) = mpsc::sync_channel(QUEUED_BATCHES);
+        let wakeup = Arc::new(Mutex::new(None));
+        let worker_wakeup = wakeup.clone();
+        thread::spawn(move || {
+            batch_records(format, batching, records, batch_sender, worker_wakeup)
+        });
+
+        let mut stream = Stream {
+            batches,
+            wakeup,
+            ring,
+            rows: 0,
+            finished: false,
+        };
+        let frame = match stream.batches.recv() {
+            Ok(batch) => batch.map_err(TVError::LoadingFailed)?,
+            Err(_) => return Err(TVError::LoadingFailed("No rows on stdin!".into())),
+        };
+        stream.rows = frame.height();
+        Ok((stream, frame))
+    }
+
+    // Read the input until it is closed, appending all rows to the frame
+    pub fn read_all(mut self, mut frame: DataFrame) -> Result<DataFrame, TVError> {
+        while let Ok(batch) = self.batches.recv() {
+            frame.vstack_mut(&batch.map_err(TVError::LoadingFailed)?)?;
+            self.rows = frame.height();
+        }
+        frame.as_single_chunk_par();
+        Ok(frame)
+    }
+
+    pub fn set_wakeup(&self, sender: Option<Sender<Event>>) {
+        *self.wakeup.lock().unwrap() = sender;
+    }
+
+    // Batches received since the last poll, and the errors of batches that could not be parsed
+    pub fn poll(&mut self) -> (Vec<DataFrame>, Vec<String>) {
+        let (mut frames, mut errors) = (Vec::new(), Vec::new());
+        loop {
+            match self.batches.try_recv() {
+                Ok(Ok(frame)) => {
+                    self.rows += frame.height();
+                    frames.push(frame);
+                }
+                Ok(Err(e)) => errors.push(e),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        (frames, errors)
+    }
+}
+
+// Send the records of the input one by one. Lines of csv records with an odd number
+// of quotes continue on the next line.
+fn read_records(
+    input: impl BufRead,
+    pending: Option<String>,
+    quoted: bool,
+    sender: SyncSender<String>,
+) {
+    if let Some(record) = pending
+        && sender.send(record).is_err()
+    {
+        return;
+    }
+    let mut lines = input.lines().map_while(Result::ok);
+    while let Some(mut record) = lines.next() {
+        while quoted && record.matches('"').count() % 2 == 1 {
+            let Some(line) = lines.next() else { break };
+            record.push('\n');
+            record.push_str(&line);
+        }
+        if record.trim().is_empty() {
+            continue;
+        }
+        // Blocks while the batches are not taken, so a fast producer waits for tv
+        if sender.send(record).is_err() {
+            return;
+        }
+    }
+}
+
+// Collect records into batches and parse them into frames
+fn batch_records(
+    format: Format,
+    batching: Batching,
+    records: Receiver<String>,
+    sender: SyncSender<Batch>,
+    wakeup: Arc<Mutex<Option<Sender<Event>>>>,
+) {
+    let mut schema: Option<SchemaRef> = None;
+    let mut closed = false;
+    while !closed {
+        let mut batch = Vec::new();
+        let mut deadline = None;
+        while batch.len() < batching.rows {
+            // Wait for the first record of a batch, then at most the interval for more
+            let timeout = match deadline {
+                Some(deadline) => deadline - Instant::now().min(deadline),
+                None => Duration::MAX,
+            };
+            match records.recv_timeout(timeout) {
+                Ok(record) => {
+                    batch.push(record);
+                    deadline.get_or_insert_with(|| Instant::now() + batching.interval);
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    closed = true;
+                    break;
+                }
+            }
+        }
+        if batch.is_empty() && schema.is_some() {
+            continue;
+        }
+        let parsed = format.parse(&batch, schema.as_ref());
+        if let Ok(frame) = parsed.as_ref() {
+            schema.get_or_insert_with(|| frame.schema().clone());
+        }
+        if sender.send(parsed).is_err() {
+            return;
+        }
+        if let Some(wakeup) = wakeup.lock().unwrap().as_ref() {
+            let _ = wakeup.send(Event::Wakeup);
+        }
+    }
+}
+
+// Columns in the order their keys first appear. Numbers become numeric columns,
+// other values except strings are kept as json text.
+fn json_frame(records: &[String], schema: Option<&SchemaRef>) -> DataFrame {
+    let objects: Vec<serde_json::Map<String, serde_json::Value>> = records
+        .iter()
+        .filter_map(|record| serde_json::from_str(record).ok())
+        .collect();
+    let names: Vec<String> = match schema {
+        Some(schema) => schema.iter_names().map(|name| name.to_string()).collect(),
+        None => {
+            let mut names: Vec<String> = Vec::new();
+            for key in objects.iter().flat_map(|object| object.keys()) {
+                if !names.contains(key) {
+                    names.push(key.clone());
+                }
+            }
+            names
+        }
+    };
+    let columns = names
+        .iter()
+        .map(|name| {
+            let values: Vec<Option<String>> = objects
+                .iter()
+                .map(|object| match object.get(name) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(serde_json::Value::String(text)) => Some(text.clone()),
+                    Some(value) => Some(value.to_string()),
+                })
+                .collect();
+            let series = Series::new(name.as_str().into(), values);
+            let typed = match schema.and_then(|schema| schema.get(name)) {
+                Some(dtype) => series.cast(dtype).ok(),
+                None => [DataType::Int64, DataType::Float64]
+                    .iter()
+                    .find_map(|dtype| series.strict_cast(dtype).ok()),
+            };
+            typed.unwrap_or(series).into_column()
+        })
+        .collect();
+    DataFrame::new(columns).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_growing_input() {
+        let input = "\nid;name\n1;\"two\nlines\"\n2;b\n";
+        let batching = Batching::new(&TVConfig::default());
+        let (stream, frame) = Stream::start(Cursor::new(input), None, batching).unwrap();
+        let frame = stream.read_all(frame).unwrap();
+        assert_eq!(frame.get_column_names(), ["id", "name"]);
+        assert_eq!(frame.column("id").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(frame.height(), 2);
+        assert_eq!(
+            frame.column("name").unwrap().str().unwrap().get(0),
+            Some("two\nlines")
+        );
+
+        let records: Vec<String> = [r#"{"a": 1, "b": {"c": true}}"#, r#"{"d": "x", "a": 2}"#]
+            .map(String::from)
+            .to_vec();
+        let frame = json_frame(&records, None);
+        assert_eq!(frame.get_column_names(), ["a", "b", "d"]);
+        assert_eq!(frame.column("a").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(
+            frame.column("b").unwrap().str().unwrap().get(0),
+            Some(r#"{"c":true}"#)
+        );
+        let later = json_frame(&[r#"{"a": 2.5, "e": 1}"#.to_string()], Some(frame.schema()));
+        assert_eq!(later.get_column_names(), ["a", "b", "d"]);
+        assert_eq!(later.column("a").unwrap().dtype(), &DataType::Int64);
+    }
+}
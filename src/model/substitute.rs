@@ -0,0 +1,72 @@
+use regex::{Regex, RegexBuilder};
+
+// A vim like `s/<pattern>/<replacement>/[gi]` substitution
+pub struct Substitution {
+    pub pattern: Regex,
+    pub replacement: String,
+    pub global: bool, // Replace all occurrences instead of only the first
+}
+
+impl Substitution {
+    // Parse the part after the `s`, e.g. `/foo/bar/g`. Any non alphanumeric delimiter can be used.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let mut chars = expr.chars();
+        let delimiter = chars
+            .next()
+            .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+            .ok_or("Expected a substitution like s/foo/bar/g")?;
+        let parts = split_unescaped(chars.as_str(), delimiter);
+        let (pattern, replacement, flags) = match parts.as_slice() {
+            [pattern, replacement] => (pattern, replacement, ""),
+            [pattern, replacement, flags] => (pattern, replacement, flags.as_str()),
+            _ => return Err("Expected a substitution like s/foo/bar/g".to_string()),
+        };
+        if let Some(flag) = flags.chars().find(|c| !matches!(c, 'g' | 'i')) {
+            return Err(format!("Unknown substitution flag: {flag}"));
+        }
+        let pattern = RegexBuilder::new(pattern)
+            .case_insensitive(flags.contains('i'))
+            .build()
+            .map_err(|e| format!("Invalid pattern: {e}"))?;
+
+        Ok(Substitution {
+            pattern,
+            // Vim style group references `\1` in regex crate syntax
+            replacement: Regex::new(r"\\(\d)")
+                .unwrap()
+                .replace_all(replacement, "$${$1}")
+                .to_string(),
+            global: flags.contains('g'),
+        })
+    }
+
+    // The substituted value, None if the pattern does not match
+    pub fn apply(&self, value: &str) -> Option<String> {
+        if !self.pattern.is_match(value) {
+            return None;
+        }
+        let limit = if self.global { 0 } else { 1 };
+        Some(
+            self.pattern
+                .replacen(value, limit, self.replacement.as_str())
+                .to_string(),
+        )
+    }
+}
+
+// Split at the delimiter, unless it is escaped with a backslash
+fn split_unescaped(s: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&delimiter) {
+            parts.last_mut().unwrap().push(delimiter);
+            chars.next();
+        } else if c == delimiter {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts
+}
@@ -1,14 +1,23 @@
 use rayon::prelude::*;
-use std::{sync::Arc, time::Instant};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+    time::Instant,
+};
 
 use tracing::{error, trace};
 
 use crate::{
-    model::{Column, UIData, UILayout, column_view::ColumnStatus},
+    model::{Column, UIData, UILayout, column_groups, column_view::ColumnStatus},
     tui::{COLUMN_WIDTH_COLLAPSED_COLUMN, COLUMN_WIDTH_MARGIN},
 };
 
 use super::ColumnView;
+use super::row_groups::RowGroups;
+
+// Widest a column is rendered, unless it is expanded
+const MAX_COLUMN_WIDTH: usize = 25;
 
 pub struct TableView {
     pub name: String,
@@ -16,7 +25,7 @@ pub struct TableView {
     pub visible_columns: Vec<usize>, // Idx of visible columns that are send to the UI for rendering.
     pub visible_width: usize,
     pub curser_row: usize,
-    pub curser_column: usize,
+    pub curser_column: usize, // Data index of the selected column relative to the column offset
     pub offset_row: usize,
     pub offset_column: usize,
     pub data: Vec<ColumnView>, // Currently visible part of the table
@@ -26,6 +35,13 @@ pub struct TableView {
     pub index: ColumnView,
     pub heigh: usize,
     pub width: usize,
+    pub highlighted_rows: HashSet<usize>, // Data indices of rows that are rendered highlighted
+    pub bookmarks: Arc<BTreeSet<usize>>, // Data indices of bookmarked rows, shared by all tables of a dataset
+    pub marked_rows: BTreeSet<usize>,    // Data indices of rows selected for bulk actions
+    pub visual_anchor: Option<usize>,    // Row position a visual selection started at
+    pub filter: Option<(String, String)>, // Column name and term of the filter that created this table
+    pub sort: Option<(String, bool)>,     // Column name and direction the rows are sorted by
+    pub groups: Option<RowGroups>, // Sections of rows, their headers are shown in the index column
 }
 
 impl TableView {
@@ -46,11 +62,26 @@ impl TableView {
             index: ColumnView::empty(),
             heigh: 0,
             width: 0,
+            highlighted_rows: HashSet::new(),
+            bookmarks: Arc::new(BTreeSet::new()),
+            marked_rows: BTreeSet::new(),
+            visual_anchor: None,
+            filter: None,
+            sort: None,
+            groups: None,
+        }
+    }
+
+    // Width of the index column, if it is shown
+    pub fn index_width(&self) -> usize {
+        match &self.groups {
+            Some(groups) => groups.label_width(),
+            None => self.index.width,
         }
     }
 
     // Helper function to wrap cell content to ensure csv valid format
-    fn wrap_cell_content(c: &String) -> String {
+    fn wrap_cell_content(c: &str) -> String {
         let needs_escaping = c.chars().any(|c| c == '"');
         let needs_wrapping = c.chars().any(|c| c == ' ' || c == '\t' || c == ',');
         let mut out = String::from(c);
@@ -66,37 +97,119 @@ impl TableView {
 
     pub fn get_current_row(&self, data: &Vec<Column>) -> String {
         let row = self.rows[self.offset_row + self.curser_row];
+        Self::get_row(data, row)
+    }
 
+    // Csv line of the row with the given data index
+    pub fn get_row(data: &[Column], row: usize) -> String {
         let content = data
             .iter()
-            .map(|c| Self::wrap_cell_content(&c.data[row]))
+            .map(|c| Self::wrap_cell_content(&c.value(row)))
             .collect::<Vec<String>>();
 
         content.join(",")
     }
 
+    // Rows with a header line, fields are quoted where needed to paste them into a spreadsheet
+    pub fn get_rows(data: &[Column], rows: &[usize], delimiter: char) -> String {
+        let quote = |field: &str| {
+            if field.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        };
+        let separator = delimiter.to_string();
+        let mut lines = vec![
+            data.iter()
+                .map(|c| quote(&c.name))
+                .collect::<Vec<String>>()
+                .join(&separator),
+        ];
+        for &row in rows {
+            let fields: Vec<String> = data
+                .iter()
+                .map(|c| c.value(row))
+                .map(|v| {
+                    if v.as_ref() == "∅" {
+                        String::new()
+                    } else {
+                        quote(&v)
+                    }
+                })
+                .collect();
+            lines.push(fields.join(&separator));
+        }
+        lines.join("\n")
+    }
+
+    // Row positions between the visual anchor and the curser
+    fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let curser = self.offset_row + self.curser_row;
+        let last = self.rows.len().checked_sub(1)?;
+        Some(anchor.min(curser).min(last)..=anchor.max(curser).min(last))
+    }
+
+    // Data indices of the visually selected rows, in the order of the view
+    pub fn visual_rows(&self) -> Vec<usize> {
+        self.visual_range()
+            .map(|range| self.rows[range].to_vec())
+            .unwrap_or_default()
+    }
+
+    // Add the visually selected rows to the marked rows
+    pub fn end_visual(&mut self) {
+        let rows = self.visual_rows();
+        self.marked_rows.extend(rows);
+        self.visual_anchor = None;
+    }
+
     pub fn get_current_cell(&self, data: &Vec<Column>) -> String {
         let row = self.rows[self.offset_row + self.curser_row];
         let column = self.offset_column + self.curser_column;
 
-        data[column].data[row].clone()
+        data[column].value(row).to_string()
+    }
+
+    // Return the mask index positions of rows whose values in the key columns appear more than once,
+    // together with the number of duplicate groups.
+    pub fn duplicate_rows(&self, data: &[Column], key_columns: &[usize]) -> (Vec<usize>, usize) {
+        let mut groups: HashMap<Vec<&str>, Vec<usize>> = HashMap::new();
+        for (midx, &ridx) in self.rows.iter().enumerate() {
+            let key = key_columns
+                .iter()
+                .map(|&cidx| &*data[cidx].values()[ridx])
+                .collect::<Vec<&str>>();
+            groups.entry(key).or_default().push(midx);
+        }
+
+        let duplicates = groups
+            .into_values()
+            .filter(|g| g.len() > 1)
+            .collect::<Vec<Vec<usize>>>();
+        let ngroups = duplicates.len();
+        let mut matches = duplicates.into_iter().flatten().collect::<Vec<usize>>();
+        matches.sort_unstable();
+        (matches, ngroups)
     }
 
     pub fn toggle_column_status(&mut self, data: &mut Vec<Column>, toggle_to_expand: bool) {
+        let column = self.offset_column + self.curser_column;
         let new_status = if toggle_to_expand {
-            match data[self.visible_columns[self.curser_column]].status {
+            match data[column].status {
                 ColumnStatus::COLLAPSED => ColumnStatus::EXPANDED,
                 ColumnStatus::NORMAL => ColumnStatus::EXPANDED,
                 ColumnStatus::EXPANDED => ColumnStatus::COLLAPSED,
             }
         } else {
-            match data[self.visible_columns[self.curser_column]].status {
+            match data[column].status {
                 ColumnStatus::COLLAPSED => ColumnStatus::NORMAL,
                 ColumnStatus::NORMAL => ColumnStatus::COLLAPSED,
                 ColumnStatus::EXPANDED => ColumnStatus::COLLAPSED,
             }
         };
-        data[self.visible_columns[self.curser_column]].status = new_status;
+        data[column].status = new_status;
     }
 
     pub fn move_selection_beginning(
@@ -156,9 +269,10 @@ impl TableView {
         if self.curser_row + self.offset_row < (self.rows.len() - 1) {
             // Somewhere in the Frame
             if self.curser_row < layout.table_height - 1 {
-                // Somewhere in the middle of the table
+                // Somewhere in the middle of the table, no column is shown if the terminal is too narrow
+                let visible_rows = self.data.first().map_or(0, |c| c.data.len());
                 self.curser_row =
-                    std::cmp::min(self.curser_row + size, self.data[0].data.len() - 1);
+                    std::cmp::min(self.curser_row + size, visible_rows.saturating_sub(1));
             } else {
                 // At the bottom of the table, need to shift table down
                 self.offset_row = std::cmp::min(self.offset_row + size, self.rows.len() - 1);
@@ -177,10 +291,18 @@ impl TableView {
         layout: &UILayout,
         uidata: &mut UIData,
     ) {
-        if self.curser_column > 0 {
-            self.curser_column = self.curser_column.saturating_sub(1);
-        } else if self.offset_column > 0 {
-            self.offset_column = self.offset_column.saturating_sub(1);
+        let selected = self.offset_column + self.curser_column;
+        // Columns folded into a summary column are skipped
+        if let Some(previous) = (0..selected)
+            .rev()
+            .find(|&idx| !Self::is_skipped(data, idx))
+        {
+            if previous >= self.offset_column {
+                self.curser_column = previous - self.offset_column;
+            } else {
+                self.offset_column = previous;
+                self.curser_column = 0;
+            }
         }
         self.update(data, layout, uidata);
     }
@@ -191,20 +313,17 @@ impl TableView {
         layout: &UILayout,
         uidata: &mut UIData,
     ) {
-        if self.curser_column + self.offset_column < (data.len() - 1) {
-            // Somewhere before the last column
-            if self.curser_column < (self.visible_columns.len() - 1) {
-                // In the middle
-                self.curser_column += 1;
-            } else {
-                // At the end of the screen
-                self.offset_column += 1;
-            }
+        let selected = self.offset_column + self.curser_column;
+        // Columns folded into a summary column are skipped
+        if let Some(next) = (selected + 1..data.len()).find(|&idx| !Self::is_skipped(data, idx)) {
+            // At the end of the screen the table is shifted until the column is visible
+            self.curser_column = next - self.offset_column;
             self.update(data, layout, uidata);
         } else {
             // At the last visible column (which could be wider then the screen)
-            if self.visible_width > self.width && self.offset_column < (data.len() - 1) {
+            if self.visible_width > self.width && self.offset_column < selected {
                 self.offset_column += 1;
+                self.curser_column -= 1;
                 self.update(data, layout, uidata);
             }
         }
@@ -219,12 +338,9 @@ impl TableView {
         uidata: &mut UIData,
     ) {
         // If relevant column is already visible, only select the right row, otherwise move the view.
+        let column = Self::shown_column(data, column);
         if self.visible_columns.contains(&column) {
-            self.curser_column = self
-                .visible_columns
-                .iter()
-                .position(|&c| c == column)
-                .unwrap_or(0);
+            self.curser_column = column - self.offset_column;
         } else {
             self.offset_column = column;
             self.curser_column = 0;
@@ -348,19 +464,41 @@ impl TableView {
         next_match_idx
     }
 
-    fn build_index(&mut self) {
+    // Row numbers, or with relative numbers the distance of each row to the curser row
+    fn build_index(&mut self, relative: bool) {
         let rbegin = self.offset_row;
         let rend = std::cmp::min(rbegin + self.heigh, self.rows.len());
 
+        // Grouped rows show the headers of their sections instead of the row numbers
+        if let Some(groups) = &self.groups {
+            self.index = ColumnView {
+                name: groups.column.clone(),
+                width: groups.label_width(),
+                data: (rbegin..rend).map(|pos| groups.label(pos)).collect(),
+                styles: Vec::new(),
+            };
+            return;
+        }
+        let curser = rbegin + self.curser_row;
         let data = self.rows[rbegin..rend]
             .iter()
-            .map(|idx| (idx + 1).to_string())
+            .enumerate()
+            .map(|(offset, idx)| match rbegin + offset {
+                pos if relative && pos != curser => pos.abs_diff(curser).to_string(),
+                _ => (idx + 1).to_string(),
+            })
             .collect::<Vec<String>>();
-        let width = data.last().map(|s| s.len()).unwrap_or(3);
+        // As wide as the row numbers, so the gutter keeps its width while the curser moves
+        let width = self.rows[rbegin..rend]
+            .iter()
+            .map(|idx| (idx + 1).to_string().len())
+            .max()
+            .unwrap_or(3);
         self.index = ColumnView {
             name: "".to_string(),
             width,
             data,
+            styles: Vec::new(),
         }
     }
 
@@ -370,16 +508,61 @@ impl TableView {
             name: "...".to_string(),
             width: 3,
             data,
+            styles: Vec::new(),
+        }
+    }
+
+    // Values of the columns folded into the summary column, side by side
+    fn get_summary_column(
+        data: &[Column],
+        head: usize,
+        rows: Range<usize>,
+        mask: &[usize],
+    ) -> ColumnView {
+        let members = &data[column_groups::members(data, head)];
+        let values = mask[rows]
+            .iter()
+            .map(|&ridx| {
+                members
+                    .iter()
+                    .map(|c| c.display_value(ridx))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            })
+            .collect();
+        let width = data[head].render_width;
+        ColumnView {
+            name: Self::get_visible_name(column_groups::summary_name(data, head), width),
+            width,
+            data: values,
+            styles: Vec::new(),
         }
     }
 
+    // Columns filtered out by their name, or folded into a summary column, are not shown
+    fn is_skipped(data: &[Column], idx: usize) -> bool {
+        data[idx].hidden || column_groups::is_hidden(data, idx)
+    }
+
+    // The column shown for the given one, its summary column or the next column that is not filtered out
+    fn shown_column(data: &[Column], idx: usize) -> usize {
+        let idx = column_groups::head(data, idx);
+        if !data[idx].hidden {
+            return idx;
+        }
+        (idx..data.len())
+            .chain((0..idx).rev())
+            .find(|&idx| !Self::is_skipped(data, idx))
+            .unwrap_or(idx)
+    }
+
     fn get_visible_name(name: String, width: usize) -> String {
         let mut reduced_name = name.clone();
         if width < 3 {
             return "".to_string();
         }
-        if reduced_name.len() > width {
-            reduced_name = reduced_name[0..width - 3].to_string();
+        if reduced_name.chars().count() > width {
+            reduced_name = reduced_name.chars().take(width - 3).collect();
             reduced_name.push_str("...");
         }
         reduced_name
@@ -394,9 +577,19 @@ impl TableView {
         }
     }
 
+    // Width needed to show the selected column completely, including its separator
+    pub fn selected_column_width(&self, data: &[Column]) -> usize {
+        data.get(self.offset_column + self.curser_column)
+            .map_or(0, |column| {
+                Self::calculate_column_width(column, MAX_COLUMN_WIDTH) + 1
+            })
+    }
+
     pub fn update(&mut self, data: &mut Vec<Column>, layout: &UILayout, uidata: &mut UIData) {
         self.width = layout.table_width;
         self.heigh = layout.table_height;
+        // Keep the selected row on screen, e.g. after the terminal got smaller
+        self.clamp_rows();
 
         let rbegin = self.offset_row;
         let rend = std::cmp::min(rbegin + self.heigh, self.rows.len());
@@ -416,38 +609,20 @@ impl TableView {
             layout.height
         );
 
-        self.visible_columns = Vec::new();
-        let mut visible_width = 0;
-
-        // Calculate current render with for each column
-        // This could change because a column was expanded or collapsed
-        for column in data.iter_mut() {
-            column.render_width = Self::calculate_column_width(column, 25);
+        // Keep the selected column on screen, e.g. after the terminal was resized
+        let selected_column = Self::shown_column(
+            data,
+            (self.offset_column + self.curser_column).min(data.len().saturating_sub(1)),
+        );
+        self.offset_column = column_groups::head(data, self.offset_column).min(selected_column);
+        self.fit_columns(data, layout.table_width);
+        while self.offset_column < selected_column
+            && !self.visible_columns.contains(&selected_column)
+        {
+            self.offset_column += 1;
+            self.fit_columns(data, layout.table_width);
         }
-
-        // Create a list of columns that fit in the table
-        for (cidx, column) in data[self.offset_column..].iter_mut().enumerate() {
-            if visible_width + (column.render_width + 1) <= layout.table_width {
-                //if (column.render_width+1) <= width_budget {
-                self.visible_columns.push(cidx + self.offset_column);
-                //width_budget -= column.render_width + 1; // Rendered with and 1 spacer character
-                visible_width += column.render_width + 1;
-            } else {
-                // Add the last partial visible column
-                if visible_width < layout.table_width {
-                    let remaining_width = layout.table_width - visible_width;
-                    self.visible_columns.push(cidx + self.offset_column);
-                    visible_width += remaining_width;
-                    column.render_width = remaining_width;
-                }
-                break;
-            }
-        }
-        // Store how wide the table would be in its full rendering to know the most right column is only partially rendered
-        self.visible_width = visible_width;
-
-        // Growing columns can reduce the number of visible columns. Make sure the column curser is at most the last visible column
-        self.curser_column = std::cmp::min(self.curser_column, self.visible_columns.len() - 1);
+        self.curser_column = selected_column - self.offset_column;
 
         // Create ColumnViews for visible columns
 
@@ -455,13 +630,38 @@ impl TableView {
         self.data = Vec::with_capacity(self.visible_columns.len());
         for idx in self.visible_columns.iter() {
             if let Some(column) = data.get(*idx) {
-                if column.status == ColumnStatus::COLLAPSED {
+                if column.folded {
+                    self.data.push(Self::get_summary_column(
+                        data,
+                        *idx,
+                        rbegin..rend,
+                        &self.rows,
+                    ));
+                } else if column.status == ColumnStatus::COLLAPSED {
                     self.data.push(Self::get_collapsed_column(rend - rbegin));
                 } else {
-                    let col_data = self.rows[rbegin..rend]
+                    // Last row with a value, shown in the blank rows below it
+                    let mut previous = match column.fill_down {
+                        true => self.rows[..rbegin]
+                            .iter()
+                            .rev()
+                            .find(|&&ridx| !column.is_blank(ridx))
+                            .copied(),
+                        false => None,
+                    };
+                    let (col_data, styles) = self.rows[rbegin..rend]
                         .iter()
-                        .map(|&ridx| column.data[ridx].clone())
-                        .collect();
+                        .map(|&ridx| {
+                            if !column.fill_down || !column.is_blank(ridx) {
+                                previous = Some(ridx);
+                            } else if let Some(previous) = previous {
+                                let cell = column.display_cell(previous);
+                                return (cell.text, Some(cell.style.unwrap_or_default().dim()));
+                            }
+                            let cell = column.display_cell(ridx);
+                            (cell.text, cell.style)
+                        })
+                        .unzip();
                     let name = Self::get_visible_name(column.name.clone(), column.render_width);
                     let width = column.render_width;
                     //trace!("Visible Column: \"{name}\", width: {width}");
@@ -470,6 +670,7 @@ impl TableView {
                         name,
                         width,
                         data: col_data,
+                        styles,
                     });
                 }
             } else {
@@ -477,21 +678,120 @@ impl TableView {
             }
         }
 
+        // Position of the sorted column among the visible columns, to mark it in the header
+        uidata.sorted_column = self.sort.as_ref().and_then(|(name, ascending)| {
+            self.visible_columns
+                .iter()
+                .position(|&idx| {
+                    data[idx].name == *name && data[idx].status != ColumnStatus::COLLAPSED
+                })
+                .map(|pos| (pos, *ascending))
+        });
+
+        uidata.column_range = (
+            self.visible_columns.first().copied().unwrap_or(0),
+            self.visible_columns.last().copied().unwrap_or(0),
+            data.len(),
+        );
+
         // Update the index
         uidata.layout = layout.clone();
-        self.build_index();
+        self.build_index(uidata.relative_numbers);
 
         self.update_uidata(uidata);
     }
 
+    // Make sure the curser row is inside the viewport and no rows are left empty at the bottom
+    fn clamp_rows(&mut self) {
+        let height = self.heigh.max(1);
+        let selected = std::cmp::min(
+            self.offset_row + self.curser_row,
+            self.rows.len().saturating_sub(1),
+        );
+        self.offset_row = std::cmp::min(self.offset_row, self.rows.len().saturating_sub(height));
+        if selected < self.offset_row {
+            self.offset_row = selected;
+        } else if selected >= self.offset_row + height {
+            self.offset_row = selected + 1 - height;
+        }
+        self.curser_row = selected - self.offset_row;
+    }
+
+    // Collect the columns, starting at the column offset, that fit in the table width
+    fn fit_columns(&mut self, data: &mut [Column], table_width: usize) {
+        self.visible_columns = Vec::new();
+        let mut visible_width = 0;
+
+        // Calculate current render with for each column
+        // This could change because a column was expanded or collapsed
+        for column in data.iter_mut() {
+            column.render_width = Self::calculate_column_width(column, MAX_COLUMN_WIDTH);
+        }
+        // Summary columns show the values of all their folded columns
+        for idx in 0..data.len() {
+            if data[idx].folded && !column_groups::is_hidden(data, idx) {
+                let members = column_groups::members(data, idx);
+                let values_width: usize = data[members.clone()]
+                    .iter()
+                    .map(|c| c.render_width + 1)
+                    .sum();
+                let name_width = column_groups::summary_name(data, idx).len() + COLUMN_WIDTH_MARGIN;
+                data[idx].render_width = values_width.max(name_width).min(MAX_COLUMN_WIDTH);
+            }
+        }
+
+        // Create a list of columns that fit in the table
+        for cidx in self.offset_column..data.len() {
+            if Self::is_skipped(data, cidx) {
+                continue;
+            }
+            let column = &mut data[cidx];
+            if visible_width + (column.render_width + 1) <= table_width {
+                self.visible_columns.push(cidx);
+                visible_width += column.render_width + 1;
+            } else {
+                // Add the last partial visible column
+                if visible_width < table_width {
+                    let remaining_width = table_width - visible_width;
+                    self.visible_columns.push(cidx);
+                    visible_width += remaining_width;
+                    column.render_width = remaining_width;
+                }
+                break;
+            }
+        }
+        // Store how wide the table would be in its full rendering to know the most right column is only partially rendered
+        self.visible_width = visible_width;
+    }
+
     pub fn update_uidata(&self, uidata: &mut UIData) {
         uidata.name = self.name.clone();
         uidata.table = self.data.clone();
         uidata.index = self.index.clone();
-        uidata.selected_column = self.curser_column;
+        uidata.selected_column = self
+            .visible_columns
+            .iter()
+            .position(|&c| c == self.offset_column + self.curser_column)
+            .unwrap_or(0);
         uidata.selected_row = self.curser_row;
         uidata.nrows = self.rows.len();
         uidata.abs_selected_row = self.offset_row + self.curser_row;
+        let rend = std::cmp::min(self.offset_row + self.heigh, self.rows.len());
+        uidata.highlighted_rows = self.rows[self.offset_row.min(rend)..rend]
+            .iter()
+            .map(|ridx| self.highlighted_rows.contains(ridx))
+            .collect();
+        uidata.bookmarked_rows = self.rows[self.offset_row.min(rend)..rend]
+            .iter()
+            .map(|ridx| self.bookmarks.contains(ridx))
+            .collect();
+        let visual = self.visual_range();
+        uidata.marked_rows = (self.offset_row.min(rend)..rend)
+            .map(|pos| {
+                self.marked_rows.contains(&self.rows[pos])
+                    || visual.as_ref().is_some_and(|v| v.contains(&pos))
+            })
+            .collect();
         uidata.last_update = Instant::now();
     }
 }
@@ -1,4 +1,5 @@
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tracing::trace;
 
@@ -6,54 +7,186 @@ use crate::{domain::CMDMode, inputter::InputResult};
 
 use super::{ColumnView, Model};
 
-use crate::tui::{CMDLINE_HEIGH, SCROLLBAR_WIDTH, TABLE_HEADER_HEIGHT};
+use crate::tui::{
+    CELL_LINE_HEIGHT, CMDLINE_HEIGH, SCROLLBAR_WIDTH, TABLE_HEADER_HEIGHT, TOP_VALUES_PANEL_HEIGHT,
+    TOTALS_ROW_HEIGHT,
+};
 
 pub struct UIData {
     pub name: String,
+    pub tab_names: Vec<String>,
+    pub active_tab: usize,
     pub table: Vec<ColumnView>,
     pub index: ColumnView,
     pub nrows: usize, // Total number of raws in this View
     pub selected_row: usize,
     pub selected_column: usize,
     pub abs_selected_row: usize,
+    pub highlighted_rows: Vec<bool>, // Per visible row, if it should be rendered highlighted
+    pub bookmarked_rows: Vec<bool>,  // Per visible row, if it is bookmarked
+    pub marked_rows: Vec<bool>,      // Per visible row, if it is part of the row selection
     pub show_popup: bool,
+    pub popup_title: String,
     pub popup_message: String,
     pub layout: UILayout,
     pub last_update: Instant,
     pub cmdinput: InputResult,
     pub cmd_mode: Option<CMDMode>,
+    pub cmd_preview: String, // E.g. the number of rows a filter being typed would match
+    pub dirty: bool,         // Data was modified and not exported yet
+    pub column_stats: String, // Quick stats of the selected column
+    pub crosshair: bool,     // Dim everything except the row and column of the curser
+    pub relative_numbers: bool, // The index shows the distance of rows to the curser row, like vim's relativenumber
+    pub recording_macro: Option<char>, // Register keys are recorded into
+    pub key_hints: Vec<(String, String)>, // Keys continuing a started key sequence and what they do
+    pub sorted_column: Option<(usize, bool)>, // Visible column the table is sorted by, and if ascending
+    pub cell_line: String, // Untruncated value of the selected cell, shown above the status line
+    pub column_range: (usize, usize, usize), // First and last visible column, and the number of columns
     pub active_cmdinput: bool,
     pub status_message: String,
     pub last_status_message_update: Instant,
+    pub status_level: MessageLevel, // Warnings and errors are shown until the next key press
+    pub chart: Option<ChartData>,
+    pub plot: Option<PlotData>,
+    pub profile: ProfileData,
+    pub json: Option<JsonData>,
+    pub top_values: Option<TopValuesData>,
+    pub totals: Option<Vec<String>>, // Aggregate of each visible column, shown below the table
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonToken {
+    Key,
+    String,
+    Number,
+    Bool,
+    Null,
+    Punctuation,
+}
+
+// Visible lines of a json document, each made of tokens that are colored individually
+#[derive(Clone)]
+pub struct JsonData {
+    pub title: String,
+    pub lines: Vec<Vec<(String, JsonToken)>>,
+    pub selected: usize,
+}
+
+// Timings shown in the profiling overlay
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MessageLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProfileData {
+    pub show: bool,
+    pub load_time: Duration,
+    pub collect_time: Duration, // Collecting the cells of the visible table
+    pub draw_time: Duration,
+    pub latency: Duration, // From receiving an input event until it is drawn
+}
+
+// Most frequent values of the selected column, shown in a panel below the table
+#[derive(Clone, Debug)]
+pub struct TopValuesData {
+    pub title: String,
+    pub values: Vec<(String, usize)>, // Value and count, most frequent first
+    pub total: usize,                 // Rows counted
+}
+
+// Aggregated values per category, rendered as a bar chart popup
+#[derive(Clone, Debug)]
+pub struct ChartData {
+    pub title: String,
+    pub bars: Vec<(String, f64)>,
+}
+
+// Visible part of a line chart, with three labels per axis
+#[derive(Clone, Debug)]
+pub struct PlotData {
+    pub title: String,
+    pub points: Vec<(f64, f64)>,
+    pub x_bounds: [f64; 2],
+    pub y_bounds: [f64; 2],
+    pub x_labels: Vec<String>,
+    pub y_labels: Vec<String>,
 }
 
 impl UIData {
     pub fn empty() -> Self {
         UIData {
             name: String::new(),
+            tab_names: Vec::new(),
+            active_tab: 0,
             table: Vec::new(),
             index: ColumnView {
                 name: "".to_string(),
                 width: 0,
                 data: Vec::new(),
+                styles: Vec::new(),
             },
             nrows: 0,
             selected_row: 0,
             selected_column: 0,
             abs_selected_row: 0,
+            highlighted_rows: Vec::new(),
+            bookmarked_rows: Vec::new(),
+            marked_rows: Vec::new(),
             show_popup: false,
+            popup_title: String::new(),
             popup_message: String::new(),
             layout: UILayout::default(),
             last_update: Instant::now(),
             cmdinput: InputResult::default(),
             cmd_mode: None,
+            cmd_preview: String::new(),
+            dirty: false,
+            column_stats: String::new(),
+            crosshair: false,
+            relative_numbers: false,
+            recording_macro: None,
+            key_hints: Vec::new(),
+            sorted_column: None,
+            cell_line: String::new(),
+            column_range: (0, 0, 0),
             active_cmdinput: false,
             status_message: String::new(),
             last_status_message_update: Instant::now(),
+            status_level: MessageLevel::Info,
+            chart: None,
+            plot: None,
+            profile: ProfileData::default(),
+            json: None,
+            top_values: None,
+            totals: None,
         }
     }
 }
 
+// Loaded rows (over all columns) and columns of a dataset that is being loaded
+#[derive(Default, Clone, Debug)]
+pub struct LoadProgress {
+    pub rows_done: usize,
+    pub rows_total: usize,
+    pub columns_done: usize,
+    pub columns_total: usize,
+    pub preview: Option<Arc<LoadPreview>>, // Sent once, before the file is read completely
+}
+
+// Schema and first rows of a file, read quickly to be shown while the whole file is loaded
+#[derive(Default, Clone, Debug)]
+pub struct LoadPreview {
+    pub columns: Vec<(String, String)>, // Name and dtype
+    pub rows: Vec<Vec<String>>,
+    pub nrows: Option<usize>,
+    pub nrows_exact: bool, // If nrows is counted, or estimated from the start of the file
+    pub parsing: Option<String>, // Delimiter, encoding and header row of csv files
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct UILayout {
     pub width: usize,
@@ -62,6 +195,8 @@ pub struct UILayout {
     pub table_height: usize,
     pub index_width: usize,
     pub index_height: usize,
+    pub panel_height: usize, // Top values panel below the table, 0 if it is hidden
+    pub totals_height: usize, // Totals row below the table, 0 if it is hidden
     pub statusline_width: usize,
     pub statusline_height: usize,
 }
@@ -71,17 +206,33 @@ impl UILayout {
         let table = model.tables.last().unwrap();
         let mut index_width = 0;
         if table.show_index {
-            index_width = table.index.width;
+            index_width = table.index_width();
+        }
+        let mut layout = UILayout::from_values(index_width, ui_width, ui_height);
+        if model.shows_top_values() {
+            // Small terminals keep at least half of the height for the table
+            layout.panel_height = TOP_VALUES_PANEL_HEIGHT.min(layout.table_height / 2);
+            layout.table_height -= layout.panel_height;
+            layout.index_height = layout.table_height;
         }
-        UILayout::from_values(index_width, ui_width, ui_height)
+        if model.shows_totals() {
+            // Not on terminals too low for a row of the table besides it
+            layout.totals_height = TOTALS_ROW_HEIGHT.min(layout.table_height - 1);
+            layout.table_height -= layout.totals_height;
+            layout.index_height = layout.table_height;
+        }
+        layout
     }
 
     pub fn from_values(index_width: usize, ui_width: usize, ui_height: usize) -> Self {
         let cmdline_heigth = CMDLINE_HEIGH;
         let cmdline_width = ui_width;
 
-        let table_width = ui_width - SCROLLBAR_WIDTH - index_width;
-        let table_height = ui_height - cmdline_heigth - TABLE_HEADER_HEIGHT;
+        let table_width = ui_width.saturating_sub(SCROLLBAR_WIDTH + index_width);
+        // At least one row, the curser needs a row to be on
+        let table_height = ui_height
+            .saturating_sub(cmdline_heigth + TABLE_HEADER_HEIGHT + CELL_LINE_HEIGHT)
+            .max(1);
         let index_height = table_height;
 
         let layout = UILayout {
@@ -91,6 +242,8 @@ impl UILayout {
             table_height,
             index_width,
             index_height,
+            panel_height: 0,
+            totals_height: 0,
             statusline_width: cmdline_width,
             statusline_height: cmdline_heigth,
         };
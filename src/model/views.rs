@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::TVError;
+
+const VIEWS_PATH: &str = "~/.config/tv/views.json";
+
+// Filters, sort and column layout of a table that can be re-applied by name.
+// Columns are referenced by name, to allow applying a view to other files with the same columns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedView {
+    pub filters: Vec<(String, String)>, // Column name and filter term, in the order they were applied
+    pub sort: Option<(String, bool)>,   // Column name and if sorted ascending
+    pub collapsed: Vec<String>,
+    pub expanded: Vec<String>,
+}
+
+fn views_path() -> PathBuf {
+    PathBuf::from(shellexpand::tilde(VIEWS_PATH).to_string())
+}
+
+pub fn load_views() -> Result<BTreeMap<String, SavedView>, TVError> {
+    let path = views_path();
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| TVError::LoadingFailed(format!("Invalid views file {path:?}: {e}")))
+}
+
+pub fn store_views(views: &BTreeMap<String, SavedView>) -> Result<(), TVError> {
+    let path = views_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(views)
+        .map_err(|e| TVError::LoadingFailed(format!("Storing views failed: {e}")))?;
+    fs::write(path, content)?;
+    Ok(())
+}
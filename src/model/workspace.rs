@@ -0,0 +1,473 @@
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use super::{Directory, Model, Status, WorkspaceRequest, format_size};
+use crate::domain::{Event, InputMode, Message, TVConfig, TVError};
+
+// All loaded tables. User input goes to the active one, the others keep their state until they are switched to.
+pub struct Workspace {
+    config: TVConfig,
+    models: Vec<Model>,
+    active: usize,
+    panes: Option<[usize; 2]>, // Tables shown left and right of a split, one of them is the active one
+    size: (usize, usize),      // Size of the terminal, shared by the panes of a split
+    scroll_lock: Option<bool>, // The panes scroll together, and their columns too if true
+    directory: Option<Directory>, // Files listed in a sidebar, when browsing a directory
+    events: Option<Sender<Event>>, // Main loop events, passed on to every table
+}
+
+impl Workspace {
+    pub fn new(config: &TVConfig, model: Model) -> Self {
+        let size = (model.uilayout.width, model.uilayout.height);
+        Workspace {
+            config: config.clone(),
+            models: vec![model],
+            active: 0,
+            panes: None,
+            size,
+            scroll_lock: None,
+            directory: None,
+            events: None,
+        }
+    }
+
+    // Let the background work of all tables, also of ones opened later, wake up the main loop
+    pub fn set_event_sender(&mut self, sender: Sender<Event>) {
+        for model in self.models.iter_mut() {
+            model.set_event_sender(Some(sender.clone()));
+        }
+        self.events = Some(sender);
+    }
+
+    // Show the files of the directory in a sidebar, the active table should be one of them
+    pub fn browse(&mut self, directory: Directory) -> Result<(), TVError> {
+        self.directory = Some(directory);
+        self.resize()?;
+        self.announce_file();
+        Ok(())
+    }
+
+    pub fn directory(&self) -> Option<&Directory> {
+        self.directory.as_ref()
+    }
+
+    // Columns taken by the sidebar, the tables get the rest
+    pub fn sidebar_width(&self) -> usize {
+        self.directory
+            .as_ref()
+            .map_or(0, |directory| directory.width().min(self.size.0 / 3))
+    }
+
+    pub fn active(&self) -> &Model {
+        &self.models[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Model {
+        &mut self.models[self.active]
+    }
+
+    // Tables shown left and right, if the view is split
+    pub fn panes(&self) -> Option<(&Model, &Model)> {
+        self.panes
+            .map(|[left, right]| (&self.models[left], &self.models[right]))
+    }
+
+    // Width of the left pane, the right one gets the rest
+    pub fn left_pane_width(width: usize) -> usize {
+        width / 2
+    }
+
+    // Every table with unsaved edits was asked to quit
+    pub fn is_quitting(&self) -> bool {
+        self.active().status == Status::QUITTING
+            && self
+                .models
+                .iter()
+                .all(|model| model.status == Status::QUITTING || !model.is_dirty())
+    }
+
+    // Quitting asks about the unsaved edits of each table, one after the other
+    fn quit_next_table(&mut self) -> Result<(), TVError> {
+        if self.active().status != Status::QUITTING {
+            // Cancelled, the tables asked before have to be asked again
+            if !self.active().is_confirming_quit() {
+                for model in self.models.iter_mut() {
+                    model.status = Status::READY;
+                }
+            }
+            return Ok(());
+        }
+        let next = self
+            .models
+            .iter()
+            .position(|model| model.status != Status::QUITTING && model.is_dirty());
+        if let Some(idx) = next {
+            self.show_table(idx);
+            self.resize()?;
+            self.active_mut().quit();
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, message: Option<Message>) -> Result<(), TVError> {
+        let message = match self.directory.as_ref() {
+            Some(directory)
+                if directory.focused && self.active().input_mode() == InputMode::Normal =>
+            {
+                self.update_sidebar(message)?
+            }
+            _ => message,
+        };
+        match message {
+            Some(Message::NextTable) => self.switch_table(1),
+            Some(Message::PreviousTable) => self.switch_table(-1),
+            Some(Message::SwitchPane) => self.switch_pane(),
+            Some(Message::Resize(width, height)) => {
+                // Every table has to fit the new size once it is switched to
+                self.size = (width, height);
+                self.resize()?;
+            }
+            message => {
+                // Keep running after failed commands, the error is shown instead
+                if let Err(e) = self.active_mut().update(message) {
+                    self.active_mut().show_error(format!("{e:?}"));
+                }
+            }
+        }
+
+        match self.active_mut().take_workspace_request() {
+            Some(WorkspaceRequest::Open(path)) => self.open(path, false)?,
+            Some(WorkspaceRequest::Split(path)) => self.open(path, true)?,
+            Some(WorkspaceRequest::Only) => {
+                self.panes = None;
+                self.resize()?;
+            }
+            Some(WorkspaceRequest::ScrollLock(columns)) => self.toggle_scroll_lock(columns),
+            None => (),
+        }
+
+        self.quit_next_table()?;
+
+        if let (Some(columns), Some(other)) = (self.scroll_lock, self.other_pane()) {
+            let position = self.active().scroll_position();
+            self.models[other].scroll_to(position, columns);
+        }
+        Ok(())
+    }
+
+    fn toggle_scroll_lock(&mut self, columns: bool) {
+        self.scroll_lock = if self.scroll_lock == Some(columns) {
+            None
+        } else {
+            Some(columns)
+        };
+        let message = match self.scroll_lock {
+            Some(true) => "Panes scroll together, with their columns",
+            Some(false) => "Panes scroll together",
+            None => "Panes scroll independently",
+        };
+        self.active_mut().set_status_message(message);
+    }
+
+    // Keys moving the selection of the focused sidebar, other messages go to the table
+    fn update_sidebar(&mut self, message: Option<Message>) -> Result<Option<Message>, TVError> {
+        let page = self.size.1.saturating_sub(2) as isize;
+        let Some(directory) = self.directory.as_mut() else {
+            return Ok(message);
+        };
+        match message {
+            Some(Message::MoveDown(rows)) => directory.select(rows as isize),
+            Some(Message::MoveUp(rows)) => directory.select(-(rows as isize)),
+            Some(Message::MovePageDown) => directory.select(page),
+            Some(Message::MovePageUp) => directory.select(-page),
+            Some(Message::MoveEnd) => directory.select(isize::MAX),
+            Some(Message::MoveBeginning) => directory.select(isize::MIN),
+            Some(Message::Enter) => {
+                self.open_selected()?;
+                return Ok(None);
+            }
+            Some(Message::Exit) => {
+                directory.focused = false;
+                self.announce_table();
+                return Ok(None);
+            }
+            message => return Ok(message),
+        }
+        self.announce_file();
+        Ok(None)
+    }
+
+    // Load the file selected in the sidebar into the active pane, or show it if it was loaded before
+    fn open_selected(&mut self) -> Result<(), TVError> {
+        let Some(directory) = self.directory.as_mut() else {
+            return Ok(());
+        };
+        directory.focused = false;
+        let path = directory.selected_path();
+        match self
+            .models
+            .iter()
+            .position(|model| model.file_path() == Some(path.as_path()))
+        {
+            Some(idx) => {
+                self.show_table(idx);
+                self.resize()?;
+                self.announce_table();
+            }
+            None => self.open(path, false)?,
+        }
+        Ok(())
+    }
+
+    fn announce_file(&mut self) {
+        if let Some(directory) = self.directory.as_ref() {
+            let (name, size) = &directory.files[directory.selected];
+            let message = format!("{name}, {}, Enter loads it", format_size(*size));
+            self.active_mut().set_status_message(message);
+        }
+    }
+
+    // Fit the tables to the terminal, or to their pane
+    fn resize(&mut self) -> Result<(), TVError> {
+        let width = self.size.0 - self.sidebar_width();
+        let height = self.size.1;
+        let left_width = Workspace::left_pane_width(width);
+        for (idx, model) in self.models.iter_mut().enumerate() {
+            let width = match self.panes {
+                Some([left, _]) if left == idx => left_width,
+                Some([_, right]) if right == idx => width - left_width,
+                _ => width,
+            };
+            model.update(Some(Message::Resize(width, height)))?;
+        }
+        Ok(())
+    }
+
+    // Load a file as additional table and make it the active one, in a new pane when splitting
+    fn open(&mut self, path: PathBuf, split: bool) -> Result<(), TVError> {
+        let layout = &self.active().uilayout;
+        let loaded =
+            Model::init(&self.config, layout.width, layout.height).and_then(|mut model| {
+                model.load_data_file(path)?;
+                Ok(model)
+            });
+        match loaded {
+            Ok(mut model) => {
+                model.set_event_sender(self.events.clone());
+                self.models.push(model);
+                let opened = self.models.len() - 1;
+                self.panes = match (self.panes, self.other_pane()) {
+                    _ if split => Some([self.active, opened]),
+                    // Without splitting, the file replaces the table of the active pane
+                    (Some([left, _]), Some(other)) if left == self.active => Some([opened, other]),
+                    (Some(_), Some(other)) => Some([other, opened]),
+                    (panes, _) => panes,
+                };
+                self.active = opened;
+                self.resize()?;
+                self.announce_table();
+            }
+            Err(e) => self
+                .active_mut()
+                .show_error(format!("Opening file failed! {e:?}")),
+        }
+        Ok(())
+    }
+
+    fn other_pane(&self) -> Option<usize> {
+        self.panes?.into_iter().find(|&idx| idx != self.active)
+    }
+
+    // Focus the next pane, going from the sidebar to the left and right table
+    fn switch_pane(&mut self) {
+        let last_pane = self.panes.is_none_or(|[_, right]| right == self.active);
+        if let Some(directory) = self.directory.as_mut() {
+            if directory.focused {
+                directory.focused = false;
+                if let Some([left, _]) = self.panes {
+                    self.active = left;
+                }
+                self.announce_table();
+                return;
+            }
+            if last_pane {
+                directory.focused = true;
+                self.announce_file();
+                return;
+            }
+        }
+        match self.other_pane() {
+            Some(other) => {
+                self.active = other;
+                self.announce_table();
+            }
+            None => self
+                .active_mut()
+                .set_status_message("No split, show a file next to this one with :split [file]"),
+        }
+    }
+
+    fn switch_table(&mut self, step: isize) {
+        // The table of the other pane stays where it is
+        let other = self.other_pane();
+        let candidates: Vec<usize> = (0..self.models.len())
+            .filter(|&idx| Some(idx) != other)
+            .collect();
+        if candidates.len() < 2 {
+            self.active_mut()
+                .set_status_message("No other tables! Open one with :open <file>");
+            return;
+        }
+        let position = candidates
+            .iter()
+            .position(|&idx| idx == self.active)
+            .unwrap();
+        let next =
+            candidates[(position as isize + step).rem_euclid(candidates.len() as isize) as usize];
+        self.show_table(next);
+        if self.panes.is_some()
+            && let Err(e) = self.resize()
+        {
+            self.active_mut().show_error(format!("{e:?}"));
+        }
+        self.announce_table();
+    }
+
+    // Make a loaded table the active one, in the pane of the previously active table
+    fn show_table(&mut self, idx: usize) {
+        if Some(idx) != self.other_pane()
+            && let Some(panes) = self.panes.as_mut()
+        {
+            for pane in panes.iter_mut().filter(|pane| **pane == self.active) {
+                *pane = idx;
+            }
+        }
+        self.active = idx;
+    }
+
+    fn announce_table(&mut self) {
+        let message = format!(
+            "Table {}/{}: {}",
+            self.active + 1,
+            self.models.len(),
+            self.active().get_uidata().name
+        );
+        self.active_mut().set_status_message(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyCode;
+
+    use super::*;
+
+    #[test]
+    fn split_panes() {
+        let config = TVConfig::default();
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model
+            .load_data_file(PathBuf::from("tests/fixtures/testdata_01.csv"))
+            .unwrap();
+        let mut workspace = Workspace::new(&config, model);
+        let mut command = vec![Message::EnterCommand];
+        command.extend(
+            "split"
+                .chars()
+                .map(|c| Message::RawKey(KeyCode::Char(c).into())),
+        );
+        command.push(Message::RawKey(KeyCode::Enter.into()));
+        for message in command {
+            workspace.update(Some(message)).unwrap();
+        }
+
+        let (left, right) = workspace.panes().unwrap();
+        assert_eq!((left.uilayout.width, right.uilayout.width), (60, 60));
+        assert_eq!(workspace.active, 1);
+        // The cursors of the panes move independently
+        workspace.update(Some(Message::MoveDown(2))).unwrap();
+        workspace.update(Some(Message::SwitchPane)).unwrap();
+        assert_eq!(workspace.active, 0);
+        let (left, right) = workspace.panes().unwrap();
+        assert_eq!(left.get_uidata().abs_selected_row, 0);
+        assert_eq!(right.get_uidata().abs_selected_row, 2);
+
+        // Unless they are locked
+        workspace.toggle_scroll_lock(false);
+        workspace.update(Some(Message::MoveDown(3))).unwrap();
+        let (left, right) = workspace.panes().unwrap();
+        assert_eq!(left.get_uidata().abs_selected_row, 3);
+        assert_eq!(right.get_uidata().abs_selected_row, 3);
+    }
+
+    fn typed(text: &str) -> Vec<Message> {
+        let mut keys: Vec<Message> = text
+            .chars()
+            .map(|c| Message::RawKey(KeyCode::Char(c).into()))
+            .collect();
+        keys.push(Message::RawKey(KeyCode::Enter.into()));
+        keys
+    }
+
+    #[test]
+    fn quit_asks_for_edits_of_every_table() {
+        let config = TVConfig::default();
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model
+            .load_data_file(PathBuf::from("tests/fixtures/testdata_01.csv"))
+            .unwrap();
+        let mut workspace = Workspace::new(&config, model);
+        let mut messages = vec![Message::EditCell];
+        messages.extend(typed("42"));
+        messages.push(Message::EnterCommand);
+        messages.extend(typed("open tests/fixtures/testdata_01.csv"));
+        for message in messages {
+            workspace.update(Some(message)).unwrap();
+        }
+        assert_eq!(workspace.active, 1);
+        assert!(workspace.models[0].is_dirty());
+
+        // The edited table is shown with the question, Esc cancels quitting
+        workspace.update(Some(Message::Quit)).unwrap();
+        assert!(!workspace.is_quitting());
+        assert_eq!(workspace.active, 0);
+        assert!(workspace.active().is_confirming_quit());
+        workspace.update(Some(Message::Exit)).unwrap();
+        assert!(workspace.models.iter().all(|m| m.status == Status::READY));
+
+        // q in the popup discards the edits
+        workspace.update(Some(Message::Quit)).unwrap();
+        assert!(!workspace.is_quitting());
+        workspace.update(Some(Message::Quit)).unwrap();
+        assert!(workspace.is_quitting());
+    }
+
+    #[test]
+    fn browse_directory() {
+        let config = TVConfig::default();
+        let directory = Directory::scan(&PathBuf::from("tests/fixtures")).unwrap();
+        let mut model = Model::init(&config, 120, 40).unwrap();
+        model.load_data_file(directory.selected_path()).unwrap();
+        let mut workspace = Workspace::new(&config, model);
+        workspace.browse(directory).unwrap();
+        let sidebar_width = workspace.sidebar_width();
+        assert!(sidebar_width > 0);
+        assert_eq!(workspace.active().uilayout.width, 120 - sidebar_width);
+
+        // The selected file is loaded as additional table, which gets the keys
+        workspace.update(Some(Message::MoveDown(2))).unwrap();
+        workspace.update(Some(Message::Enter)).unwrap();
+        assert_eq!(
+            workspace.active().file_path(),
+            Some(PathBuf::from("tests/fixtures/testdata_04.csv").as_path())
+        );
+        assert!(!workspace.directory().unwrap().focused);
+        assert_eq!(workspace.active().uilayout.width, 120 - sidebar_width);
+
+        // Files loaded before are shown again
+        workspace.update(Some(Message::SwitchPane)).unwrap();
+        workspace.update(Some(Message::MoveBeginning)).unwrap();
+        workspace.update(Some(Message::Enter)).unwrap();
+        assert_eq!((workspace.active, workspace.models.len()), (0, 2));
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::Write;
+use std::path::Path;
+
+use calamine::{Data, Reader, Xlsx, open_workbook};
+use polars::prelude::*;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::domain::TVError;
+
+// Workbooks are read with calamine and written with rust_xlsxwriter.
+// Written workbooks have a single sheet with a frozen header row and columns sized to their content.
+
+// Widest a column gets sized to its content, in pixels
+const MAX_COLUMN_WIDTH: u32 = 400;
+
+// Build a typed column, using the narrowest of integer, float, bool or string that fits all values
+fn build_column(name: &str, values: Vec<Option<String>>) -> Column {
+    let present = || values.iter().flatten();
+    if present().all(|v| v.parse::<i64>().is_ok()) {
+        let parsed: Vec<Option<i64>> = values.iter().map(|v| v.as_ref()?.parse().ok()).collect();
+        Column::new(name.into(), parsed)
+    } else if present().all(|v| v.parse::<f64>().is_ok()) {
+        let parsed: Vec<Option<f64>> = values.iter().map(|v| v.as_ref()?.parse().ok()).collect();
+        Column::new(name.into(), parsed)
+    } else if present().all(|v| v == "true" || v == "false") {
+        let parsed: Vec<Option<bool>> = values
+            .iter()
+            .map(|v| v.as_ref().map(|b| b == "true"))
+            .collect();
+        Column::new(name.into(), parsed)
+    } else {
+        Column::new(name.into(), values)
+    }
+}
+
+fn build_frame(mut rows: Vec<Vec<Option<String>>>) -> Result<DataFrame, TVError> {
+    if rows.is_empty() {
+        return Ok(DataFrame::empty());
+    }
+    let ncolumns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let header = rows.remove(0);
+
+    // The first row is the header, missing or repeated names are replaced
+    let mut names: Vec<String> = Vec::with_capacity(ncolumns);
+    for cidx in 0..ncolumns {
+        let mut name = header
+            .get(cidx)
+            .cloned()
+            .flatten()
+            .unwrap_or(format!("column_{}", cidx + 1));
+        if names.contains(&name) {
+            name = format!("{name}_{}", cidx + 1);
+        }
+        names.push(name);
+    }
+
+    let columns = names
+        .iter()
+        .enumerate()
+        .map(|(cidx, name)| {
+            let values = rows
+                .iter()
+                .map(|r| r.get(cidx).cloned().flatten())
+                .collect::<Vec<Option<String>>>();
+            build_column(name, values)
+        })
+        .collect::<Vec<Column>>();
+    Ok(DataFrame::new(columns)?)
+}
+
+// Text of a cell as the type of its column is inferred from, empty cells are missing values
+fn cell_text(cell: &Data) -> Option<String> {
+    match cell {
+        Data::Empty | Data::Error(_) => None,
+        cell => Some(cell.to_string()),
+    }
+}
+
+// Read all sheets of the workbook as (sheet name, data frame) in workbook order
+pub fn read_workbook(path: &Path) -> Result<Vec<(String, DataFrame)>, TVError> {
+    let failed =
+        |e: calamine::XlsxError| TVError::LoadingFailed(format!("Parsing xlsx failed! {e}"));
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(failed)?;
+
+    let mut sheets = Vec::new();
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name).map_err(failed)?;
+        let rows = range
+            .rows()
+            .map(|row| row.iter().map(cell_text).collect())
+            .collect();
+        sheets.push((name, build_frame(rows)?));
+    }
+    if sheets.is_empty() {
+        return Err(TVError::LoadingFailed(
+            "Workbook contains no sheets!".into(),
+        ));
+    }
+    Ok(sheets)
+}
+
+// Cell of a value, numbers and booleans keep their type. Null cells are left out.
+fn write_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &AnyValue,
+) -> Result<(), XlsxError> {
+    match value {
+        AnyValue::Null => (),
+        AnyValue::Boolean(b) => {
+            sheet.write_boolean(row, col, *b)?;
+        }
+        AnyValue::Float32(v) if !v.is_finite() => (),
+        AnyValue::Float64(v) if !v.is_finite() => (),
+        v if v.dtype().is_primitive_numeric() => {
+            if let Some(number) = v.extract::<f64>() {
+                sheet.write_number(row, col, number)?;
+            }
+        }
+        v => {
+            sheet.write_string(row, col, v.str_value().as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+// Write the frame as single sheet workbook with a frozen bold header row and columns sized to their content.
+// The sheet name is shortened to what excel allows.
+pub fn write_workbook(
+    writer: &mut impl Write,
+    frame: &DataFrame,
+    sheet_name: &str,
+) -> Result<(), TVError> {
+    let mut sheet_name: String = sheet_name
+        .chars()
+        .filter(|c| !"[]:*?/\\".contains(*c))
+        .take(31)
+        .collect();
+    if sheet_name.trim().is_empty() || sheet_name.starts_with('\'') || sheet_name.ends_with('\'') {
+        sheet_name = "Sheet1".to_string();
+    }
+
+    let write = || -> Result<Vec<u8>, XlsxError> {
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(&sheet_name)?;
+        let header = Format::new().set_bold();
+        for (cidx, column) in frame.get_columns().iter().enumerate() {
+            let col = cidx as u16;
+            sheet.write_string_with_format(0, col, column.name().as_str(), &header)?;
+            for ridx in 0..frame.height() {
+                if let Ok(value) = column.get(ridx) {
+                    write_cell(sheet, ridx as u32 + 1, col, &value)?;
+                }
+            }
+        }
+        sheet.set_freeze_panes(1, 0)?;
+        sheet.set_autofit_max_width(MAX_COLUMN_WIDTH).autofit();
+        workbook.save_to_buffer()
+    };
+    let bytes = write().map_err(|e| TVError::LoadingFailed(format!("Writing xlsx failed! {e}")))?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_workbook() {
+        let frame = df!(
+            "id" => [1i64, 2],
+            "price" => [Some(19.99), None],
+            "ok" => [true, false],
+            "name" => ["a & <b>", "c"],
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("tv-export-{}.xlsx", std::process::id()));
+        write_workbook(
+            &mut std::fs::File::create(&path).unwrap(),
+            &frame,
+            "data [1]",
+        )
+        .unwrap();
+        let sheets = read_workbook(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (name, read) = sheets.unwrap().remove(0);
+        assert_eq!(name, "data 1");
+        assert!(read.equals_missing(&frame));
+
+        // Every sheet is read, in workbook order
+        let mut workbook = Workbook::new();
+        workbook
+            .add_worksheet()
+            .set_name("b")
+            .unwrap()
+            .write(0, 0, "x")
+            .unwrap();
+        workbook
+            .add_worksheet()
+            .set_name("a")
+            .unwrap()
+            .write(0, 0, "y")
+            .unwrap();
+        workbook.save(&path).unwrap();
+        let sheets = read_workbook(&path);
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<String> = sheets.unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["b", "a"]);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use tracing::{info, warn};
+
+use crate::domain::{Event, RemoteCommand, RemoteReply, TVError};
+
+// Control socket, removed when tv exits
+pub struct Listener {
+    path: PathBuf,
+}
+
+// Accept json commands on the unix socket, one per line, and hand them to the main loop.
+// Every command is answered once tv ran it, with `{"ok": true}`, or `{"ok": false, "error": "..."}`
+// if it could not be parsed or showed a warning, e.g. for an unknown column.
+pub fn listen(path: &Path, sender: Sender<Event>) -> Result<Listener, TVError> {
+    // A socket left behind by a crashed tv can not be bound again. Other files are never removed.
+    if UnixStream::connect(path).is_err() && path.exists() {
+        if !fs::symlink_metadata(path)?.file_type().is_socket() {
+            return Err(TVError::IoError(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{path:?} exists and is no socket"),
+            )));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = bind_private(path)?;
+    info!("Listening for commands on {path:?}");
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let sender = sender.clone();
+                    thread::spawn(move || serve(stream, sender));
+                }
+                Err(e) => warn!("Accepting a connection failed: {e}"),
+            }
+        }
+    });
+    Ok(Listener {
+        path: path.to_path_buf(),
+    })
+}
+
+// Commands can run shell commands and write files, only the user may send them.
+// A socket is created with the permissions of the umask, so it is bound in a directory only the
+// user can enter, made private, and only then moved to the path.
+fn bind_private(path: &Path) -> Result<UnixListener, TVError> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let private = path.with_file_name(format!(".{name}.{}", std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&private)?;
+    let bound = private.join("socket");
+    let result = UnixListener::bind(&bound).and_then(|listener| {
+        fs::set_permissions(&bound, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&bound, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&bound);
+    fs::remove_dir(&private)?;
+    Ok(result?)
+}
+
+fn serve(stream: UnixStream, sender: Sender<Event>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<RemoteCommand>(&line) {
+            Ok(command) => {
+                let (reply, result) = mpsc::channel();
+                if sender
+                    .send(Event::Remote(command, RemoteReply::new(reply)))
+                    .is_err()
+                {
+                    return; // tv is quitting
+                }
+                result
+                    .recv()
+                    .unwrap_or(Err("The command was not run".to_string()))
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        let reply = match result {
+            Ok(()) => serde_json::json!({"ok": true}),
+            Err(error) => serde_json::json!({"ok": false, "error": error}),
+        };
+        if writeln!(writer, "{reply}").is_err() {
+            return;
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn accept_json_commands() {
+        let path = std::env::temp_dir().join(format!("tv-listen-{}.sock", std::process::id()));
+        let (sender, events) = mpsc::channel();
+        let listener = listen(&path, sender).unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        writeln!(stream, r#"{{"cmd": "goto", "row": 42}}"#).unwrap();
+        writeln!(stream, r#"{{"cmd": "goto", "row": 1, "column": "nope"}}"#).unwrap();
+        writeln!(stream, r#"{{"cmd": "jump"}}"#).unwrap();
+        let mut replies = BufReader::new(stream).lines();
+
+        // Answered with the outcome the main loop sends back
+        let Event::Remote(command, reply) = events.recv().unwrap() else {
+            panic!("No remote command");
+        };
+        assert_eq!(
+            command,
+            RemoteCommand::Goto {
+                row: 42,
+                column: None
+            }
+        );
+        reply.send(Ok(()));
+        assert_eq!(replies.next().unwrap().unwrap(), r#"{"ok":true}"#);
+        let Event::Remote(_, reply) = events.recv().unwrap() else {
+            panic!("No remote command");
+        };
+        reply.send(Err("Unknown column: nope".into()));
+        assert_eq!(
+            replies.next().unwrap().unwrap(),
+            r#"{"ok":false,"error":"Unknown column: nope"}"#
+        );
+        assert!(
+            replies
+                .next()
+                .unwrap()
+                .unwrap()
+                .starts_with(r#"{"ok":false,"error":"unknown variant"#)
+        );
+
+        // Never reachable by group or other users, and bound without leaving anything behind
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0);
+        let private = path.with_file_name(format!(
+            ".{}.{}",
+            path.file_name().unwrap().to_string_lossy(),
+            std::process::id()
+        ));
+        assert!(!private.exists());
+        drop(listener);
+        assert!(!path.exists());
+
+        // A file given by mistake is kept
+        let path = std::env::temp_dir().join(format!("tv-listen-{}.csv", std::process::id()));
+        fs::write(&path, "id\n1\n").unwrap();
+        let (sender, _) = mpsc::channel();
+        let result = listen(&path, sender);
+        let kept = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+        assert_eq!(kept.unwrap(), "id\n1\n");
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, LazyLock};
+
+use polars::prelude::DataType;
+use ratatui::style::{Color, Style, palette::tailwind};
+use regex::Regex;
+
+use crate::model::column_view::Column;
+
+// Number of values checked when deciding if a pattern renderer applies to a column
+const PATTERN_DETECTION_SAMPLE_SIZE: usize = 100;
+
+static URL_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(https?|ftp)://[^\s"'<>]+|www\.[^\s"'<>]+"#).unwrap());
+
+pub struct RenderedCell {
+    pub text: String,
+    pub style: Option<Style>,
+}
+
+// Renders the values of columns it applies to, e.g. based on their dtype or content.
+pub trait CellRenderer: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Decides if this renderer is used for the given column
+    fn applies_to(&self, column: &Column) -> bool;
+
+    fn render(&self, value: &str) -> RenderedCell;
+}
+
+// Ordered list of renderers, the first one that applies to a column is used for it.
+pub struct RendererRegistry {
+    renderers: Vec<Arc<dyn CellRenderer>>,
+}
+
+impl RendererRegistry {
+    // Registry containing the builtin renderers
+    pub fn new() -> Self {
+        RendererRegistry {
+            renderers: vec![
+                Arc::new(UrlRenderer),
+                Arc::new(BooleanRenderer),
+                Arc::new(NumberRenderer),
+            ],
+        }
+    }
+
+    // Register a renderer, taking precedence over all renderers registered before
+    pub fn register(&mut self, renderer: Arc<dyn CellRenderer>) {
+        self.renderers.insert(0, renderer);
+    }
+
+    pub fn find(&self, column: &Column) -> Option<Arc<dyn CellRenderer>> {
+        self.renderers
+            .iter()
+            .find(|r| r.applies_to(column))
+            .map(Arc::clone)
+    }
+}
+
+fn is_null(value: &str) -> bool {
+    value == "∅"
+}
+
+// First url contained in the value
+pub fn find_url(value: &str) -> Option<&str> {
+    URL_PATTERN
+        .find(value)
+        .map(|m| m.as_str().trim_end_matches(['.', ',', ')', ';']))
+}
+
+// Underlines values containing urls
+struct UrlRenderer;
+
+impl CellRenderer for UrlRenderer {
+    fn name(&self) -> &str {
+        "url"
+    }
+
+    fn applies_to(&self, column: &Column) -> bool {
+        column.dtype == DataType::String
+            && (0..column.len())
+                .map(|ridx| column.value(ridx))
+                .filter(|v| !is_null(v))
+                .take(PATTERN_DETECTION_SAMPLE_SIZE)
+                .any(|v| find_url(&v).is_some())
+    }
+
+    fn render(&self, value: &str) -> RenderedCell {
+        let style = find_url(value).map(|_| Style::new().fg(tailwind::BLUE.c500).underlined());
+        RenderedCell {
+            text: value.to_string(),
+            style,
+        }
+    }
+}
+
+// Colors boolean values
+struct BooleanRenderer;
+
+impl CellRenderer for BooleanRenderer {
+    fn name(&self) -> &str {
+        "boolean"
+    }
+
+    fn applies_to(&self, column: &Column) -> bool {
+        column.dtype == DataType::Boolean
+    }
+
+    fn render(&self, value: &str) -> RenderedCell {
+        let style = match value {
+            "true" => Some(Style::new().fg(tailwind::GREEN.c500)),
+            "false" => Some(Style::new().fg(tailwind::RED.c500)),
+            _ => None,
+        };
+        RenderedCell {
+            text: value.to_string(),
+            style,
+        }
+    }
+}
+
+// Colors negative numbers
+struct NumberRenderer;
+
+impl CellRenderer for NumberRenderer {
+    fn name(&self) -> &str {
+        "number"
+    }
+
+    fn applies_to(&self, column: &Column) -> bool {
+        column.dtype.is_primitive_numeric()
+    }
+
+    fn render(&self, value: &str) -> RenderedCell {
+        let style = if value.starts_with('-') {
+            Some(Style::new().fg(tailwind::RED.c500))
+        } else {
+            None
+        };
+        RenderedCell {
+            text: value.to_string(),
+            style,
+        }
+    }
+}
+
+// Colors values matching a regular expression, applies to columns in which sampled values match
+pub struct PatternRenderer {
+    name: String,
+    pattern: Regex,
+    style: Style,
+}
+
+impl PatternRenderer {
+    // Create from a `<regex>=<color>` specification, e.g. `^ERR.*=red`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (pattern, color) = spec.rsplit_once('=').ok_or(format!(
+            "Renderer \"{spec}\" is not of the form <regex>=<color>"
+        ))?;
+        let color = color
+            .parse::<Color>()
+            .map_err(|_| format!("Unknown color \"{color}\""))?;
+        let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {e}"))?;
+        Ok(PatternRenderer {
+            name: spec.to_string(),
+            pattern: regex,
+            style: Style::new().fg(color),
+        })
+    }
+}
+
+impl CellRenderer for PatternRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn applies_to(&self, column: &Column) -> bool {
+        (0..column.len())
+            .map(|ridx| column.value(ridx))
+            .filter(|v| !is_null(v))
+            .take(PATTERN_DETECTION_SAMPLE_SIZE)
+            .any(|v| self.pattern.is_match(&v))
+    }
+
+    fn render(&self, value: &str) -> RenderedCell {
+        let style = if self.pattern.is_match(value) {
+            Some(self.style)
+        } else {
+            None
+        };
+        RenderedCell {
+            text: value.to_string(),
+            style,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+use serde_json::json;
+
+use crate::domain::TVError;
+
+// Most frequent values listed per column
+const TOP_VALUES: usize = 3;
+// Characters of a value shown in the report
+const MAX_VALUE_WIDTH: usize = 30;
+
+fn shorten(value: &str) -> String {
+    if value.chars().count() > MAX_VALUE_WIDTH {
+        let short: String = value.chars().take(MAX_VALUE_WIDTH - 1).collect();
+        format!("{short}…")
+    } else {
+        value.to_string()
+    }
+}
+
+// Per column profile of every sheet: dtype, nulls, distinct values, min/max/mean and top values
+pub fn stats(sheets: &[(String, DataFrame)]) -> Result<String, TVError> {
+    let mut report = String::new();
+    for (name, frame) in sheets {
+        report.push_str(&format!(
+            "{name}: {} rows, {} columns\n",
+            frame.height(),
+            frame.width()
+        ));
+        for column in frame.get_columns() {
+            report.push_str(&format!("\n{} ({})\n", column.name(), column.dtype()));
+            report.push_str(&format!(
+                "  nulls {} | distinct {}",
+                column.null_count(),
+                column.n_unique()?
+            ));
+
+            let dtype = column.dtype();
+            let orderable = dtype.is_primitive_numeric()
+                || dtype.is_temporal()
+                || matches!(dtype, DataType::String | DataType::Boolean);
+            if orderable && column.null_count() < column.len() {
+                let min = column.min_reduce()?.into_value();
+                let max = column.max_reduce()?.into_value();
+                report.push_str(&format!(
+                    " | min {} | max {}",
+                    shorten(&min.str_value()),
+                    shorten(&max.str_value())
+                ));
+            }
+            if dtype.is_primitive_numeric()
+                && let Some(mean) = column.as_materialized_series().mean()
+            {
+                report.push_str(&format!(" | mean {mean:.2}"));
+            }
+            report.push('\n');
+
+            let values = column.cast(&DataType::String)?;
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for value in values.str()?.into_iter().flatten() {
+                *counts.entry(value).or_default() += 1;
+            }
+            let mut top: Vec<(&str, usize)> = counts.into_iter().collect();
+            top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            if !top.is_empty() {
+                let top: Vec<String> = top
+                    .iter()
+                    .take(TOP_VALUES)
+                    .map(|(value, count)| format!("{} ({count})", shorten(value)))
+                    .collect();
+                report.push_str(&format!("  top {}\n", top.join(", ")));
+            }
+        }
+        report.push('\n');
+    }
+    Ok(report)
+}
+
+// Column names and dtypes of every sheet, as aligned text or json
+pub fn schema(sheets: &[(String, DataFrame)], as_json: bool) -> String {
+    if as_json {
+        let sheets: Vec<serde_json::Value> = sheets
+            .iter()
+            .map(|(name, frame)| {
+                let columns: Vec<serde_json::Value> = frame
+                    .get_columns()
+                    .iter()
+                    .map(|c| json!({"name": c.name().as_str(), "dtype": c.dtype().to_string()}))
+                    .collect();
+                json!({"name": name, "rows": frame.height(), "columns": columns})
+            })
+            .collect();
+        return format!("{}\n", serde_json::Value::Array(sheets));
+    }
+
+    let mut report = String::new();
+    for (name, frame) in sheets {
+        report.push_str(&format!("{name}: {} rows\n", frame.height()));
+        let width = frame
+            .get_column_names()
+            .iter()
+            .map(|n| n.chars().count())
+            .max()
+            .unwrap_or(0);
+        for column in frame.get_columns() {
+            report.push_str(&format!(
+                "  {:width$}  {}\n",
+                column.name().as_str(),
+                column.dtype()
+            ));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_stats() {
+        let frame = df!(
+            "qty" => [Some(1), Some(3), None, Some(3)],
+            "name" => ["b", "a", "b", "b"],
+        )
+        .unwrap();
+        let report = stats(&[("data.csv".to_string(), frame)]).unwrap();
+        assert!(report.starts_with("data.csv: 4 rows, 2 columns\n"));
+        assert!(report.contains("qty (i32)\n  nulls 1 | distinct 3 | min 1 | max 3 | mean 2.33\n"));
+        assert!(report.contains("  top b (3), a (1)\n"));
+    }
+
+    #[test]
+    fn print_schema() {
+        let frame = df!("quantity" => [1i64], "ok" => [true]).unwrap();
+        let sheets = [("data.csv".to_string(), frame)];
+        assert_eq!(
+            schema(&sheets, false),
+            "data.csv: 1 rows\n  quantity  i64\n  ok        bool\n"
+        );
+        assert_eq!(
+            schema(&sheets, true),
+            "[{\"name\":\"data.csv\",\"rows\":1,\"columns\":[{\"name\":\"quantity\",\"dtype\":\"i64\"},{\"name\":\"ok\",\"dtype\":\"bool\"}]}]\n"
+        );
+    }
+}
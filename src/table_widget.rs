@@ -0,0 +1,282 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::widgets::{
+    Cell, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget, Table, TableState,
+    Widget,
+};
+
+use crate::model::column_view::ColumnView;
+use crate::tui::TABLE_HEADER_HEIGHT;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableStyles {
+    pub row: Style,
+    pub alt_row: Style,
+    pub selected_row: Style,
+    pub highlighted_row: Style,
+    pub bookmarked_row: Style,
+    pub marked_row: Style,
+    pub header: Style,
+    pub selected_cell: Style,
+    pub selected_column: Style, // Column of the curser in crosshair mode
+}
+
+// Which part of the rows is shown and where the curser is, kept between draws
+#[derive(Debug, Default)]
+pub struct TableWidgetState {
+    pub offset: usize, // Row of the data shown in the first visible row
+    pub selected: Option<(usize, usize)>, // Visible row and column of the curser
+    pub total_rows: usize, // Rows of the data, the scrollbar shows which of them are visible
+    table: TableState,
+    scrollbar: ScrollbarState,
+}
+
+impl TableWidgetState {
+    pub fn select(&mut self, offset: usize, selected: Option<(usize, usize)>, total_rows: usize) {
+        self.offset = offset;
+        self.selected = selected;
+        self.total_rows = total_rows;
+    }
+}
+
+// Grid of the visible rows of columns, below a header row that never scrolls,
+// with a scrollbar if not all rows of the data fit
+pub struct TableWidget<'a> {
+    columns: &'a [ColumnView],
+    styles: TableStyles,
+    highlighted_rows: &'a [bool], // Per visible row, the first of them wins
+    bookmarked_rows: &'a [bool],
+    marked_rows: &'a [bool],
+    sorted_column: Option<(usize, bool)>, // Visible column the rows are sorted by, and if ascending
+    crosshair: bool,
+    ascii: bool,   // NULL instead of ∅
+    stripe: usize, // Rows per group of alternating background color, 0 disables striping
+}
+
+impl<'a> TableWidget<'a> {
+    pub fn new(columns: &'a [ColumnView], styles: TableStyles) -> Self {
+        TableWidget {
+            columns,
+            styles,
+            highlighted_rows: &[],
+            bookmarked_rows: &[],
+            marked_rows: &[],
+            sorted_column: None,
+            crosshair: false,
+            ascii: false,
+            stripe: 0,
+        }
+    }
+
+    pub fn row_marks(
+        mut self,
+        highlighted: &'a [bool],
+        bookmarked: &'a [bool],
+        marked: &'a [bool],
+    ) -> Self {
+        self.highlighted_rows = highlighted;
+        self.bookmarked_rows = bookmarked;
+        self.marked_rows = marked;
+        self
+    }
+
+    pub fn sorted_column(mut self, sorted_column: Option<(usize, bool)>) -> Self {
+        self.sorted_column = sorted_column;
+        self
+    }
+
+    pub fn crosshair(mut self, crosshair: bool) -> Self {
+        self.crosshair = crosshair;
+        self
+    }
+
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    pub fn stripe(mut self, stripe: usize) -> Self {
+        self.stripe = stripe;
+        self
+    }
+
+    // Alternating background for groups of rows, based on the absolute row so stripes scroll with the rows
+    fn stripe_style(&self, row: usize) -> Style {
+        if self.stripe > 0 && (row / self.stripe) % 2 == 1 {
+            self.styles.alt_row
+        } else {
+            self.styles.row
+        }
+    }
+
+    fn row_style(&self, ridx: usize, offset: usize) -> Style {
+        let is = |rows: &[bool]| rows.get(ridx).copied().unwrap_or(false);
+        let style = if is(self.marked_rows) {
+            self.styles.marked_row
+        } else if is(self.bookmarked_rows) {
+            self.styles.bookmarked_row
+        } else if is(self.highlighted_rows) {
+            self.styles.highlighted_row
+        } else {
+            self.stripe_style(offset + ridx)
+        };
+        // In crosshair mode everything except the curser row and column is dimmed
+        if self.crosshair { style.dim() } else { style }
+    }
+
+    fn header(&self) -> Row<'a> {
+        if self.columns.is_empty() {
+            return Row::new([Cell::from("NO DATA")]);
+        }
+        Row::new(
+            self.columns
+                .iter()
+                .enumerate()
+                .map(|(idx, c)| match self.sorted_column {
+                    Some((sorted, ascending)) if sorted == idx => {
+                        let mut name: String =
+                            c.name.chars().take(c.width.saturating_sub(2)).collect();
+                        name.push(if ascending { '▲' } else { '▼' });
+                        Cell::from(name)
+                    }
+                    _ => Cell::from(c.name.as_str()),
+                }),
+        )
+        .style(self.styles.header)
+    }
+}
+
+impl<'a> StatefulWidget for TableWidget<'a> {
+    type State = TableWidgetState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let widths: Vec<Constraint> = match self.columns.is_empty() {
+            true => vec![Constraint::Length(area.width)],
+            false => self
+                .columns
+                .iter()
+                .map(|c| Constraint::Length(c.width as u16))
+                .collect(),
+        };
+        // The header is a separate table in a fixed top row, so it never scrolls with the rows
+        let [header_area, area] = Layout::vertical([
+            Constraint::Length(TABLE_HEADER_HEIGHT as u16),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        Widget::render(
+            Table::new([self.header()], widths.clone()),
+            header_area,
+            buf,
+        );
+
+        let text = |value: &'a String| match value.as_str() {
+            "∅" if self.ascii => "NULL",
+            value => value,
+        };
+        let nrows = self.columns.first().map_or(0, |c| c.data.len());
+        let mut rows: Vec<Row> = (0..nrows)
+            .map(|ridx| {
+                Row::new(self.columns.iter().map(|c| {
+                    let cell = Cell::from(text(&c.data[ridx]));
+                    match c.styles.get(ridx).copied().flatten() {
+                        Some(cell_style) => cell.style(cell_style),
+                        None => cell,
+                    }
+                }))
+                .style(self.row_style(ridx, state.offset))
+            })
+            .collect();
+        // Fill up the rest of the table with empty strings to have the empty part of the table render with the same style.
+        let viewport = area.height as usize;
+        for _ in nrows..viewport {
+            rows.push(Row::new([""].repeat(self.columns.len().max(1))).style(self.styles.row));
+        }
+
+        let mut table = Table::new(rows, widths)
+            .row_highlight_style(self.styles.selected_row)
+            .cell_highlight_style(self.styles.selected_cell);
+        if self.crosshair {
+            table = table
+                .row_highlight_style(self.styles.selected_row.not_dim())
+                .column_highlight_style(self.styles.selected_column.not_dim())
+                .cell_highlight_style(self.styles.selected_cell.not_dim().reversed());
+        }
+        state
+            .table
+            .select_cell(state.selected.filter(|_| nrows > 0));
+        StatefulWidget::render(table, area, buf, &mut state.table);
+
+        // Everything fits on the screen, there is nothing to scroll
+        if state.total_rows > viewport {
+            // The scrollable content are the possible row offsets, so the thumb covers the visible rows
+            state.scrollbar = state
+                .scrollbar
+                .content_length(state.total_rows - viewport + 1)
+                .viewport_content_length(viewport)
+                .position(state.offset);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight).render(
+                area,
+                buf,
+                &mut state.scrollbar,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, width: usize, data: &[&str]) -> ColumnView {
+        ColumnView {
+            name: name.to_string(),
+            width,
+            data: data.iter().map(|v| v.to_string()).collect(),
+            styles: Vec::new(),
+        }
+    }
+
+    fn lines(buf: &Buffer) -> Vec<String> {
+        (0..buf.area.height)
+            .map(|y| {
+                (0..buf.area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn render_table_widget() {
+        let columns = [
+            column("id", 3, &["1", "2", "3"]),
+            column("name", 6, &["a", "∅", "c"]),
+        ];
+        let mut state = TableWidgetState::default();
+        state.select(4, Some((1, 1)), 10);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 12, 4));
+        TableWidget::new(&columns, TableStyles::default())
+            .sorted_column(Some((1, false)))
+            .ascii(true)
+            .render(buf.area, &mut buf, &mut state);
+        assert_eq!(
+            lines(&buf),
+            [
+                "id  name▼   ",
+                "1   a      ▲",
+                "2   NULL   █",
+                "3   c      ▼"
+            ]
+        );
+        assert_eq!(state.table.selected_cell(), Some((1, 1)));
+
+        // Without rows there is no curser, and all rows fit
+        let mut buf = Buffer::empty(Rect::new(0, 0, 12, 2));
+        state.select(0, Some((0, 0)), 0);
+        TableWidget::new(&[], TableStyles::default()).render(buf.area, &mut buf, &mut state);
+        assert_eq!(lines(&buf), ["NO DATA     ", "            "]);
+        assert_eq!(state.table.selected_cell(), None);
+    }
+}
@@ -1,25 +1,40 @@
 use ratatui::layout::{Constraint, Layout, Margin, Position};
 use ratatui::style::{Color, Style, palette::tailwind};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{
-    Block, Borders, Cell, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
-    TableState,
+    Axis, Bar, BarChart, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, Paragraph,
+    Row, Table, TableState,
 };
 use ratatui::{Frame, layout::Rect};
+use std::io::{self, Write};
 use std::time::Instant;
 
-use crate::domain::TVConfig;
-use crate::model::{UIData, UILayout};
+use crate::capabilities::{ascii_symbols, degrade_colors, strip_colors};
+use crate::domain::{InputMode, TVConfig};
+use crate::graphics::{self, Image, Protocol};
+use crate::model::{
+    ChartData, Directory, JsonToken, LoadPreview, LoadProgress, MessageLevel, PlotData, UIData,
+    UILayout, format_size,
+};
 use crate::popup::Popup;
+use crate::table_widget::{TableStyles, TableWidget, TableWidgetState};
 
 pub const INDEX_COLUMN_BORDER: usize = 2;
 pub const SCROLLBAR_WIDTH: usize = 1;
 pub const TABLE_HEADER_HEIGHT: usize = 1;
 pub const CMDLINE_HEIGH: usize = 1;
+pub const CELL_LINE_HEIGHT: usize = 1;
+pub const TOP_VALUES_PANEL_ROWS: usize = 5;
+pub const TOP_VALUES_PANEL_HEIGHT: usize = TOP_VALUES_PANEL_ROWS + 1; // With its title line
+pub const TOTALS_ROW_HEIGHT: usize = 1;
 pub const POPUP_VERTICAL_MARGIN: usize = 3;
 pub const MAX_POPUP_CONTENT_WIDTH: usize = 65;
 pub const STATUS_MESSAGE_DISPLAY_DURATION: std::time::Duration = std::time::Duration::new(2, 0);
 pub const COLUMN_WIDTH_MARGIN: usize = 1;
 pub const COLUMN_WIDTH_COLLAPSED_COLUMN: usize = 3;
+// Widest column of the table preview shown while loading
+const PREVIEW_COLUMN_WIDTH: usize = 25;
 
 #[derive(Clone)]
 struct UIColors {
@@ -27,15 +42,24 @@ struct UIColors {
     header_bg: Color,
     header_fg: Color,
     row_fg: Color,
-    row_bg: Color,
     selected_row_fg: Color,
     selected_row_bg: Color,
+    highlighted_row_fg: Color,
+    highlighted_row_bg: Color,
+    bookmarked_row_fg: Color,
+    bookmarked_row_bg: Color,
+    marked_row_fg: Color,
+    marked_row_bg: Color,
     selected_column_fg: Color,
     selected_cell_fg: Color,
     selected_cell_bg: Color,
     normal_row_color: Color,
     alt_row_color: Color,
     footer_border_color: Color,
+    warning_fg: Color,
+    warning_bg: Color,
+    error_fg: Color,
+    error_bg: Color,
 }
 
 impl UIColors {
@@ -45,15 +69,24 @@ impl UIColors {
             header_bg: color.c900,
             header_fg: tailwind::SLATE.c200,
             row_fg: tailwind::SLATE.c200,
-            row_bg: tailwind::SLATE.c950,
             selected_row_fg: tailwind::YELLOW.c100,
             selected_row_bg: tailwind::YELLOW.c950,
+            highlighted_row_fg: tailwind::ORANGE.c200,
+            highlighted_row_bg: tailwind::ORANGE.c950,
+            bookmarked_row_fg: tailwind::VIOLET.c200,
+            bookmarked_row_bg: tailwind::VIOLET.c950,
+            marked_row_fg: tailwind::CYAN.c100,
+            marked_row_bg: tailwind::CYAN.c900,
             selected_column_fg: color.c400,
             selected_cell_fg: tailwind::BLUE.c600,
             selected_cell_bg: tailwind::BLUE.c50,
             normal_row_color: tailwind::SLATE.c950,
             alt_row_color: tailwind::SLATE.c900,
             footer_border_color: color.c400,
+            warning_fg: tailwind::AMBER.c100,
+            warning_bg: tailwind::AMBER.c800,
+            error_fg: tailwind::RED.c50,
+            error_bg: tailwind::RED.c800,
         }
     }
 
@@ -63,60 +96,136 @@ impl UIColors {
             header_bg: color.c100,
             header_fg: tailwind::SLATE.c800,
             row_fg: tailwind::SLATE.c700,
-            row_bg: tailwind::SLATE.c50,
             selected_row_fg: tailwind::AMBER.c900,
             selected_row_bg: tailwind::AMBER.c100,
+            highlighted_row_fg: tailwind::ORANGE.c900,
+            highlighted_row_bg: tailwind::ORANGE.c200,
+            bookmarked_row_fg: tailwind::VIOLET.c900,
+            bookmarked_row_bg: tailwind::VIOLET.c200,
+            marked_row_fg: tailwind::CYAN.c950,
+            marked_row_bg: tailwind::CYAN.c200,
             selected_column_fg: color.c700,
             selected_cell_fg: tailwind::BLUE.c50,
             selected_cell_bg: tailwind::BLUE.c600,
             normal_row_color: tailwind::SLATE.c50,
             alt_row_color: tailwind::SLATE.c100,
             footer_border_color: color.c600,
+            warning_fg: tailwind::AMBER.c950,
+            warning_bg: tailwind::AMBER.c300,
+            error_fg: tailwind::RED.c50,
+            error_bg: tailwind::RED.c600,
         }
     }
 }
 struct UIStyles {
     row: Style,
+    alt_row: Style,
     selected_row: Style,
+    highlighted_row: Style,
+    bookmarked_row: Style,
+    marked_row: Style,
     header: Style,
     statusline: Style,
+    status_warning: Style,
+    status_error: Style,
     selected_cell: Style,
+    selected_column: Style, // Column of the curser in crosshair mode
     popup: Style,
 }
 impl UIStyles {
     const fn new(colors: &UIColors) -> Self {
         Self {
-            row: Style::new().fg(colors.row_fg).bg(colors.row_bg),
+            row: Style::new().fg(colors.row_fg).bg(colors.normal_row_color),
+            alt_row: Style::new().fg(colors.row_fg).bg(colors.alt_row_color),
             selected_row: Style::new()
                 .fg(colors.selected_row_fg)
                 .bg(colors.selected_row_bg),
+            highlighted_row: Style::new()
+                .fg(colors.highlighted_row_fg)
+                .bg(colors.highlighted_row_bg),
+            bookmarked_row: Style::new()
+                .fg(colors.bookmarked_row_fg)
+                .bg(colors.bookmarked_row_bg)
+                .bold(),
+            marked_row: Style::new()
+                .fg(colors.marked_row_fg)
+                .bg(colors.marked_row_bg),
             header: Style::new()
                 .fg(colors.header_fg)
                 .bg(colors.header_bg)
                 .bold()
                 .underlined(),
             statusline: Style::new().fg(colors.header_fg).bg(colors.header_bg),
+            status_warning: Style::new().fg(colors.warning_fg).bg(colors.warning_bg),
+            status_error: Style::new().fg(colors.error_fg).bg(colors.error_bg).bold(),
             selected_cell: Style::new()
                 .fg(colors.selected_cell_fg)
                 .bg(colors.selected_cell_bg)
                 .bold()
                 .underlined(),
+            selected_column: Style::new()
+                .fg(colors.selected_column_fg)
+                .bg(colors.selected_row_bg),
             popup: Style::new().fg(colors.header_fg).bg(colors.header_bg),
         }
     }
+
+    fn table(&self) -> TableStyles {
+        TableStyles {
+            row: self.row,
+            alt_row: self.alt_row,
+            selected_row: self.selected_row,
+            highlighted_row: self.highlighted_row,
+            bookmarked_row: self.bookmarked_row,
+            marked_row: self.marked_row,
+            header: self.header,
+            selected_cell: self.selected_cell,
+            selected_column: self.selected_column,
+        }
+    }
+
+    // Without colors, rows and cells are told apart by modifiers only
+    fn monochrome() -> Self {
+        Self {
+            row: Style::new(),
+            alt_row: Style::new(),
+            selected_row: Style::new().reversed(),
+            highlighted_row: Style::new().bold(),
+            bookmarked_row: Style::new().bold().underlined(),
+            marked_row: Style::new().italic().underlined(),
+            header: Style::new().bold().underlined(),
+            statusline: Style::new().reversed(),
+            status_warning: Style::new().bold(),
+            status_error: Style::new().bold().reversed(),
+            selected_cell: Style::new().bold().underlined(),
+            selected_column: Style::new().bold(),
+            popup: Style::new(),
+        }
+    }
 }
 
 pub struct TableUI {
     styles: UIStyles,
-    table_state: TableState,
-    scrollbar_state: ScrollbarState,
+    truecolor: bool,
+    no_color: bool,
+    ascii: bool,   // Draw ascii characters only, NULL instead of ∅
+    stripe: usize, // Rows per group of alternating background color, 0 disables striping
+    table_state: TableWidgetState,
     last_render: Instant,
+    graphics: Option<Protocol>, // Charts and plots are drawn as images, if the terminal can show them
+    image_id: u32,
+    image: Option<String>, // Escape sequence of the image of the drawn frame
+    image_shown: bool,
     //headers: Vec<HeaderElement>,
     //visible_headers: Vec<usize>,
 }
 
 struct TableUILayout {
     table: Rect,
+    totals: Rect,
+    totals_label: Rect, // Below the index column
+    panel: Rect,
+    cell_line: Rect,
     statusline: Rect,
     index: Rect,
 }
@@ -135,22 +244,37 @@ impl TableUI {
         } else {
             UIColors::new_dark(&PALETTES[0])
         };
-        let styles = UIStyles::new(&colors);
+        let styles = if config.no_color {
+            UIStyles::monochrome()
+        } else {
+            UIStyles::new(&colors)
+        };
 
         Self {
             styles,
-            table_state: TableState::default(),
-            scrollbar_state: ScrollbarState::new(1).position(0),
+            truecolor: config.capabilities.truecolor,
+            no_color: config.no_color,
+            ascii: config.ascii,
+            stripe: config.stripe,
+            table_state: TableWidgetState::default(),
             last_render: Instant::now() - std::time::Duration::from_secs(1),
+            graphics: Protocol::detect(&config.capabilities)
+                .filter(|_| !config.no_color && !config.ascii),
+            image_id: graphics::image_id(),
+            image: None,
+            image_shown: false,
         }
     }
 
-    fn create_layout(frame: &Frame, s: &UILayout) -> TableUILayout {
+    fn create_layout(area: Rect, s: &UILayout) -> TableUILayout {
         let vertical = &Layout::vertical([
             Constraint::Length((s.table_height + TABLE_HEADER_HEIGHT) as u16),
+            Constraint::Length(s.totals_height as u16),
+            Constraint::Length(s.panel_height as u16),
+            Constraint::Length(CELL_LINE_HEIGHT as u16),
             Constraint::Length(s.statusline_height as u16),
         ]);
-        let vsplit = vertical.split(frame.area());
+        let vsplit = vertical.split(area);
 
         let mut index_width = s.index_width;
         if index_width > 0 {
@@ -161,30 +285,253 @@ impl TableUI {
             Constraint::Length((s.table_width + SCROLLBAR_WIDTH) as u16),
         ]);
         let hsplit = horizontal.split(vsplit[0]);
+        let totals = horizontal.split(vsplit[1]);
         //trace!("Splitting table for index from total={}, index={}, table={}", vsplit[0].width, hsplit[0].width, hsplit[1].width);
 
         TableUILayout {
             table: hsplit[1],
-            statusline: vsplit[1],
+            totals: totals[1],
+            totals_label: totals[0],
+            panel: vsplit[2],
+            cell_line: vsplit[3],
+            statusline: vsplit[4],
             index: hsplit[0],
         }
     }
 
     pub fn draw(&mut self, data: &UIData, frame: &mut Frame) {
-        let layout = Self::create_layout(frame, &data.layout);
+        self.draw_in(data, frame, frame.area());
+    }
+
+    // Draw the table into a part of the frame, e.g. a pane of a split
+    pub fn draw_in(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let layout = Self::create_layout(area, &data.layout);
 
         self.render_table(data, frame, layout.table);
         self.render_index(data, frame, layout.index);
+        self.render_totals(data, frame, layout.totals, layout.totals_label);
+        self.render_top_values(data, frame, layout.panel);
         self.render_statusline(data, frame, layout.statusline);
+        self.render_cell_line(data, frame, layout.cell_line);
 
+        if data.chart.is_some() {
+            self.render_chart(data, frame, layout.table);
+        }
+        if data.plot.is_some() {
+            self.render_plot(data, frame, layout.table);
+        }
+        if data.json.is_some() {
+            self.render_json(data, frame, layout.table);
+        }
         if data.show_popup {
             self.render_popup(data, frame, layout.table);
         }
+        if data.profile.show {
+            self.render_profile(data, frame, layout.table);
+        }
+        if !data.key_hints.is_empty() {
+            self.render_key_hints(data, frame, layout.table);
+        }
+        self.adapt_buffer(frame);
         self.last_render = Instant::now();
     }
 
+    // Show the image of the drawn frame, or remove the one shown before. Images are not part of the
+    // frame, they are written after it. Returns true if the terminal has to be cleared to remove it.
+    pub fn write_graphics(&mut self, out: &mut impl Write) -> io::Result<bool> {
+        let Some(protocol) = self.graphics else {
+            return Ok(false);
+        };
+        let mut sequence = String::new();
+        // The image of the last frame is replaced
+        if self.image_shown {
+            match protocol.remove(self.image_id) {
+                Some(remove) => sequence.push_str(&remove),
+                None if self.image.is_none() => {
+                    self.image_shown = false;
+                    return Ok(true);
+                }
+                None => {}
+            }
+        }
+        self.image_shown = self.image.is_some();
+        if let Some(image) = self.image.take() {
+            sequence.push_str(&image);
+        }
+        if !sequence.is_empty() {
+            out.write_all(sequence.as_bytes())?;
+            out.flush()?;
+        }
+        Ok(false)
+    }
+
+    // Draw everything again with the next frame, e.g. after the terminal was cleared
+    pub fn invalidate(&mut self) {
+        self.last_render = Instant::now() - std::time::Duration::from_secs(1);
+    }
+
+    // Fit the rendered frame to the terminal and the colors and characters the user asked for
+    fn adapt_buffer(&self, frame: &mut Frame) {
+        if self.no_color {
+            strip_colors(frame.buffer_mut());
+        } else if !self.truecolor {
+            degrade_colors(frame.buffer_mut());
+        }
+        if self.ascii {
+            ascii_symbols(frame.buffer_mut());
+        }
+    }
+
+    // Files of the browsed directory with their sizes, the selection is highlighted while the sidebar has focus
+    pub fn draw_sidebar(&self, directory: &Directory, frame: &mut Frame, area: Rect) {
+        let title = directory
+            .path
+            .file_name()
+            .map_or(directory.path.to_string_lossy(), |name| {
+                name.to_string_lossy()
+            });
+        let block = Block::default()
+            .borders(Borders::RIGHT)
+            .title(Line::from(title.to_string()).style(self.styles.header))
+            .style(self.styles.row);
+        let rows = directory
+            .files
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, size))| {
+                let style = match idx == directory.selected {
+                    true if directory.focused => self.styles.selected_row,
+                    true => self.styles.marked_row,
+                    false => self.styles.row,
+                };
+                Row::new([
+                    Cell::from(name.as_str()),
+                    Cell::from(Line::from(format_size(*size)).right_aligned()),
+                ])
+                .style(style)
+            });
+        let widths = [Constraint::Fill(1), Constraint::Length(9)];
+        let mut state = TableState::default().with_selected(Some(directory.selected));
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(Table::new(rows, widths).block(block), area, &mut state);
+    }
+
+    // Only redraw if the model changed since the last render, or a status message has to be hidden
+    // Progress bar shown while the data is loaded, before there is a table to show
+    pub fn draw_loading(&mut self, progress: &LoadProgress, frame: &mut Frame) {
+        let ratio = if progress.rows_total > 0 {
+            progress.rows_done as f64 / progress.rows_total as f64
+        } else {
+            0.0
+        };
+        let label = format!(
+            "{} of {} columns, {:.0}% of rows",
+            progress.columns_done,
+            progress.columns_total,
+            ratio * 100.0
+        );
+        let gauge = Gauge::default()
+            .block(Block::bordered().title("Loading"))
+            .gauge_style(self.styles.header)
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(label);
+        let area = frame.area();
+
+        // With a preview, the start of the table is shown above the progress
+        if let Some(preview) = progress.preview.as_ref() {
+            frame.render_widget(Clear, area);
+            let [title_area, table_area, gauge_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Fill(1),
+                Constraint::Length(3),
+            ])
+            .areas(area);
+            let title = format!("{}, loading ...", Self::preview_title(preview));
+            frame.render_widget(Paragraph::new(title).style(self.styles.header), title_area);
+            self.render_preview_table(preview, frame, table_area);
+            frame.render_widget(gauge, gauge_area);
+            self.adapt_buffer(frame);
+            return;
+        }
+
+        let [_, area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        let area = area.inner(Margin {
+            vertical: 0,
+            horizontal: area.width / 6,
+        });
+        frame.render_widget(Clear, area);
+        frame.render_widget(gauge, area);
+        self.adapt_buffer(frame);
+    }
+
+    // Schema and first rows of a file before it is loaded, with the keys adjusting how it is parsed
+    pub fn draw_preview(&mut self, preview: Option<&LoadPreview>, frame: &mut Frame) {
+        let area = frame.area();
+        frame.render_widget(Clear, area);
+        let [title_area, table_area, help_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+        let title = match preview {
+            Some(preview) => match preview.parsing.as_ref() {
+                Some(parsing) => format!("{} ({parsing})", Self::preview_title(preview)),
+                None => Self::preview_title(preview),
+            },
+            None => "No preview of this file".to_string(),
+        };
+        frame.render_widget(Paragraph::new(title).style(self.styles.header), title_area);
+        if let Some(preview) = preview {
+            self.render_preview_table(preview, frame, table_area);
+        }
+        let help = match preview.is_some_and(|p| p.parsing.is_some()) {
+            true => "Enter: load  d: next delimiter  h: toggle header row  q: quit",
+            false => "Enter: load  q: quit",
+        };
+        frame.render_widget(Paragraph::new(help).style(self.styles.header), help_area);
+        self.adapt_buffer(frame);
+    }
+
+    // E.g. `4 columns, ~1200 rows`
+    fn preview_title(preview: &LoadPreview) -> String {
+        let nrows = match preview.nrows {
+            Some(n) if preview.nrows_exact => format!("{n} rows"),
+            Some(n) => format!("~{n} rows"),
+            None => "counting rows".to_string(),
+        };
+        format!("{} columns, {nrows}", preview.columns.len())
+    }
+
+    fn render_preview_table(&self, preview: &LoadPreview, frame: &mut Frame, area: Rect) {
+        let widths = preview.columns.iter().enumerate().map(|(cidx, (name, _))| {
+            let width = preview
+                .rows
+                .iter()
+                .filter_map(|row| row.get(cidx))
+                .map(|value| value.chars().count())
+                .fold(name.chars().count(), usize::max);
+            Constraint::Length(width.min(PREVIEW_COLUMN_WIDTH) as u16)
+        });
+        let header = Row::new(preview.columns.iter().map(|(name, _)| name.as_str()))
+            .style(self.styles.header);
+        let rows = preview
+            .rows
+            .iter()
+            .map(|row| Row::new(row.iter().map(|v| v.as_str())).style(self.styles.row));
+        frame.render_widget(Table::new(rows, widths).header(header), area);
+    }
+
     pub fn needs_redrawing(&self, data: &UIData) -> bool {
+        let status_message_expiry =
+            data.last_status_message_update + STATUS_MESSAGE_DISPLAY_DURATION;
         data.last_update - self.last_render > std::time::Duration::ZERO
+            || (self.last_render < status_message_expiry && status_message_expiry <= Instant::now())
     }
 
     // pub fn get_table_size(&self, frame: &Frame, model: &Model) -> (usize, usize) {
@@ -219,11 +566,69 @@ impl TableUI {
         }
     }
 
+    // Timings of the main loop in the top right corner
+    fn render_profile(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let profile = &data.profile;
+        let lines = [
+            ("load", profile.load_time),
+            ("collect", profile.collect_time),
+            ("draw", profile.draw_time),
+            ("latency", profile.latency),
+        ]
+        .iter()
+        .map(|(name, duration)| {
+            Line::from(format!(
+                "{name:<8}{:>10.2}ms",
+                duration.as_secs_f64() * 1000.0
+            ))
+        })
+        .collect::<Vec<Line>>();
+        let width = 22.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let area = Rect::new(
+            area.right().saturating_sub(width + 1),
+            area.y + 1,
+            width,
+            height,
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(self.styles.popup)
+                .block(Block::bordered().title("Profile")),
+            area,
+        );
+    }
+
+    // Keys continuing a started key sequence, in the bottom right corner like which-key of vim
+    fn render_key_hints(&self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = data
+            .key_hints
+            .iter()
+            .map(|(keys, description)| Line::from(format!("{keys:<3}{description}")))
+            .collect();
+        let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let area = Rect::new(
+            area.right().saturating_sub(width + 1),
+            area.bottom().saturating_sub(height),
+            width,
+            height,
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(self.styles.popup)
+                .block(Block::bordered().title("Keys")),
+            area,
+        );
+    }
+
     fn render_popup(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
         let popup = Popup::default()
             .content(data.popup_message.clone())
             .style(self.styles.popup)
-            .title("Key Bindings")
+            .title(data.popup_title.clone())
             .title_style(Style::new().white().bold())
             .border_style(Style::new().white().bold());
         let popup_vertical_margin = (area.height.saturating_sub(
@@ -238,104 +643,378 @@ impl TableUI {
             }),
         );
     }
-    fn render_table(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
-        let columns = &data.table;
-        if columns.is_empty() || columns[0].data.is_empty() {
-            // Render an empty table
-            let mut rows = Vec::new();
-            for _ in 0..data.layout.table_height {
-                rows.push(Row::new([""]).style(self.styles.row));
-            }
-            let widths: Vec<Constraint>;
-            let header: Row;
-
-            if columns.is_empty() {
-                widths = vec![Constraint::Length(area.width)];
-                header = Row::new(vec![Cell::from("NO DATA")]);
+    fn render_chart(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let Some(chart) = data.chart.as_ref() else {
+            return;
+        };
+        let area = area.inner(Margin {
+            vertical: 1,
+            horizontal: 2,
+        });
+        let block = Block::new()
+            .title(chart.title.clone())
+            .title_style(Style::new().white().bold())
+            .borders(Borders::ALL)
+            .border_style(Style::new().white().bold());
+        let texts = chart.bars.iter().map(|(_, value)| {
+            if value.fract() == 0.0 {
+                format!("{value:.0}")
             } else {
-                widths = columns
-                    .iter()
-                    .map(|c| Constraint::Length(c.width as u16))
-                    .collect::<Vec<Constraint>>();
-                header = Row::new(
-                    columns
-                        .iter()
-                        .map(|c| Cell::from(c.name.clone()))
-                        .collect::<Vec<Cell>>(),
-                )
-                .style(self.styles.header);
+                format!("{value:.2}")
             }
-
-            let table = Table::new(rows, widths).header(header);
-            frame.render_widget(table, area);
+        });
+        if let Some(protocol) = self.graphics {
+            let texts = texts.collect();
+            self.render_chart_image(chart, texts, protocol, block, frame, area);
             return;
         }
 
-        let mut rows = Vec::new();
-        let nrows = data.table[0].data.len(); // Assume there is always at least one column
-        for ridx in 0..nrows {
-            rows.push(
-                Row::new(
-                    columns
-                        .iter()
-                        .map(|c| c.data[ridx].clone())
-                        .collect::<Vec<String>>(),
-                )
-                .style(self.styles.row),
+        // Bars have a integer length, scale them relative to the largest value
+        let max = chart.bars.iter().map(|(_, v)| v.abs()).fold(0.0, f64::max);
+        let bars = chart
+            .bars
+            .iter()
+            .zip(texts)
+            .map(|((label, value), text)| {
+                let length = if max > 0.0 {
+                    (value.max(0.0) / max * 1000.0) as u64
+                } else {
+                    0
+                };
+                Bar::with_label(label.clone(), length).text_value(text)
+            })
+            .collect::<Vec<Bar>>();
+
+        let barchart = BarChart::horizontal(bars)
+            .bar_width(1)
+            .bar_gap(0)
+            .max(1000)
+            .style(self.styles.popup)
+            .block(block);
+        frame.render_widget(Clear, area);
+        frame.render_widget(barchart, area);
+    }
+
+    fn render_plot(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let Some(plot) = data.plot.as_ref() else {
+            return;
+        };
+        let area = area.inner(Margin {
+            vertical: 1,
+            horizontal: 2,
+        });
+        let block = Block::new()
+            .title(plot.title.clone())
+            .title_style(Style::new().white().bold())
+            .borders(Borders::ALL)
+            .border_style(Style::new().white().bold());
+        if let Some(protocol) = self.graphics {
+            self.render_plot_image(plot, protocol, block, frame, area);
+            return;
+        }
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::new().fg(tailwind::BLUE.c400))
+            .data(&plot.points);
+        let chart = Chart::new(vec![dataset])
+            .block(block)
+            .style(self.styles.popup)
+            .x_axis(
+                Axis::default()
+                    .bounds(plot.x_bounds)
+                    .labels(plot.x_labels.clone()),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds(plot.y_bounds)
+                    .labels(plot.y_labels.clone()),
             );
+        frame.render_widget(Clear, area);
+        frame.render_widget(chart, area);
+    }
+
+    // Labels and values around an image of the bars, one bar per line
+    fn render_chart_image(
+        &mut self,
+        chart: &ChartData,
+        texts: Vec<String>,
+        protocol: Protocol,
+        block: Block,
+        frame: &mut Frame,
+        area: Rect,
+    ) {
+        let inner = block.inner(area);
+        let width = |texts: &mut dyn Iterator<Item = &String>| {
+            texts.map(|t| t.chars().count()).max().unwrap_or(0) as u16
+        };
+        let label_width = width(&mut chart.bars.iter().map(|(l, _)| l)).min(inner.width / 3);
+        let [labels_area, bars_area, values_area] = Layout::horizontal([
+            Constraint::Length(label_width + 1),
+            Constraint::Fill(1),
+            Constraint::Length(width(&mut texts.iter()) + 1),
+        ])
+        .areas(inner);
+        let shown = chart.bars.len().min(inner.height as usize);
+        let labels: Vec<Line> = chart.bars[..shown]
+            .iter()
+            .map(|(label, _)| Line::from(label.as_str()))
+            .collect();
+        let values: Vec<Line> = texts[..shown]
+            .iter()
+            .map(|text| Line::from(text.as_str()).right_aligned())
+            .collect();
+        frame.render_widget(Clear, area);
+        frame.render_widget(block.style(self.styles.popup), area);
+        frame.render_widget(Paragraph::new(labels), labels_area);
+        frame.render_widget(Paragraph::new(values), values_area);
+
+        let bars_area = Rect {
+            height: shown as u16,
+            ..bars_area
+        };
+        if !bars_area.is_empty() {
+            let mut image = Image::for_area(bars_area, self.image_background());
+            let values: Vec<f64> = chart.bars[..shown].iter().map(|(_, v)| *v).collect();
+            image.bars(&values, tailwind::BLUE.c400);
+            self.image = Some(protocol.show(&image, bars_area, self.image_id));
+        }
+    }
+
+    // Axis labels around an image of the line
+    fn render_plot_image(
+        &mut self,
+        plot: &PlotData,
+        protocol: Protocol,
+        block: Block,
+        frame: &mut Frame,
+        area: Rect,
+    ) {
+        let inner = block.inner(area);
+        let label_width = plot.y_labels.iter().map(|l| l.chars().count()).max();
+        let [y_labels_area, graph_area] = Layout::horizontal([
+            Constraint::Length(label_width.unwrap_or(0) as u16 + 1),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+        let [graph_area, x_labels_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(graph_area);
+
+        // Labels are spread evenly, the first one at the bottom or left
+        let spread = |idx: usize, count: usize, size: usize| match count {
+            0 | 1 => 0,
+            _ => idx * size / (count - 1),
+        };
+        let rows = graph_area.height as usize;
+        let mut y_labels = vec![Line::default(); rows];
+        for (idx, label) in plot.y_labels.iter().enumerate() {
+            let row =
+                rows.saturating_sub(1) - spread(idx, plot.y_labels.len(), rows.saturating_sub(1));
+            if let Some(line) = y_labels.get_mut(row) {
+                *line = Line::from(label.as_str());
+            }
         }
-        // Fill up the rest of the table with empty strings to have the empty part of the table render with the same style.
-        for _ in nrows..data.layout.table_height {
-            rows.push(Row::new([""].repeat(columns.len())).style(self.styles.row));
+        let mut x_labels = vec![' '; graph_area.width as usize];
+        for (idx, label) in plot.x_labels.iter().enumerate() {
+            let free = x_labels.len().saturating_sub(label.chars().count());
+            let start = spread(idx, plot.x_labels.len(), free);
+            for (pos, chr) in label.chars().enumerate() {
+                if let Some(cell) = x_labels.get_mut(start + pos) {
+                    *cell = chr;
+                }
+            }
         }
-        let widths = columns
+        frame.render_widget(Clear, area);
+        frame.render_widget(block.style(self.styles.popup), area);
+        frame.render_widget(Paragraph::new(y_labels), y_labels_area);
+        frame.render_widget(
+            Paragraph::new(x_labels.into_iter().collect::<String>()),
+            x_labels_area,
+        );
+
+        if !graph_area.is_empty() {
+            let mut image = Image::for_area(graph_area, self.image_background());
+            let axes = self.styles.popup.fg.unwrap_or(Color::White);
+            image.plot(
+                &plot.points,
+                plot.x_bounds,
+                plot.y_bounds,
+                [tailwind::BLUE.c400, axes],
+            );
+            self.image = Some(protocol.show(&image, graph_area, self.image_id));
+        }
+    }
+
+    fn image_background(&self) -> Color {
+        self.styles.popup.bg.unwrap_or(Color::Black)
+    }
+
+    fn render_json(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let Some(json) = data.json.as_ref() else {
+            return;
+        };
+        let area = area.inner(Margin {
+            vertical: 1,
+            horizontal: 2,
+        });
+        let lines = json
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(idx, tokens)| {
+                let spans = tokens
+                    .iter()
+                    .map(|(text, token)| {
+                        let style = match token {
+                            JsonToken::Key => Style::new().fg(tailwind::BLUE.c500).bold(),
+                            JsonToken::String => Style::new().fg(tailwind::GREEN.c500),
+                            JsonToken::Number => Style::new().fg(tailwind::AMBER.c500),
+                            JsonToken::Bool => Style::new().fg(tailwind::FUCHSIA.c500),
+                            JsonToken::Null => Style::new().fg(tailwind::SLATE.c500),
+                            JsonToken::Punctuation => Style::new(),
+                        };
+                        Span::styled(text.clone(), style)
+                    })
+                    .collect::<Vec<Span>>();
+                let line = Line::from(spans);
+                if idx == json.selected {
+                    line.style(self.styles.selected_row)
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<Line>>();
+
+        let block = Block::new()
+            .title(json.title.clone())
+            .title_style(Style::new().white().bold())
+            .borders(Borders::ALL)
+            .border_style(Style::new().white().bold());
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).style(self.styles.popup).block(block),
+            area,
+        );
+    }
+
+    fn render_table(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let table = TableWidget::new(&data.table, self.styles.table())
+            .row_marks(
+                &data.highlighted_rows,
+                &data.bookmarked_rows,
+                &data.marked_rows,
+            )
+            .sorted_column(data.sorted_column)
+            .crosshair(data.crosshair)
+            .ascii(self.ascii)
+            .stripe(self.stripe);
+        self.table_state.select(
+            data.abs_selected_row.saturating_sub(data.selected_row),
+            Some((data.selected_row, data.selected_column)),
+            data.nrows,
+        );
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    // Selected cell value on the left, the visible part of a wide table on the right
+    // Value, count, share and a bar per value, the bars are relative to the most frequent value
+    // Aggregates of the visible columns, aligned with them below the rows
+    fn render_totals(&mut self, data: &UIData, frame: &mut Frame, area: Rect, label: Rect) {
+        let Some(totals) = data.totals.as_ref().filter(|_| area.height > 0) else {
+            return;
+        };
+        let widths = data
+            .table
             .iter()
             .map(|c| Constraint::Length(c.width as u16))
             .collect::<Vec<Constraint>>();
+        let row = Row::new(totals.iter().map(|t| Cell::from(t.clone())));
+        frame.render_widget(Table::new([row], widths).style(self.styles.header), area);
+        frame.render_widget(Paragraph::new("total").style(self.styles.header), label);
+    }
 
-        //trace!("num rows: {}, nrows {}", rows.len(), nrows);
-        let header = Row::new(
-            columns
-                .iter()
-                .map(|c| Cell::from(c.name.clone()))
-                .collect::<Vec<Cell>>(),
-        )
-        .style(self.styles.header);
-
-        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
-        self.scrollbar_state = self
-            .scrollbar_state
-            .content_length(data.nrows)
-            .position(data.abs_selected_row); //.viewport_content_length(1);
-
-        let table = Table::new(rows, widths)
-            //.block(Block::new().title("Table"))
-            .row_highlight_style(self.styles.selected_row)
-            .cell_highlight_style(self.styles.selected_cell)
-            .header(header);
-        //.highlight_symbol(">>");
-        //self.table_state.select_column(Some(model.get_selected_column()));
-        //self.table_state.select(Some(model.get_selected_row()));
-        self.table_state
-            .select_cell(Some((data.selected_row, data.selected_column)));
-        frame.render_stateful_widget(table, area, &mut self.table_state);
+    fn render_top_values(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let Some(top) = data.top_values.as_ref().filter(|_| area.height > 0) else {
+            return;
+        };
+        let value_width = top
+            .values
+            .iter()
+            .map(|(value, _)| value.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(area.width as usize / 3);
+        let count_width = top.values.first().map_or(1, |(_, c)| c.to_string().len());
+        let max = top.values.first().map_or(1, |(_, c)| *c).max(1);
+        let bar_width = (area.width as usize).saturating_sub(value_width + count_width + 10);
+        let lines: Vec<Line> = top
+            .values
+            .iter()
+            .map(|(value, count)| {
+                let value: String = value.chars().take(value_width).collect();
+                let share = *count as f64 / top.total.max(1) as f64 * 100.0;
+                let bar = "█".repeat(count * bar_width / max);
+                Line::from(format!(
+                    "{value:<value_width$} {count:>count_width$} {share:>5.1}% {bar}"
+                ))
+            })
+            .collect();
+        let block = Block::new()
+            .borders(Borders::TOP)
+            .title(top.title.clone())
+            .style(self.styles.row);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
 
-        // using an inner vertical margin of 1 unit makes the scrollbar inside the block
-        frame.render_stateful_widget(
-            scrollbar,
-            area.inner(Margin {
-                vertical: 1,
-                horizontal: 0,
-            }),
-            &mut self.scrollbar_state,
-        );
+    fn render_cell_line(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        let (first, last, ncolumns) = data.column_range;
+        let right = if ncolumns > 0 {
+            format!(" cols {}–{} of {ncolumns}", first + 1, last + 1)
+        } else {
+            String::new()
+        };
+        let [left_area, right_area] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(right.chars().count() as u16),
+        ])
+        .areas(area);
+        let cell_line = match self.ascii {
+            true => data.cell_line.replace('∅', "NULL"),
+            false => data.cell_line.clone(),
+        };
+        frame.render_widget(Paragraph::new(cell_line).style(self.styles.row), left_area);
+        frame.render_widget(Paragraph::new(right).style(self.styles.row), right_area);
     }
 
     fn render_statusline(&mut self, data: &UIData, frame: &mut Frame, area: Rect) {
+        // Active input mode on the very left
+        let mode = InputMode::from_cmd_mode(data.cmd_mode).label();
+        let mode = match data.recording_macro {
+            Some(register) => format!(" {mode} rec @{register} "),
+            None => format!(" {mode} "),
+        };
+        let [mode_area, area] =
+            Layout::horizontal([Constraint::Length(mode.len() as u16), Constraint::Min(0)])
+                .areas(area);
+        frame.render_widget(
+            Paragraph::new(mode).style(self.styles.statusline.reversed().bold()),
+            mode_area,
+        );
+
         let mut render_curser = false;
         let mut prompt = String::new();
+        let mut style = self.styles.statusline;
 
-        let right = if data.nrows > 0 {
+        let right = if data.cmd_mode.is_some() && !data.cmd_preview.is_empty() {
+            data.cmd_preview.clone()
+        } else if data.nrows > 0 && !data.column_stats.is_empty() {
+            format!(
+                "{}   {}/{}",
+                data.column_stats,
+                data.abs_selected_row + 1,
+                data.nrows
+            )
+        } else if data.nrows > 0 {
             format!("{}/{}", data.abs_selected_row + 1, data.nrows)
         } else {
             "0/0".to_string()
@@ -348,13 +1027,43 @@ impl TableUI {
                 format!("{}{}", prompt, data.cmdinput.input)
             }
             None => {
-                if (data.last_status_message_update + STATUS_MESSAGE_DISPLAY_DURATION)
+                if data.status_level != MessageLevel::Info {
+                    style = match data.status_level {
+                        MessageLevel::Warning => self.styles.status_warning,
+                        _ => self.styles.status_error,
+                    };
+                    data.status_message.clone()
+                } else if (data.last_status_message_update + STATUS_MESSAGE_DISPLAY_DURATION)
                     - Instant::now()
                     > std::time::Duration::ZERO
                 {
                     data.status_message.clone()
                 } else {
-                    data.name.clone()
+                    let name = if data.tab_names.len() > 1 {
+                        // Show all tabs, marking the active one
+                        let tabs = data
+                            .tab_names
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, name)| {
+                                if idx == data.active_tab {
+                                    format!("[{name}]")
+                                } else {
+                                    name.clone()
+                                }
+                            })
+                            .collect::<Vec<String>>()
+                            .join(" ");
+                        format!("{tabs} | {}", data.name)
+                    } else {
+                        data.name.clone()
+                    };
+                    // Mark modified data that was not exported yet
+                    if data.dirty {
+                        format!("{name} [+]")
+                    } else {
+                        name
+                    }
                 }
             }
         };
@@ -371,13 +1080,20 @@ impl TableUI {
             width = total_width.saturating_sub(right_len)
         );
 
-        let status_bar = Paragraph::new(status_string).style(self.styles.statusline);
+        let status_bar = Paragraph::new(status_string).style(style);
 
         frame.render_widget(status_bar, area);
 
         if render_curser {
+            // Wide characters before the curser, like 日, take two cells
+            let typed: String = data
+                .cmdinput
+                .input
+                .chars()
+                .take(data.cmdinput.curser_pos)
+                .collect();
             let curser_pos = Position::new(
-                area.x + data.cmdinput.curser_pos as u16 + prompt.len() as u16,
+                area.x + Span::raw(typed).width() as u16 + prompt.len() as u16,
                 area.y,
             );
             frame.set_cursor_position(curser_pos);